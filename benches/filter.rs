@@ -0,0 +1,83 @@
+// Matching a stream of events against many subscriptions, as a relay does,
+// with plain filters and with compiled ones
+
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use nostr_types::{
+    CompiledFilter, Event, EventBuilder, EventKind, Filter, PrivateKey, PublicKeyHex, Tag, Unixtime,
+};
+
+const SUBSCRIPTIONS: usize = 200;
+const AUTHORS: usize = 1000;
+const FOLLOWS: usize = 100;
+const EVENTS: usize = 500;
+
+fn setup() -> (Vec<Filter>, Vec<Event>) {
+    let keys: Vec<PrivateKey> = (0..AUTHORS).map(|_| PrivateKey::generate()).collect();
+
+    // Each subscription follows a hundred authors and a few hashtags
+    let filters = (0..SUBSCRIPTIONS)
+        .map(|i| {
+            let mut filter = Filter::new();
+            for j in 0..FOLLOWS {
+                let key = &keys[(i * 7 + j * 13) % AUTHORS];
+                filter.add_author(PublicKeyHex::from(key.public_key()));
+            }
+            filter.add_event_kind(EventKind::TextNote);
+            filter.add_event_kind(EventKind::Repost);
+            for j in 0..5 {
+                filter.add_tag_value('t', format!("topic{}", (i + j) % 20));
+            }
+            filter.since = Some(Unixtime(1_600_000_000));
+            filter
+        })
+        .collect();
+
+    let events = (0..EVENTS)
+        .map(|i| {
+            EventBuilder::new()
+                .content("Hello")
+                .tag(Tag::Hashtag(format!("topic{}", i % 25)))
+                .sign(&keys[i % AUTHORS])
+                .unwrap()
+        })
+        .collect();
+
+    (filters, events)
+}
+
+fn bench_filters(c: &mut Criterion) {
+    let (filters, events) = setup();
+    let compiled: Vec<CompiledFilter> = filters.iter().map(|f| f.compile()).collect();
+
+    let mut group = c.benchmark_group("match events against subscriptions");
+    let _ = group.bench_function("Filter", |b| {
+        b.iter(|| {
+            events
+                .iter()
+                .map(|e| {
+                    filters
+                        .iter()
+                        .filter(|f| f.event_matches(black_box(e)))
+                        .count()
+                })
+                .sum::<usize>()
+        })
+    });
+    let _ = group.bench_function("CompiledFilter", |b| {
+        b.iter(|| {
+            events
+                .iter()
+                .map(|e| {
+                    compiled
+                        .iter()
+                        .filter(|f| f.event_matches(black_box(e)))
+                        .count()
+                })
+                .sum::<usize>()
+        })
+    });
+    group.finish();
+}
+
+criterion_group!(benches, bench_filters);
+criterion_main!(benches);
@@ -19,6 +19,28 @@ pub enum Error {
     #[error("Bech32 Error: {0}")]
     Bech32(#[from] bech32::Error),
 
+    /// BIP-32 key derivation error
+    #[cfg(feature = "nip06")]
+    #[error("BIP-32 Error: {0}")]
+    Bip32(#[from] bip32::Error),
+
+    /// BIP-39 mnemonic error
+    #[cfg(feature = "nip06")]
+    #[error("BIP-39 Mnemonic Error: {0}")]
+    Bip39(#[from] bip39::Error),
+
+    /// An event broke a content policy
+    #[error("Content policy violation: {0}")]
+    ContentPolicyViolation(#[from] crate::ContentPolicyViolation),
+
+    /// An event failed to meet a delegation condition (NIP-26)
+    #[error("Delegation condition failed: {0}")]
+    DelegationConditionFailed(#[from] crate::DelegationConditionFailure),
+
+    /// Duplicate name in a nostr.json
+    #[error("Duplicate NIP-05 name: {0}")]
+    DuplicateNip05Name(String),
+
     /// Encryption/Decryption Error
     #[error("Encryption/Decryption Error")]
     Encryption,
@@ -35,6 +57,14 @@ pub enum Error {
     #[error("Event is in the future")]
     EventInFuture,
 
+    /// Event JSON could not be parsed by the fast parser
+    #[error("Event JSON parse error at byte {0}: {1}")]
+    EventJsonParse(usize, &'static str),
+
+    /// Event exceeds a relay limitation
+    #[error("Event exceeds the relay's {0}")]
+    EventExceedsLimit(&'static str),
+
     /// Formatting error
     #[error("Formatting Error: {0}")]
     Fmt(#[from] std::fmt::Error),
@@ -47,10 +77,42 @@ pub enum Error {
     #[error("Hex Decode Error: {0}")]
     HexDecode(#[from] hex::FromHexError),
 
+    /// HTTP response too large
+    #[error("HTTP response of {0} bytes is too large")]
+    HttpResponseTooLarge(usize),
+
+    /// Insufficient proof of work (NIP-13)
+    #[error("Insufficient proof of work: {0} bits but {1} required")]
+    InsufficientPow(u8, u8),
+
+    /// Invalid application-specific data (NIP-78)
+    #[error("Invalid application data: {0}")]
+    InvalidAppData(String),
+
+    /// Invalid AUTH event (NIP-42)
+    #[error("Invalid AUTH event: {0}")]
+    InvalidAuthEvent(&'static str),
+
+    /// Invalid delegation (NIP-26)
+    #[error("Invalid delegation: {0}")]
+    InvalidDelegation(String),
+
+    /// Invalid chunked encrypted payloads
+    #[error("Invalid encrypted chunks: {0}")]
+    InvalidEncryptedChunks(String),
+
     /// Invalid encrypted private key
     #[error("Invalid Encrypted Private Key")]
     InvalidEncryptedPrivateKey,
 
+    /// Invalid Event Address
+    #[error("Invalid event address")]
+    InvalidEventAddr,
+
+    /// Invalid flat event
+    #[error("Invalid flat event: {0}")]
+    InvalidFlatEvent(&'static str),
+
     /// Invalid Event Pointer
     #[error("Invalid event pointer")]
     InvalidEventPointer,
@@ -63,10 +125,46 @@ pub enum Error {
     #[error("Invalid event Id Prefix")]
     InvalidIdPrefix,
 
+    /// Invalid imeta tag
+    #[error("Invalid imeta tag: {0}")]
+    InvalidIMeta(String),
+
+    /// Invalid NIP-51 list
+    #[error("Invalid list: {0}")]
+    InvalidList(String),
+
+    /// Invalid lightning address (LUD-16)
+    #[error("Invalid lightning address: {0}")]
+    InvalidLightningAddress(String),
+
+    /// Invalid LNURL (LUD-01)
+    #[error("Invalid LNURL: {0}")]
+    InvalidLnUrl(String),
+
     /// Invalid digest length
     #[error("Invalid digest length")]
     InvalidLength(#[from] hmac::digest::InvalidLength),
 
+    /// Invalid gift wrap or seal (NIP-59)
+    #[error("Invalid gift wrap: {0}")]
+    InvalidGiftWrap(String),
+
+    /// Invalid NIP-05 identifier
+    #[error("Invalid NIP-05 identifier: {0}")]
+    InvalidNip05Identifier(String),
+
+    /// Invalid PGN (chess game notation)
+    #[error("Invalid PGN: {0}")]
+    InvalidPgn(String),
+
+    /// Invalid Poll
+    #[error("Invalid poll: {0}")]
+    InvalidPoll(String),
+
+    /// A mention in a content template refers past the end of the profiles
+    #[error("Mention {{{0}}} is out of range of the profiles")]
+    InvalidMention(usize),
+
     /// Invalid Profile
     #[error("Invalid Profile")]
     InvalidProfile,
@@ -79,6 +177,18 @@ pub enum Error {
     #[error("Invalid Public Key Prefix")]
     InvalidPublicKeyPrefix,
 
+    /// Invalid relay discovery event
+    #[error("Invalid relay discovery event: {0}")]
+    InvalidRelayDiscovery(String),
+
+    /// Invalid relay message, with the index of the malformed element if any
+    #[error("Invalid relay message: {1}")]
+    InvalidRelayMessage(Option<usize>, String),
+
+    /// Invalid vanity prefix
+    #[error("Invalid vanity prefix: {0}")]
+    InvalidVanityPrefix(String),
+
     /// Invalid URL
     #[error("Invalid URL: \"{0}\"")]
     InvalidUrl(#[from] url::ParseError),
@@ -95,10 +205,27 @@ pub enum Error {
     #[error("Missing URL Authority")]
     InvalidUrlMissingAuthority,
 
+    /// A named field of Metadata was used as an extra field
+    #[error("{0} is a named Metadata field, not an extra field")]
+    MetadataFieldNotExtra(String),
+
+    /// A nostr.json exceeds a limit
+    #[error("nostr.json exceeds a limit: {0}")]
+    Nip05LimitExceeded(String),
+
     /// Pad error
     #[error("Encryption/Decryption padding error")]
     Pad(#[from] inout::PadError),
 
+    /// PKCS#8 error
+    #[cfg(feature = "pkcs8")]
+    #[error("PKCS#8 Error: {0}")]
+    Pkcs8(#[from] pkcs8::Error),
+
+    /// A PreEvent broke a kind-specific rule
+    #[error("Invalid event: {0}")]
+    PreEventInvalid(#[from] crate::PreEventValidationFailure),
+
     /// Parse integer error
     #[error("Parse integer error")]
     ParseInt(#[from] std::num::ParseIntError),
@@ -111,6 +238,11 @@ pub enum Error {
     #[error("JSON (de)serialization error: {0}")]
     SerdeJson(#[from] serde_json::Error),
 
+    /// Binary encoding error
+    #[cfg(feature = "speedy")]
+    #[error("Binary encoding error: {0}")]
+    Speedy(#[from] speedy::Error),
+
     /// Try from slice error
     #[error("Try From Slice error: {0}")]
     Slice(#[from] std::array::TryFromSliceError),
@@ -119,6 +251,10 @@ pub enum Error {
     #[error("System Time Error: {0}")]
     Time(#[from] std::time::SystemTimeError),
 
+    /// Unsupported binary encoding version
+    #[error("Unsupported binary encoding version: {0}")]
+    UnsupportedBinaryVersion(u8),
+
     /// Unknown event kind
     #[error("Unknown event kind = {0}")]
     UnknownEventKind(u64),
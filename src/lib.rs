@@ -30,12 +30,38 @@ mod error;
 pub use error::Error;
 
 mod types;
+#[cfg(any(test, feature = "test-util"))]
+pub use types::TestSigner;
 pub use types::{
-    find_nostr_bech32_pos, find_nostr_url_pos, ClientMessage, ContentSegment, DelegationConditions,
-    EncryptedPrivateKey, Event, EventDelegation, EventKind, EventKindIterator, EventKindOrRange,
-    EventPointer, Fee, Filter, Id, IdHex, IdHexPrefix, KeySecurity, Metadata, Nip05, NostrBech32,
-    NostrUrl, PayRequestData, PreEvent, PrivateKey, Profile, PublicKey, PublicKeyHex,
-    PublicKeyHexPrefix, RelayFees, RelayInformationDocument, RelayLimitation, RelayMessage,
-    RelayRetention, RelayUrl, ShatteredContent, Signature, SignatureHex, SimpleRelayList,
-    SimpleRelayUsage, Span, SubscriptionId, Tag, UncheckedUrl, Unixtime, Url,
+    compose_mentions, find_nostr_bech32_pos, find_nostr_entities, find_nostr_url_pos,
+    resolve_tag_reference, rewrite_legacy_mentions, verify_events_batch, verify_signatures_batch,
+    AppData, AuthChallengeManager, Birthday, Bookmarks, ChessGame, ChessResult, ClientMessage,
+    CompiledFilter, ContentEncryptionAlgorithm, ContentPolicy, ContentPolicyViolation,
+    ContentSegment, ConversationKeyCache, CountResult, DelegationConditionFailure,
+    DelegationConditions, EncryptedContent, EncryptedPrivateKey, Event, EventAddr, EventBuilder,
+    EventDelegation, EventFlat, EventKind, EventKindIterator, EventKindOrRange, EventPointer,
+    EventSummary, Fee, Filter, FilterMatchMode, FollowSet, GiftWrap, GrantedDelegation, IMeta, Id,
+    IdHandle, IdHex, IdHexPrefix, IdTable, IngestStats, IngestStatsSnapshot, KeySecurity,
+    LightningAddress, ListBuilder, LnUrl, LongFormContent, LongFormContentBuilder, Metadata,
+    MetadataChange, MuteList, NAddr, NEvent, NProfile, Nip05, Nip05Builder, Nip05Identifier,
+    Nip05Limits, NostrBech32, NostrUri, NostrUrl, PayRequestData, PinList, Poll, PollOption,
+    PollResponse, PollTally, PollType, PreEvent, PreEventValidationFailure, PrivateKey,
+    PrivateKeyInput, Profile, ProfilePointer, PublicKey, PublicKeyHandle, PublicKeyHex,
+    PublicKeyHexPrefix, PublicKeyTable, Reason, ReasonPrefix, RelayCapabilities, RelayDiscovery,
+    RelayFees, RelayInformationDocument, RelayLimitation, RelayMessage, RelayMessageLimits,
+    RelayMessageRef, RelayMonitorAnnouncement, RelayMonitorTimeout, RelayNetwork, RelayRetention,
+    RelayRtt, RelayUrl, Rumor, SanitizedField, Seal, SearchMatcher, ShatteredContent, Signature,
+    SignatureHex, Signer, SimpleRelayList, SimpleRelayUsage, Span, Subscription, SubscriptionId,
+    SubscriptionState, SubstringSearch, Tag, TagFilter, UncheckedUrl, Unixtime, Url, VerifiedEvent,
+    XOnlyPublicKey,
 };
+#[cfg(feature = "http")]
+pub use types::{fetch_relay_information, HttpRequest, RELAY_INFORMATION_MAX_BYTES};
+#[cfg(feature = "speedy")]
+pub use types::{from_binary, to_binary, BINARY_FORMAT_VERSION};
+#[cfg(feature = "rayon")]
+pub use types::{verify_all, verify_all_until_failure};
+#[cfg(feature = "nip05-verify")]
+pub use types::{verify_nip05_body, Nip05Status, NIP05_MAX_RESPONSE_BYTES};
+#[cfg(feature = "sql")]
+pub use types::{SqlDialect, SqlParam};
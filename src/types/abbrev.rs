@@ -0,0 +1,19 @@
+// Abbreviation of the bech32 forms of ids and keys, for display.
+
+// Shorten a bech32 string to its human-readable part, and `n` characters of
+// the data at each end
+pub(crate) fn abbreviate_bech32(bech32: &str, n: usize) -> String {
+    let Some(data_start) = bech32.find('1').map(|pos| pos + 1) else {
+        return bech32.to_owned();
+    };
+    let data_len = bech32.len() - data_start;
+    if n.saturating_mul(2) >= data_len {
+        return bech32.to_owned();
+    }
+    // bech32 strings are ASCII, so these are char boundaries
+    format!(
+        "{}…{}",
+        bech32.get(..data_start + n).unwrap_or_default(),
+        bech32.get(bech32.len() - n..).unwrap_or_default()
+    )
+}
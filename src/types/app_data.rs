@@ -0,0 +1,135 @@
+use super::{
+    ContentEncryptionAlgorithm, EncryptedContent, Event, EventKind, PreEvent, PrivateKey, Tag,
+    Unixtime,
+};
+use crate::Error;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+
+/// Application-specific data (NIP-78), kept by an application in a kind 30078
+/// event under an identifier in its 'd' tag. This lets applications store
+/// arbitrary settings on relays, optionally encrypted (NIP-44) to the user
+/// themself so that only they can read them.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct AppData {
+    /// The application identifier, stored in the 'd' tag
+    pub identifier: String,
+
+    /// Whether stored values are encrypted to the author
+    pub encrypted: bool,
+}
+
+impl AppData {
+    /// Create a new `AppData` for the given application identifier
+    pub fn new(identifier: &str, encrypted: bool) -> AppData {
+        AppData {
+            identifier: identifier.to_owned(),
+            encrypted,
+        }
+    }
+
+    /// Create a signed event holding `value` as JSON, to be published in place of
+    /// any earlier one with the same identifier
+    pub fn store<T: Serialize>(&self, value: &T, private_key: &PrivateKey) -> Result<Event, Error> {
+        let json = serde_json::to_string(value)?;
+        let content = if self.encrypted {
+            private_key.encrypt(
+                &private_key.public_key(),
+                &json,
+                ContentEncryptionAlgorithm::Nip44v2,
+            )?
+        } else {
+            json
+        };
+
+        let pre_event = PreEvent {
+            pubkey: private_key.public_key(),
+            created_at: Unixtime::now()?,
+            kind: EventKind::AppSpecificData,
+            tags: vec![Tag::Identifier(self.identifier.clone())],
+            content,
+            ots: None,
+        };
+        Event::new(pre_event, private_key)
+    }
+
+    /// Load the value stored in `event`. The event must be signed by
+    /// `private_key` and carry this application's identifier. Encrypted content
+    /// is detected and decrypted whether or not `self.encrypted` is set.
+    pub fn load<T: DeserializeOwned>(
+        &self,
+        event: &Event,
+        private_key: &PrivateKey,
+    ) -> Result<T, Error> {
+        if event.kind != EventKind::AppSpecificData {
+            return Err(Error::WrongEventKind);
+        }
+        if event.pubkey != private_key.public_key() {
+            return Err(Error::InvalidAppData(
+                "Event was not authored by this key".to_owned(),
+            ));
+        }
+        let identifier = event.tags.iter().find_map(|tag| match tag {
+            Tag::Identifier(identifier) => Some(identifier),
+            _ => None,
+        });
+        if identifier != Some(&self.identifier) {
+            return Err(Error::InvalidAppData(format!(
+                "Event is not for application {}",
+                self.identifier
+            )));
+        }
+        event.verify(None)?;
+
+        if EncryptedContent::detect(&event.content).is_some() {
+            let json = private_key.decrypt_auto(&event.pubkey, &event.content)?;
+            Ok(serde_json::from_str(&json)?)
+        } else {
+            Ok(serde_json::from_str(&event.content)?)
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use serde::Deserialize;
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    struct Settings {
+        theme: String,
+        font_size: u8,
+    }
+
+    #[test]
+    fn test_app_data_roundtrip() {
+        let private_key = PrivateKey::generate();
+        let settings = Settings {
+            theme: "dark".to_owned(),
+            font_size: 14,
+        };
+
+        for encrypted in [false, true] {
+            let app_data = AppData::new("com.example.client", encrypted);
+            let event = app_data.store(&settings, &private_key).unwrap();
+            assert_eq!(event.kind, EventKind::AppSpecificData);
+            assert_eq!(
+                event.tags,
+                vec![Tag::Identifier("com.example.client".to_owned())]
+            );
+            assert_eq!(event.content.contains("dark"), !encrypted);
+
+            let loaded: Settings = app_data.load(&event, &private_key).unwrap();
+            assert_eq!(loaded, settings);
+
+            // Another application's data is rejected
+            let other_app = AppData::new("org.example.other", encrypted);
+            assert!(other_app.load::<Settings>(&event, &private_key).is_err());
+
+            // Someone else's data is rejected
+            assert!(app_data
+                .load::<Settings>(&event, &PrivateKey::generate())
+                .is_err());
+        }
+    }
+}
@@ -0,0 +1,170 @@
+use super::{Event, EventKind, PublicKey, RelayUrl, Tag, Unixtime};
+use crate::Error;
+use rand_core::{OsRng, RngCore};
+use std::collections::HashMap;
+
+// How far the created_at of an AUTH event may be from the present, in seconds
+const MAX_CLOCK_SKEW: u64 = 600;
+
+/// Issues NIP-42 authentication challenges for a relay, and checks the AUTH
+/// events (kind 22242) which clients send in response.
+///
+/// Each challenge is random, expires after a lifetime, and can be used to
+/// authenticate only once.
+#[derive(Clone, Debug)]
+pub struct AuthChallengeManager {
+    relay_url: RelayUrl,
+    lifetime: i64,
+    challenges: HashMap<String, Unixtime>,
+}
+
+impl AuthChallengeManager {
+    /// Create a manager for the relay at `relay_url`, whose challenges expire
+    /// `lifetime_secs` seconds after they are issued
+    pub fn new(relay_url: RelayUrl, lifetime_secs: u32) -> AuthChallengeManager {
+        AuthChallengeManager {
+            relay_url,
+            lifetime: i64::from(lifetime_secs),
+            challenges: HashMap::new(),
+        }
+    }
+
+    /// Issue a new challenge, to send to a client in an AUTH message. Expired
+    /// challenges are dropped.
+    pub fn issue(&mut self) -> Result<String, Error> {
+        Ok(self.issue_at(Unixtime::now()?))
+    }
+
+    fn issue_at(&mut self, now: Unixtime) -> String {
+        self.prune_at(now);
+        let mut bytes = [0_u8; 16];
+        OsRng.fill_bytes(&mut bytes);
+        let challenge = hex::encode(bytes);
+        let _ = self
+            .challenges
+            .insert(challenge.clone(), Unixtime(now.0 + self.lifetime));
+        challenge
+    }
+
+    /// Check an AUTH event from a client, returning the public key it
+    /// authenticates. The event must be a valid kind 22242 event created
+    /// within ten minutes of now, for this relay, answering a challenge that
+    /// was issued here and has not expired or been used.
+    pub fn validate(&mut self, event: &Event) -> Result<PublicKey, Error> {
+        self.validate_at(event, Unixtime::now()?)
+    }
+
+    fn validate_at(&mut self, event: &Event, now: Unixtime) -> Result<PublicKey, Error> {
+        if event.kind != EventKind::Auth {
+            return Err(Error::WrongEventKind);
+        }
+        if event.created_at.0.abs_diff(now.0) > MAX_CLOCK_SKEW {
+            return Err(Error::InvalidAuthEvent("created_at is too far from now"));
+        }
+        let relay =
+            tag_value(event, "relay").ok_or(Error::InvalidAuthEvent("missing relay tag"))?;
+        if RelayUrl::try_from_str(&relay).ok().as_ref() != Some(&self.relay_url) {
+            return Err(Error::InvalidAuthEvent("relay tag is for another relay"));
+        }
+        let challenge = tag_value(event, "challenge")
+            .ok_or(Error::InvalidAuthEvent("missing challenge tag"))?;
+        match self.challenges.get(&challenge) {
+            Some(expiry) if *expiry >= now => {}
+            _ => return Err(Error::InvalidAuthEvent("unknown or expired challenge")),
+        }
+        event.verify(None)?;
+
+        let _ = self.challenges.remove(&challenge);
+        Ok(event.pubkey)
+    }
+
+    /// Drop expired challenges
+    pub fn prune(&mut self) -> Result<(), Error> {
+        self.prune_at(Unixtime::now()?);
+        Ok(())
+    }
+
+    fn prune_at(&mut self, now: Unixtime) {
+        self.challenges.retain(|_, expiry| *expiry >= now);
+    }
+
+    /// The number of outstanding challenges
+    pub fn len(&self) -> usize {
+        self.challenges.len()
+    }
+
+    /// Whether there are no outstanding challenges
+    pub fn is_empty(&self) -> bool {
+        self.challenges.is_empty()
+    }
+}
+
+// The value of the first tag of this name
+fn tag_value(event: &Event, name: &str) -> Option<String> {
+    event.tags.iter().find_map(|tag| match tag {
+        Tag::Other { tag, data } if tag == name => data.first().cloned(),
+        _ => None,
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{EventBuilder, PrivateKey};
+
+    fn auth_event(private_key: &PrivateKey, relay: &str, challenge: &str, now: Unixtime) -> Event {
+        let tag = |name: &str, value: &str| Tag::Other {
+            tag: name.to_owned(),
+            data: vec![value.to_owned()],
+        };
+        EventBuilder::new()
+            .kind(EventKind::Auth)
+            .created_at(now)
+            .tag(tag("relay", relay))
+            .tag(tag("challenge", challenge))
+            .sign(private_key)
+            .unwrap()
+    }
+
+    #[test]
+    fn test_auth_challenge_manager() {
+        let relay = "wss://relay.example.com/";
+        let mut manager = AuthChallengeManager::new(RelayUrl::try_from_str(relay).unwrap(), 60);
+        let private_key = PrivateKey::generate();
+        let now = Unixtime(1_700_000_000);
+
+        let challenge = manager.issue_at(now);
+        assert_eq!(manager.len(), 1);
+        let event = auth_event(&private_key, relay, &challenge, now);
+        assert_eq!(
+            manager.validate_at(&event, now).unwrap(),
+            private_key.public_key()
+        );
+        // Challenges are single-use
+        assert!(manager.validate_at(&event, now).is_err());
+        assert!(manager.is_empty());
+
+        let challenge = manager.issue_at(now);
+        let other_relay = auth_event(&private_key, "wss://other.example.com/", &challenge, now);
+        assert!(manager.validate_at(&other_relay, now).is_err());
+        let unknown = auth_event(&private_key, relay, "nope", now);
+        assert!(manager.validate_at(&unknown, now).is_err());
+        let stale = auth_event(&private_key, relay, &challenge, Unixtime(now.0 - 3600));
+        assert!(manager.validate_at(&stale, now).is_err());
+        // Extreme times are too far from now, not an overflow
+        for extreme in [i64::MIN, i64::MAX] {
+            let event = auth_event(&private_key, relay, &challenge, Unixtime(extreme));
+            assert!(manager.validate_at(&event, now).is_err());
+        }
+        let mut tampered = auth_event(&private_key, relay, &challenge, now);
+        tampered.content = "changed".to_owned();
+        assert!(manager.validate_at(&tampered, now).is_err());
+
+        // Expiry
+        let later = Unixtime(now.0 + 61);
+        let event = auth_event(&private_key, relay, &challenge, later);
+        assert!(manager.validate_at(&event, later).is_err());
+        manager.prune_at(later);
+        assert!(manager.is_empty());
+    }
+}
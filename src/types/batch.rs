@@ -0,0 +1,172 @@
+use super::{Event, Id, PublicKey, Signature, Unixtime};
+use crate::Error;
+#[cfg(feature = "rayon")]
+use rayon::prelude::*;
+use std::thread;
+
+// Below this many items per thread, spawning threads costs more than it saves
+const MIN_ITEMS_PER_THREAD: usize = 64;
+
+// Run `f` over all items, splitting the work across the available cores. Each
+// worker creates its own state with `init` and passes it to every call of `f`.
+fn run_batch<T, S, I, F>(items: &[T], init: I, f: F) -> Vec<Result<(), Error>>
+where
+    T: Sync,
+    I: Fn() -> S + Sync,
+    F: Fn(&mut S, &T) -> Result<(), Error> + Sync,
+{
+    let threads = num_cpus::get().min(items.len() / MIN_ITEMS_PER_THREAD);
+    if threads <= 1 {
+        let mut state = init();
+        return items.iter().map(|item| f(&mut state, item)).collect();
+    }
+
+    let chunk_size = items.len().div_ceil(threads);
+    let (init, f) = (&init, &f);
+    thread::scope(|scope| {
+        let handles: Vec<_> = items
+            .chunks(chunk_size)
+            .map(|chunk| {
+                scope.spawn(move || {
+                    let mut state = init();
+                    chunk
+                        .iter()
+                        .map(|item| f(&mut state, item))
+                        .collect::<Vec<_>>()
+                })
+            })
+            .collect();
+        handles
+            .into_iter()
+            .flat_map(|handle| handle.join().unwrap())
+            .collect()
+    })
+}
+
+/// Verify many events (see `Event::verify()`), in parallel for large batches.
+///
+/// Each event is serialized and hashed only once, with that hash used both to
+/// verify the signature and to check the Id, and each thread serializes into
+/// one buffer which it reuses for all of its events. The results are in the
+/// same order as the events.
+pub fn verify_events_batch(events: &[Event], maxtime: Option<Unixtime>) -> Vec<Result<(), Error>> {
+    run_batch(events, Vec::new, |buf, event| {
+        event.verify_with_buffer(maxtime, buf)
+    })
+}
+
+/// Verify many BIP-340 signatures over 32-byte hashes (such as event Ids), in
+/// parallel for large batches. The results are in the same order as the input.
+pub fn verify_signatures_batch(items: &[(PublicKey, Id, Signature)]) -> Vec<Result<(), Error>> {
+    run_batch(
+        items,
+        || (),
+        |(), (pubkey, id, signature)| pubkey.verify_id(*id, *signature),
+    )
+}
+
+/// Verify many events (see `Event::verify()`) on rayon's global thread pool,
+/// recomputing each Id and checking each signature in parallel. The results are
+/// in the same order as the events.
+#[cfg(feature = "rayon")]
+pub fn verify_all(events: &[Event]) -> Vec<Result<(), Error>> {
+    events
+        .par_iter()
+        .map_init(Vec::new, |buf, event| event.verify_with_buffer(None, buf))
+        .collect()
+}
+
+/// Verify many events as `verify_all()` does, but stop at the first failure,
+/// returning its index and error. Events after a failure may not be verified
+/// at all.
+#[cfg(feature = "rayon")]
+pub fn verify_all_until_failure(events: &[Event]) -> Result<(), (usize, Error)> {
+    match events
+        .par_iter()
+        .enumerate()
+        .map_init(Vec::new, |buf, (i, event)| {
+            event.verify_with_buffer(None, buf).map_err(|e| (i, e))
+        })
+        .find_map_first(Result::err)
+    {
+        Some(failure) => Err(failure),
+        None => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{PreEvent, TestSigner};
+
+    #[test]
+    fn test_verify_batch() {
+        let signer = TestSigner::new(b"batch");
+        let mut events: Vec<Event> = (0..300)
+            .map(|i| {
+                signer
+                    .sign_event(PreEvent {
+                        pubkey: signer.public_key(),
+                        created_at: Unixtime(1680000000 + i),
+                        kind: crate::EventKind::TextNote,
+                        tags: vec![],
+                        content: format!("note {i}"),
+                        ots: None,
+                    })
+                    .unwrap()
+            })
+            .collect();
+        events[7].content = "tampered".to_owned();
+        events[250].id = Id([0; 32]);
+
+        let results = verify_events_batch(&events, None);
+        assert_eq!(results.len(), events.len());
+        for (i, result) in results.iter().enumerate() {
+            match i {
+                7 => assert!(matches!(result, Err(Error::Signature(_)))),
+                250 => assert!(matches!(result, Err(Error::HashMismatch))),
+                _ => assert!(result.is_ok()),
+            }
+        }
+
+        let items: Vec<(PublicKey, Id, Signature)> =
+            events.iter().map(|e| (e.pubkey, e.id, e.sig)).collect();
+        let results = verify_signatures_batch(&items);
+        assert!(results[7].is_ok()); // the id and signature still match
+        assert!(results[250].is_err());
+        assert_eq!(results.iter().filter(|r| r.is_ok()).count(), 299);
+    }
+
+    #[cfg(feature = "rayon")]
+    #[test]
+    fn test_verify_all() {
+        let signer = TestSigner::new(b"rayon");
+        let mut events: Vec<Event> = (0..100)
+            .map(|i| {
+                signer
+                    .sign_event(PreEvent {
+                        pubkey: signer.public_key(),
+                        created_at: Unixtime(1680000000 + i),
+                        kind: crate::EventKind::TextNote,
+                        tags: vec![],
+                        content: format!("note {i}"),
+                        ots: None,
+                    })
+                    .unwrap()
+            })
+            .collect();
+        assert!(verify_all_until_failure(&events).is_ok());
+
+        events[42].id = Id([0; 32]);
+        events[77].content = "tampered".to_owned();
+        let results = verify_all(&events);
+        assert!(matches!(results[42], Err(Error::HashMismatch)));
+        assert!(matches!(results[77], Err(Error::Signature(_))));
+        assert_eq!(results.iter().filter(|r| r.is_ok()).count(), 98);
+
+        assert!(matches!(
+            verify_all_until_failure(&events),
+            Err((42, Error::HashMismatch))
+        ));
+    }
+}
@@ -0,0 +1,73 @@
+use crate::Error;
+use speedy::{LittleEndian, Readable, Writable};
+
+/// The version of the binary encoding written by `to_binary()`. It is bumped
+/// whenever the encoding of any type changes, so that data persisted by an
+/// older version is rejected rather than misread.
+pub const BINARY_FORMAT_VERSION: u8 = 1;
+
+/// Encode a value (such as an `Event`, `Id`, `PublicKey`, `Signature` or
+/// `Filter`) in a compact binary form prefixed with `BINARY_FORMAT_VERSION`,
+/// suitable for persisting without the cost of JSON
+pub fn to_binary<T: Writable<LittleEndian>>(value: &T) -> Result<Vec<u8>, Error> {
+    let mut output: Vec<u8> = Vec::with_capacity(1 + value.bytes_needed()?);
+    output.push(BINARY_FORMAT_VERSION);
+    value.write_to_stream_with_ctx(LittleEndian::default(), &mut output)?;
+    Ok(output)
+}
+
+/// Decode a value encoded by `to_binary()`
+pub fn from_binary<'a, T: Readable<'a, LittleEndian>>(bytes: &'a [u8]) -> Result<T, Error> {
+    match bytes.split_first() {
+        Some((&BINARY_FORMAT_VERSION, rest)) => {
+            Ok(T::read_from_buffer_with_ctx(LittleEndian::default(), rest)?)
+        }
+        Some((&version, _)) => Err(Error::UnsupportedBinaryVersion(version)),
+        None => Err(Error::UnsupportedBinaryVersion(0)),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{Event, EventKind, Filter, Id, PreEvent, PrivateKey, PublicKey, Tag, Unixtime};
+
+    #[test]
+    fn test_binary_roundtrip() {
+        let private_key = PrivateKey::generate();
+        let event = Event::new(
+            PreEvent {
+                pubkey: private_key.public_key(),
+                created_at: Unixtime(1_700_000_000),
+                kind: EventKind::TextNote,
+                tags: vec![Tag::Hashtag("nostr".to_owned()), Tag::Protected],
+                content: "Hello World!".to_owned(),
+                ots: None,
+            },
+            &private_key,
+        )
+        .unwrap();
+
+        let bytes = to_binary(&event).unwrap();
+        assert_eq!(bytes[0], BINARY_FORMAT_VERSION);
+        assert!(bytes.len() < serde_json::to_string(&event).unwrap().len());
+        let decoded: Event = from_binary(&bytes).unwrap();
+        assert_eq!(decoded, event);
+        assert!(decoded.verify(None).is_ok());
+
+        let decoded: Id = from_binary(&to_binary(&event.id).unwrap()).unwrap();
+        assert_eq!(decoded, event.id);
+        let decoded: PublicKey = from_binary(&to_binary(&event.pubkey).unwrap()).unwrap();
+        assert_eq!(decoded, event.pubkey);
+
+        let filter = Filter::mock();
+        let decoded: Filter = from_binary(&to_binary(&filter).unwrap()).unwrap();
+        assert_eq!(decoded, filter);
+
+        // Other versions are rejected
+        let mut bytes = bytes;
+        bytes[0] = BINARY_FORMAT_VERSION + 1;
+        assert!(from_binary::<Event>(&bytes).is_err());
+        assert!(from_binary::<Event>(&[]).is_err());
+    }
+}
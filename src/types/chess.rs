@@ -0,0 +1,286 @@
+use crate::Error;
+use lazy_static::lazy_static;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+#[cfg(feature = "speedy")]
+use speedy::{Readable, Writable};
+use std::fmt;
+
+lazy_static! {
+    static ref TAG_PAIR_RE: Regex =
+        Regex::new(r#"^\[([A-Za-z0-9_]+)\s+"((?:[^"\\]|\\.)*)"\]$"#).unwrap();
+    static ref MOVE_NUMBER_RE: Regex = Regex::new(r"^[0-9]+\.+").unwrap();
+    static ref SAN_RE: Regex = Regex::new(
+        r"^(?:[NBRQK]?[a-h]?[1-8]?x?[a-h][1-8](?:=[NBRQ])?|O-O(?:-O)?|0-0(?:-0)?|--)[+#]?[!?]{0,2}$"
+    )
+    .unwrap();
+    static ref NAG_RE: Regex = Regex::new(r"^\$[0-9]+$").unwrap();
+}
+
+/// The outcome of a chess game, as recorded in PGN
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "speedy", derive(Readable, Writable))]
+pub enum ChessResult {
+    /// White won (`1-0`)
+    WhiteWins,
+    /// Black won (`0-1`)
+    BlackWins,
+    /// The game was drawn (`1/2-1/2`)
+    Draw,
+    /// The game is ongoing, abandoned, or the result is unknown (`*`)
+    Unknown,
+}
+
+impl ChessResult {
+    /// Parse a PGN game termination marker
+    pub fn try_from_str(s: &str) -> Option<ChessResult> {
+        match s {
+            "1-0" => Some(ChessResult::WhiteWins),
+            "0-1" => Some(ChessResult::BlackWins),
+            "1/2-1/2" => Some(ChessResult::Draw),
+            "*" => Some(ChessResult::Unknown),
+            _ => None,
+        }
+    }
+
+    /// Render as a PGN game termination marker
+    pub fn as_str(&self) -> &'static str {
+        match *self {
+            ChessResult::WhiteWins => "1-0",
+            ChessResult::BlackWins => "0-1",
+            ChessResult::Draw => "1/2-1/2",
+            ChessResult::Unknown => "*",
+        }
+    }
+}
+
+impl fmt::Display for ChessResult {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+/// A chess game parsed and validated from the PGN content of a kind 64 event (NIP-64)
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "speedy", derive(Readable, Writable))]
+pub struct ChessGame {
+    /// The PGN tag pairs, in the order they were given
+    pub tags: Vec<(String, String)>,
+
+    /// The moves of the main line in Standard Algebraic Notation
+    pub moves: Vec<String>,
+
+    /// The game termination marker at the end of the movetext, if any
+    pub termination_marker: Option<ChessResult>,
+}
+
+impl ChessGame {
+    // Mock data for testing
+    #[allow(dead_code)]
+    pub(crate) fn mock() -> ChessGame {
+        ChessGame::try_from_pgn(
+            r#"[Event "Casual Game"]
+[White "Alice"]
+[Black "Bob"]
+[Result "1-0"]
+[Termination "normal"]
+
+1. e4 e5 2. Qh5 Nc6 3. Bc4 Nf6 4. Qxf7# 1-0"#,
+        )
+        .unwrap()
+    }
+
+    /// Parse and validate a PGN string
+    pub fn try_from_pgn(pgn: &str) -> Result<ChessGame, Error> {
+        let mut tags: Vec<(String, String)> = Vec::new();
+        let mut movetext = String::new();
+
+        let mut in_tags = true;
+        for line in pgn.lines() {
+            let line = line.trim();
+            if line.starts_with('%') {
+                // escape mechanism, line is ignored
+                continue;
+            }
+            if in_tags && line.starts_with('[') {
+                let caps = TAG_PAIR_RE
+                    .captures(line)
+                    .ok_or_else(|| Error::InvalidPgn(format!("Malformed tag pair: {line}")))?;
+                let value = caps[2].replace("\\\"", "\"").replace("\\\\", "\\");
+                tags.push((caps[1].to_owned(), value));
+                continue;
+            }
+            if !line.is_empty() {
+                in_tags = false;
+            }
+            movetext.push_str(line);
+            movetext.push('\n');
+        }
+
+        let (moves, termination_marker) = parse_movetext(&movetext)?;
+
+        if moves.is_empty() && tags.is_empty() {
+            return Err(Error::InvalidPgn("Empty game".to_owned()));
+        }
+
+        let game = ChessGame {
+            tags,
+            moves,
+            termination_marker,
+        };
+
+        // The Result tag and the termination marker must agree
+        if let (Some(tag), Some(marker)) = (game.tag("Result"), game.termination_marker) {
+            if tag != marker.as_str() {
+                return Err(Error::InvalidPgn(format!(
+                    "Result tag {tag} does not match termination marker {marker}"
+                )));
+            }
+        }
+
+        Ok(game)
+    }
+
+    /// Get the value of a PGN tag pair by name
+    pub fn tag(&self, name: &str) -> Option<&str> {
+        self.tags
+            .iter()
+            .find(|(n, _)| n == name)
+            .map(|(_, v)| v.as_str())
+    }
+
+    /// The name of the player of the white pieces
+    pub fn white(&self) -> Option<&str> {
+        self.tag("White")
+    }
+
+    /// The name of the player of the black pieces
+    pub fn black(&self) -> Option<&str> {
+        self.tag("Black")
+    }
+
+    /// The result of the game, from the Result tag or else the termination marker
+    pub fn result(&self) -> Option<ChessResult> {
+        match self.tag("Result") {
+            Some(r) => ChessResult::try_from_str(r),
+            None => self.termination_marker,
+        }
+    }
+
+    /// How the game ended (e.g. "normal", "time forfeit"), from the Termination tag
+    pub fn termination(&self) -> Option<&str> {
+        self.tag("Termination")
+    }
+}
+
+fn parse_movetext(movetext: &str) -> Result<(Vec<String>, Option<ChessResult>), Error> {
+    let mut moves: Vec<String> = Vec::new();
+    let mut termination_marker: Option<ChessResult> = None;
+
+    // Strip comments and variations, keeping only the main line
+    let mut cleaned = String::with_capacity(movetext.len());
+    let mut depth: usize = 0;
+    let mut chars = movetext.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '{' => {
+                if !chars.by_ref().any(|c| c == '}') {
+                    return Err(Error::InvalidPgn("Unterminated comment".to_owned()));
+                }
+                cleaned.push(' ');
+            }
+            ';' => {
+                let _ = chars.by_ref().any(|c| c == '\n');
+                cleaned.push(' ');
+            }
+            '(' => {
+                depth += 1;
+                cleaned.push(' ');
+            }
+            ')' => {
+                if depth == 0 {
+                    return Err(Error::InvalidPgn("Unbalanced variation".to_owned()));
+                }
+                depth -= 1;
+                cleaned.push(' ');
+            }
+            c if depth > 0 => {
+                if c == '\n' {
+                    cleaned.push(' ');
+                }
+            }
+            c => cleaned.push(c),
+        }
+    }
+    if depth > 0 {
+        return Err(Error::InvalidPgn("Unterminated variation".to_owned()));
+    }
+
+    for token in cleaned.split_whitespace() {
+        if termination_marker.is_some() {
+            return Err(Error::InvalidPgn(format!(
+                "Unexpected token after game termination: {token}"
+            )));
+        }
+        if let Some(result) = ChessResult::try_from_str(token) {
+            termination_marker = Some(result);
+            continue;
+        }
+        if NAG_RE.is_match(token) {
+            continue;
+        }
+        let san = MOVE_NUMBER_RE.replace(token, "");
+        if san.is_empty() {
+            continue;
+        }
+        if !SAN_RE.is_match(&san) {
+            return Err(Error::InvalidPgn(format!("Invalid move: {san}")));
+        }
+        moves.push(san.into_owned());
+    }
+
+    Ok((moves, termination_marker))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    test_serde! {ChessGame, test_chess_game_serde}
+
+    #[test]
+    fn test_chess_game_accessors() {
+        let game = ChessGame::mock();
+        assert_eq!(game.white(), Some("Alice"));
+        assert_eq!(game.black(), Some("Bob"));
+        assert_eq!(game.result(), Some(ChessResult::WhiteWins));
+        assert_eq!(game.termination(), Some("normal"));
+        assert_eq!(game.moves.len(), 7);
+        assert_eq!(game.moves[6], "Qxf7#");
+    }
+
+    #[test]
+    fn test_chess_game_movetext_only() {
+        let game = ChessGame::try_from_pgn("1. e4 *").unwrap();
+        assert_eq!(game.white(), None);
+        assert_eq!(game.result(), Some(ChessResult::Unknown));
+
+        let game = ChessGame::try_from_pgn(
+            "1.d4 {a comment} d5 (1...Nf6 2. c4) 2.c4 $1 dxc4 3. O-O-O+ 1/2-1/2",
+        )
+        .unwrap();
+        assert_eq!(game.moves, vec!["d4", "d5", "c4", "dxc4", "O-O-O+"]);
+        assert_eq!(game.result(), Some(ChessResult::Draw));
+    }
+
+    #[test]
+    fn test_chess_game_invalid() {
+        assert!(ChessGame::try_from_pgn("").is_err());
+        assert!(ChessGame::try_from_pgn("[White Alice]\n\n1. e4 *").is_err());
+        assert!(ChessGame::try_from_pgn("1. e4 e9 *").is_err());
+        assert!(ChessGame::try_from_pgn("1. e4 {unterminated").is_err());
+        assert!(ChessGame::try_from_pgn("1. e4 1-0 e5").is_err());
+        assert!(ChessGame::try_from_pgn("[Result \"0-1\"]\n\n1. e4 1-0").is_err());
+        assert!(ChessGame::try_from_pgn("buy my coin").is_err());
+    }
+}
@@ -1,10 +1,11 @@
-use super::{Event, Filter, SubscriptionId};
+use super::{Event, Filter, RelayLimitation, SubscriptionId};
 use serde::de::Error as DeError;
 use serde::de::{Deserialize, Deserializer, SeqAccess, Visitor};
 use serde::ser::{Serialize, SerializeSeq, Serializer};
 #[cfg(feature = "speedy")]
 use speedy::{Readable, Writable};
 use std::fmt;
+use std::io;
 
 /// A message from a client to a relay
 #[derive(Clone, Debug, Eq, PartialEq)]
@@ -21,9 +22,72 @@ pub enum ClientMessage {
 
     /// Used to send authentication events
     Auth(Box<Event>),
+
+    /// A request for the number of events matching the filters (NIP-45)
+    Count(SubscriptionId, Vec<Filter>),
 }
 
 impl ClientMessage {
+    /// The size of the message as JSON, in bytes, as a relay would measure it
+    /// against its `max_message_length`. This does not allocate.
+    pub fn estimated_size(&self) -> usize {
+        json_len(self)
+    }
+
+    /// Split a REQ or COUNT message into messages which fit the relay's
+    /// limits on the number of filters and the length of a message (NIP-11).
+    /// Filters too long to fit a message are split, by halving their longest
+    /// lists, until they do (see `Filter::split()`).
+    ///
+    /// If more than one message is needed, each gets its own subscription id,
+    /// made by appending `:<n>` to the original, since a relay would replace a
+    /// subscription on receiving another REQ with the same id. Otherwise, and
+    /// for other messages, the message is returned unchanged.
+    pub fn split_req(&self, limitation: &RelayLimitation) -> Vec<ClientMessage> {
+        let (id, filters, message): (_, _, fn(SubscriptionId, Vec<Filter>) -> ClientMessage) =
+            match self {
+                ClientMessage::Req(id, filters) => (id, filters, ClientMessage::Req),
+                ClientMessage::Count(id, filters) => (id, filters, ClientMessage::Count),
+                _ => return vec![self.clone()],
+            };
+        let max_length = limitation.max_message_length.unwrap_or(usize::MAX);
+        let max_filters = limitation.max_filters.unwrap_or(usize::MAX).max(1);
+
+        // The room for filters, allowing for the longest subscription id we
+        // might make
+        let longest_id = SubscriptionId(format!("{}:{}", id.0, filters.len()));
+        let room = max_length.saturating_sub(message(longest_id, vec![]).estimated_size());
+
+        let mut parts = Vec::new();
+        for filter in filters {
+            fit_filter(filter.clone(), room, &mut parts);
+        }
+
+        let mut groups: Vec<Vec<Filter>> = Vec::new();
+        let mut group: Vec<Filter> = Vec::new();
+        let mut group_size = 0;
+        for filter in parts {
+            // With the comma separating it from the filter before
+            let size = json_len(&filter) + 1;
+            if !group.is_empty() && (group.len() >= max_filters || group_size + size > room) {
+                groups.push(std::mem::take(&mut group));
+                group_size = 0;
+            }
+            group_size += size;
+            group.push(filter);
+        }
+        groups.push(group);
+
+        if groups.len() == 1 {
+            return vec![message(id.clone(), groups.remove(0))];
+        }
+        groups
+            .into_iter()
+            .enumerate()
+            .map(|(n, group)| message(SubscriptionId(format!("{}:{n}", id.0)), group))
+            .collect()
+    }
+
     // Mock data for testing
     #[allow(dead_code)]
     pub(crate) fn mock() -> ClientMessage {
@@ -31,6 +95,46 @@ impl ClientMessage {
     }
 }
 
+// Split a filter until each part fits in `room` bytes, or cannot be split further
+fn fit_filter(filter: Filter, room: usize, parts: &mut Vec<Filter>) {
+    let longest = filter
+        .tags
+        .iter()
+        .map(|(_, values)| values.len())
+        .chain([filter.ids.len(), filter.authors.len(), filter.kinds.len()])
+        .max()
+        .unwrap_or(0);
+    if longest <= 1 || json_len(&filter) <= room {
+        parts.push(filter);
+        return;
+    }
+    for part in filter.split(longest.div_ceil(2)) {
+        fit_filter(part, room, parts);
+    }
+}
+
+// The length of a value as JSON
+fn json_len<T: Serialize + ?Sized>(value: &T) -> usize {
+    let mut counter = ByteCounter(0);
+    // Writing to a ByteCounter cannot fail
+    let _ = serde_json::to_writer(&mut counter, value);
+    counter.0
+}
+
+// Counts the bytes written to it
+struct ByteCounter(usize);
+
+impl io::Write for ByteCounter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0 += buf.len();
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
 impl Serialize for ClientMessage {
     fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
     where
@@ -44,7 +148,7 @@ impl Serialize for ClientMessage {
                 seq.end()
             }
             ClientMessage::Req(id, filters) => {
-                let mut seq = serializer.serialize_seq(Some(3))?;
+                let mut seq = serializer.serialize_seq(Some(2 + filters.len()))?;
                 seq.serialize_element("REQ")?;
                 seq.serialize_element(&id)?;
                 for filter in filters {
@@ -64,6 +168,15 @@ impl Serialize for ClientMessage {
                 seq.serialize_element(&event)?;
                 seq.end()
             }
+            ClientMessage::Count(id, filters) => {
+                let mut seq = serializer.serialize_seq(Some(2 + filters.len()))?;
+                seq.serialize_element("COUNT")?;
+                seq.serialize_element(&id)?;
+                for filter in filters {
+                    seq.serialize_element(&filter)?;
+                }
+                seq.end()
+            }
         }
     }
 }
@@ -121,6 +234,15 @@ impl<'de> Visitor<'de> for ClientMessageVisitor {
                 .next_element()?
                 .ok_or_else(|| DeError::custom("Message missing event field"))?;
             Ok(ClientMessage::Auth(Box::new(event)))
+        } else if word == "COUNT" {
+            let id: SubscriptionId = seq
+                .next_element()?
+                .ok_or_else(|| DeError::custom("Message missing id field"))?;
+            let mut filters: Vec<Filter> = vec![];
+            while let Some(filter) = seq.next_element()? {
+                filters.push(filter);
+            }
+            Ok(ClientMessage::Count(id, filters))
         } else {
             Err(DeError::custom(format!("Unknown Message: {word}")))
         }
@@ -130,6 +252,74 @@ impl<'de> Visitor<'de> for ClientMessageVisitor {
 #[cfg(test)]
 mod test {
     use super::*;
+    use crate::{PrivateKey, PublicKeyHex};
 
     test_serde! {ClientMessage, test_client_message_serde}
+
+    #[test]
+    fn test_client_message_wire_format() {
+        let filter = r#"{"kinds":[1],"limit":10}"#;
+        for json in [
+            format!(r#"["REQ","sub1",{filter},{{"kinds":[0]}}]"#),
+            r#"["REQ","sub1"]"#.to_owned(),
+            r#"["CLOSE","sub1"]"#.to_owned(),
+            format!(r#"["COUNT","sub1",{filter}]"#),
+        ] {
+            let message: ClientMessage = serde_json::from_str(&json).unwrap();
+            assert_eq!(serde_json::to_string(&message).unwrap(), json);
+        }
+
+        let message: ClientMessage =
+            serde_json::from_str(&format!(r#"["COUNT","sub1",{filter}]"#)).unwrap();
+        assert!(matches!(message, ClientMessage::Count(_, filters) if filters.len() == 1));
+
+        let auth = ClientMessage::Auth(Box::new(Event::mock()));
+        let json = serde_json::to_string(&auth).unwrap();
+        assert!(json.starts_with(r#"["AUTH",{"#));
+        assert_eq!(serde_json::from_str::<ClientMessage>(&json).unwrap(), auth);
+
+        assert!(serde_json::from_str::<ClientMessage>(r#"["NOPE","sub1"]"#).is_err());
+        assert!(serde_json::from_str::<ClientMessage>(r#"["CLOSE"]"#).is_err());
+    }
+
+    #[test]
+    fn test_split_req() {
+        let mut filter = Filter::new();
+        for _ in 0..500 {
+            filter.add_author(PublicKeyHex::from(PrivateKey::generate().public_key()));
+        }
+        let req = ClientMessage::Req(SubscriptionId("contacts".to_owned()), vec![filter]);
+        let size = req.estimated_size();
+        assert_eq!(size, serde_json::to_string(&req).unwrap().len());
+
+        // Unlimited relays take it whole
+        assert_eq!(
+            req.split_req(&RelayLimitation::default()),
+            vec![req.clone()]
+        );
+
+        let limitation = RelayLimitation {
+            max_message_length: Some(16384),
+            max_filters: Some(2),
+            ..Default::default()
+        };
+        let messages = req.split_req(&limitation);
+        assert!(messages.len() > 1);
+        let mut authors = 0;
+        for (n, message) in messages.iter().enumerate() {
+            assert!(message.estimated_size() <= 16384);
+            match message {
+                ClientMessage::Req(id, filters) => {
+                    assert_eq!(id.0, format!("contacts:{n}"));
+                    assert!(filters.len() <= 2);
+                    authors += filters.iter().map(|f| f.authors.len()).sum::<usize>();
+                }
+                _ => panic!("expected REQ"),
+            }
+        }
+        assert_eq!(authors, 500);
+
+        let close = ClientMessage::Close(SubscriptionId("contacts".to_owned()));
+        assert_eq!(close.split_req(&limitation), vec![close]);
+    }
 }
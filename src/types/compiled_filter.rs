@@ -0,0 +1,202 @@
+use super::{Event, Filter, Id, SearchMatcher, SubstringSearch, Tag, Unixtime, XOnlyPublicKey};
+use std::collections::HashSet;
+
+/// A `Filter` prepared for matching many events, made with `Filter::compile()`.
+///
+/// The ids, authors, kinds and tag values of the filter are put into hash sets
+/// once, so that matching an event costs a few lookups rather than scans of
+/// the filter's lists and hex conversions of the event's id and author. This
+/// suits relays matching every incoming event against many live subscriptions.
+///
+/// It matches exactly as `Filter::event_matches()` does.
+#[derive(Clone, Debug)]
+pub struct CompiledFilter {
+    ids: Option<HashSet<Id>>,
+    authors: Option<HashSet<XOnlyPublicKey>>,
+    kinds: Option<HashSet<u64>>,
+    tags: Vec<(String, HashSet<String>)>,
+    since: Option<Unixtime>,
+    until: Option<Unixtime>,
+    search: Option<String>,
+}
+
+impl CompiledFilter {
+    /// Compile a filter. Ids and authors which are not full-length hex (such as
+    /// prefixes) can never match exactly, so they are dropped.
+    pub fn new(filter: &Filter) -> CompiledFilter {
+        let ids = (!filter.ids.is_empty()).then(|| {
+            filter
+                .ids
+                .iter()
+                .filter_map(|id| Id::try_from_hex_string(id).ok())
+                .collect()
+        });
+        let authors = (!filter.authors.is_empty()).then(|| {
+            filter
+                .authors
+                .iter()
+                .filter_map(|author| XOnlyPublicKey::try_from_hex_string(author).ok())
+                .collect()
+        });
+        let kinds = (!filter.kinds.is_empty())
+            .then(|| filter.kinds.iter().map(|kind| u64::from(*kind)).collect());
+        let tags = filter
+            .tags
+            .iter()
+            .map(|(letter, values)| (letter.to_string(), values.iter().cloned().collect()))
+            .collect();
+
+        CompiledFilter {
+            ids,
+            authors,
+            kinds,
+            tags,
+            since: filter.since,
+            until: filter.until,
+            search: filter.search.clone(),
+        }
+    }
+
+    /// Whether an event matches the filter (see `Filter::event_matches()`)
+    pub fn event_matches(&self, event: &Event) -> bool {
+        self.event_matches_with_search(event, &SubstringSearch)
+    }
+
+    /// Whether an event matches the filter, matching any `search` query with the
+    /// given `SearchMatcher`
+    pub fn event_matches_with_search<M: SearchMatcher + ?Sized>(
+        &self,
+        event: &Event,
+        matcher: &M,
+    ) -> bool {
+        if self.since.is_some_and(|since| event.created_at < since)
+            || self.until.is_some_and(|until| event.created_at > until)
+        {
+            return false;
+        }
+
+        if let Some(kinds) = &self.kinds {
+            if !kinds.contains(&u64::from(event.kind)) {
+                return false;
+            }
+        }
+
+        if let Some(ids) = &self.ids {
+            if !ids.contains(&event.id) {
+                return false;
+            }
+        }
+
+        if let Some(authors) = &self.authors {
+            if !authors.contains(&event.pubkey.into()) {
+                return false;
+            }
+        }
+
+        if let Some(query) = &self.search {
+            if !matcher.matches(query, event) {
+                return false;
+            }
+        }
+
+        self.tags.iter().all(|(name, values)| {
+            event.tags.iter().any(|tag| {
+                !matches!(tag, Tag::Empty)
+                    && tag.tagname() == *name
+                    && tag.value().is_some_and(|value| values.contains(&value))
+            })
+        })
+    }
+}
+
+impl From<&Filter> for CompiledFilter {
+    fn from(filter: &Filter) -> CompiledFilter {
+        CompiledFilter::new(filter)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{EventBuilder, EventKind, IdHex, PrivateKey, PublicKeyHex};
+
+    #[test]
+    fn test_compiled_filter() {
+        let private_key = PrivateKey::generate();
+        let event = EventBuilder::new()
+            .content("Hello")
+            .created_at(Unixtime(1_700_000_000))
+            .tag(Tag::Hashtag("nostr".to_owned()))
+            .sign(&private_key)
+            .unwrap();
+        let id: IdHex = event.id.into();
+        let author: PublicKeyHex = event.pubkey.into();
+
+        let mut filters = vec![Filter::new()];
+        let mut filter = Filter::new();
+        filter.add_id(id.clone());
+        filter.add_author(author.clone());
+        filter.add_event_kind(EventKind::TextNote);
+        filter.add_tag_value('t', "nostr".to_owned());
+        filter.since = Some(Unixtime(1_700_000_000));
+        filters.push(filter.clone());
+        filter.add_id(IdHex::mock());
+        filters.push(filter.clone());
+        filter.ids = vec![id.prefix(10)];
+        filters.push(filter.clone());
+        filter.ids = vec![];
+        filter.add_tag_value('p', author.into_string());
+        filters.push(filter.clone());
+        filter.tags.set('p', vec![]);
+        filter.kinds = vec![EventKind::Reaction];
+        filters.push(filter.clone());
+        filter.kinds = vec![];
+        filter.search = Some("hello".to_owned());
+        filters.push(filter.clone());
+        filter.until = Some(Unixtime(1_699_999_999));
+        filters.push(filter);
+
+        let expected = [true, true, true, false, false, false, true, false];
+        for (filter, expected) in filters.iter().zip(expected) {
+            assert_eq!(filter.event_matches(&event), expected, "{filter:?}");
+            assert_eq!(filter.compile().event_matches(&event), expected);
+        }
+    }
+
+    #[test]
+    fn test_compiled_filter_mixed_case() {
+        let private_key = PrivateKey::generate();
+        let event = EventBuilder::new()
+            .content("Hello")
+            .sign(&private_key)
+            .unwrap();
+        let id: IdHex = event.id.into();
+        let author: PublicKeyHex = event.pubkey.into();
+
+        // Mixed-case hex, and an upper-case id which does not match
+        let mixed = |hex: &str| {
+            let (start, end) = (hex.get(..32).unwrap(), hex.get(32..).unwrap());
+            format!("{}{end}", start.to_uppercase())
+        };
+        let other = IdHex::mock().as_str().to_uppercase();
+        for json in [
+            format!(r#"{{"ids":["{}"]}}"#, mixed(id.as_str())),
+            format!(r#"{{"authors":["{}"]}}"#, mixed(author.as_str())),
+            format!(
+                r#"{{"ids":["{other}"],"authors":["{}"]}}"#,
+                mixed(author.as_str())
+            ),
+        ] {
+            let filter: Filter = serde_json::from_str(&json).unwrap();
+            assert_eq!(
+                filter.event_matches(&event),
+                filter.compile().event_matches(&event),
+                "{json}"
+            );
+        }
+        let filter: Filter =
+            serde_json::from_str(&format!(r#"{{"ids":["{}"]}}"#, mixed(id.as_str()))).unwrap();
+        assert!(filter.event_matches(&event));
+        assert!(filter.compile().event_matches(&event));
+    }
+}
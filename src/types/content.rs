@@ -1,8 +1,20 @@
-use super::{find_nostr_bech32_pos, NostrBech32, NostrUrl};
+use super::{
+    find_nostr_bech32_pos, EventAddr, EventPointer, NostrBech32, NostrUrl, ProfilePointer,
+    PublicKey, Tag,
+};
+use crate::Error;
 use lazy_static::lazy_static;
 use linkify::{LinkFinder, LinkKind};
 use regex::Regex;
 
+lazy_static! {
+    // A legacy NIP-08 mention
+    static ref TAG_RE: Regex = Regex::new(r"(\#\[\d+\])").unwrap();
+
+    // A mention placeholder in a template for compose_mentions()
+    static ref MENTION_RE: Regex = Regex::new(r"\{(\d+)\}").unwrap();
+}
+
 /// This is like Range<usize>, except we impl offset() on it
 /// This is like linkify::Span, except we impl offset() on it and don't need
 ///   the as_str() or kind() functions.
@@ -26,7 +38,8 @@ pub enum ContentSegment {
     /// A Nostr URL
     NostrUrl(NostrUrl),
 
-    /// A reference to an event tag by index
+    /// A reference to an event tag by index, a legacy mention (NIP-08). See
+    /// `resolve_tag_reference()`.
     TagReference(usize),
 
     /// A hyperlink
@@ -112,20 +125,19 @@ fn shatter_content_1(mut content: &str) -> Vec<ContentSegment> {
 // Pass 2 - `TagReference`s
 #[allow(clippy::string_slice)] // Regex positions are trusted
 fn shatter_content_2(content: &str) -> Vec<ContentSegment> {
-    lazy_static! {
-        static ref TAG_RE: Regex = Regex::new(r"(\#\[\d+\])").unwrap();
-    }
-
     let mut segments: Vec<ContentSegment> = Vec::new();
 
     let mut pos = 0;
     for mat in TAG_RE.find_iter(content) {
+        // An index too large for a usize is left as plain text
+        let Some(u) = tag_reference_index(mat.as_str()) else {
+            continue;
+        };
+
         let mut inner_segments = shatter_content_3(&content[pos..mat.start()]);
         apply_offset(&mut inner_segments, pos);
         segments.append(&mut inner_segments);
 
-        // If panics on unwrap, something is wrong with Regex.
-        let u: usize = content[mat.start() + 2..mat.end() - 1].parse().unwrap();
         segments.push(ContentSegment::TagReference(u));
         pos = mat.end();
     }
@@ -157,6 +169,106 @@ fn shatter_content_3(content: &str) -> Vec<ContentSegment> {
     segments
 }
 
+// The index in a `#[index]` matched by TAG_RE
+fn tag_reference_index(mention: &str) -> Option<usize> {
+    mention.get(2..mention.len() - 1)?.parse().ok()
+}
+
+/// Resolve a legacy mention (NIP-08), `#[index]`, against the tags of its
+/// event. A 'p' tag gives an npub (or an nprofile, if it has a relay hint), an
+/// 'e' tag a note (or an nevent), and an 'a' tag an naddr. Other tags, 'a'
+/// tags which cannot be encoded as an naddr, and indices out of range, give
+/// `None`.
+pub fn resolve_tag_reference(tags: &[Tag], index: usize) -> Option<NostrBech32> {
+    let tag = tags.get(index)?;
+    match tag {
+        Tag::Pubkey {
+            pubkey,
+            recommended_relay_url,
+            ..
+        } => {
+            let pubkey = PublicKey::try_from(pubkey.clone()).ok()?;
+            Some(match recommended_relay_url {
+                Some(relay) => NostrBech32::Profile(ProfilePointer {
+                    pubkey,
+                    relays: vec![relay.clone()],
+                }),
+                None => NostrBech32::Pubkey(pubkey),
+            })
+        }
+        Tag::Event {
+            id,
+            recommended_relay_url,
+            ..
+        } => Some(match recommended_relay_url {
+            Some(relay) => NostrBech32::EventPointer(EventPointer {
+                id: *id,
+                relays: vec![relay.clone()],
+                author: None,
+                kind: None,
+            }),
+            None => NostrBech32::Id(*id),
+        }),
+        Tag::Address { .. } => EventAddr::try_from_tag(tag)
+            .ok()
+            .and_then(|addr| NostrBech32::new_event_addr(addr).ok()),
+        _ => None,
+    }
+}
+
+/// Rewrite the legacy mentions (NIP-08) in content, `#[index]`, as `nostr:`
+/// URIs (NIP-27), resolving them against the tags of its event with
+/// `resolve_tag_reference()`. Mentions which do not resolve are left as they
+/// are.
+pub fn rewrite_legacy_mentions(content: &str, tags: &[Tag]) -> String {
+    TAG_RE
+        .replace_all(content, |caps: &regex::Captures<'_>| {
+            let mention = &caps[0];
+            tag_reference_index(mention)
+                .and_then(|index| resolve_tag_reference(tags, index))
+                .map(|bech32| NostrUrl(bech32).to_string())
+                .unwrap_or_else(|| mention.to_owned())
+        })
+        .into_owned()
+}
+
+/// Compose content which mentions people (NIP-27), along with the 'p' tags
+/// that go with it. Each `{index}` in the template is replaced by a `nostr:`
+/// URI for `profiles[index]`: an nprofile if it has relays, otherwise an npub.
+///
+/// The tags hold one 'p' tag (see `ProfilePointer::to_tag()`) for each person
+/// mentioned, in the order they are first mentioned, and no others, so that the
+/// content and tags agree. Profiles which are not mentioned get no tag.
+pub fn compose_mentions(
+    template: &str,
+    profiles: &[ProfilePointer],
+) -> Result<(String, Vec<Tag>), Error> {
+    let mut tags: Vec<Tag> = Vec::new();
+    let mut mentioned: Vec<PublicKey> = Vec::new();
+    let mut invalid: Option<usize> = None;
+    let content = MENTION_RE.replace_all(template, |caps: &regex::Captures<'_>| {
+        let index = caps[1].parse::<usize>().unwrap_or(usize::MAX);
+        let Some(profile) = profiles.get(index) else {
+            let _ = invalid.get_or_insert(index);
+            return String::new();
+        };
+        if !mentioned.contains(&profile.pubkey) {
+            mentioned.push(profile.pubkey);
+            tags.push(profile.to_tag());
+        }
+        let bech32 = if profile.relays.is_empty() {
+            NostrBech32::Pubkey(profile.pubkey)
+        } else {
+            NostrBech32::Profile(profile.clone())
+        };
+        NostrUrl(bech32).to_string()
+    });
+    if let Some(index) = invalid {
+        return Err(Error::InvalidMention(index));
+    }
+    Ok((content.into_owned(), tags))
+}
+
 fn apply_offset(segments: &mut [ContentSegment], offset: usize) {
     for segment in segments.iter_mut() {
         match segment {
@@ -171,6 +283,118 @@ fn apply_offset(segments: &mut [ContentSegment], offset: usize) {
 mod test {
     use super::*;
 
+    #[test]
+    fn test_compose_mentions() {
+        let alice = ProfilePointer::mock();
+        let bob = ProfilePointer {
+            pubkey: PublicKey::mock(),
+            relays: vec![],
+        };
+        let (content, tags) = compose_mentions(
+            "Hi {1}, meet {0}. Thanks {1}! {x} {}",
+            &[alice.clone(), bob.clone()],
+        )
+        .unwrap();
+        assert_eq!(
+            content,
+            format!(
+                "Hi nostr:{}, meet nostr:{}. Thanks nostr:{}! {{x}} {{}}",
+                bob.pubkey.as_bech32_string(),
+                alice.as_bech32_string(),
+                bob.pubkey.as_bech32_string()
+            )
+        );
+        assert_eq!(tags, vec![bob.to_tag(), alice.to_tag()]);
+
+        // The content and tags agree
+        let mentioned: Vec<PublicKey> = NostrUrl::find_all_in_string(&content)
+            .into_iter()
+            .filter_map(|url| match url.0 {
+                NostrBech32::Pubkey(pubkey) => Some(pubkey),
+                NostrBech32::Profile(profile) => Some(profile.pubkey),
+                _ => None,
+            })
+            .collect();
+        for tag in tags.iter() {
+            let profile = ProfilePointer::try_from_tag(tag).unwrap();
+            assert!(mentioned.contains(&profile.pubkey));
+        }
+
+        let (content, tags) = compose_mentions("No one", std::slice::from_ref(&alice)).unwrap();
+        assert_eq!(content, "No one");
+        assert!(tags.is_empty());
+
+        assert!(matches!(
+            compose_mentions("Hi {2}", &[alice, bob]),
+            Err(Error::InvalidMention(2))
+        ));
+        assert!(matches!(
+            compose_mentions("Hi {99999999999999999999999}", &[]),
+            Err(Error::InvalidMention(_))
+        ));
+    }
+
+    #[test]
+    fn test_legacy_mentions() {
+        let pubkey = PublicKey::mock();
+        let id = crate::Id::mock();
+        let relay = crate::UncheckedUrl::from_str("wss://relay.example.com");
+        let addr = EventAddr::mock();
+        let tags = vec![
+            Tag::Pubkey {
+                pubkey: pubkey.into(),
+                recommended_relay_url: None,
+                petname: None,
+            },
+            Tag::Event {
+                id,
+                recommended_relay_url: Some(relay.clone()),
+                marker: None,
+            },
+            Tag::Hashtag("nostr".to_owned()),
+            addr.to_tag(),
+        ];
+        let content = "Hi #[0], see #[1] and #[3]; #[2] #[4] #[99999999999999999999999]";
+        let rewritten = rewrite_legacy_mentions(content, &tags);
+        let nevent = EventPointer {
+            id,
+            relays: vec![relay],
+            author: None,
+            kind: None,
+        }
+        .as_bech32_string();
+        assert_eq!(
+            rewritten,
+            format!(
+                "Hi nostr:{}, see nostr:{} and nostr:{}; #[2] #[4] #[99999999999999999999999]",
+                pubkey.as_bech32_string(),
+                nevent,
+                addr.as_bech32_string().unwrap()
+            )
+        );
+        assert!(matches!(
+            resolve_tag_reference(&tags, 0),
+            Some(NostrBech32::Pubkey(_))
+        ));
+        assert!(resolve_tag_reference(&tags, 2).is_none());
+
+        // An 'a' tag whose 'd' is too long for an naddr is left as it is
+        let mut long = addr.clone();
+        long.d = "x".repeat(300);
+        let tags = vec![long.to_tag()];
+        assert!(resolve_tag_reference(&tags, 0).is_none());
+        assert_eq!(rewrite_legacy_mentions("see #[0]", &tags), "see #[0]");
+
+        // Indices too large are plain text, not a panic
+        let pieces = ShatteredContent::new(content.to_owned());
+        let references = pieces
+            .segments
+            .iter()
+            .filter(|s| matches!(s, ContentSegment::TagReference(_)))
+            .count();
+        assert_eq!(references, 5);
+    }
+
     #[test]
     fn test_shatter_content() {
         let content_str = "My friend #[0]  wrote me this note: nostr:note10ttnuuvcs29y3k23gwrcurw2ksvgd7c2rrqlfx7urmt5m963vhss8nja90 and it might have referred to https://github.com/Giszmo/nostr.info/blob/master/assets/js/main.js";
@@ -0,0 +1,366 @@
+use super::nip44;
+use super::private_key::{nip04_decrypt_with_shared_x, nip04_encrypt_with_shared_x};
+use super::{PrivateKey, PublicKey, XOnlyPublicKey};
+use crate::Error;
+use base64::Engine;
+use rand_core::{OsRng, RngCore};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::{Arc, Mutex};
+use zeroize::Zeroize;
+
+/// An algorithm for encrypting event content to a counterparty
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum ContentEncryptionAlgorithm {
+    /// NIP-04: AES-256-CBC, rendered as `<base64 ciphertext>?iv=<base64 iv>`.
+    /// This is deprecated as it is not authenticated and leaks the message length.
+    Nip04,
+
+    /// NIP-44 version 2: ChaCha20 with HMAC-SHA256 and padding, rendered as
+    /// version-prefixed base64
+    Nip44v2,
+}
+
+/// Encrypted event content, as found in the content of an encrypted direct
+/// message or gift wrap
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct EncryptedContent(pub String);
+
+impl EncryptedContent {
+    /// Detect which algorithm encrypted `content` from the shape of the payload,
+    /// without attempting to decrypt it. This returns `None` if the content does
+    /// not look like the output of any supported algorithm.
+    pub fn detect(content: &str) -> Option<ContentEncryptionAlgorithm> {
+        if content.contains("?iv=") {
+            // NIP-04: <base64 ciphertext>?iv=<base64 16-byte iv>
+            let mut parts = content.split("?iv=");
+            let (ciphertext, iv) = match (parts.next(), parts.next(), parts.next()) {
+                (Some(ciphertext), Some(iv), None) => (ciphertext, iv),
+                _ => return None,
+            };
+            let ciphertext = base64::engine::general_purpose::STANDARD
+                .decode(ciphertext)
+                .ok()?;
+            let iv = base64::engine::general_purpose::STANDARD.decode(iv).ok()?;
+            if ciphertext.is_empty() || ciphertext.len() % 16 != 0 || iv.len() != 16 {
+                return None;
+            }
+            Some(ContentEncryptionAlgorithm::Nip04)
+        } else {
+            // NIP-44: base64 of a version byte, then nonce, padded ciphertext and mac
+            let data = base64::engine::general_purpose::STANDARD
+                .decode(content)
+                .ok()?;
+            match data.first() {
+                Some(&nip44::VERSION) if data.len() >= nip44::MIN_PAYLOAD_LEN => {
+                    Some(ContentEncryptionAlgorithm::Nip44v2)
+                }
+                _ => None,
+            }
+        }
+    }
+
+    /// The algorithm that encrypted this content, if it can be detected
+    pub fn algorithm(&self) -> Option<ContentEncryptionAlgorithm> {
+        EncryptedContent::detect(&self.0)
+    }
+}
+
+// The secrets shared with one counterparty, from which content is encrypted
+// and decrypted
+struct ConversationKeys {
+    // The x coordinate of the ECDH shared point, used directly by NIP-04
+    shared_x: [u8; 32],
+
+    // The NIP-44 conversation key
+    nip44: [u8; 32],
+}
+
+impl ConversationKeys {
+    fn new(private_key: &PrivateKey, other: &PublicKey) -> ConversationKeys {
+        let shared_x = private_key.shared_x(other);
+        let nip44 = nip44::conversation_key(&shared_x);
+        ConversationKeys { shared_x, nip44 }
+    }
+
+    fn encrypt(
+        &self,
+        plaintext: &str,
+        algorithm: ContentEncryptionAlgorithm,
+    ) -> Result<String, Error> {
+        match algorithm {
+            ContentEncryptionAlgorithm::Nip04 => {
+                let (iv, ciphertext) =
+                    nip04_encrypt_with_shared_x(&self.shared_x, plaintext.as_bytes());
+                Ok(format!(
+                    "{}?iv={}",
+                    base64::engine::general_purpose::STANDARD.encode(ciphertext),
+                    base64::engine::general_purpose::STANDARD.encode(iv)
+                ))
+            }
+            ContentEncryptionAlgorithm::Nip44v2 => {
+                let mut nonce: [u8; 32] = [0; 32];
+                OsRng.fill_bytes(&mut nonce);
+                nip44::encrypt(&self.nip44, plaintext, &nonce)
+            }
+        }
+    }
+
+    fn decrypt_auto(&self, ciphertext: &str) -> Result<String, Error> {
+        match EncryptedContent::detect(ciphertext) {
+            Some(ContentEncryptionAlgorithm::Nip04) => {
+                let mut parts = ciphertext.split("?iv=");
+                let (ciphertext, iv) = match (parts.next(), parts.next(), parts.next()) {
+                    (Some(ciphertext), Some(iv), None) => (ciphertext, iv),
+                    _ => return Err(Error::BadEncryptedMessage),
+                };
+                let ciphertext = base64::engine::general_purpose::STANDARD.decode(ciphertext)?;
+                let iv: [u8; 16] = base64::engine::general_purpose::STANDARD
+                    .decode(iv)?
+                    .try_into()
+                    .map_err(|_| Error::BadEncryptedMessage)?;
+                let mut plaintext = nip04_decrypt_with_shared_x(&self.shared_x, &ciphertext, iv)?;
+                let output = std::str::from_utf8(&plaintext).map(|s| s.to_owned());
+                plaintext.zeroize();
+                Ok(output?)
+            }
+            Some(ContentEncryptionAlgorithm::Nip44v2) => nip44::decrypt(&self.nip44, ciphertext),
+            None => Err(Error::BadEncryptedMessage),
+        }
+    }
+}
+
+impl Drop for ConversationKeys {
+    fn drop(&mut self) {
+        self.shared_x.zeroize();
+        self.nip44.zeroize();
+    }
+}
+
+impl PrivateKey {
+    /// Encrypt content for `other` with the given algorithm, rendering it as a
+    /// string suitable for an event's content
+    pub fn encrypt(
+        &self,
+        other: &PublicKey,
+        plaintext: &str,
+        algorithm: ContentEncryptionAlgorithm,
+    ) -> Result<String, Error> {
+        ConversationKeys::new(self, other).encrypt(plaintext, algorithm)
+    }
+
+    /// Decrypt content from `other`, detecting which algorithm was used
+    pub fn decrypt_auto(&self, other: &PublicKey, ciphertext: &str) -> Result<String, Error> {
+        ConversationKeys::new(self, other).decrypt_auto(ciphertext)
+    }
+}
+
+/// A bounded cache of the secrets shared with counterparties, so that encrypting
+/// to or decrypting from the same counterparty many times (e.g. a large DM
+/// backlog) does not repeat the ECDH computation each time.
+///
+/// Entries are keyed by both our own and the counterparty's public key, so one
+/// cache may be used with several private keys. When the cache is full, the
+/// least recently used entry is evicted.
+pub struct ConversationKeyCache {
+    capacity: usize,
+    inner: Mutex<CacheInner>,
+}
+
+#[derive(Default)]
+struct CacheInner {
+    tick: u64,
+    entries: HashMap<(XOnlyPublicKey, XOnlyPublicKey), (Arc<ConversationKeys>, u64)>,
+}
+
+impl fmt::Debug for ConversationKeyCache {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ConversationKeyCache")
+            .field("capacity", &self.capacity)
+            .field("len", &self.len())
+            .finish()
+    }
+}
+
+impl ConversationKeyCache {
+    /// Create a new cache holding up to `capacity` conversations
+    pub fn new(capacity: usize) -> ConversationKeyCache {
+        ConversationKeyCache {
+            capacity: capacity.max(1),
+            inner: Mutex::new(CacheInner::default()),
+        }
+    }
+
+    /// The number of cached conversations
+    pub fn len(&self) -> usize {
+        self.inner.lock().unwrap().entries.len()
+    }
+
+    /// Whether the cache is empty
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Remove all cached conversations
+    pub fn clear(&self) {
+        self.inner.lock().unwrap().entries.clear();
+    }
+
+    /// Compute and cache the conversation keys for each of `others` in advance
+    pub fn precompute(&self, private_key: &PrivateKey, others: &[PublicKey]) {
+        for other in others {
+            let _ = self.get(private_key, other);
+        }
+    }
+
+    /// Encrypt content for `other` with the given algorithm (see `PrivateKey::encrypt()`)
+    pub fn encrypt(
+        &self,
+        private_key: &PrivateKey,
+        other: &PublicKey,
+        plaintext: &str,
+        algorithm: ContentEncryptionAlgorithm,
+    ) -> Result<String, Error> {
+        self.get(private_key, other).encrypt(plaintext, algorithm)
+    }
+
+    /// Decrypt content from `other`, detecting which algorithm was used (see
+    /// `PrivateKey::decrypt_auto()`)
+    pub fn decrypt_auto(
+        &self,
+        private_key: &PrivateKey,
+        other: &PublicKey,
+        ciphertext: &str,
+    ) -> Result<String, Error> {
+        self.get(private_key, other).decrypt_auto(ciphertext)
+    }
+
+    fn get(&self, private_key: &PrivateKey, other: &PublicKey) -> Arc<ConversationKeys> {
+        let key = (private_key.public_key().into(), (*other).into());
+
+        {
+            let mut inner = self.inner.lock().unwrap();
+            inner.tick += 1;
+            let tick = inner.tick;
+            if let Some((keys, last_used)) = inner.entries.get_mut(&key) {
+                *last_used = tick;
+                return keys.clone();
+            }
+        }
+
+        // Compute outside of the lock
+        let keys = Arc::new(ConversationKeys::new(private_key, other));
+
+        let mut inner = self.inner.lock().unwrap();
+        if inner.entries.len() >= self.capacity && !inner.entries.contains_key(&key) {
+            let oldest = inner
+                .entries
+                .iter()
+                .min_by_key(|(_, (_, last_used))| *last_used)
+                .map(|(k, _)| *k);
+            if let Some(oldest) = oldest {
+                let _ = inner.entries.remove(&oldest);
+            }
+        }
+        inner.tick += 1;
+        let tick = inner.tick;
+        let _ = inner.entries.insert(key, (keys.clone(), tick));
+        keys
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_encrypt_decrypt_auto() {
+        let sender = PrivateKey::generate();
+        let receiver = PrivateKey::generate();
+
+        for algorithm in [
+            ContentEncryptionAlgorithm::Nip04,
+            ContentEncryptionAlgorithm::Nip44v2,
+        ] {
+            let ciphertext = sender
+                .encrypt(&receiver.public_key(), "Hello there", algorithm)
+                .unwrap();
+            assert_eq!(EncryptedContent::detect(&ciphertext), Some(algorithm));
+            let plaintext = receiver
+                .decrypt_auto(&sender.public_key(), &ciphertext)
+                .unwrap();
+            assert_eq!(plaintext, "Hello there");
+        }
+
+        assert!(receiver
+            .decrypt_auto(&sender.public_key(), "not encrypted")
+            .is_err());
+        assert!(receiver
+            .decrypt_auto(&sender.public_key(), "abc?iv=def?iv=ghi")
+            .is_err());
+    }
+
+    #[test]
+    fn test_encrypted_content_detect() {
+        // NIP-44 test vector payload
+        let nip44 = "AgAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAABee0G5VSK0/9YypIObAtDKfYEAjD35uVkHyB0F4DwrcNaCXlCWZKaArsGrY6M9wnuTMxWfp1RTN9Xga8no+kF5Vsb";
+        assert_eq!(
+            EncryptedContent::detect(nip44),
+            Some(ContentEncryptionAlgorithm::Nip44v2)
+        );
+
+        let nip04 = "zJxfaJ32rN5Dg1ODjOlEew==?iv=EV5bUjcc4OX2Km/zPp4ndQ==";
+        assert_eq!(
+            EncryptedContent::detect(nip04),
+            Some(ContentEncryptionAlgorithm::Nip04)
+        );
+        assert_eq!(
+            EncryptedContent(nip04.to_owned()).algorithm(),
+            Some(ContentEncryptionAlgorithm::Nip04)
+        );
+
+        // Plaintext, a bad iv, and an unknown NIP-44 version
+        assert_eq!(EncryptedContent::detect("Hello there"), None);
+        assert_eq!(
+            EncryptedContent::detect("zJxfaJ32rN5Dg1ODjOlEew==?iv=AAAA"),
+            None
+        );
+        assert_eq!(EncryptedContent::detect(&format!("#{}", nip44)), None);
+        assert_eq!(EncryptedContent::detect("AQID"), None);
+    }
+
+    #[test]
+    fn test_conversation_key_cache() {
+        let me = PrivateKey::generate();
+        let friends: Vec<PrivateKey> = (0..3).map(|_| PrivateKey::generate()).collect();
+        let friend_keys: Vec<PublicKey> = friends.iter().map(|f| f.public_key()).collect();
+
+        let cache = ConversationKeyCache::new(2);
+        cache.precompute(&me, &friend_keys[0..2]);
+        assert_eq!(cache.len(), 2);
+
+        for (friend, friend_key) in friends.iter().zip(&friend_keys) {
+            for algorithm in [
+                ContentEncryptionAlgorithm::Nip04,
+                ContentEncryptionAlgorithm::Nip44v2,
+            ] {
+                let ciphertext = friend.encrypt(&me.public_key(), "hi", algorithm).unwrap();
+                let plaintext = cache.decrypt_auto(&me, friend_key, &ciphertext).unwrap();
+                assert_eq!(plaintext, "hi");
+
+                let ciphertext = cache.encrypt(&me, friend_key, "hey", algorithm).unwrap();
+                let plaintext = friend.decrypt_auto(&me.public_key(), &ciphertext).unwrap();
+                assert_eq!(plaintext, "hey");
+            }
+        }
+
+        // The least recently used entry (friend 0) was evicted
+        assert_eq!(cache.len(), 2);
+        let inner = cache.inner.lock().unwrap();
+        assert!(!inner
+            .entries
+            .contains_key(&(me.public_key().into(), friend_keys[0].into())));
+    }
+}
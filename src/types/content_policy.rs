@@ -0,0 +1,432 @@
+use super::tag::TagElement;
+use super::{Event, EventKind, Reason, ReasonPrefix, Tag};
+use std::borrow::Cow;
+use std::fmt;
+
+/// Limits on the events a relay (or client) is willing to accept, checked with
+/// `Event::validate_against()`. Limits which are `None` (or `false`) are not
+/// checked; the default checks nothing.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct ContentPolicy {
+    /// The most bytes of content
+    pub max_bytes: Option<usize>,
+
+    /// Reject NUL characters in the content or in tags
+    pub forbid_nul: bool,
+
+    /// The most tags
+    pub max_tags: Option<usize>,
+
+    /// The most bytes in any one string of a tag (its name or a value)
+    pub max_tag_length: Option<usize>,
+
+    /// The most strings in any one tag, counting its name
+    pub max_tag_values: Option<usize>,
+
+    /// Reject tags with no strings at all (`[]`)
+    pub forbid_empty_tags: bool,
+
+    /// The only kinds accepted, if limited
+    pub allowed_kinds: Option<Vec<EventKind>>,
+}
+
+/// The way in which an event breaks a `ContentPolicy`
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ContentPolicyViolation {
+    /// The content is longer than `max_bytes`
+    ContentTooLarge {
+        /// The length of the content in bytes
+        bytes: usize,
+        /// The limit
+        max: usize,
+    },
+
+    /// The content has a NUL character
+    NulInContent,
+
+    /// A tag has a NUL character
+    NulInTag {
+        /// The index of the offending tag
+        index: usize,
+    },
+
+    /// There are more than `max_tags` tags
+    TooManyTags {
+        /// The number of tags
+        count: usize,
+        /// The limit
+        max: usize,
+    },
+
+    /// A string in a tag is longer than `max_tag_length`
+    TagTooLong {
+        /// The index of the offending tag
+        index: usize,
+        /// The limit
+        max: usize,
+    },
+
+    /// A tag has more than `max_tag_values` strings
+    TooManyTagValues {
+        /// The index of the offending tag
+        index: usize,
+        /// The limit
+        max: usize,
+    },
+
+    /// A tag is empty
+    EmptyTag {
+        /// The index of the offending tag
+        index: usize,
+    },
+
+    /// The kind is not among the `allowed_kinds`
+    KindNotAllowed(EventKind),
+}
+
+impl ContentPolicyViolation {
+    /// A short code for the violation, stable for machines to match on
+    pub fn code(&self) -> &'static str {
+        match self {
+            ContentPolicyViolation::ContentTooLarge { .. } => "content-too-large",
+            ContentPolicyViolation::NulInContent => "nul-in-content",
+            ContentPolicyViolation::NulInTag { .. } => "nul-in-tag",
+            ContentPolicyViolation::TooManyTags { .. } => "too-many-tags",
+            ContentPolicyViolation::TagTooLong { .. } => "tag-too-long",
+            ContentPolicyViolation::TooManyTagValues { .. } => "too-many-tag-values",
+            ContentPolicyViolation::EmptyTag { .. } => "empty-tag",
+            ContentPolicyViolation::KindNotAllowed(_) => "kind-not-allowed",
+        }
+    }
+
+    /// The reason to give in an OK message rejecting the event: `blocked` for a
+    /// kind which is not allowed, otherwise `invalid`, followed by the code
+    /// and a description
+    pub fn to_reason(&self) -> Reason {
+        let prefix = match self {
+            ContentPolicyViolation::KindNotAllowed(_) => ReasonPrefix::Blocked,
+            _ => ReasonPrefix::Invalid,
+        };
+        Reason::new(Some(prefix), &format!("{}: {}", self.code(), self))
+    }
+}
+
+impl fmt::Display for ContentPolicyViolation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ContentPolicyViolation::ContentTooLarge { bytes, max } => {
+                write!(f, "Content is {bytes} bytes, more than {max}")
+            }
+            ContentPolicyViolation::NulInContent => write!(f, "Content has a NUL character"),
+            ContentPolicyViolation::NulInTag { index } => {
+                write!(f, "Tag {index} has a NUL character")
+            }
+            ContentPolicyViolation::TooManyTags { count, max } => {
+                write!(f, "Event has {count} tags, more than {max}")
+            }
+            ContentPolicyViolation::TagTooLong { index, max } => {
+                write!(f, "Tag {index} has a string longer than {max} bytes")
+            }
+            ContentPolicyViolation::TooManyTagValues { index, max } => {
+                write!(f, "Tag {index} has more than {max} strings")
+            }
+            ContentPolicyViolation::EmptyTag { index } => write!(f, "Tag {index} is empty"),
+            ContentPolicyViolation::KindNotAllowed(kind) => {
+                write!(f, "Kind {} is not accepted", u64::from(*kind))
+            }
+        }
+    }
+}
+
+impl std::error::Error for ContentPolicyViolation {}
+
+impl ContentPolicy {
+    // The first way in which the event breaks the policy, checking the cheapest
+    // limits first
+    pub(crate) fn check(&self, event: &Event) -> Option<ContentPolicyViolation> {
+        if let Some(allowed) = &self.allowed_kinds {
+            if !allowed.contains(&event.kind) {
+                return Some(ContentPolicyViolation::KindNotAllowed(event.kind));
+            }
+        }
+        if let Some(max) = self.max_bytes {
+            if event.content.len() > max {
+                return Some(ContentPolicyViolation::ContentTooLarge {
+                    bytes: event.content.len(),
+                    max,
+                });
+            }
+        }
+        if let Some(max) = self.max_tags {
+            if event.tags.len() > max {
+                return Some(ContentPolicyViolation::TooManyTags {
+                    count: event.tags.len(),
+                    max,
+                });
+            }
+        }
+        if self.forbid_nul && event.content.contains('\0') {
+            return Some(ContentPolicyViolation::NulInContent);
+        }
+
+        let checks_tags = self.forbid_nul
+            || self.forbid_empty_tags
+            || self.max_tag_length.is_some()
+            || self.max_tag_values.is_some();
+        if !checks_tags {
+            return None;
+        }
+        for (index, tag) in event.tags.iter().enumerate() {
+            let strings = tag_strings(tag);
+            if self.forbid_empty_tags && strings.is_empty() {
+                return Some(ContentPolicyViolation::EmptyTag { index });
+            }
+            if let Some(max) = self.max_tag_values {
+                if strings.len() > max {
+                    return Some(ContentPolicyViolation::TooManyTagValues { index, max });
+                }
+            }
+            if let Some(max) = self.max_tag_length {
+                if strings.iter().any(|s| s.len() > max) {
+                    return Some(ContentPolicyViolation::TagTooLong { index, max });
+                }
+            }
+            if self.forbid_nul && strings.iter().any(|s| s.contains('\0')) {
+                return Some(ContentPolicyViolation::NulInTag { index });
+            }
+        }
+        None
+    }
+}
+
+// The strings of a tag, as it appears in JSON
+fn tag_strings(tag: &Tag) -> Vec<Cow<'_, str>> {
+    tag.elements()
+        .into_iter()
+        .map(TagElement::into_str)
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{
+        DelegationConditions, EventBuilder, Id, PrivateKey, PublicKeyHex, SignatureHex,
+        UncheckedUrl, Unixtime,
+    };
+
+    #[test]
+    fn test_tag_strings() {
+        let pubkey = PublicKeyHex::mock();
+        let pk = pubkey.as_str();
+        let id = Id::mock().as_hex_string();
+        let conditions = DelegationConditions::mock();
+        let sig = SignatureHex::mock();
+        let url = UncheckedUrl::mock();
+        let u = url.as_str();
+        let marker = Some("reply".to_owned());
+        let tags = vec![
+            (
+                Tag::Address {
+                    kind: EventKind::LongFormContent,
+                    pubkey: pubkey.clone(),
+                    d: "post".to_owned(),
+                    relay_url: None,
+                    marker: marker.clone(),
+                },
+                format!(r#"["a","30023:{pk}:post","","reply"]"#),
+            ),
+            (
+                Tag::ContentWarning("spoilers".to_owned()),
+                r#"["content-warning","spoilers"]"#.to_owned(),
+            ),
+            (
+                Tag::Delegation {
+                    pubkey: pubkey.clone(),
+                    conditions: conditions.clone(),
+                    sig: sig.clone(),
+                },
+                format!(
+                    r#"["delegation","{pk}","{}","{}"]"#,
+                    conditions.as_string(),
+                    sig.0
+                ),
+            ),
+            (
+                Tag::Event {
+                    id: Id::mock(),
+                    recommended_relay_url: Some(url.clone()),
+                    marker: marker.clone(),
+                },
+                format!(r#"["e","{id}","{u}","reply"]"#),
+            ),
+            (
+                Tag::Expiration(Unixtime(1_700_000_000)),
+                r#"["expiration",1700000000]"#.to_owned(),
+            ),
+            (
+                Tag::Pubkey {
+                    pubkey: pubkey.clone(),
+                    recommended_relay_url: None,
+                    petname: None,
+                },
+                format!(r#"["p","{pk}"]"#),
+            ),
+            (
+                Tag::Hashtag("nostr".to_owned()),
+                r#"["t","nostr"]"#.to_owned(),
+            ),
+            (
+                Tag::Reference {
+                    url: url.clone(),
+                    marker: Some("read".to_owned()),
+                },
+                format!(r#"["r","{u}","read"]"#),
+            ),
+            (
+                Tag::Geohash("u4pru".to_owned()),
+                r#"["g","u4pru"]"#.to_owned(),
+            ),
+            (Tag::Identifier("x".to_owned()), r#"["d","x"]"#.to_owned()),
+            (
+                Tag::Subject("hi".to_owned()),
+                r#"["subject","hi"]"#.to_owned(),
+            ),
+            (
+                Tag::Nonce {
+                    nonce: "12".to_owned(),
+                    target: Some("20".to_owned()),
+                },
+                r#"["nonce","12","20"]"#.to_owned(),
+            ),
+            (
+                Tag::Parameter("p".to_owned()),
+                r#"["parameter","p"]"#.to_owned(),
+            ),
+            (Tag::Title("T".to_owned()), r#"["title","T"]"#.to_owned()),
+            (
+                Tag::Other {
+                    tag: "x".to_owned(),
+                    data: vec!["y".to_owned(), "".to_owned()],
+                },
+                r#"["x","y",""]"#.to_owned(),
+            ),
+            (Tag::Empty, "[]".to_owned()),
+            (Tag::Protected, r#"["-"]"#.to_owned()),
+        ];
+
+        // Every variant is listed above; a new one fails to compile here until
+        // it is added to the list
+        let variant = |tag: &Tag| match tag {
+            Tag::ContentWarning(_) => 0,
+            Tag::Delegation { .. } => 1,
+            Tag::Event { .. } => 2,
+            Tag::Expiration(_) => 3,
+            Tag::Pubkey { .. } => 4,
+            Tag::Hashtag(_) => 5,
+            Tag::Reference { .. } => 6,
+            Tag::Geohash(_) => 7,
+            Tag::Identifier(_) => 8,
+            Tag::Subject(_) => 9,
+            Tag::Nonce { .. } => 10,
+            Tag::Parameter(_) => 11,
+            Tag::Title(_) => 12,
+            Tag::Other { .. } => 13,
+            Tag::Empty => 14,
+            Tag::Address { .. } => 15,
+            Tag::Protected => 16,
+        };
+        let mut seen: Vec<usize> = tags.iter().map(|(tag, _)| variant(tag)).collect();
+        seen.sort();
+        assert_eq!(seen, (0..=16).collect::<Vec<_>>());
+
+        for (tag, json) in tags {
+            assert_eq!(serde_json::to_string(&tag).unwrap(), json);
+            let values: Vec<serde_json::Value> = serde_json::from_str(&json).unwrap();
+            let strings: Vec<String> = values
+                .into_iter()
+                .map(|value| match value {
+                    serde_json::Value::String(s) => s,
+                    other => other.to_string(),
+                })
+                .collect();
+            assert_eq!(tag_strings(&tag), strings, "{tag:?}");
+        }
+    }
+
+    #[test]
+    fn test_content_policy() {
+        let private_key = PrivateKey::generate();
+        let event = |content: &str, tags: Vec<Tag>| {
+            EventBuilder::new()
+                .content(content)
+                .tags(tags)
+                .sign(&private_key)
+                .unwrap()
+        };
+        let hashtag = |s: &str| Tag::Hashtag(s.to_owned());
+
+        let policy = ContentPolicy {
+            max_bytes: Some(10),
+            forbid_nul: true,
+            max_tags: Some(2),
+            max_tag_length: Some(8),
+            max_tag_values: Some(2),
+            forbid_empty_tags: true,
+            allowed_kinds: Some(vec![EventKind::TextNote]),
+        };
+        let ok = event("Hello", vec![hashtag("nostr")]);
+        assert!(ok.validate_against(&policy).is_ok());
+        assert!(ok.validate_against(&ContentPolicy::default()).is_ok());
+
+        let check = |event: Event| match event.validate_against(&policy) {
+            Err(crate::Error::ContentPolicyViolation(violation)) => violation,
+            other => panic!("unexpected {other:?}"),
+        };
+        assert_eq!(
+            check(event("Hello world", vec![])),
+            ContentPolicyViolation::ContentTooLarge { bytes: 11, max: 10 }
+        );
+        assert_eq!(
+            check(event("a\0b", vec![])),
+            ContentPolicyViolation::NulInContent
+        );
+        assert_eq!(
+            check(event("", vec![hashtag("a"), hashtag("b"), hashtag("c")])),
+            ContentPolicyViolation::TooManyTags { count: 3, max: 2 }
+        );
+        assert_eq!(
+            check(event("", vec![hashtag("ok"), hashtag("toolongtag")])),
+            ContentPolicyViolation::TagTooLong { index: 1, max: 8 }
+        );
+        assert_eq!(
+            check(event("", vec![hashtag("a"), Tag::Empty])),
+            ContentPolicyViolation::EmptyTag { index: 1 }
+        );
+        assert_eq!(
+            check(event("", vec![hashtag("a\0")])),
+            ContentPolicyViolation::NulInTag { index: 0 }
+        );
+        let three = Tag::Other {
+            tag: "x".to_owned(),
+            data: vec!["1".to_owned(), "2".to_owned()],
+        };
+        let violation = check(event("", vec![three]));
+        assert_eq!(
+            violation,
+            ContentPolicyViolation::TooManyTagValues { index: 0, max: 2 }
+        );
+        assert_eq!(
+            violation.to_reason().to_string(),
+            "invalid: too-many-tag-values: Tag 0 has more than 2 strings"
+        );
+
+        let reaction = EventBuilder::new()
+            .kind(EventKind::Reaction)
+            .sign(&private_key)
+            .unwrap();
+        let violation = check(reaction);
+        assert_eq!(violation.code(), "kind-not-allowed");
+        assert_eq!(violation.to_reason().prefix, Some(ReasonPrefix::Blocked));
+    }
+}
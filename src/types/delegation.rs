@@ -1,4 +1,7 @@
-use super::{EventKind, PrivateKey, PublicKey, PublicKeyHex, Signature, SignatureHex, Unixtime};
+use super::{
+    Event, EventKind, Id, PrivateKey, PublicKey, PublicKeyHex, Signature, SignatureHex, Tag,
+    Unixtime,
+};
 use crate::Error;
 use serde::de::Error as DeError;
 use serde::de::{Deserialize, Deserializer, Visitor};
@@ -20,12 +23,107 @@ pub enum EventDelegation {
     DelegatedBy(PublicKey),
 }
 
+/// A delegation condition which an event failed to meet, with the condition's
+/// value and the event's actual value
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum DelegationConditionFailure {
+    /// The event kind is not one of the delegated kinds
+    Kind {
+        /// The delegated kinds
+        allowed: Vec<EventKind>,
+        /// The kind of the event
+        actual: EventKind,
+    },
+
+    /// The event kind is not below the delegated limit
+    KindNotBelow {
+        /// Event kinds must be below this
+        limit: u64,
+        /// The kind of the event
+        actual: u64,
+    },
+
+    /// The event kind is not above the delegated limit
+    KindNotAbove {
+        /// Event kinds must be above this
+        limit: u64,
+        /// The kind of the event
+        actual: u64,
+    },
+
+    /// The event was created before the delegation started
+    CreatedTooEarly {
+        /// Events must be created after this
+        created_after: Unixtime,
+        /// The created_at of the event
+        actual: Unixtime,
+    },
+
+    /// The event was created after the delegation ended
+    CreatedTooLate {
+        /// Events must be created before this
+        created_before: Unixtime,
+        /// The created_at of the event
+        actual: Unixtime,
+    },
+}
+
+impl fmt::Display for DelegationConditionFailure {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DelegationConditionFailure::Kind { allowed, actual } => {
+                let allowed: Vec<String> = allowed
+                    .iter()
+                    .map(|k| format!("{}", u64::from(*k)))
+                    .collect();
+                write!(
+                    f,
+                    "Event Kind not delegated: kind {} is not one of kind={}",
+                    u64::from(*actual),
+                    allowed.join(",")
+                )
+            }
+            DelegationConditionFailure::KindNotBelow { limit, actual } => write!(
+                f,
+                "Event Kind not delegated: kind {actual} does not meet kind<{limit}"
+            ),
+            DelegationConditionFailure::KindNotAbove { limit, actual } => write!(
+                f,
+                "Event Kind not delegated: kind {actual} does not meet kind>{limit}"
+            ),
+            DelegationConditionFailure::CreatedTooEarly {
+                created_after,
+                actual,
+            } => write!(
+                f,
+                "Event created before delegation started: created_at {actual} does not meet created_at>{created_after}"
+            ),
+            DelegationConditionFailure::CreatedTooLate {
+                created_before,
+                actual,
+            } => write!(
+                f,
+                "Event created after delegation ended: created_at {actual} does not meet created_at<{created_before}"
+            ),
+        }
+    }
+}
+
+impl std::error::Error for DelegationConditionFailure {}
+
 /// Conditions of delegation
 #[derive(Clone, Debug, Default, PartialEq, Eq)]
 #[cfg_attr(feature = "speedy", derive(Readable, Writable))]
 pub struct DelegationConditions {
-    /// If the delegation is only for a given event kind
-    pub kind: Option<EventKind>,
+    /// If the delegation is only for given event kinds (any of them). If empty,
+    /// the delegation is not restricted by kind.
+    pub kinds: Vec<EventKind>,
+
+    /// If the delegation is only for event kinds numerically below a given number
+    pub kind_below: Option<u64>,
+
+    /// If the delegation is only for event kinds numerically above a given number
+    pub kind_above: Option<u64>,
 
     /// If the delegation is only for events created after a certain time
     pub created_after: Option<Unixtime>,
@@ -38,6 +136,50 @@ pub struct DelegationConditions {
 }
 
 impl DelegationConditions {
+    /// Create new empty conditions. Add conditions with the builder methods
+    /// `kind()`, `created_after()` and `created_before()`.
+    pub fn new() -> DelegationConditions {
+        DelegationConditions::default()
+    }
+
+    /// Restrict the delegation to an event kind. If called several times, any of
+    /// the given kinds are allowed.
+    pub fn kind(mut self, kind: EventKind) -> DelegationConditions {
+        if !self.kinds.contains(&kind) {
+            self.kinds.push(kind);
+        }
+        self.update_full_string();
+        self
+    }
+
+    /// Restrict the delegation to event kinds numerically below `kind`
+    pub fn kind_below(mut self, kind: u64) -> DelegationConditions {
+        self.kind_below = Some(kind);
+        self.update_full_string();
+        self
+    }
+
+    /// Restrict the delegation to event kinds numerically above `kind`
+    pub fn kind_above(mut self, kind: u64) -> DelegationConditions {
+        self.kind_above = Some(kind);
+        self.update_full_string();
+        self
+    }
+
+    /// Restrict the delegation to events created after a time
+    pub fn created_after(mut self, time: Unixtime) -> DelegationConditions {
+        self.created_after = Some(time);
+        self.update_full_string();
+        self
+    }
+
+    /// Restrict the delegation to events created before a time
+    pub fn created_before(mut self, time: Unixtime) -> DelegationConditions {
+        self.created_before = Some(time);
+        self.update_full_string();
+        self
+    }
+
     /// Return in conmpiled string form. If full form is stored, it is returned, otherwise it is compiled from parts.
     pub fn as_string(&self) -> String {
         match &self.full_string {
@@ -49,8 +191,19 @@ impl DelegationConditions {
     /// Compile full string from parts.
     fn compile_full_string(&self) -> String {
         let mut parts: Vec<String> = Vec::new();
-        if let Some(kind) = self.kind {
-            parts.push(format!("kind={}", u64::from(kind)));
+        if !self.kinds.is_empty() {
+            let kinds: Vec<String> = self
+                .kinds
+                .iter()
+                .map(|k| format!("{}", u64::from(*k)))
+                .collect();
+            parts.push(format!("kind={}", kinds.join(",")));
+        }
+        if let Some(kind_below) = self.kind_below {
+            parts.push(format!("kind<{}", kind_below));
+        }
+        if let Some(kind_above) = self.kind_above {
+            parts.push(format!("kind>{}", kind_above));
         }
         if let Some(created_after) = self.created_after {
             parts.push(format!("created_at>{}", created_after.0));
@@ -61,7 +214,7 @@ impl DelegationConditions {
         parts.join("&")
     }
 
-    #[allow(dead_code)]
+    // Render and store the full string form, so that it need not be compiled again
     fn update_full_string(&mut self) {
         self.full_string = Some(self.compile_full_string())
     }
@@ -73,9 +226,20 @@ impl DelegationConditions {
         let parts = s.split('&');
         for part in parts {
             if let Some(kindstr) = part.strip_prefix("kind=") {
-                let event_num = kindstr.parse::<u64>()?;
-                let event_kind: EventKind = From::from(event_num);
-                output.kind = Some(event_kind);
+                // Some implementations list several kinds, e.g. kind=1,6,7
+                for numstr in kindstr.split(',') {
+                    let event_num = numstr.parse::<u64>()?;
+                    let event_kind: EventKind = From::from(event_num);
+                    if !output.kinds.contains(&event_kind) {
+                        output.kinds.push(event_kind);
+                    }
+                }
+            }
+            if let Some(kindstr) = part.strip_prefix("kind<") {
+                output.kind_below = Some(kindstr.parse::<u64>()?);
+            }
+            if let Some(kindstr) = part.strip_prefix("kind>") {
+                output.kind_above = Some(kindstr.parse::<u64>()?);
             }
             if let Some(timestr) = part.strip_prefix("created_at>") {
                 let time = timestr.parse::<i64>()?;
@@ -95,7 +259,9 @@ impl DelegationConditions {
     #[allow(dead_code)]
     pub(crate) fn mock() -> DelegationConditions {
         let mut dc = DelegationConditions {
-            kind: Some(EventKind::Repost),
+            kinds: vec![EventKind::Repost],
+            kind_below: None,
+            kind_above: None,
             created_after: Some(Unixtime(1677700000)),
             created_before: None,
             full_string: None,
@@ -104,14 +270,81 @@ impl DelegationConditions {
         dc
     }
 
+    /// Check that an event with the given kind and creation time satisfies these
+    /// conditions. On failure, the condition which failed is returned.
+    pub fn check(
+        &self,
+        kind: EventKind,
+        created_at: Unixtime,
+    ) -> Result<(), DelegationConditionFailure> {
+        let kind_num: u64 = kind.into();
+        if !self.kinds.is_empty() && !self.kinds.contains(&kind) {
+            return Err(DelegationConditionFailure::Kind {
+                allowed: self.kinds.clone(),
+                actual: kind,
+            });
+        }
+        if let Some(below) = self.kind_below {
+            if kind_num >= below {
+                return Err(DelegationConditionFailure::KindNotBelow {
+                    limit: below,
+                    actual: kind_num,
+                });
+            }
+        }
+        if let Some(above) = self.kind_above {
+            if kind_num <= above {
+                return Err(DelegationConditionFailure::KindNotAbove {
+                    limit: above,
+                    actual: kind_num,
+                });
+            }
+        }
+        self.check_created_at(created_at)
+    }
+
+    // Check that the creation time is within the conditions' time window
+    fn check_created_at(&self, created_at: Unixtime) -> Result<(), DelegationConditionFailure> {
+        if let Some(created_after) = self.created_after {
+            if created_at < created_after {
+                return Err(DelegationConditionFailure::CreatedTooEarly {
+                    created_after,
+                    actual: created_at,
+                });
+            }
+        }
+        if let Some(created_before) = self.created_before {
+            if created_at > created_before {
+                return Err(DelegationConditionFailure::CreatedTooLate {
+                    created_before,
+                    actual: created_at,
+                });
+            }
+        }
+        Ok(())
+    }
+
+    // The delegation token which the delegator signs
+    fn token(&self, pubkey_delegatee: &str) -> String {
+        format!("nostr:delegation:{}:{}", pubkey_delegatee, self.as_string())
+    }
+
     /// Generate the signature part of a Delegation tag
     pub fn generate_signature(
         &self,
         pubkey: PublicKeyHex,
         private_key: PrivateKey,
     ) -> Result<SignatureHex, Error> {
-        let input = format!("nostr:delegation:{}:{}", pubkey, self.as_string());
-        let signature = private_key.sign(input.as_bytes())?;
+        self.sign(pubkey.as_str(), &private_key)
+    }
+
+    // Sign the delegation token for the delegatee
+    pub(crate) fn sign(
+        &self,
+        pubkey_delegatee: &str,
+        private_key: &PrivateKey,
+    ) -> Result<SignatureHex, Error> {
+        let signature = private_key.sign(self.token(pubkey_delegatee).as_bytes())?;
         Ok(signature.into())
     }
 
@@ -122,15 +355,97 @@ impl DelegationConditions {
         pubkey_delegatee: &PublicKey,
         signature: Signature,
     ) -> Result<(), Error> {
-        let input = format!(
-            "nostr:delegation:{}:{}",
-            pubkey_delegatee.as_hex_string(),
-            self.as_string()
-        );
+        let input = self.token(&pubkey_delegatee.as_hex_string());
         pubkey_delegater.verify(input.as_bytes(), signature)
     }
 }
 
+/// A delegation granted by a delegator, as found in the delegation tags of
+/// events published by the delegatee
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct GrantedDelegation {
+    /// The delegatee
+    pub delegatee: PublicKey,
+
+    /// The conditions of the delegation, including its validity window
+    /// (`created_after`, `created_before`) and allowed kinds
+    pub conditions: DelegationConditions,
+
+    /// The delegator's signature
+    pub sig: SignatureHex,
+
+    /// Ids of the events carrying this delegation
+    pub events: Vec<Id>,
+}
+
+impl GrantedDelegation {
+    /// Find all delegations granted by `delegator` in the given events, grouped by
+    /// delegatee in the order they were first seen. Tags whose signature does not
+    /// verify are ignored. The same delegation used by several events is reported
+    /// once, listing all of those events.
+    pub fn find_all(
+        delegator: &PublicKey,
+        events: &[Event],
+    ) -> Vec<(PublicKey, Vec<GrantedDelegation>)> {
+        let mut output: Vec<(PublicKey, Vec<GrantedDelegation>)> = Vec::new();
+        for event in events {
+            for tag in &event.tags {
+                let (pubkey, conditions, sig) = match tag {
+                    Tag::Delegation {
+                        pubkey,
+                        conditions,
+                        sig,
+                    } => (pubkey, conditions, sig),
+                    _ => continue,
+                };
+                match PublicKey::try_from_hex_string(pubkey) {
+                    Ok(pk) if pk == *delegator => {}
+                    _ => continue,
+                }
+                let signature = match Signature::try_from_hex_string(sig) {
+                    Ok(signature) => signature,
+                    Err(_) => continue,
+                };
+                if conditions
+                    .verify_signature(delegator, &event.pubkey, signature)
+                    .is_err()
+                {
+                    continue;
+                }
+
+                let index = match output.iter().position(|(pk, _)| *pk == event.pubkey) {
+                    Some(index) => index,
+                    None => {
+                        output.push((event.pubkey, Vec::new()));
+                        output.len() - 1
+                    }
+                };
+                let granted = &mut output[index].1;
+                match granted.iter_mut().find(|g| g.sig == *sig) {
+                    Some(g) => {
+                        if !g.events.contains(&event.id) {
+                            g.events.push(event.id);
+                        }
+                    }
+                    None => granted.push(GrantedDelegation {
+                        delegatee: event.pubkey,
+                        conditions: conditions.clone(),
+                        sig: sig.clone(),
+                        events: vec![event.id],
+                    }),
+                }
+            }
+        }
+        output
+    }
+
+    /// Whether the delegation's validity window includes the given time, with
+    /// the same bounds as `DelegationConditions::check()`
+    pub fn is_active_at(&self, time: Unixtime) -> bool {
+        self.conditions.check_created_at(time).is_ok()
+    }
+}
+
 impl Serialize for DelegationConditions {
     fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
     where
@@ -169,8 +484,6 @@ impl Visitor<'_> for DelegationConditionsVisitor {
 #[cfg(test)]
 mod test {
     use super::*;
-    use crate::Tag;
-
     test_serde! {DelegationConditions, test_delegation_conditions_serde}
 
     #[test]
@@ -283,7 +596,9 @@ mod test {
     #[test]
     fn test_as_string() {
         let dc = DelegationConditions {
-            kind: Some(EventKind::TextNote),
+            kinds: vec![EventKind::TextNote],
+            kind_below: None,
+            kind_above: None,
             created_before: Some(Unixtime(2000000)),
             created_after: Some(Unixtime(1000000)),
             full_string: None,
@@ -293,4 +608,202 @@ mod test {
             "kind=1&created_at>1000000&created_at<2000000"
         );
     }
+
+    #[test]
+    fn test_builder() {
+        let dc = DelegationConditions::new()
+            .kind(EventKind::TextNote)
+            .created_after(Unixtime(1000000))
+            .created_before(Unixtime(2000000));
+        assert_eq!(
+            dc.as_string(),
+            "kind=1&created_at>1000000&created_at<2000000"
+        );
+        // The string form is rendered once, as the conditions are built
+        assert_eq!(
+            dc.full_string.as_deref(),
+            Some("kind=1&created_at>1000000&created_at<2000000")
+        );
+
+        // Builder methods replace any parsed string form
+        let dc = DelegationConditions::try_from_str("kind=1")
+            .unwrap()
+            .created_before(Unixtime(2000000));
+        assert_eq!(dc.as_string(), "kind=1&created_at<2000000");
+    }
+
+    #[test]
+    fn test_new_delegation_tag() {
+        let delegator_private_key = PrivateKey::generate();
+        let delegatee_public_key = PublicKey::mock();
+        let conditions = DelegationConditions::new()
+            .kind(EventKind::TextNote)
+            .created_after(Unixtime(1000000));
+        let tag = Tag::new_delegation(&delegator_private_key, &delegatee_public_key, &conditions)
+            .unwrap();
+        if let Tag::Delegation {
+            pubkey,
+            conditions: c,
+            sig,
+        } = tag
+        {
+            assert_eq!(pubkey, delegator_private_key.public_key().into());
+            assert_eq!(c, conditions);
+            assert!(c
+                .verify_signature(
+                    &delegator_private_key.public_key(),
+                    &delegatee_public_key,
+                    Signature::try_from(sig).unwrap()
+                )
+                .is_ok());
+        } else {
+            panic!("Incorrect tag type")
+        }
+    }
+
+    #[test]
+    fn test_kind_ranges() {
+        let str = "kind>29999&kind<40000&created_at>1000000";
+        let dc = DelegationConditions::try_from_str(str).unwrap();
+        assert_eq!(dc.kind_above, Some(29999));
+        assert_eq!(dc.kind_below, Some(40000));
+        assert_eq!(dc.as_string(), str);
+
+        assert!(dc
+            .check(EventKind::LongFormContent, Unixtime(1000001))
+            .is_ok());
+        assert!(dc.check(EventKind::TextNote, Unixtime(1000001)).is_err());
+        assert!(dc
+            .check(EventKind::Other(40000), Unixtime(1000001))
+            .is_err());
+        assert!(dc.check(EventKind::Other(30000), Unixtime(10)).is_err());
+
+        let dc = DelegationConditions::new().kind_below(10).kind_above(0);
+        assert_eq!(dc.as_string(), "kind<10&kind>0");
+    }
+
+    #[test]
+    fn test_multiple_kinds() {
+        let str = "kind=1,6,7&created_at>1000000";
+        let dc = DelegationConditions::try_from_str(str).unwrap();
+        assert_eq!(
+            dc.kinds,
+            vec![EventKind::TextNote, EventKind::Repost, EventKind::Reaction]
+        );
+        assert_eq!(dc.as_string(), str);
+        assert!(dc.check(EventKind::Repost, Unixtime(1000001)).is_ok());
+        assert!(dc.check(EventKind::Metadata, Unixtime(1000001)).is_err());
+
+        // Repeated kind conditions also mean any of them
+        let dc = DelegationConditions::try_from_str("kind=1&kind=7").unwrap();
+        assert!(dc.check(EventKind::TextNote, Unixtime(0)).is_ok());
+        assert!(dc.check(EventKind::Reaction, Unixtime(0)).is_ok());
+        assert!(dc.check(EventKind::Repost, Unixtime(0)).is_err());
+
+        let dc = DelegationConditions::new()
+            .kind(EventKind::TextNote)
+            .kind(EventKind::Reaction);
+        assert_eq!(dc.as_string(), "kind=1,7");
+    }
+
+    #[test]
+    fn test_check_failure_details() {
+        let dc = DelegationConditions::try_from_str("kind=1,7&created_at<2000000").unwrap();
+        let failure = dc.check(EventKind::Repost, Unixtime(1000000)).unwrap_err();
+        assert_eq!(
+            failure,
+            DelegationConditionFailure::Kind {
+                allowed: vec![EventKind::TextNote, EventKind::Reaction],
+                actual: EventKind::Repost
+            }
+        );
+        assert_eq!(
+            format!("{failure}"),
+            "Event Kind not delegated: kind 6 is not one of kind=1,7"
+        );
+
+        let failure = dc
+            .check(EventKind::TextNote, Unixtime(3000000))
+            .unwrap_err();
+        assert_eq!(
+            failure,
+            DelegationConditionFailure::CreatedTooLate {
+                created_before: Unixtime(2000000),
+                actual: Unixtime(3000000)
+            }
+        );
+    }
+
+    #[test]
+    fn test_granted_delegations() {
+        use crate::{PreEvent, TestSigner};
+
+        let delegator = TestSigner::new(b"delegator");
+        let alice = delegator.derive(1);
+        let bob = delegator.derive(2);
+        let other = delegator.derive(3);
+
+        let notes = DelegationConditions::new()
+            .kind(EventKind::TextNote)
+            .created_after(Unixtime(1680000000))
+            .created_before(Unixtime(1690000000));
+        let reactions = DelegationConditions::new().kind(EventKind::Reaction);
+
+        let make_event = |signer: &TestSigner, tag: Tag, kind: EventKind, time: i64| {
+            signer
+                .sign_event(PreEvent {
+                    pubkey: signer.public_key(),
+                    created_at: Unixtime(time),
+                    kind,
+                    tags: vec![tag],
+                    content: "".to_owned(),
+                    ots: None,
+                })
+                .unwrap()
+        };
+
+        let alice_notes =
+            Tag::new_delegation(delegator.private_key(), &alice.public_key(), &notes).unwrap();
+        let alice_reactions =
+            Tag::new_delegation(delegator.private_key(), &alice.public_key(), &reactions).unwrap();
+        let bob_notes =
+            Tag::new_delegation(delegator.private_key(), &bob.public_key(), &notes).unwrap();
+        let not_mine = Tag::new_delegation(other.private_key(), &bob.public_key(), &notes).unwrap();
+        // Bob reusing Alice's delegation does not verify
+        let stolen = alice_notes.clone();
+
+        let events = vec![
+            make_event(&alice, alice_notes.clone(), EventKind::TextNote, 1680000001),
+            make_event(&alice, alice_notes, EventKind::TextNote, 1680000002),
+            make_event(&bob, bob_notes, EventKind::TextNote, 1680000003),
+            make_event(&alice, alice_reactions, EventKind::Reaction, 1680000004),
+            make_event(&bob, not_mine, EventKind::TextNote, 1680000005),
+            make_event(&bob, stolen, EventKind::TextNote, 1680000006),
+        ];
+
+        let granted = GrantedDelegation::find_all(&delegator.public_key(), &events);
+        assert_eq!(granted.len(), 2);
+
+        let (delegatee, alices) = &granted[0];
+        assert_eq!(*delegatee, alice.public_key());
+        assert_eq!(alices.len(), 2);
+        assert_eq!(alices[0].conditions, notes);
+        assert_eq!(alices[0].events, vec![events[0].id, events[1].id]);
+        assert_eq!(alices[1].conditions, reactions);
+        assert!(alices[0].is_active_at(Unixtime(1685000000)));
+        assert!(!alices[0].is_active_at(Unixtime(1695000000)));
+        // The bounds agree with the check on events
+        for time in [1680000000, 1690000000, 1679999999, 1690000001] {
+            assert_eq!(
+                alices[0].is_active_at(Unixtime(time)),
+                notes.check(EventKind::TextNote, Unixtime(time)).is_ok()
+            );
+        }
+        assert!(alices[1].is_active_at(Unixtime(1695000000)));
+
+        let (delegatee, bobs) = &granted[1];
+        assert_eq!(*delegatee, bob.public_key());
+        assert_eq!(bobs.len(), 1);
+        assert_eq!(bobs[0].events, vec![events[2].id]);
+    }
 }
@@ -1,6 +1,8 @@
 use super::{
-    EventDelegation, EventKind, Id, Metadata, PrivateKey, PublicKey, PublicKeyHex, RelayUrl,
-    Signature, Tag, Unixtime,
+    rewrite_legacy_mentions, ChessGame, ContentEncryptionAlgorithm, ContentPolicy,
+    EncryptedContent, EventAddr, EventDelegation, EventFlat, EventKind, EventSummary, IMeta, Id,
+    IngestStats, Metadata, PrivateKey, PublicKey, PublicKeyHex, RelayLimitation, RelayUrl,
+    Signature, Tag, Unixtime, VerifiedEvent,
 };
 use crate::Error;
 use base64::Engine;
@@ -8,6 +10,7 @@ use k256::sha2::{Digest, Sha256};
 use serde::{Deserialize, Serialize};
 #[cfg(feature = "speedy")]
 use speedy::{Readable, Writable};
+use std::fmt;
 use std::sync::atomic::{AtomicBool, AtomicU64, AtomicU8, Ordering};
 use std::sync::mpsc::Sender;
 use std::sync::Arc;
@@ -48,18 +51,37 @@ pub struct Event {
     pub sig: Signature,
 }
 
-macro_rules! serialize_inner_event {
-    ($pubkey:expr, $created_at:expr, $kind:expr, $tags:expr,
-     $content:expr) => {{
-        format!(
-            "[0,{},{},{},{},{}]",
-            serde_json::to_string($pubkey)?,
-            serde_json::to_string($created_at)?,
-            serde_json::to_string($kind)?,
-            serde_json::to_string($tags)?,
-            serde_json::to_string($content)?
-        )
-    }};
+// Serialize the NIP-01 id preimage `[0,<pubkey>,<created_at>,<kind>,<tags>,<content>]`
+// into `buf`, replacing its contents, and hash it
+fn hash_inner_event(
+    buf: &mut Vec<u8>,
+    pubkey: &PublicKey,
+    created_at: &Unixtime,
+    kind: &EventKind,
+    tags: &[Tag],
+    content: &str,
+) -> Id {
+    fn write<T: Serialize + ?Sized>(buf: &mut Vec<u8>, value: &T) {
+        // None of these types fail to serialize, and writing to a Vec cannot fail
+        serde_json::to_writer(buf, value).expect("event fields serialize to JSON");
+    }
+
+    buf.clear();
+    buf.extend_from_slice(b"[0,");
+    write(buf, pubkey);
+    buf.push(b',');
+    write(buf, created_at);
+    buf.push(b',');
+    write(buf, kind);
+    buf.push(b',');
+    write(buf, tags);
+    buf.push(b',');
+    write(buf, content);
+    buf.push(b']');
+
+    let mut hasher = Sha256::new();
+    hasher.update(&buf[..]);
+    Id(hasher.finalize().into())
 }
 
 /// Data used to construct an event
@@ -79,7 +101,139 @@ pub struct PreEvent {
     pub ots: Option<String>,
 }
 
+/// A kind-specific rule which a PreEvent breaks (see `PreEvent::validate()`)
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum PreEventValidationFailure {
+    /// Metadata content is not valid Metadata JSON
+    InvalidMetadata(String),
+
+    /// A contact list has a tag which is not a 'p' tag
+    ContactListNonPubkeyTag {
+        /// The index of the offending tag
+        index: usize,
+    },
+
+    /// A parameterized replaceable event has no 'd' tag
+    MissingIdentifier,
+
+    /// An encrypted direct message does not have exactly one 'p' tag
+    DirectMessageRecipients {
+        /// The number of 'p' tags
+        count: usize,
+    },
+
+    /// An encrypted direct message's content is not NIP-04 ciphertext
+    DirectMessageNotEncrypted,
+
+    /// A deletion does not refer to any event with an 'e' or 'a' tag
+    DeletionWithoutTarget,
+
+    /// A reaction does not refer to any event with an 'e' tag
+    ReactionWithoutTarget,
+}
+
+impl fmt::Display for PreEventValidationFailure {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PreEventValidationFailure::InvalidMetadata(e) => {
+                write!(f, "Metadata content is not valid: {e}")
+            }
+            PreEventValidationFailure::ContactListNonPubkeyTag { index } => {
+                write!(f, "Contact list tag {index} is not a 'p' tag")
+            }
+            PreEventValidationFailure::MissingIdentifier => {
+                write!(f, "Parameterized replaceable event has no 'd' tag")
+            }
+            PreEventValidationFailure::DirectMessageRecipients { count } => write!(
+                f,
+                "Encrypted direct message must have one 'p' tag, but has {count}"
+            ),
+            PreEventValidationFailure::DirectMessageNotEncrypted => {
+                write!(
+                    f,
+                    "Encrypted direct message content is not NIP-04 encrypted"
+                )
+            }
+            PreEventValidationFailure::DeletionWithoutTarget => {
+                write!(f, "Deletion does not refer to any event")
+            }
+            PreEventValidationFailure::ReactionWithoutTarget => {
+                write!(f, "Reaction does not refer to any event")
+            }
+        }
+    }
+}
+
+impl std::error::Error for PreEventValidationFailure {}
+
 impl PreEvent {
+    /// Compute the Id the event will have, serializing the NIP-01 preimage into
+    /// `buf` rather than a freshly allocated string (see
+    /// `Event::compute_id_into()`)
+    pub fn compute_id_into(&self, buf: &mut Vec<u8>) -> Id {
+        hash_inner_event(
+            buf,
+            &self.pubkey,
+            &self.created_at,
+            &self.kind,
+            &self.tags,
+            &self.content,
+        )
+    }
+
+    /// Check kind-specific rules before signing, so that malformed events are
+    /// caught before they are published. Kinds without specific rules always
+    /// pass.
+    pub fn validate(&self) -> Result<(), Error> {
+        let has_tag = |f: fn(&Tag) -> bool| self.tags.iter().any(f);
+
+        let failure = match self.kind {
+            EventKind::Metadata => serde_json::from_str::<Metadata>(&self.content)
+                .err()
+                .map(|e| PreEventValidationFailure::InvalidMetadata(format!("{e}"))),
+            EventKind::ContactList => self
+                .tags
+                .iter()
+                .position(|t| !matches!(t, Tag::Pubkey { .. }))
+                .map(|index| PreEventValidationFailure::ContactListNonPubkeyTag { index }),
+            EventKind::EncryptedDirectMessage => {
+                let count = self
+                    .tags
+                    .iter()
+                    .filter(|t| matches!(t, Tag::Pubkey { .. }))
+                    .count();
+                if count != 1 {
+                    Some(PreEventValidationFailure::DirectMessageRecipients { count })
+                } else if EncryptedContent::detect(&self.content)
+                    != Some(ContentEncryptionAlgorithm::Nip04)
+                {
+                    Some(PreEventValidationFailure::DirectMessageNotEncrypted)
+                } else {
+                    None
+                }
+            }
+            EventKind::EventDeletion
+                if !has_tag(|t| matches!(t, Tag::Event { .. } | Tag::Address { .. })) =>
+            {
+                Some(PreEventValidationFailure::DeletionWithoutTarget)
+            }
+            EventKind::Reaction if !has_tag(|t| matches!(t, Tag::Event { .. })) => {
+                Some(PreEventValidationFailure::ReactionWithoutTarget)
+            }
+            kind if kind.is_parameterized_replaceable()
+                && !has_tag(|t| matches!(t, Tag::Identifier(_))) =>
+            {
+                Some(PreEventValidationFailure::MissingIdentifier)
+            }
+            _ => None,
+        };
+
+        match failure {
+            Some(failure) => Err(failure.into()),
+            None => Ok(()),
+        }
+    }
+
     /// Create a NIP-04 EncryptedDirectMessage PreEvent.
     ///
     /// Note that this creates the 'p' tag, but does not add a recommended_relay_url to it,
@@ -89,13 +243,11 @@ impl PreEvent {
         recipient_public_key: PublicKey,
         message: &str,
     ) -> Result<PreEvent, Error> {
-        let input: &[u8] = message.as_bytes();
-        let (iv, ciphertext) = private_key.nip04_encrypt(&recipient_public_key, input)?;
-        let content = format!(
-            "{}?iv={}",
-            base64::engine::general_purpose::STANDARD.encode(ciphertext),
-            base64::engine::general_purpose::STANDARD.encode(iv)
-        );
+        let content = private_key.encrypt(
+            &recipient_public_key,
+            message,
+            ContentEncryptionAlgorithm::Nip04,
+        )?;
 
         Ok(PreEvent {
             pubkey: private_key.public_key(),
@@ -113,21 +265,8 @@ impl PreEvent {
 }
 
 impl Event {
-    fn hash(input: &PreEvent) -> Result<Id, Error> {
-        let serialized: String = serialize_inner_event!(
-            &input.pubkey,
-            &input.created_at,
-            &input.kind,
-            &input.tags,
-            &input.content
-        );
-
-        // Hash
-        let mut hasher = Sha256::new();
-        hasher.update(serialized.as_bytes());
-        let id = hasher.finalize();
-        let id: [u8; 32] = id.into();
-        Ok(Id(id))
+    pub(crate) fn hash(input: &PreEvent) -> Result<Id, Error> {
+        Ok(input.compute_id_into(&mut Vec::new()))
     }
 
     /// Create a new event
@@ -150,6 +289,66 @@ impl Event {
         })
     }
 
+    /// Parse an event from its NIP-01 JSON form. This is equivalent to
+    /// `serde_json::from_slice()`, but faster: the fixed shape of an event is
+    /// parsed directly, validating the structure and hex fields in a single pass.
+    ///
+    /// This does not verify the event.
+    pub fn from_json_fast(json: &[u8]) -> Result<Event, Error> {
+        super::fast_parse::parse_event(json)
+    }
+
+    /// Encode in the flat binary form (see `EventFlat`)
+    pub fn to_flat(&self) -> Result<Vec<u8>, Error> {
+        let mut output: Vec<u8> = Vec::new();
+        EventFlat::write(self, &mut output)?;
+        Ok(output)
+    }
+
+    /// Check the structure of an event's JSON in a single pass, without building
+    /// the event: that all required fields are present, and that the hex fields
+    /// have the right lengths. This returns a summary of the event, so that
+    /// relays can cheaply reject junk (or events they do not want) before fully
+    /// parsing and verifying it.
+    pub fn quick_check_json(json: &str) -> Result<EventSummary, Error> {
+        super::fast_parse::summarize_event(json.as_bytes(), None)
+    }
+
+    /// Check the structure of an event's JSON, as `quick_check_json()` does, and
+    /// also that it is within the message length, tag count and content length
+    /// limits of a relay
+    pub fn quick_check_json_with_limits(
+        json: &str,
+        limits: &RelayLimitation,
+    ) -> Result<EventSummary, Error> {
+        super::fast_parse::summarize_event(json.as_bytes(), Some(limits))
+    }
+
+    /// Create a new event on behalf of a delegator (NIP-26), attaching the given
+    /// delegation tag (see `Tag::new_delegation()`) and signing with the
+    /// delegatee's private key.
+    ///
+    /// This fails if the delegation would not be valid for the event.
+    pub fn new_delegated(
+        mut input: PreEvent,
+        privkey: &PrivateKey,
+        delegation: Tag,
+    ) -> Result<Event, Error> {
+        if !matches!(delegation, Tag::Delegation { .. }) {
+            return Err(Error::InvalidDelegation("Not a delegation tag".to_owned()));
+        }
+
+        // Replace any pre-existing delegation tag
+        input.tags.retain(|t| !matches!(t, Tag::Delegation { .. }));
+        input.tags.push(delegation);
+
+        let event = Self::new(input, privkey)?;
+        match event.delegation() {
+            EventDelegation::InvalidDelegation(reason) => Err(Error::InvalidDelegation(reason)),
+            _ => Ok(event),
+        }
+    }
+
     /// Create a new event with proof of work.
     ///
     /// This can take a long time, and is only cancellable by killing the thread.
@@ -159,16 +358,11 @@ impl Event {
         zero_bits: u8,
         work_sender: Option<Sender<u8>>,
     ) -> Result<Event, Error> {
-        let target = Some(format!("{zero_bits}"));
-
         // Strip any pre-existing nonce tags
         input.tags.retain(|t| !matches!(t, Tag::Nonce { .. }));
 
         // Add nonce tag to the end
-        input.tags.push(Tag::Nonce {
-            nonce: "0".to_string(),
-            target: target.clone(),
-        });
+        input.tags.push(Tag::new_nonce(0, zero_bits));
         let index = input.tags.len() - 1;
 
         let cores = num_cpus::get();
@@ -182,7 +376,6 @@ impl Event {
         for core in 0..cores {
             let mut attempt: u64 = core as u64 * (u64::MAX / cores as u64);
             let mut input = input.clone();
-            let target = target.clone();
             let index = index;
             let quitting = quitting.clone();
             let nonce = nonce.clone();
@@ -190,19 +383,15 @@ impl Event {
             let best_work = best_work.clone();
             let work_sender = work_sender.clone();
             let join_handle = thread::spawn(move || {
+                let mut buf: Vec<u8> = Vec::new();
                 loop {
                     if quitting.load(Ordering::Relaxed) {
                         break;
                     }
 
-                    input.tags[index] = Tag::Nonce {
-                        nonce: format!("{attempt}"),
-                        target: target.clone(),
-                    };
-
-                    let Id(id) = Self::hash(&input).unwrap();
+                    input.tags[index] = Tag::new_nonce(attempt, zero_bits);
 
-                    let leading_zeroes = get_leading_zero_bits(&id);
+                    let leading_zeroes = input.compute_id_into(&mut buf).leading_zero_bits();
                     if leading_zeroes >= zero_bits {
                         nonce.store(attempt, Ordering::Relaxed);
                         quitting.store(true, Ordering::Relaxed);
@@ -230,10 +419,7 @@ impl Event {
         }
 
         // We found the nonce. Do it for reals
-        input.tags[index] = Tag::Nonce {
-            nonce: format!("{}", nonce.load(Ordering::Relaxed)),
-            target,
-        };
+        input.tags[index] = Tag::new_nonce(nonce.load(Ordering::Relaxed), zero_bits);
         let id = Self::hash(&input).unwrap();
 
         // Signature
@@ -251,29 +437,40 @@ impl Event {
         })
     }
 
+    /// Compute the Id of this event from its contents, serializing the NIP-01
+    /// preimage into `buf` rather than a freshly allocated string. Reusing the
+    /// same buffer across many events avoids an allocation per event.
+    ///
+    /// This does not check the result against `self.id`.
+    pub fn compute_id_into(&self, buf: &mut Vec<u8>) -> Id {
+        hash_inner_event(
+            buf,
+            &self.pubkey,
+            &self.created_at,
+            &self.kind,
+            &self.tags,
+            &self.content,
+        )
+    }
+
     /// Check the validity of an event. This is useful if you deserialize an event
     /// from the network. If you create an event using new() it should already be
     /// trustworthy.
     pub fn verify(&self, maxtime: Option<Unixtime>) -> Result<(), Error> {
-        use k256::schnorr::signature::Verifier;
+        self.verify_with_buffer(maxtime, &mut Vec::new())
+    }
 
-        let serialized: String = serialize_inner_event!(
-            &self.pubkey,
-            &self.created_at,
-            &self.kind,
-            &self.tags,
-            &self.content
-        );
+    // As verify(), serializing into a reusable buffer (see compute_id_into())
+    pub(crate) fn verify_with_buffer(
+        &self,
+        maxtime: Option<Unixtime>,
+        buf: &mut Vec<u8>,
+    ) -> Result<(), Error> {
+        // Hash once, and verify the signature against that hash
+        let id = self.compute_id_into(buf);
 
         // Verify the signature
-        self.pubkey.0.verify(serialized.as_bytes(), &self.sig.0)?;
-
-        // Also verify the ID is the SHA256
-        // (the above verify function also does it internally,
-        //  so there is room for improvement here)
-        let mut hasher = Sha256::new();
-        hasher.update(serialized.as_bytes());
-        let id = hasher.finalize();
+        self.pubkey.verify_id(id, self.sig)?;
 
         // Optional verify that the message was in the past
         if let Some(mt) = maxtime {
@@ -282,13 +479,19 @@ impl Event {
             }
         }
 
-        if *id != self.id.0 {
+        if id != self.id {
             Err(Error::HashMismatch)
         } else {
             Ok(())
         }
     }
 
+    /// Verify the event (see `verify()`), turning it into a `VerifiedEvent` so
+    /// that it need not be verified again
+    pub fn into_verified(self, maxtime: Option<Unixtime>) -> Result<VerifiedEvent, Error> {
+        VerifiedEvent::try_new(self, maxtime)
+    }
+
     // Mock data for testing
     #[allow(dead_code)]
     pub(crate) fn mock() -> Event {
@@ -305,6 +508,31 @@ impl Event {
         Event::new(pre, &private_key).unwrap()
     }
 
+    /// Parse an event from JSON, recording the outcome in `stats`
+    pub fn parse_with_stats(json: &str, stats: &IngestStats) -> Result<Event, Error> {
+        match serde_json::from_str::<Event>(json) {
+            Ok(event) => {
+                stats.record_event(event.kind, json.len());
+                Ok(event)
+            }
+            Err(e) => {
+                stats.record_parse_failure(&e, json.len());
+                Err(e.into())
+            }
+        }
+    }
+
+    /// Verify the event (see `verify()`), recording the outcome in `stats`
+    pub fn verify_with_stats(
+        &self,
+        maxtime: Option<Unixtime>,
+        stats: &IngestStats,
+    ) -> Result<(), Error> {
+        let result = self.verify(maxtime);
+        stats.record_verification(&result);
+        result
+    }
+
     /// Create an event that sets Metadata
     pub fn new_set_metadata(
         mut input: PreEvent,
@@ -672,6 +900,26 @@ impl Event {
         output
     }
 
+    /// The events this event quotes (NIP-18 'q' tags), with optional
+    /// recommended relay Urls
+    pub fn quoted_events(&self) -> Vec<(Id, Option<RelayUrl>)> {
+        let mut output: Vec<(Id, Option<RelayUrl>)> = Vec::new();
+
+        for tag in self.tags.iter() {
+            if let Tag::Other { tag, data } = tag {
+                if tag != "q" || data.is_empty() {
+                    continue;
+                }
+                if let Ok(id) = Id::try_from_hex_string(&data[0]) {
+                    let relay_url = data.get(1).and_then(|r| RelayUrl::try_from_str(r).ok());
+                    output.push((id, relay_url));
+                }
+            }
+        }
+
+        output
+    }
+
     /// If this event reacts to another, get that other event's Id,
     /// the reaction content, and an optional Recommended relay Url
     pub fn reacts_to(&self) -> Option<(Id, String, Option<RelayUrl>)> {
@@ -754,23 +1002,51 @@ impl Event {
         None
     }
 
-    /// If this event specifies a content warning, return that subject string
-    pub fn content_warning(&self) -> Option<String> {
+    /// If this event specifies a content warning (NIP-36), return it along with
+    /// the reason, if one was given
+    pub fn content_warning(&self) -> Option<Option<String>> {
         for tag in self.tags.iter() {
-            if let Tag::ContentWarning(warn) = tag {
-                return Some(warn.clone());
+            match tag {
+                Tag::ContentWarning(warn) if warn.is_empty() => return Some(None),
+                Tag::ContentWarning(warn) => return Some(Some(warn.clone())),
+                Tag::Other { tag, .. } if tag == "content-warning" => return Some(None),
+                _ => {}
             }
         }
 
         None
     }
 
-    /// If this is a parameterized event, get the parameter
+    /// If this event expires (NIP-40), get the time at which it expires
+    pub fn expires_at(&self) -> Option<Unixtime> {
+        self.tags.iter().find_map(|tag| match tag {
+            Tag::Expiration(time) => Some(*time),
+            _ => None,
+        })
+    }
+
+    /// Whether this event has expired as of `now` (NIP-40). Clients should not
+    /// show expired events, and relays should neither store nor serve them.
+    pub fn is_expired(&self, now: Unixtime) -> bool {
+        match self.expires_at() {
+            Some(expiration) => expiration <= now,
+            None => false,
+        }
+    }
+
+    /// Whether this event is protected (NIP-70). Relays should only accept a
+    /// protected event from a client authenticated (NIP-42) as its author.
+    pub fn is_protected(&self) -> bool {
+        self.tags.contains(&Tag::Protected)
+    }
+
+    /// If this is a parameterized replaceable event, get the parameter from its
+    /// 'd' tag
     pub fn parameter(&self) -> Option<String> {
         if self.kind.is_parameterized_replaceable() {
             for tag in self.tags.iter() {
-                if let Tag::Parameter(param) = tag {
-                    return Some(param.to_owned());
+                if let Tag::Identifier(d) = tag {
+                    return Some(d.to_owned());
                 }
             }
             Some("".to_owned()) // implicit
@@ -779,6 +1055,21 @@ impl Event {
         }
     }
 
+    /// If this is a replaceable or parameterized replaceable event, get its address
+    pub fn addr(&self) -> Option<EventAddr> {
+        let d = if self.kind.is_replaceable() {
+            "".to_owned()
+        } else {
+            self.parameter()?
+        };
+        Some(EventAddr {
+            d,
+            relays: vec![],
+            kind: self.kind,
+            author: self.pubkey,
+        })
+    }
+
     /// Return all the hashtags this event refers to
     pub fn hashtags(&self) -> Vec<String> {
         if self.kind != EventKind::TextNote {
@@ -815,100 +1106,127 @@ impl Event {
         output
     }
 
-    /// Get the proof-of-work count of leading bits
+    /// Get the proof-of-work count of leading bits. This is no more than the
+    /// target committed to in the nonce tag, so that events which were lucky
+    /// rather than worked for do not count (NIP-13).
     pub fn pow(&self) -> u8 {
         // Count leading bits in the Id field
-        let zeroes: u8 = get_leading_zero_bits(&self.id.0);
+        let zeroes: u8 = self.id.leading_zero_bits();
 
         // Check that they meant it
-        let mut target_zeroes: u8 = 0;
-        for tag in self.tags.iter() {
-            if let Tag::Nonce { nonce: _, target } = tag {
-                if let Some(t) = target {
-                    target_zeroes = t.parse::<u8>().unwrap_or(0);
-                }
-                break;
-            }
-        }
+        let target_zeroes: u8 = self
+            .tags
+            .iter()
+            .find(|t| matches!(t, Tag::Nonce { .. }))
+            .and_then(|t| t.nonce_target())
+            .unwrap_or(0);
 
         zeroes.min(target_zeroes)
     }
 
+    /// Check that the event has at least `min_difficulty` bits of proof of work
+    /// (NIP-13), counting both the Id hash and the target committed to in its
+    /// nonce tag (see `pow()`)
+    pub fn check_pow(&self, min_difficulty: u8) -> Result<(), Error> {
+        let pow = self.pow();
+        if pow < min_difficulty {
+            Err(Error::InsufficientPow(pow, min_difficulty))
+        } else {
+            Ok(())
+        }
+    }
+
+    /// If this is a chess event (NIP-64), parse and validate the PGN content
+    pub fn chess_game(&self) -> Result<ChessGame, Error> {
+        if self.kind != EventKind::Chess {
+            return Err(Error::WrongEventKind);
+        }
+        ChessGame::try_from_pgn(&self.content)
+    }
+
+    /// The content, with any legacy mentions (NIP-08) rewritten as `nostr:`
+    /// URIs (see `rewrite_legacy_mentions()`)
+    pub fn content_with_nip27_mentions(&self) -> String {
+        rewrite_legacy_mentions(&self.content, &self.tags)
+    }
+
+    /// The media attachments described by the 'imeta' tags (NIP-92). Invalid
+    /// tags are skipped.
+    pub fn imeta(&self) -> Vec<IMeta> {
+        self.tags
+            .iter()
+            .filter_map(|tag| IMeta::try_from_tag(tag).ok())
+            .collect()
+    }
+
+    /// The media attachment for a URL in the content, if it has an 'imeta' tag
+    pub fn imeta_for(&self, url: &str) -> Option<IMeta> {
+        self.imeta().into_iter().find(|imeta| imeta.url.0 == url)
+    }
+
+    /// If this is a metadata event (kind 0), parse its content
+    pub fn metadata(&self) -> Result<Metadata, Error> {
+        if self.kind != EventKind::Metadata {
+            return Err(Error::WrongEventKind);
+        }
+        Ok(serde_json::from_str(&self.content)?)
+    }
+
+    /// Check the event against a content policy, as a relay would before
+    /// accepting it. The error is a `ContentPolicyViolation` for the first
+    /// limit broken, whose `to_reason()` can be sent back in the OK message.
+    pub fn validate_against(&self, policy: &ContentPolicy) -> Result<(), Error> {
+        match policy.check(self) {
+            Some(violation) => Err(violation.into()),
+            None => Ok(()),
+        }
+    }
+
     /// Was this event delegated, was that valid, and if so what is the pubkey of
     /// the delegator?
     pub fn delegation(&self) -> EventDelegation {
         for tag in self.tags.iter() {
-            if let Tag::Delegation {
-                pubkey,
-                conditions,
-                sig,
-            } = tag
-            {
-                // Convert hex strings into functional types
-                let signature = match Signature::try_from_hex_string(sig) {
-                    Ok(sig) => sig,
-                    Err(e) => return EventDelegation::InvalidDelegation(format!("{e}")),
-                };
-                let delegator_pubkey = match PublicKey::try_from_hex_string(pubkey) {
-                    Ok(pk) => pk,
-                    Err(e) => return EventDelegation::InvalidDelegation(format!("{e}")),
-                };
-
-                // Verify the delegation tag
-                match conditions.verify_signature(&delegator_pubkey, &self.pubkey, signature) {
-                    Ok(_) => {
-                        // Check conditions
-                        if let Some(kind) = conditions.kind {
-                            if self.kind != kind {
-                                return EventDelegation::InvalidDelegation(
-                                    "Event Kind not delegated".to_owned(),
-                                );
-                            }
-                        }
-                        if let Some(created_after) = conditions.created_after {
-                            if self.created_at < created_after {
-                                return EventDelegation::InvalidDelegation(
-                                    "Event created before delegation started".to_owned(),
-                                );
-                            }
-                        }
-                        if let Some(created_before) = conditions.created_before {
-                            if self.created_at > created_before {
-                                return EventDelegation::InvalidDelegation(
-                                    "Event created after delegation ended".to_owned(),
-                                );
-                            }
-                        }
-                        return EventDelegation::DelegatedBy(delegator_pubkey);
+            if let Tag::Delegation { .. } = tag {
+                return match tag.validate_delegation_for_event(self) {
+                    Ok(delegator) => EventDelegation::DelegatedBy(delegator),
+                    Err(Error::InvalidDelegation(reason)) => {
+                        EventDelegation::InvalidDelegation(reason)
                     }
-                    Err(e) => {
-                        return EventDelegation::InvalidDelegation(format!("{e}"));
+                    Err(Error::DelegationConditionFailed(failure)) => {
+                        EventDelegation::InvalidDelegation(format!("{failure}"))
                     }
-                }
+                    Err(e) => EventDelegation::InvalidDelegation(format!("{e}")),
+                };
             }
         }
 
         EventDelegation::NotDelegated
     }
-}
 
-#[inline]
-fn get_leading_zero_bits(bytes: &[u8]) -> u8 {
-    let mut res = 0_u8;
-    for b in bytes {
-        if *b == 0 {
-            res += 8;
-        } else {
-            res += b.leading_zeros() as u8;
-            return res;
+    /// The author of the event: the delegator if the event carries a valid
+    /// delegation (NIP-26), otherwise the pubkey that signed it.
+    pub fn effective_author(&self) -> PublicKey {
+        match self.delegation() {
+            EventDelegation::DelegatedBy(delegator) => delegator,
+            _ => self.pubkey,
+        }
+    }
+
+    /// Verify the event (see `verify()`), and also verify the delegation (NIP-26)
+    /// signature and conditions if the event was delegated.
+    pub fn verify_with_delegation(&self, maxtime: Option<Unixtime>) -> Result<(), Error> {
+        self.verify(maxtime)?;
+        match self.delegation() {
+            EventDelegation::InvalidDelegation(reason) => Err(Error::InvalidDelegation(reason)),
+            _ => Ok(()),
         }
     }
-    res
 }
 
 #[cfg(test)]
 mod test {
     use crate::types::*;
+    use crate::Error;
 
     test_serde! {Event, test_event_serde}
 
@@ -988,6 +1306,36 @@ mod test {
         Event::new(preevent, &privkey).unwrap()
     }
 
+    #[test]
+    fn test_event_compute_id_into() {
+        let mut buf: Vec<u8> = Vec::new();
+        let other = Event::mock();
+        assert_eq!(other.compute_id_into(&mut buf), other.id);
+
+        // The buffer is reused and holds the NIP-01 preimage
+        let event = Event::mock();
+        assert_eq!(event.compute_id_into(&mut buf), event.id);
+        let expected = format!(
+            "[0,{},{},{},{},{}]",
+            serde_json::to_string(&event.pubkey).unwrap(),
+            serde_json::to_string(&event.created_at).unwrap(),
+            serde_json::to_string(&event.kind).unwrap(),
+            serde_json::to_string(&event.tags).unwrap(),
+            serde_json::to_string(&event.content).unwrap()
+        );
+        assert_eq!(buf, expected.as_bytes());
+
+        let pre_event = PreEvent {
+            pubkey: event.pubkey,
+            created_at: event.created_at,
+            kind: event.kind,
+            tags: event.tags.clone(),
+            content: event.content.clone(),
+            ots: None,
+        };
+        assert_eq!(pre_event.compute_id_into(&mut buf), event.id);
+    }
+
     #[test]
     fn test_event_with_delegation_ok() {
         let delegator_privkey = PrivateKey::mock();
@@ -1013,7 +1361,10 @@ mod test {
         // check delegation
         if let EventDelegation::InvalidDelegation(reason) = event.delegation() {
             // expected type, check returned delegator key
-            assert_eq!(reason, "Event created after delegation ended");
+            assert_eq!(
+                reason,
+                "Event created after delegation ended: created_at 1690000000 does not meet created_at<1680050000"
+            );
         } else {
             panic!(
                 "Expected InvalidDelegation result, got {:?}",
@@ -1031,7 +1382,10 @@ mod test {
         // check delegation
         if let EventDelegation::InvalidDelegation(reason) = event.delegation() {
             // expected type, check returned delegator key
-            assert_eq!(reason, "Event created before delegation started");
+            assert_eq!(
+                reason,
+                "Event created before delegation started: created_at 1610000000 does not meet created_at>1680000000"
+            );
         } else {
             panic!(
                 "Expected InvalidDelegation result, got {:?}",
@@ -1039,4 +1393,319 @@ mod test {
             );
         }
     }
+
+    #[test]
+    fn test_event_chess_game() {
+        let privkey = PrivateKey::mock();
+        let preevent = PreEvent {
+            pubkey: privkey.public_key(),
+            created_at: Unixtime::mock(),
+            kind: EventKind::Chess,
+            tags: vec![],
+            content: "[White \"Alice\"]\n[Black \"Bob\"]\n\n1. e4 e5 *".to_string(),
+            ots: None,
+        };
+        let event = Event::new(preevent, &privkey).unwrap();
+        let game = event.chess_game().unwrap();
+        assert_eq!(game.white(), Some("Alice"));
+        assert_eq!(game.result(), Some(ChessResult::Unknown));
+    }
+
+    #[test]
+    fn test_event_effective_author() {
+        let delegator_privkey = PrivateKey::mock();
+        let delegator_pubkey = delegator_privkey.public_key();
+        let event = create_event_with_delegation(delegator_privkey, Unixtime(1680000012));
+        assert_eq!(event.effective_author(), delegator_pubkey);
+        assert!(event.verify_with_delegation(None).is_ok());
+
+        let delegator_privkey = PrivateKey::mock();
+        let event = create_event_with_delegation(delegator_privkey, Unixtime(1690000000));
+        assert_eq!(event.effective_author(), event.pubkey);
+        assert!(event.verify(None).is_ok());
+        assert!(matches!(
+            event.verify_with_delegation(None),
+            Err(Error::InvalidDelegation(_))
+        ));
+
+        let event = Event::mock();
+        assert_eq!(event.effective_author(), event.pubkey);
+        assert!(event.verify_with_delegation(None).is_ok());
+    }
+
+    #[test]
+    fn test_event_new_delegated() {
+        let delegator_privkey = PrivateKey::generate();
+        let delegatee_privkey = PrivateKey::generate();
+        let delegation = Tag::new_delegation(
+            &delegator_privkey,
+            &delegatee_privkey.public_key(),
+            &DelegationConditions::new()
+                .kind(EventKind::TextNote)
+                .created_after(Unixtime(1680000000)),
+        )
+        .unwrap();
+
+        let preevent = PreEvent {
+            pubkey: delegatee_privkey.public_key(),
+            created_at: Unixtime(1680000012),
+            kind: EventKind::TextNote,
+            tags: vec![],
+            content: "Hello on behalf of someone else".to_string(),
+            ots: None,
+        };
+        let event =
+            Event::new_delegated(preevent.clone(), &delegatee_privkey, delegation.clone()).unwrap();
+        assert!(event.verify_with_delegation(None).is_ok());
+        assert_eq!(event.effective_author(), delegator_privkey.public_key());
+
+        // Conditions not met
+        let mut bad = preevent;
+        bad.kind = EventKind::Reaction;
+        assert!(Event::new_delegated(bad, &delegatee_privkey, delegation).is_err());
+    }
+
+    #[test]
+    fn test_event_check_pow() {
+        let privkey = PrivateKey::generate();
+        let preevent = PreEvent {
+            pubkey: privkey.public_key(),
+            created_at: Unixtime::now().unwrap(),
+            kind: EventKind::TextNote,
+            tags: vec![],
+            content: "Work for it".to_string(),
+            ots: None,
+        };
+        let event = Event::new_with_pow(preevent.clone(), &privkey, 8, None).unwrap();
+        assert!(event.id.leading_zero_bits() >= 8);
+        assert_eq!(event.tags.last().unwrap().nonce_target(), Some(8));
+        assert_eq!(event.pow(), 8);
+        assert!(event.check_pow(8).is_ok());
+        assert!(matches!(
+            event.check_pow(9),
+            Err(Error::InsufficientPow(8, 9))
+        ));
+
+        // Without a committed target, lucky hashes do not count
+        let mut preevent = preevent;
+        preevent.tags = vec![Tag::Nonce {
+            nonce: "0".to_string(),
+            target: None,
+        }];
+        let event = Event::new(preevent, &privkey).unwrap();
+        assert_eq!(event.pow(), 0);
+        assert!(event.check_pow(1).is_err());
+        assert!(event.check_pow(0).is_ok());
+    }
+
+    #[test]
+    fn test_event_expiration() {
+        let privkey = PrivateKey::generate();
+        let event = EventBuilder::new()
+            .content("Gone soon")
+            .created_at(Unixtime(1_700_000_000))
+            .expiration(Unixtime(1_700_000_600))
+            .sign(&privkey)
+            .unwrap();
+        assert_eq!(event.expires_at(), Some(Unixtime(1_700_000_600)));
+        assert!(!event.is_expired(Unixtime(1_700_000_599)));
+        assert!(event.is_expired(Unixtime(1_700_000_600)));
+
+        let event = EventBuilder::new()
+            .content("Forever")
+            .sign(&privkey)
+            .unwrap();
+        assert_eq!(event.expires_at(), None);
+        assert!(!event.is_expired(Unixtime(i64::MAX)));
+    }
+
+    #[test]
+    fn test_event_content_warning() {
+        let privkey = PrivateKey::generate();
+        let event = EventBuilder::new()
+            .content_warning(Some("spoilers"))
+            .sign(&privkey)
+            .unwrap();
+        assert_eq!(event.content_warning(), Some(Some("spoilers".to_owned())));
+
+        let event = EventBuilder::new()
+            .content_warning(None)
+            .sign(&privkey)
+            .unwrap();
+        assert_eq!(event.content_warning(), Some(None));
+
+        // A reasonless tag survives the trip through JSON
+        let json = serde_json::to_string(&event).unwrap();
+        assert!(json.contains(r#"["content-warning"]"#));
+        let event: Event = serde_json::from_str(&json).unwrap();
+        assert!(event.verify(None).is_ok());
+        assert_eq!(event.content_warning(), Some(None));
+
+        let event = EventBuilder::new().sign(&privkey).unwrap();
+        assert_eq!(event.content_warning(), None);
+    }
+
+    #[test]
+    fn test_event_parameter_and_addr() {
+        let privkey = PrivateKey::generate();
+        let event = EventBuilder::new()
+            .kind(EventKind::LongFormContent)
+            .tag(Tag::Identifier("my-article".to_owned()))
+            .sign(&privkey)
+            .unwrap();
+        assert_eq!(event.parameter(), Some("my-article".to_owned()));
+        let addr = event.addr().unwrap();
+        assert_eq!(addr.kind, EventKind::LongFormContent);
+        assert_eq!(addr.author, privkey.public_key());
+        assert_eq!(addr.d, "my-article");
+
+        // The 'd' tag is implicitly empty
+        let event = EventBuilder::new()
+            .kind(EventKind::LongFormContent)
+            .sign(&privkey)
+            .unwrap();
+        assert_eq!(event.parameter(), Some("".to_owned()));
+
+        let event = EventBuilder::new()
+            .kind(EventKind::Metadata)
+            .sign(&privkey)
+            .unwrap();
+        assert_eq!(event.parameter(), None);
+        assert_eq!(event.addr().unwrap().d, "");
+
+        let event = EventBuilder::new().sign(&privkey).unwrap();
+        assert_eq!(event.addr(), None);
+    }
+
+    #[test]
+    fn test_pre_event_validate() {
+        let privkey = PrivateKey::generate();
+        let pre_event = |kind: EventKind, tags: Vec<Tag>, content: &str| {
+            EventBuilder::new()
+                .kind(kind)
+                .tags(tags)
+                .content(content)
+                .to_pre_event(privkey.public_key())
+                .unwrap()
+        };
+        let p_tag = Tag::Pubkey {
+            pubkey: PublicKeyHex::mock(),
+            recommended_relay_url: None,
+            petname: None,
+        };
+        let e_tag = Tag::Event {
+            id: Id::mock(),
+            recommended_relay_url: None,
+            marker: None,
+        };
+        let failure = |pre_event: PreEvent| match pre_event.validate() {
+            Err(Error::PreEventInvalid(failure)) => Some(failure),
+            _ => None,
+        };
+
+        assert!(pre_event(EventKind::Metadata, vec![], r#"{"name":"bob"}"#)
+            .validate()
+            .is_ok());
+        assert!(matches!(
+            failure(pre_event(EventKind::Metadata, vec![], "bob")),
+            Some(PreEventValidationFailure::InvalidMetadata(_))
+        ));
+
+        assert!(pre_event(EventKind::ContactList, vec![p_tag.clone()], "")
+            .validate()
+            .is_ok());
+        assert_eq!(
+            failure(pre_event(
+                EventKind::ContactList,
+                vec![p_tag.clone(), e_tag.clone()],
+                ""
+            )),
+            Some(PreEventValidationFailure::ContactListNonPubkeyTag { index: 1 })
+        );
+
+        assert!(pre_event(
+            EventKind::LongFormContent,
+            vec![Tag::Identifier("x".to_owned())],
+            ""
+        )
+        .validate()
+        .is_ok());
+        assert_eq!(
+            failure(pre_event(EventKind::LongFormContent, vec![], "")),
+            Some(PreEventValidationFailure::MissingIdentifier)
+        );
+
+        let dm = PreEvent::new_nip04(&privkey, PrivateKey::generate().public_key(), "hi").unwrap();
+        assert!(dm.validate().is_ok());
+        assert_eq!(
+            failure(pre_event(EventKind::EncryptedDirectMessage, vec![], "hi")),
+            Some(PreEventValidationFailure::DirectMessageRecipients { count: 0 })
+        );
+        assert_eq!(
+            failure(pre_event(
+                EventKind::EncryptedDirectMessage,
+                vec![p_tag],
+                "hi"
+            )),
+            Some(PreEventValidationFailure::DirectMessageNotEncrypted)
+        );
+
+        assert!(pre_event(EventKind::EventDeletion, vec![e_tag.clone()], "")
+            .validate()
+            .is_ok());
+        assert_eq!(
+            failure(pre_event(EventKind::EventDeletion, vec![], "")),
+            Some(PreEventValidationFailure::DeletionWithoutTarget)
+        );
+        assert_eq!(
+            failure(pre_event(EventKind::Reaction, vec![], "+")),
+            Some(PreEventValidationFailure::ReactionWithoutTarget)
+        );
+
+        assert!(pre_event(EventKind::TextNote, vec![], "")
+            .validate()
+            .is_ok());
+    }
+
+    #[test]
+    fn test_event_metadata() {
+        let privkey = PrivateKey::mock();
+        let pre_event = PreEvent {
+            pubkey: privkey.public_key(),
+            created_at: Unixtime::mock(),
+            kind: EventKind::TextNote,
+            tags: vec![],
+            content: "".to_owned(),
+            ots: None,
+        };
+        let event = Event::new_set_metadata(pre_event, &privkey, Metadata::mock()).unwrap();
+        assert_eq!(event.metadata().unwrap(), Metadata::mock());
+
+        let mut event = event;
+        event.content = "not json".to_owned();
+        assert!(event.metadata().is_err());
+        event.kind = EventKind::TextNote;
+        assert!(matches!(event.metadata(), Err(Error::WrongEventKind)));
+    }
+
+    #[test]
+    fn test_event_protected() {
+        let privkey = PrivateKey::generate();
+        let event = EventBuilder::new()
+            .protected()
+            .protected()
+            .sign(&privkey)
+            .unwrap();
+        assert!(event.is_protected());
+        assert_eq!(event.tags, vec![Tag::Protected]);
+
+        let json = serde_json::to_string(&event).unwrap();
+        assert!(json.contains(r#""tags":[["-"]]"#));
+        let event: Event = serde_json::from_str(&json).unwrap();
+        assert!(event.verify(None).is_ok());
+        assert!(event.is_protected());
+
+        let event = EventBuilder::new().sign(&privkey).unwrap();
+        assert!(!event.is_protected());
+    }
 }
@@ -0,0 +1,287 @@
+use super::{EventKind, PublicKey, PublicKeyHex, Tag, UncheckedUrl};
+use crate::Error;
+use bech32::{FromBase32, ToBase32};
+use serde::{Deserialize, Serialize};
+#[cfg(feature = "speedy")]
+use speedy::{Readable, Writable};
+
+/// The address of a replaceable or parameterized replaceable event: its kind,
+/// author and 'd' tag, which together identify the latest version of the event
+/// (NIP-01, NIP-33), along with some relays in which that event may be found.
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq)]
+#[cfg_attr(feature = "speedy", derive(Readable, Writable))]
+pub struct EventAddr {
+    /// The parameter in the 'd' tag (empty for replaceable events)
+    pub d: String,
+
+    /// Some of the relays where this could be found
+    pub relays: Vec<UncheckedUrl>,
+
+    /// Kind
+    pub kind: EventKind,
+
+    /// Author
+    pub author: PublicKey,
+}
+
+/// An event address, by its NIP-19 name
+pub type NAddr = EventAddr;
+
+impl EventAddr {
+    /// Render as the value of an 'a' tag, `<kind>:<pubkey hex>:<d>`
+    pub fn as_tag_value(&self) -> String {
+        format!(
+            "{}:{}:{}",
+            u64::from(self.kind),
+            self.author.as_hex_string(),
+            self.d
+        )
+    }
+
+    /// Parse the value of an 'a' tag, `<kind>:<pubkey hex>:<d>`. The 'd' part may
+    /// itself contain colons.
+    pub fn try_from_tag_value(value: &str) -> Result<EventAddr, Error> {
+        let (kind, pubkey, d) = split_tag_value(value).ok_or(Error::InvalidEventAddr)?;
+        Ok(EventAddr {
+            d: d.to_owned(),
+            relays: vec![],
+            kind: EventKind::from(kind),
+            author: PublicKey::try_from(pubkey)?,
+        })
+    }
+
+    /// Check that this can be exported as a bech32 encoded string ("naddr"): its
+    /// 'd' must be at most 255 bytes long, and its kind must fit in 32 bits.
+    pub fn check_bech32(&self) -> Result<(), Error> {
+        if self.d.len() > u8::MAX as usize || u64::from(self.kind) > u32::MAX as u64 {
+            return Err(Error::InvalidEventAddr);
+        }
+        Ok(())
+    }
+
+    /// Export as a bech32 encoded string ("naddr"). Relay URLs longer than
+    /// 255 bytes cannot be encoded, and are left out. A 'd' longer than 255
+    /// bytes, or a kind beyond 32 bits, cannot be encoded either, so this
+    /// returns an error for them (see `check_bech32()`).
+    pub fn as_bech32_string(&self) -> Result<String, Error> {
+        self.check_bech32()?;
+
+        // Compose
+        let mut tlv: Vec<u8> = Vec::new();
+
+        // Push 'd'
+        tlv.push(0); // the special value, in this case the 'd' identifier
+        tlv.push(self.d.len() as u8); // the length of the string
+        tlv.extend(self.d.as_bytes());
+
+        // Push relays
+        for relay in &self.relays {
+            let Ok(len) = u8::try_from(relay.0.len()) else {
+                continue;
+            };
+            tlv.push(1); // type 'relay'
+            tlv.push(len); // the length of the string
+            tlv.extend(relay.0.as_bytes());
+        }
+
+        // Push author
+        tlv.push(2); // type 'author'
+        tlv.push(32); // the length of the value (always 32 for public key)
+        tlv.extend(self.author.as_bytes());
+
+        // Push kind
+        let kind = u64::from(self.kind) as u32;
+        tlv.push(3); // type 'kind'
+        tlv.push(4); // the length of the value (always 4 for kind)
+        tlv.extend(kind.to_be_bytes());
+
+        Ok(bech32::encode(
+            "naddr",
+            tlv.to_base32(),
+            bech32::Variant::Bech32,
+        )?)
+    }
+
+    /// Import from a bech32 encoded string ("naddr")
+    pub fn try_from_bech32_string(s: &str) -> Result<EventAddr, Error> {
+        let data = bech32::decode(s)?;
+        if data.0 != "naddr" {
+            return Err(Error::WrongBech32("naddr".to_string(), data.0));
+        }
+        let mut d: Option<String> = None;
+        let mut relays: Vec<UncheckedUrl> = Vec::new();
+        let mut author: Option<PublicKey> = None;
+        let mut kind: Option<EventKind> = None;
+        let tlv = Vec::<u8>::from_base32(&data.1)?;
+        let mut pos = 0;
+        loop {
+            // we need at least 2 more characters for anything meaningful
+            if pos + 2 > tlv.len() {
+                break;
+            }
+            let ty = tlv[pos];
+            let len = tlv[pos + 1] as usize;
+            pos += 2;
+            if pos + len > tlv.len() {
+                return Err(Error::InvalidEventAddr);
+            }
+            let value = &tlv[pos..pos + len];
+            match ty {
+                0 => {
+                    // special, the 'd' identifier
+                    d = Some(std::str::from_utf8(value)?.to_owned());
+                }
+                1 => {
+                    // relay
+                    relays.push(UncheckedUrl::from_str(std::str::from_utf8(value)?));
+                }
+                2 => {
+                    // author, 32 bytes of public key
+                    if len != 32 {
+                        return Err(Error::InvalidEventAddr);
+                    }
+                    author = Some(PublicKey::from_bytes(value)?);
+                }
+                3 => {
+                    // kind, 4 bytes big-endian
+                    let bytes: [u8; 4] = value.try_into().map_err(|_| Error::InvalidEventAddr)?;
+                    kind = Some(EventKind::from(u64::from(u32::from_be_bytes(bytes))));
+                }
+                _ => {} // unhandled type for naddr
+            }
+            pos += len;
+        }
+        match (d, author, kind) {
+            (Some(d), Some(author), Some(kind)) => Ok(EventAddr {
+                d,
+                relays,
+                kind,
+                author,
+            }),
+            _ => Err(Error::InvalidEventAddr),
+        }
+    }
+
+    /// Convert into an 'a' tag, with the first relay (if any) as the
+    /// recommended relay URL
+    pub fn to_tag(&self) -> Tag {
+        Tag::Address {
+            kind: self.kind,
+            pubkey: self.author.into(),
+            d: self.d.clone(),
+            relay_url: self.relays.first().cloned(),
+            marker: None,
+        }
+    }
+
+    /// Convert from an 'a' tag
+    pub fn try_from_tag(tag: &Tag) -> Result<EventAddr, Error> {
+        match tag {
+            Tag::Address {
+                kind,
+                pubkey,
+                d,
+                relay_url,
+                ..
+            } => Ok(EventAddr {
+                d: d.clone(),
+                relays: relay_url.iter().cloned().collect(),
+                kind: *kind,
+                author: PublicKey::try_from(pubkey.clone())?,
+            }),
+            _ => Err(Error::InvalidEventAddr),
+        }
+    }
+
+    // Mock data for testing
+    #[allow(dead_code)]
+    pub(crate) fn mock() -> EventAddr {
+        EventAddr {
+            d: "Test D Identifier 1lkjf23".to_string(),
+            relays: vec![UncheckedUrl::from_str("wss://relay.example.com")],
+            kind: EventKind::LongFormContent,
+            author: PublicKey::mock(),
+        }
+    }
+}
+
+// Split an 'a' tag value into its kind, pubkey hex and 'd' parts
+pub(crate) fn split_tag_value(value: &str) -> Option<(u64, PublicKeyHex, &str)> {
+    let mut parts = value.splitn(3, ':');
+    let kind = parts.next()?.parse::<u64>().ok()?;
+    let pubkey = PublicKeyHex::try_from_str(parts.next()?).ok()?;
+    let d = parts.next()?;
+    Some((kind, pubkey, d))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    test_serde! {EventAddr, test_event_addr_serde}
+
+    #[test]
+    fn test_event_addr_tag() {
+        let addr = EventAddr::mock();
+        let tag = addr.to_tag();
+        assert_eq!(EventAddr::try_from_tag(&tag).unwrap(), addr);
+
+        let json = serde_json::to_string(&tag).unwrap();
+        assert_eq!(
+            json,
+            format!(
+                r#"["a","30023:{}:Test D Identifier 1lkjf23","wss://relay.example.com"]"#,
+                addr.author.as_hex_string()
+            )
+        );
+        let tag2: Tag = serde_json::from_str(&json).unwrap();
+        assert_eq!(tag2, tag);
+
+        let value = addr.as_tag_value();
+        let mut parsed = EventAddr::try_from_tag_value(&value).unwrap();
+        assert!(parsed.relays.is_empty());
+        parsed.relays = addr.relays.clone();
+        assert_eq!(parsed, addr);
+
+        // 'd' may contain colons
+        let value = format!("30023:{}:a:b:c", addr.author.as_hex_string());
+        assert_eq!(EventAddr::try_from_tag_value(&value).unwrap().d, "a:b:c");
+
+        assert!(EventAddr::try_from_tag_value("30023:nothex:abc").is_err());
+        assert!(EventAddr::try_from_tag_value("kind:abc").is_err());
+
+        // Malformed 'a' tags are kept as other tags
+        let tag: Tag = serde_json::from_str(r#"["a","not an address"]"#).unwrap();
+        assert!(matches!(tag, Tag::Other { .. }));
+    }
+
+    #[test]
+    fn test_event_addr_bech32() {
+        let addr = EventAddr::mock();
+        let bech32 = addr.as_bech32_string().unwrap();
+        assert!(bech32.starts_with("naddr1"));
+        assert_eq!(EventAddr::try_from_bech32_string(&bech32).unwrap(), addr);
+
+        // A replaceable event has an empty 'd'
+        let mut replaceable = EventAddr::mock();
+        replaceable.d = String::new();
+        replaceable.kind = EventKind::ContactList;
+        let bech32 = replaceable.as_bech32_string().unwrap();
+        assert_eq!(
+            EventAddr::try_from_bech32_string(&bech32).unwrap(),
+            replaceable
+        );
+
+        let mut long = EventAddr::mock();
+        long.d = "x".repeat(256);
+        assert!(long.check_bech32().is_err());
+        assert!(long.as_bech32_string().is_err());
+
+        // The author and kind are required
+        let encode =
+            |tlv: &[u8]| bech32::encode("naddr", tlv.to_base32(), bech32::Variant::Bech32).unwrap();
+        assert!(EventAddr::try_from_bech32_string(&encode(&[0, 1, b'a'])).is_err());
+        assert!(EventAddr::try_from_bech32_string(&encode(&[0, 5, b'a'])).is_err());
+        assert!(EventAddr::try_from_bech32_string(&encode(&[])).is_err());
+    }
+}
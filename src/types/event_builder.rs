@@ -0,0 +1,378 @@
+use super::{
+    compose_mentions, Event, EventKind, EventPointer, IMeta, NostrBech32, NostrUrl, PreEvent,
+    ProfilePointer, PublicKey, Signer, Tag, UncheckedUrl, Unixtime,
+};
+use crate::Error;
+use linkify::{LinkFinder, LinkKind};
+
+/// A builder for events, so that the `PreEvent` fields and tags need not be
+/// assembled by hand.
+///
+/// ```
+/// # use nostr_types::{EventBuilder, EventKind, PrivateKey};
+/// let private_key = PrivateKey::generate();
+/// let event = EventBuilder::new()
+///     .kind(EventKind::TextNote)
+///     .content("Hello world")
+///     .sign(&private_key)
+///     .unwrap();
+/// assert_eq!(event.content, "Hello world");
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct EventBuilder {
+    kind: Option<EventKind>,
+    content: String,
+    tags: Vec<Tag>,
+    created_at: Option<Unixtime>,
+}
+
+impl EventBuilder {
+    /// Start building a new event. Unless otherwise specified, it will be a
+    /// `TextNote` with empty content, created at the time it is built.
+    pub fn new() -> EventBuilder {
+        EventBuilder::default()
+    }
+
+    /// Set the kind of the event
+    pub fn kind(mut self, kind: EventKind) -> EventBuilder {
+        self.kind = Some(kind);
+        self
+    }
+
+    /// Set the content of the event
+    pub fn content(mut self, content: &str) -> EventBuilder {
+        self.content = content.to_owned();
+        self
+    }
+
+    /// Add a tag to the event
+    pub fn tag(mut self, tag: Tag) -> EventBuilder {
+        self.tags.push(tag);
+        self
+    }
+
+    /// Add several tags to the event
+    pub fn tags<I: IntoIterator<Item = Tag>>(mut self, tags: I) -> EventBuilder {
+        self.tags.extend(tags);
+        self
+    }
+
+    /// Set the time at which the event was created, rather than the time it is built
+    pub fn created_at(mut self, created_at: Unixtime) -> EventBuilder {
+        self.created_at = Some(created_at);
+        self
+    }
+
+    /// Set a time after which the event should be considered expired (NIP-40),
+    /// replacing any expiration set earlier
+    pub fn expiration(mut self, expiration: Unixtime) -> EventBuilder {
+        self.tags.retain(|t| !matches!(t, Tag::Expiration(_)));
+        self.tags.push(Tag::Expiration(expiration));
+        self
+    }
+
+    /// Mark the content as sensitive (NIP-36), with an optional reason, replacing
+    /// any content warning set earlier
+    pub fn content_warning(mut self, reason: Option<&str>) -> EventBuilder {
+        self.tags.retain(|t| match t {
+            Tag::ContentWarning(_) => false,
+            Tag::Other { tag, .. } => tag != "content-warning",
+            _ => true,
+        });
+        self.tags.push(Tag::new_content_warning(reason));
+        self
+    }
+
+    /// Mark the event as protected (NIP-70), so that relays only accept it when
+    /// published by its author
+    pub fn protected(mut self) -> EventBuilder {
+        if !self.tags.contains(&Tag::Protected) {
+            self.tags.push(Tag::Protected);
+        }
+        self
+    }
+
+    /// Make the event a reply to `event`, adding marked 'e' tags for the thread
+    /// root and the replied-to event (NIP-10), and 'p' tags for the author of
+    /// that event and everyone it tagged.
+    pub fn reply_to(mut self, event: &Event) -> EventBuilder {
+        match event.replies_to_root() {
+            Some((root, relay_url)) => {
+                self.tags.push(Tag::Event {
+                    id: root,
+                    recommended_relay_url: relay_url.map(|u| u.to_unchecked_url()),
+                    marker: Some("root".to_owned()),
+                });
+                self.tags.push(Tag::Event {
+                    id: event.id,
+                    recommended_relay_url: None,
+                    marker: Some("reply".to_owned()),
+                });
+            }
+            None => {
+                // Replying to the root itself
+                self.tags.push(Tag::Event {
+                    id: event.id,
+                    recommended_relay_url: None,
+                    marker: Some("root".to_owned()),
+                });
+            }
+        }
+
+        let pubkeys = std::iter::once(event.pubkey.into())
+            .chain(event.people().into_iter().map(|(pubkey, _, _)| pubkey));
+        for pubkey in pubkeys {
+            let already_tagged = self
+                .tags
+                .iter()
+                .any(|t| matches!(t, Tag::Pubkey { pubkey: pk, .. } if *pk == pubkey));
+            if !already_tagged {
+                self.tags.push(Tag::Pubkey {
+                    pubkey,
+                    recommended_relay_url: None,
+                    petname: None,
+                });
+            }
+        }
+
+        self
+    }
+
+    /// Quote `event` (NIP-18): add a 'q' tag for it, and a `nostr:nevent` URI
+    /// for it at the end of the content, where clients show the quoted event.
+    /// Set the content first. An event already quoted is not quoted again.
+    pub fn quote(mut self, event: &Event, relay_url: Option<UncheckedUrl>) -> EventBuilder {
+        let id = event.id.as_hex_string();
+        let already_quoted = self.tags.iter().any(
+            |t| matches!(t, Tag::Other { tag, data } if tag == "q" && data.first() == Some(&id)),
+        );
+        if already_quoted {
+            return self;
+        }
+
+        let pointer = EventPointer {
+            id: event.id,
+            relays: relay_url.iter().cloned().collect(),
+            author: Some(event.pubkey),
+            kind: Some(event.kind),
+        };
+        if !self.content.is_empty() && !self.content.ends_with(char::is_whitespace) {
+            self.content.push('\n');
+        }
+        self.content
+            .push_str(&NostrUrl(NostrBech32::EventPointer(pointer)).to_string());
+
+        self.tags.push(Tag::Other {
+            tag: "q".to_owned(),
+            data: vec![
+                id,
+                relay_url.map(|u| u.0).unwrap_or_default(),
+                event.pubkey.as_hex_string(),
+            ],
+        });
+        self
+    }
+
+    /// Set the content from a template mentioning people, adding a 'p' tag for
+    /// each person mentioned who is not tagged already (see
+    /// `compose_mentions()`)
+    pub fn content_with_mentions(
+        mut self,
+        template: &str,
+        profiles: &[ProfilePointer],
+    ) -> Result<EventBuilder, Error> {
+        let (content, tags) = compose_mentions(template, profiles)?;
+        self.content = content;
+        for tag in tags {
+            let already_tagged = match &tag {
+                Tag::Pubkey { pubkey, .. } => self
+                    .tags
+                    .iter()
+                    .any(|t| matches!(t, Tag::Pubkey { pubkey: pk, .. } if pk == pubkey)),
+                _ => false,
+            };
+            if !already_tagged {
+                self.tags.push(tag);
+            }
+        }
+        Ok(self)
+    }
+
+    /// Add an 'imeta' tag (NIP-92) for each URL in the content that is
+    /// described in `media`, in the order the URLs appear. Media not linked
+    /// from the content is left out, and a URL that already has an 'imeta' tag
+    /// is not tagged again. Set the content first.
+    pub fn attach_media(mut self, media: &[IMeta]) -> EventBuilder {
+        let mut tagged: Vec<String> = self
+            .tags
+            .iter()
+            .filter_map(|tag| IMeta::try_from_tag(tag).ok())
+            .map(|imeta| imeta.url.0)
+            .collect();
+
+        for link in LinkFinder::new()
+            .kinds(&[LinkKind::Url])
+            .links(&self.content)
+        {
+            if tagged.iter().any(|url| url == link.as_str()) {
+                continue;
+            }
+            if let Some(imeta) = media.iter().find(|imeta| imeta.url.0 == link.as_str()) {
+                self.tags.push(imeta.to_tag());
+                tagged.push(imeta.url.0.clone());
+            }
+        }
+        self
+    }
+
+    /// Finish building, producing a `PreEvent` authored by `pubkey`
+    pub fn to_pre_event(&self, pubkey: PublicKey) -> Result<PreEvent, Error> {
+        let created_at = match self.created_at {
+            Some(created_at) => created_at,
+            None => Unixtime::now()?,
+        };
+        Ok(PreEvent {
+            pubkey,
+            created_at,
+            kind: self.kind.unwrap_or(EventKind::TextNote),
+            tags: self.tags.clone(),
+            content: self.content.clone(),
+            ots: None,
+        })
+    }
+
+    /// Finish building, producing an event signed by `signer` (such as a
+    /// `PrivateKey`)
+    pub fn sign<S: Signer + ?Sized>(&self, signer: &S) -> Result<Event, Error> {
+        let pre_event = self.to_pre_event(signer.public_key())?;
+        signer.sign_event(pre_event)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::PrivateKey;
+    use crate::PublicKeyHex;
+    use crate::RelayUrl;
+
+    #[test]
+    fn test_event_builder() {
+        let private_key = PrivateKey::generate();
+        let event = EventBuilder::new()
+            .kind(EventKind::TextNote)
+            .content("Hello world")
+            .tag(Tag::Hashtag("nostr".to_owned()))
+            .created_at(Unixtime(1_700_000_000))
+            .expiration(Unixtime(1_700_000_500))
+            .expiration(Unixtime(1_700_001_000))
+            .sign(&private_key)
+            .unwrap();
+        assert!(event.verify(None).is_ok());
+        assert_eq!(event.pubkey, private_key.public_key());
+        assert_eq!(event.created_at, Unixtime(1_700_000_000));
+        assert_eq!(event.content, "Hello world");
+        assert_eq!(
+            event.tags,
+            vec![
+                Tag::Hashtag("nostr".to_owned()),
+                Tag::Expiration(Unixtime(1_700_001_000)),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_event_builder_content_with_mentions() {
+        let private_key = PrivateKey::generate();
+        let alice = ProfilePointer::mock();
+        let bob = private_key.public_key();
+        let event = EventBuilder::new()
+            .tag(alice.to_tag())
+            .content_with_mentions("cc {0} {1}", &[alice.clone(), bob.into()])
+            .unwrap()
+            .sign(&private_key)
+            .unwrap();
+        assert_eq!(
+            event.content,
+            format!(
+                "cc nostr:{} nostr:{}",
+                alice.as_bech32_string(),
+                bob.as_bech32_string()
+            )
+        );
+        assert_eq!(
+            event.tags,
+            vec![alice.to_tag(), ProfilePointer::from(bob).to_tag()]
+        );
+        assert!(EventBuilder::new()
+            .content_with_mentions("{1}", &[alice])
+            .is_err());
+    }
+
+    #[test]
+    fn test_event_builder_quote() {
+        let alice = PrivateKey::generate();
+        let bob = PrivateKey::generate();
+        let quoted = EventBuilder::new()
+            .content("Quote me")
+            .sign(&alice)
+            .unwrap();
+        let relay = UncheckedUrl::from_str("wss://relay.example.com");
+
+        let event = EventBuilder::new()
+            .content("So true")
+            .quote(&quoted, Some(relay.clone()))
+            .quote(&quoted, None)
+            .sign(&bob)
+            .unwrap();
+        let nevent = NostrUrl(NostrBech32::EventPointer(EventPointer {
+            id: quoted.id,
+            relays: vec![relay],
+            author: Some(quoted.pubkey),
+            kind: Some(EventKind::TextNote),
+        }));
+        assert_eq!(event.content, format!("So true\n{nevent}"));
+        assert_eq!(
+            event.quoted_events(),
+            vec![(
+                quoted.id,
+                Some(RelayUrl::try_from_str("wss://relay.example.com").unwrap())
+            )]
+        );
+
+        let event = EventBuilder::new().quote(&quoted, None).sign(&bob).unwrap();
+        assert!(event.content.starts_with("nostr:nevent1"));
+        assert_eq!(event.quoted_events(), vec![(quoted.id, None)]);
+    }
+
+    #[test]
+    fn test_event_builder_reply_to() {
+        let alice = PrivateKey::generate();
+        let bob = PrivateKey::generate();
+        let carol = PrivateKey::generate();
+
+        let root = EventBuilder::new().content("Root").sign(&alice).unwrap();
+        let reply = EventBuilder::new()
+            .content("Reply")
+            .reply_to(&root)
+            .sign(&bob)
+            .unwrap();
+        assert_eq!(reply.replies_to(), Some((root.id, None)));
+        assert_eq!(reply.replies_to_root(), Some((root.id, None)));
+
+        let reply2 = EventBuilder::new()
+            .content("Reply to the reply")
+            .reply_to(&reply)
+            .sign(&carol)
+            .unwrap();
+        assert_eq!(reply2.replies_to(), Some((reply.id, None)));
+        assert_eq!(reply2.replies_to_root(), Some((root.id, None)));
+
+        // Both earlier authors are tagged, once each
+        let people: Vec<PublicKeyHex> = reply2.people().into_iter().map(|p| p.0).collect();
+        assert_eq!(
+            people,
+            vec![bob.public_key().into(), alice.public_key().into()]
+        );
+    }
+}
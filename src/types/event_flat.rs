@@ -0,0 +1,340 @@
+use super::{Event, EventKind, Id, PublicKey, Signature, Tag, Unixtime, XOnlyPublicKey};
+use crate::Error;
+use k256::schnorr::Signature as KSignature;
+
+// The layout of a flat event. All integers are little-endian, and nothing needs
+// to be aligned, so records can be packed back to back.
+//
+//   0    magic "NEF" and a version byte
+//   4    u32 total length of the record
+//   8    [32] id
+//   40   [32] pubkey
+//   72   [64] sig
+//   136  i64 created_at
+//   144  u64 kind
+//   152  u32 number of tags
+//   156  u32 length of the content
+//   160  u32 length of the ots, or u32::MAX if there is none
+//   164  u32 length of the tags text
+//   168  u32 end offset (within the tags text) of each tag
+//   ...  tags text: each tag as a JSON array, back to back
+//   ...  content
+//   ...  ots
+const MAGIC: [u8; 3] = *b"NEF";
+const VERSION: u8 = 1;
+const HEADER_LEN: usize = 168;
+const NO_OTS: u32 = u32::MAX;
+
+/// An event in a flat, fixed-layout binary form, which can be read in place
+/// (for example from a memory-mapped file) without deserializing it.
+///
+/// The fixed-size fields are at fixed offsets. The tags, content and ots
+/// follow, and are validated as UTF-8 once when the record is opened, after
+/// which they are borrowed directly from the buffer. Each tag is stored as its
+/// JSON array, so it can be matched as text or parsed into a `Tag` on demand.
+///
+/// Records can be concatenated; `len()` gives the length of each.
+#[derive(Clone, Copy, Debug)]
+pub struct EventFlat<'a> {
+    bytes: &'a [u8],
+    tag_ends: &'a [u8],
+    tags: &'a str,
+    content: &'a str,
+    ots: Option<&'a str>,
+}
+
+impl<'a> EventFlat<'a> {
+    /// Write an event in the flat form, appending it to `output`
+    pub fn write(event: &Event, output: &mut Vec<u8>) -> Result<(), Error> {
+        let tags: Vec<String> = event
+            .tags
+            .iter()
+            .map(serde_json::to_string)
+            .collect::<Result<_, _>>()?;
+        let tags_len: usize = tags.iter().map(|t| t.len()).sum();
+        let ots_len = event.ots.as_ref().map(|o| o.len()).unwrap_or(0);
+        let total = HEADER_LEN + 4 * tags.len() + tags_len + event.content.len() + ots_len;
+
+        let u32_of =
+            |n: usize| u32::try_from(n).map_err(|_| Error::InvalidFlatEvent("event is too large"));
+        // As the total fits, no length can collide with NO_OTS
+        let total_u32 = u32_of(total)?;
+
+        output.reserve(total);
+        output.extend_from_slice(&MAGIC);
+        output.push(VERSION);
+        output.extend_from_slice(&total_u32.to_le_bytes());
+        output.extend_from_slice(event.id.as_slice());
+        output.extend_from_slice(&event.pubkey.to_bytes());
+        output.extend_from_slice(&event.sig.to_bytes());
+        output.extend_from_slice(&event.created_at.0.to_le_bytes());
+        output.extend_from_slice(&u64::from(event.kind).to_le_bytes());
+        output.extend_from_slice(&u32_of(tags.len())?.to_le_bytes());
+        output.extend_from_slice(&u32_of(event.content.len())?.to_le_bytes());
+        let ots_field = match event.ots {
+            Some(_) => u32_of(ots_len)?,
+            None => NO_OTS,
+        };
+        output.extend_from_slice(&ots_field.to_le_bytes());
+        output.extend_from_slice(&u32_of(tags_len)?.to_le_bytes());
+        let mut end = 0;
+        for tag in tags.iter() {
+            end += tag.len();
+            output.extend_from_slice(&u32_of(end)?.to_le_bytes());
+        }
+        for tag in tags.iter() {
+            output.extend_from_slice(tag.as_bytes());
+        }
+        output.extend_from_slice(event.content.as_bytes());
+        if let Some(ots) = &event.ots {
+            output.extend_from_slice(ots.as_bytes());
+        }
+        Ok(())
+    }
+
+    /// Open the flat event at the start of `bytes`, checking its layout. Any
+    /// bytes after the record (such as further records) are ignored.
+    pub fn from_bytes(bytes: &'a [u8]) -> Result<EventFlat<'a>, Error> {
+        let invalid = Error::InvalidFlatEvent;
+        if bytes.len() < HEADER_LEN {
+            return Err(invalid("truncated header"));
+        }
+        if bytes[0..3] != MAGIC {
+            return Err(invalid("bad magic"));
+        }
+        if bytes[3] != VERSION {
+            return Err(Error::UnsupportedBinaryVersion(bytes[3]));
+        }
+        let total = read_u32(bytes, 4) as usize;
+        if total < HEADER_LEN {
+            return Err(invalid("bad record length"));
+        }
+        let bytes = bytes.get(..total).ok_or(invalid("truncated record"))?;
+
+        let tag_count = read_u32(bytes, 152) as usize;
+        let content_len = read_u32(bytes, 156) as usize;
+        let ots_len = read_u32(bytes, 160);
+        let tags_len = read_u32(bytes, 164) as usize;
+
+        let mut rest = bytes.get(HEADER_LEN..).unwrap_or_default();
+        let mut take = |len: usize| -> Result<&'a [u8], Error> {
+            if len > rest.len() {
+                return Err(invalid("lengths exceed the record"));
+            }
+            let (taken, remaining) = rest.split_at(len);
+            rest = remaining;
+            Ok(taken)
+        };
+        let tag_ends = take(tag_count.checked_mul(4).ok_or(invalid("too many tags"))?)?;
+        let tags = take(tags_len)?;
+        let content = take(content_len)?;
+        let ots = match ots_len {
+            NO_OTS => None,
+            len => Some(take(len as usize)?),
+        };
+        if !rest.is_empty() {
+            return Err(invalid("lengths do not fill the record"));
+        }
+
+        let utf8 = |b: &'a [u8]| std::str::from_utf8(b).map_err(|_| invalid("invalid UTF-8"));
+        let tags = utf8(tags)?;
+        let mut start = 0;
+        for end in tag_ends.chunks_exact(4).map(|c| read_u32(c, 0) as usize) {
+            if end < start || tags.get(start..end).is_none() {
+                return Err(invalid("bad tag offsets"));
+            }
+            start = end;
+        }
+        if start != tags.len() {
+            return Err(invalid("bad tag offsets"));
+        }
+
+        Ok(EventFlat {
+            bytes,
+            tag_ends,
+            tags,
+            content: utf8(content)?,
+            ots: ots.map(utf8).transpose()?,
+        })
+    }
+
+    /// The length of the record, in bytes
+    pub fn len(&self) -> usize {
+        self.bytes.len()
+    }
+
+    /// Whether the record is empty (it never is)
+    pub fn is_empty(&self) -> bool {
+        self.bytes.is_empty()
+    }
+
+    /// The raw bytes of the record
+    pub fn as_bytes(&self) -> &'a [u8] {
+        self.bytes
+    }
+
+    /// The Id of the event
+    pub fn id(&self) -> Id {
+        Id(read_array(self.bytes, 8))
+    }
+
+    /// The public key of the author, unchecked (see `PublicKey::try_from()`)
+    pub fn pubkey(&self) -> XOnlyPublicKey {
+        XOnlyPublicKey(read_array(self.bytes, 40))
+    }
+
+    /// The signature, as bytes
+    pub fn sig_bytes(&self) -> [u8; 64] {
+        read_array(self.bytes, 72)
+    }
+
+    /// The time at which the event was created
+    pub fn created_at(&self) -> Unixtime {
+        Unixtime(i64::from_le_bytes(read_array(self.bytes, 136)))
+    }
+
+    /// The kind of event
+    pub fn kind(&self) -> EventKind {
+        EventKind::from(u64::from_le_bytes(read_array(self.bytes, 144)))
+    }
+
+    /// The number of tags
+    pub fn tag_count(&self) -> usize {
+        self.tag_ends.len() / 4
+    }
+
+    /// Iterate over the tags, each as its JSON array
+    pub fn tags_json(&self) -> impl Iterator<Item = &'a str> + '_ {
+        let tags = self.tags;
+        let mut start = 0;
+        self.tag_ends.chunks_exact(4).map(move |c| {
+            let end = read_u32(c, 0) as usize;
+            // Offsets were checked when the record was opened
+            let tag = tags.get(start..end).unwrap_or_default();
+            start = end;
+            tag
+        })
+    }
+
+    /// Parse the tags
+    pub fn tags(&self) -> Result<Vec<Tag>, Error> {
+        self.tags_json()
+            .map(|json| Ok(serde_json::from_str(json)?))
+            .collect()
+    }
+
+    /// The content of the event
+    pub fn content(&self) -> &'a str {
+        self.content
+    }
+
+    /// The OpenTimestamp of the event, if any
+    pub fn ots(&self) -> Option<&'a str> {
+        self.ots
+    }
+
+    /// Convert into an `Event`. This does not verify the event.
+    pub fn to_event(&self) -> Result<Event, Error> {
+        Ok(Event {
+            id: self.id(),
+            pubkey: PublicKey::try_from(self.pubkey())?,
+            created_at: self.created_at(),
+            kind: self.kind(),
+            tags: self.tags()?,
+            content: self.content.to_owned(),
+            ots: self.ots.map(|o| o.to_owned()),
+            sig: Signature(KSignature::try_from(&self.sig_bytes()[..])?),
+        })
+    }
+}
+
+impl TryFrom<EventFlat<'_>> for Event {
+    type Error = Error;
+
+    fn try_from(flat: EventFlat<'_>) -> Result<Event, Error> {
+        flat.to_event()
+    }
+}
+
+// Callers have checked that the record is long enough
+fn read_array<const N: usize>(bytes: &[u8], offset: usize) -> [u8; N] {
+    let mut array = [0_u8; N];
+    array.copy_from_slice(&bytes[offset..offset + N]);
+    array
+}
+
+fn read_u32(bytes: &[u8], offset: usize) -> u32 {
+    u32::from_le_bytes(read_array(bytes, offset))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{EventBuilder, PrivateKey};
+
+    #[test]
+    fn test_event_flat_roundtrip() {
+        let private_key = PrivateKey::generate();
+        let mut event = EventBuilder::new()
+            .content("Hello flat world \u{1F600}")
+            .tag(Tag::Hashtag("nostr".to_owned()))
+            .expiration(Unixtime(1_700_000_000))
+            .sign(&private_key)
+            .unwrap();
+        let plain = EventBuilder::new().sign(&private_key).unwrap();
+        event.ots = Some("ots data".to_owned());
+
+        let mut bytes = event.to_flat().unwrap();
+        EventFlat::write(&plain, &mut bytes).unwrap();
+
+        let flat = EventFlat::from_bytes(&bytes).unwrap();
+        assert_eq!(flat.id(), event.id);
+        assert_eq!(flat.pubkey(), event.pubkey.into());
+        assert_eq!(flat.created_at(), event.created_at);
+        assert_eq!(flat.kind(), event.kind);
+        assert_eq!(flat.content(), event.content);
+        assert_eq!(flat.ots(), Some("ots data"));
+        assert_eq!(flat.tag_count(), 2);
+        assert_eq!(
+            flat.tags_json().collect::<Vec<_>>(),
+            vec![r#"["t","nostr"]"#, r#"["expiration",1700000000]"#]
+        );
+        let decoded = Event::try_from(flat).unwrap();
+        assert_eq!(decoded, event);
+        assert!(decoded.verify(None).is_ok());
+
+        // The second record follows the first
+        let second = EventFlat::from_bytes(&bytes[flat.len()..]).unwrap();
+        assert_eq!(second.to_event().unwrap(), plain);
+        assert_eq!(second.ots(), None);
+        assert_eq!(second.tag_count(), 0);
+    }
+
+    #[test]
+    fn test_event_flat_rejects_malformed() {
+        let bytes = Event::mock().to_flat().unwrap();
+        assert!(EventFlat::from_bytes(&bytes[..bytes.len() - 1]).is_err());
+        assert!(EventFlat::from_bytes(&bytes[..100]).is_err());
+
+        let mut bad = bytes.clone();
+        bad[4..8].copy_from_slice(&8_u32.to_le_bytes());
+        assert!(EventFlat::from_bytes(&bad).is_err());
+
+        let mut bad = bytes.clone();
+        bad[3] = 2;
+        assert!(matches!(
+            EventFlat::from_bytes(&bad),
+            Err(Error::UnsupportedBinaryVersion(2))
+        ));
+
+        // Tag offsets past the tags text
+        let mut bad = bytes.clone();
+        bad[HEADER_LEN..HEADER_LEN + 4].copy_from_slice(&u32::MAX.to_le_bytes());
+        assert!(EventFlat::from_bytes(&bad).is_err());
+
+        // Content length past the record
+        let mut bad = bytes;
+        bad[156..160].copy_from_slice(&1000_u32.to_le_bytes());
+        assert!(EventFlat::from_bytes(&bad).is_err());
+    }
+}
@@ -28,6 +28,8 @@ pub enum EventKind {
     Repost,
     /// Event is a reaction to a `TextNote` event
     Reaction,
+    /// Seal: an encrypted, signed rumor (NIP-59)
+    Seal,
     /// Event creates a public channel
     ChannelCreation,
     /// Event sets metadata on a public channel
@@ -48,18 +50,40 @@ pub enum EventKind {
     PublicChatReserved48,
     /// Reserved for future public channel usage
     PublicChatReserved49,
+    /// Chess game in PGN format (NIP-64)
+    Chess,
+    /// Response to a poll (NIP-88)
+    PollResponse,
+    /// Gift wrap: an encrypted seal, signed by a random key (NIP-59)
+    GiftWrap,
+    /// Poll (NIP-88)
+    Poll,
     /// Zap Request
     ZapRequest,
     /// Zap
     Zap,
-    /// Relays List (NIP-23)
-    RelaysListNip23,
+    /// Mute List (NIP-51)
+    MuteList,
+    /// Pinned Notes (NIP-51)
+    PinList,
     /// Relays List (NIP-65)
     RelayList,
+    /// Bookmarks (NIP-51)
+    Bookmarks,
+    /// Relay Monitor Announcement (NIP-66)
+    RelayMonitorAnnouncement,
     /// Authentication
     Auth,
+    /// Follow Sets: named sets of people (NIP-51)
+    FollowSets,
     /// Long-form Content
     LongFormContent,
+    /// Draft Long-form Content (NIP-23)
+    DraftLongFormContent,
+    /// Application-specific Data (NIP-78)
+    AppSpecificData,
+    /// Relay Discovery: a monitor's observations of a relay (NIP-66)
+    RelayDiscovery,
     /// Client Settings
     ClientSettings,
     /// Relay-specific replaceable event
@@ -73,6 +97,17 @@ pub enum EventKind {
 use EventKind::*;
 
 impl EventKind {
+    /// The former name of `PinList`: kind 10001 was once proposed for a relays
+    /// list, but became pinned notes (NIP-51).
+    ///
+    /// This was a variant, and is now a constant, as kind 10001 can only
+    /// convert to one of them. Constructing it and matching on it still work,
+    /// but it cannot be imported (`use EventKind::RelaysListNip23`, or by a
+    /// glob), and it debug-prints as `PinList`.
+    #[deprecated(note = "kind 10001 is pinned notes; use EventKind::PinList")]
+    #[allow(non_upper_case_globals)]
+    pub const RelaysListNip23: EventKind = PinList;
+
     // Mock data for testing
     #[allow(dead_code)]
     pub(crate) fn mock() -> EventKind {
@@ -114,6 +149,7 @@ impl EventKind {
             Reaction => true,
             Zap => true, // like reaction, affects zap counts
             LongFormContent => true,
+            Poll => true,
             _ => false,
         }
     }
@@ -144,6 +180,7 @@ static WELL_KNOWN_KINDS: &[EventKind] = &[
     EventDeletion,
     Repost,
     Reaction,
+    Seal,
     ChannelCreation,
     ChannelMetadata,
     ChannelMessage,
@@ -154,12 +191,23 @@ static WELL_KNOWN_KINDS: &[EventKind] = &[
     PublicChatReserved47,
     PublicChatReserved48,
     PublicChatReserved49,
+    Chess,
+    PollResponse,
+    GiftWrap,
+    Poll,
     ZapRequest,
     Zap,
-    RelaysListNip23,
+    MuteList,
+    PinList,
     RelayList,
+    Bookmarks,
+    RelayMonitorAnnouncement,
     Auth,
+    FollowSets,
     LongFormContent,
+    DraftLongFormContent,
+    AppSpecificData,
+    RelayDiscovery,
     ClientSettings,
 ];
 
@@ -198,6 +246,7 @@ impl From<u64> for EventKind {
             5 => EventDeletion,
             6 => Repost,
             7 => Reaction,
+            13 => Seal,
             40 => ChannelCreation,
             41 => ChannelMetadata,
             42 => ChannelMessage,
@@ -208,12 +257,23 @@ impl From<u64> for EventKind {
             47 => PublicChatReserved47,
             48 => PublicChatReserved48,
             49 => PublicChatReserved49,
+            64 => Chess,
+            1018 => PollResponse,
+            1059 => GiftWrap,
+            1068 => Poll,
             9734 => ZapRequest,
             9735 => Zap,
-            10001 => RelaysListNip23,
+            10000 => MuteList,
+            10001 => PinList,
             10002 => RelayList,
+            10003 => Bookmarks,
+            10166 => RelayMonitorAnnouncement,
             22242 => Auth,
+            30000 => FollowSets,
             30023 => LongFormContent,
+            30024 => DraftLongFormContent,
+            30078 => AppSpecificData,
+            30166 => RelayDiscovery,
             31111 => ClientSettings,
             x if (10_000..20_000).contains(&x) => Replaceable(x),
             x if (20_000..30_000).contains(&x) => Ephemeral(x),
@@ -233,6 +293,7 @@ impl From<EventKind> for u64 {
             EventDeletion => 5,
             Repost => 6,
             Reaction => 7,
+            Seal => 13,
             ChannelCreation => 40,
             ChannelMetadata => 41,
             ChannelMessage => 42,
@@ -243,12 +304,23 @@ impl From<EventKind> for u64 {
             PublicChatReserved47 => 47,
             PublicChatReserved48 => 48,
             PublicChatReserved49 => 49,
+            Chess => 64,
+            PollResponse => 1018,
+            GiftWrap => 1059,
+            Poll => 1068,
             ZapRequest => 9734,
             Zap => 9735,
-            RelaysListNip23 => 10001,
+            MuteList => 10000,
+            PinList => 10001,
             RelayList => 10002,
+            Bookmarks => 10003,
+            RelayMonitorAnnouncement => 10166,
             Auth => 22242,
+            FollowSets => 30000,
             LongFormContent => 30023,
+            DraftLongFormContent => 30024,
+            AppSpecificData => 30078,
+            RelayDiscovery => 30166,
             ClientSettings => 31111,
             Replaceable(u) => u,
             Ephemeral(u) => u,
@@ -1,11 +1,12 @@
-use super::{Id, UncheckedUrl};
+use super::{EventKind, Id, PublicKey, UncheckedUrl};
 use crate::Error;
 use bech32::{FromBase32, ToBase32};
 use serde::{Deserialize, Serialize};
 #[cfg(feature = "speedy")]
 use speedy::{Readable, Writable};
 
-/// An event id along with some relays in which that event may be found.
+/// An event id along with some relays in which that event may be found, and
+/// optionally its author and kind.
 #[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
 #[cfg_attr(feature = "speedy", derive(Readable, Writable))]
 pub struct EventPointer {
@@ -14,26 +15,56 @@ pub struct EventPointer {
 
     /// Some of the relays where this could be in
     pub relays: Vec<UncheckedUrl>,
+
+    /// The author of the event, if known
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub author: Option<PublicKey>,
+
+    /// The kind of the event, if known
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub kind: Option<EventKind>,
 }
 
+/// An event pointer, by its NIP-19 name
+pub type NEvent = EventPointer;
+
 impl EventPointer {
-    /// Export as a bech32 encoded string ("nevent")
+    /// Export as a bech32 encoded string ("nevent"). Relay URLs longer than
+    /// 255 bytes cannot be encoded, and are left out, as is a kind beyond
+    /// 32 bits.
     pub fn as_bech32_string(&self) -> String {
         // Compose
         let mut tlv: Vec<u8> = Vec::new();
 
-        // Push Public Key
-        tlv.push(0); // the special value, in this case the public key
-        tlv.push(32); // the length of the value (always 32 for public key)
+        // Push Id
+        tlv.push(0); // the special value, in this case the id
+        tlv.push(32); // the length of the value (always 32 for id)
         tlv.extend(self.id.0);
 
         // Push relays
         for relay in &self.relays {
+            let Ok(len) = u8::try_from(relay.0.len()) else {
+                continue;
+            };
             tlv.push(1); // type 'relay'
-            tlv.push(relay.0.len() as u8); // the length of the string
+            tlv.push(len); // the length of the string
             tlv.extend(relay.0.as_bytes());
         }
 
+        // Push author
+        if let Some(author) = &self.author {
+            tlv.push(2); // type 'author'
+            tlv.push(32); // the length of the value (always 32 for public key)
+            tlv.extend(author.as_bytes());
+        }
+
+        // Push kind
+        if let Some(Ok(kind)) = self.kind.map(|k| u32::try_from(u64::from(k))) {
+            tlv.push(3); // type 'kind'
+            tlv.push(4); // the length of the value (always 4 for kind)
+            tlv.extend(kind.to_be_bytes());
+        }
+
         bech32::encode("nevent", tlv.to_base32(), bech32::Variant::Bech32).unwrap()
     }
 
@@ -45,18 +76,20 @@ impl EventPointer {
         } else {
             let mut relays: Vec<UncheckedUrl> = Vec::new();
             let mut id: Option<Id> = None;
+            let mut author: Option<PublicKey> = None;
+            let mut kind: Option<EventKind> = None;
             let tlv = Vec::<u8>::from_base32(&data.1)?;
             let mut pos = 0;
             loop {
                 // we need at least 2 more characters for anything meaningful
-                if pos > tlv.len() - 2 {
+                if pos + 2 > tlv.len() {
                     break;
                 }
                 let ty = tlv[pos];
                 let len = tlv[pos + 1] as usize;
                 pos += 2;
                 if pos + len > tlv.len() {
-                    return Err(Error::InvalidProfile);
+                    return Err(Error::InvalidEventPointer);
                 }
                 match ty {
                     0 => {
@@ -75,12 +108,31 @@ impl EventPointer {
                         let relay = UncheckedUrl::from_str(relay_str);
                         relays.push(relay);
                     }
-                    _ => {} // unhandled type for nprofile
+                    2 => {
+                        // author, 32 bytes of public key
+                        if len != 32 {
+                            return Err(Error::InvalidEventPointer);
+                        }
+                        author = Some(PublicKey::from_bytes(&tlv[pos..pos + len])?);
+                    }
+                    3 => {
+                        // kind, 4 bytes big-endian
+                        let bytes: [u8; 4] = tlv[pos..pos + len]
+                            .try_into()
+                            .map_err(|_| Error::InvalidEventPointer)?;
+                        kind = Some(EventKind::from(u64::from(u32::from_be_bytes(bytes))));
+                    }
+                    _ => {} // unhandled type for nevent
                 }
                 pos += len;
             }
             if let Some(id) = id {
-                Ok(EventPointer { id, relays })
+                Ok(EventPointer {
+                    id,
+                    relays,
+                    author,
+                    kind,
+                })
             } else {
                 Err(Error::InvalidEventPointer)
             }
@@ -101,6 +153,13 @@ impl EventPointer {
                 UncheckedUrl::from_str("wss://relay.example.com"),
                 UncheckedUrl::from_str("wss://relay2.example.com"),
             ],
+            author: Some(
+                PublicKey::try_from_hex_string(
+                    "3bf0c63fcb93463407af97a5e5ee64fa883d107ef9e558472c4eb9aaaefa459d",
+                )
+                .unwrap(),
+            ),
+            kind: Some(EventKind::TextNote),
         }
     }
 }
@@ -132,6 +191,8 @@ mod test {
                 UncheckedUrl::from_str("wss://r.x.com"),
                 UncheckedUrl::from_str("wss://djbas.sadkb.com"),
             ],
+            author: None,
+            kind: None,
         };
 
         let bech32 = "nevent1qqsrhuxx8l9ex335q7he0f09aej04zpazpl0ne2cgukyawd24mayt8gpp4mhxue69uhhytnc9e3k7mgpz4mhxue69uhkg6nzv9ejuumpv34kytnrdaks343fay";
@@ -151,4 +212,39 @@ mod test {
         let _ = EventPointer::try_from_bech32_string(bech32).unwrap();
         // it won't be equal, but should have the basics and should not error.
     }
+
+    #[test]
+    fn test_nevent_malformed() {
+        let encode = |tlv: &[u8]| {
+            bech32::encode("nevent", tlv.to_base32(), bech32::Variant::Bech32).unwrap()
+        };
+        let id = EventPointer::mock().id.0;
+        assert!(EventPointer::try_from_bech32_string(&encode(&[])).is_err());
+        assert!(EventPointer::try_from_bech32_string(&encode(&[0])).is_err());
+        assert!(EventPointer::try_from_bech32_string(&encode(&[0, 32, 1, 2])).is_err());
+
+        let mut tlv = vec![0, 32];
+        tlv.extend(id);
+        let mut bad_kind = tlv.clone();
+        bad_kind.extend([3, 2, 0, 1]);
+        assert!(EventPointer::try_from_bech32_string(&encode(&bad_kind)).is_err());
+        let mut bad_author = tlv.clone();
+        bad_author.extend([2, 3, 1, 2, 3]);
+        assert!(EventPointer::try_from_bech32_string(&encode(&bad_author)).is_err());
+    }
+
+    #[test]
+    fn test_id_as_nevent() {
+        let event_pointer = EventPointer::mock();
+        let nevent = event_pointer.id.as_nevent(
+            event_pointer.relays.clone(),
+            event_pointer.author,
+            event_pointer.kind,
+        );
+        assert!(nevent.starts_with("nevent1"));
+        assert_eq!(
+            EventPointer::try_from_bech32_string(&nevent).unwrap(),
+            event_pointer
+        );
+    }
 }
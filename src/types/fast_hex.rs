@@ -0,0 +1,56 @@
+// Hex encoding and decoding of the fixed-size values (ids, keys, signatures)
+// that make up most of the hex in nostr, using the SIMD routines of faster-hex
+// and stack buffers rather than allocated strings.
+
+use std::fmt;
+
+// Large enough for the longest value we encode, a 64-byte signature
+const MAX_BYTES: usize = 64;
+
+// Hex encode `bytes` (at most 64 of them) on the stack, and hand the result to `f`
+pub(crate) fn with_hex<R>(bytes: &[u8], f: impl FnOnce(&str) -> R) -> R {
+    let mut buf = [0_u8; MAX_BYTES * 2];
+    let hex = faster_hex::hex_encode(bytes, &mut buf[..bytes.len() * 2])
+        .expect("buffer is twice the input length");
+    f(hex)
+}
+
+pub(crate) fn encode(bytes: &[u8]) -> String {
+    with_hex(bytes, |hex| hex.to_owned())
+}
+
+pub(crate) fn write_hex<W: fmt::Write + ?Sized>(bytes: &[u8], w: &mut W) -> fmt::Result {
+    with_hex(bytes, |hex| w.write_str(hex))
+}
+
+// Decode exactly `N` bytes of hex. This returns `None` on any failure, so
+// callers fall back to their slower path to report the error.
+pub(crate) fn decode_array<const N: usize>(hex: &str) -> Option<[u8; N]> {
+    let mut bytes = [0_u8; N];
+    if hex.len() != N * 2 || faster_hex::hex_decode(hex.as_bytes(), &mut bytes).is_err() {
+        return None;
+    }
+    Some(bytes)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_fast_hex() {
+        let bytes: [u8; 64] = std::array::from_fn(|i| (i * 7) as u8);
+        let hex = encode(&bytes);
+        assert_eq!(hex, hex::encode(bytes));
+        assert_eq!(decode_array::<64>(&hex), Some(bytes));
+        assert_eq!(decode_array::<64>(&hex.to_uppercase()), Some(bytes));
+
+        let mut s = String::from("0x");
+        write_hex(&bytes[..4], &mut s).unwrap();
+        assert_eq!(s, "0x00070e15");
+
+        assert_eq!(decode_array::<32>(&hex), None);
+        assert_eq!(decode_array::<2>("00g0"), None);
+        assert_eq!(decode_array::<2>("000"), None);
+    }
+}
@@ -0,0 +1,706 @@
+// A parser specialized for the fixed shape of a NIP-01 event, which avoids the
+// overhead of serde_json for the hot path of ingesting events from relays.
+//
+// It makes a single pass over the input, decoding hex fields straight into
+// their fixed-size arrays, and validating UTF-8 only within strings. Each tag
+// is decoded in place by serde_json, so that both parsers agree on them.
+//
+// The same parser can also skim an event, checking its structure and sizes
+// without building it, so that junk can be rejected cheaply.
+
+use super::{
+    Event, EventKind, Id, PublicKey, RelayLimitation, Signature, Tag, Unixtime, XOnlyPublicKey,
+};
+use crate::Error;
+use k256::schnorr::Signature as KSignature;
+use std::borrow::Cow;
+
+/// What a quick structural check of an event's JSON found (see
+/// `Event::quick_check_json()`). Nothing in it has been verified: the pubkey has
+/// not even been checked to be a valid key.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct EventSummary {
+    /// The Id of the event, as claimed
+    pub id: Id,
+
+    /// The public key of the author, as claimed
+    pub pubkey: XOnlyPublicKey,
+
+    /// The time at which the event was created
+    pub created_at: Unixtime,
+
+    /// The kind of event
+    pub kind: EventKind,
+
+    /// The number of tags
+    pub tag_count: usize,
+
+    /// The length of the content, in characters
+    pub content_length: usize,
+}
+
+pub(crate) fn parse_event(json: &[u8]) -> Result<Event, Error> {
+    let mut parser = Parser { json, pos: 0 };
+    let event = parser.event()?;
+    parser.skip_whitespace();
+    if parser.pos != json.len() {
+        return Err(parser.error("trailing characters"));
+    }
+    Ok(event)
+}
+
+pub(crate) fn summarize_event(
+    json: &[u8],
+    limits: Option<&RelayLimitation>,
+) -> Result<EventSummary, Error> {
+    let max =
+        |limit: fn(&RelayLimitation) -> Option<usize>| limits.and_then(limit).unwrap_or(usize::MAX);
+    if json.len() > max(|l| l.max_message_length) {
+        return Err(Error::EventExceedsLimit("max_message_length"));
+    }
+    let mut parser = Parser { json, pos: 0 };
+    let summary = parser.summary()?;
+    parser.skip_whitespace();
+    if parser.pos != json.len() {
+        return Err(parser.error("trailing characters"));
+    }
+    if summary.tag_count > max(|l| l.max_event_tags) {
+        return Err(Error::EventExceedsLimit("max_event_tags"));
+    }
+    if summary.content_length > max(|l| l.max_content_length) {
+        return Err(Error::EventExceedsLimit("max_content_length"));
+    }
+    Ok(summary)
+}
+
+// Split an EVENT relay message into its subscription id and the JSON text of
+// its event, both borrowed where possible. This gives `None` for any other
+// message. The event itself is only checked to be a well-formed JSON object.
+pub(crate) fn split_event_message(json: &str) -> Result<Option<(Cow<'_, str>, &str)>, Error> {
+    let mut parser = Parser {
+        json: json.as_bytes(),
+        pos: 0,
+    };
+    if parser.peek() != Some(b'[') {
+        return Ok(None);
+    }
+    parser.pos += 1;
+    if parser.peek() != Some(b'"') || parser.raw_string().ok() != Some(&b"EVENT"[..]) {
+        return Ok(None);
+    }
+    parser.expect(b',', "expected ','")?;
+
+    let id_start = parser.pos;
+    let subscription_id = match parser.raw_string() {
+        // A string is delimited by ASCII quotes, so lies on character boundaries
+        Ok(raw) => Cow::Borrowed(
+            std::str::from_utf8(raw).map_err(|_| parser.error("invalid UTF-8 in string"))?,
+        ),
+        Err(_) => {
+            parser.pos = id_start;
+            Cow::Owned(parser.string()?)
+        }
+    };
+    parser.expect(b',', "expected ','")?;
+
+    if parser.peek() != Some(b'{') {
+        return Err(parser.error("expected an event object"));
+    }
+    let start = parser.pos;
+    parser.skip_value(0)?;
+    let event = json
+        .get(start..parser.pos)
+        .ok_or_else(|| parser.error("invalid event"))?;
+
+    parser.expect(b']', "expected ']'")?;
+    parser.skip_whitespace();
+    if parser.pos != json.len() {
+        return Err(parser.error("trailing characters"));
+    }
+    Ok(Some((subscription_id, event)))
+}
+
+struct Parser<'a> {
+    json: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Parser<'a> {
+    fn error(&self, reason: &'static str) -> Error {
+        Error::EventJsonParse(self.pos, reason)
+    }
+
+    fn skip_whitespace(&mut self) {
+        while let Some(b' ' | b'\t' | b'\n' | b'\r') = self.json.get(self.pos) {
+            self.pos += 1;
+        }
+    }
+
+    // Skip whitespace, then return the next byte without consuming it
+    fn peek(&mut self) -> Option<u8> {
+        self.skip_whitespace();
+        self.json.get(self.pos).copied()
+    }
+
+    fn expect(&mut self, byte: u8, reason: &'static str) -> Result<(), Error> {
+        if self.peek() == Some(byte) {
+            self.pos += 1;
+            Ok(())
+        } else {
+            Err(self.error(reason))
+        }
+    }
+
+    fn event(&mut self) -> Result<Event, Error> {
+        let mut id: Option<Id> = None;
+        let mut pubkey: Option<PublicKey> = None;
+        let mut created_at: Option<Unixtime> = None;
+        let mut kind: Option<EventKind> = None;
+        let mut tags: Option<Vec<Tag>> = None;
+        let mut content: Option<String> = None;
+        let mut ots: Option<Option<String>> = None;
+        let mut sig: Option<Signature> = None;
+
+        self.expect(b'{', "expected an object")?;
+        if self.peek() == Some(b'}') {
+            self.pos += 1;
+        } else {
+            loop {
+                let key_start = self.pos;
+                let key = self.string()?;
+                self.expect(b':', "expected ':'")?;
+                let duplicate = match key.as_str() {
+                    "id" => id.replace(Id(self.hex_array()?)).is_some(),
+                    "pubkey" => pubkey.replace(self.pubkey()?).is_some(),
+                    "created_at" => created_at.replace(Unixtime(self.i64()?)).is_some(),
+                    "kind" => kind.replace(EventKind::from(self.u64()?)).is_some(),
+                    "tags" => tags.replace(self.tags()?).is_some(),
+                    "content" => content.replace(self.string()?).is_some(),
+                    "ots" => ots.replace(self.optional_string()?).is_some(),
+                    "sig" => sig.replace(self.signature()?).is_some(),
+                    _ => {
+                        self.skip_value(0)?;
+                        false
+                    }
+                };
+                if duplicate {
+                    self.pos = key_start;
+                    return Err(self.error("duplicate field"));
+                }
+                match self.peek() {
+                    Some(b',') => self.pos += 1,
+                    Some(b'}') => {
+                        self.pos += 1;
+                        break;
+                    }
+                    _ => return Err(self.error("expected ',' or '}'")),
+                }
+            }
+        }
+
+        let missing = |reason| Error::EventJsonParse(self.pos, reason);
+        Ok(Event {
+            id: id.ok_or_else(|| missing("missing field 'id'"))?,
+            pubkey: pubkey.ok_or_else(|| missing("missing field 'pubkey'"))?,
+            created_at: created_at.ok_or_else(|| missing("missing field 'created_at'"))?,
+            kind: kind.ok_or_else(|| missing("missing field 'kind'"))?,
+            tags: tags.ok_or_else(|| missing("missing field 'tags'"))?,
+            content: content.ok_or_else(|| missing("missing field 'content'"))?,
+            ots: ots.flatten(),
+            sig: sig.ok_or_else(|| missing("missing field 'sig'"))?,
+        })
+    }
+
+    // Like event(), but only checking the fields which are not summarized
+    fn summary(&mut self) -> Result<EventSummary, Error> {
+        let mut id: Option<Id> = None;
+        let mut pubkey: Option<XOnlyPublicKey> = None;
+        let mut created_at: Option<Unixtime> = None;
+        let mut kind: Option<EventKind> = None;
+        let mut tag_count: Option<usize> = None;
+        let mut content_length: Option<usize> = None;
+        let mut sig: Option<()> = None;
+
+        self.expect(b'{', "expected an object")?;
+        if self.peek() == Some(b'}') {
+            self.pos += 1;
+        } else {
+            loop {
+                let key_start = self.pos;
+                let key = self.string()?;
+                self.expect(b':', "expected ':'")?;
+                let duplicate = match key.as_str() {
+                    "id" => id.replace(Id(self.hex_array()?)).is_some(),
+                    "pubkey" => pubkey.replace(XOnlyPublicKey(self.hex_array()?)).is_some(),
+                    "created_at" => created_at.replace(Unixtime(self.i64()?)).is_some(),
+                    "kind" => kind.replace(EventKind::from(self.u64()?)).is_some(),
+                    "tags" => tag_count.replace(self.skip_tags()?).is_some(),
+                    "content" => content_length.replace(self.skip_string()?).is_some(),
+                    "sig" => sig.replace(self.hex_array::<64>().map(|_| ())?).is_some(),
+                    _ => {
+                        self.skip_value(0)?;
+                        false
+                    }
+                };
+                if duplicate {
+                    self.pos = key_start;
+                    return Err(self.error("duplicate field"));
+                }
+                match self.peek() {
+                    Some(b',') => self.pos += 1,
+                    Some(b'}') => {
+                        self.pos += 1;
+                        break;
+                    }
+                    _ => return Err(self.error("expected ',' or '}'")),
+                }
+            }
+        }
+
+        let missing = |reason| Error::EventJsonParse(self.pos, reason);
+        sig.ok_or_else(|| missing("missing field 'sig'"))?;
+        Ok(EventSummary {
+            id: id.ok_or_else(|| missing("missing field 'id'"))?,
+            pubkey: pubkey.ok_or_else(|| missing("missing field 'pubkey'"))?,
+            created_at: created_at.ok_or_else(|| missing("missing field 'created_at'"))?,
+            kind: kind.ok_or_else(|| missing("missing field 'kind'"))?,
+            tag_count: tag_count.ok_or_else(|| missing("missing field 'tags'"))?,
+            content_length: content_length.ok_or_else(|| missing("missing field 'content'"))?,
+        })
+    }
+
+    // A string which contains no escapes, borrowed from the input
+    fn raw_string(&mut self) -> Result<&'a [u8], Error> {
+        self.expect(b'"', "expected a string")?;
+        let start = self.pos;
+        loop {
+            match self.json.get(self.pos) {
+                Some(b'"') => break,
+                Some(b'\\') => return Err(self.error("unexpected escape")),
+                Some(_) => self.pos += 1,
+                None => return Err(self.error("unterminated string")),
+            }
+        }
+        let end = self.pos;
+        self.pos += 1;
+        Ok(&self.json[start..end])
+    }
+
+    fn hex_array<const N: usize>(&mut self) -> Result<[u8; N], Error> {
+        let start = self.pos;
+        let hex = self.raw_string()?;
+        let mut bytes = [0_u8; N];
+        if hex::decode_to_slice(hex, &mut bytes).is_err() {
+            self.pos = start;
+            return Err(self.error("invalid hex"));
+        }
+        Ok(bytes)
+    }
+
+    fn pubkey(&mut self) -> Result<PublicKey, Error> {
+        let start = self.pos;
+        let bytes: [u8; 32] = self.hex_array()?;
+        PublicKey::from_bytes(&bytes).map_err(|_| Error::EventJsonParse(start, "invalid pubkey"))
+    }
+
+    fn signature(&mut self) -> Result<Signature, Error> {
+        let start = self.pos;
+        let bytes: [u8; 64] = self.hex_array()?;
+        KSignature::try_from(&bytes[..])
+            .map(Signature)
+            .map_err(|_| Error::EventJsonParse(start, "invalid signature"))
+    }
+
+    fn u64(&mut self) -> Result<u64, Error> {
+        self.skip_whitespace();
+        let start = self.pos;
+        let mut value: u64 = 0;
+        while let Some(digit @ b'0'..=b'9') = self.json.get(self.pos) {
+            value = value
+                .checked_mul(10)
+                .and_then(|v| v.checked_add((digit - b'0') as u64))
+                .ok_or_else(|| self.error("number out of range"))?;
+            self.pos += 1;
+        }
+        if self.pos == start {
+            return Err(self.error("expected a number"));
+        }
+        Ok(value)
+    }
+
+    fn i64(&mut self) -> Result<i64, Error> {
+        let negative = self.peek() == Some(b'-');
+        if negative {
+            self.pos += 1;
+        }
+        let start = self.pos;
+        let value = self.u64()?;
+        if negative {
+            0_i64
+                .checked_sub_unsigned(value)
+                .ok_or(Error::EventJsonParse(start, "number out of range"))
+        } else {
+            i64::try_from(value).map_err(|_| Error::EventJsonParse(start, "number out of range"))
+        }
+    }
+
+    fn string(&mut self) -> Result<String, Error> {
+        self.expect(b'"', "expected a string")?;
+        let mut output: Vec<u8> = Vec::new();
+        loop {
+            // Copy the run of ordinary characters in one go
+            let start = self.pos;
+            while let Some(&b) = self.json.get(self.pos) {
+                if b == b'"' || b == b'\\' || b < 0x20 {
+                    break;
+                }
+                self.pos += 1;
+            }
+            output.extend_from_slice(&self.json[start..self.pos]);
+
+            match self.json.get(self.pos) {
+                Some(b'"') => {
+                    self.pos += 1;
+                    break;
+                }
+                Some(b'\\') => {
+                    self.pos += 1;
+                    self.escape(&mut output)?;
+                }
+                Some(_) => return Err(self.error("control character in string")),
+                None => return Err(self.error("unterminated string")),
+            }
+        }
+        String::from_utf8(output).map_err(|_| self.error("invalid UTF-8 in string"))
+    }
+
+    // Check a string without decoding it, returning its length in characters
+    fn skip_string(&mut self) -> Result<usize, Error> {
+        self.expect(b'"', "expected a string")?;
+        let mut length = 0;
+        let mut scratch: Vec<u8> = Vec::with_capacity(4);
+        loop {
+            let start = self.pos;
+            while let Some(&b) = self.json.get(self.pos) {
+                if b == b'"' || b == b'\\' || b < 0x20 {
+                    break;
+                }
+                self.pos += 1;
+            }
+            // Escapes are ASCII, so runs between them are whole characters
+            match std::str::from_utf8(&self.json[start..self.pos]) {
+                Ok(run) => length += run.chars().count(),
+                Err(_) => return Err(self.error("invalid UTF-8 in string")),
+            }
+
+            match self.json.get(self.pos) {
+                Some(b'"') => {
+                    self.pos += 1;
+                    return Ok(length);
+                }
+                Some(b'\\') => {
+                    self.pos += 1;
+                    scratch.clear();
+                    self.escape(&mut scratch)?;
+                    length += 1;
+                }
+                Some(_) => return Err(self.error("control character in string")),
+                None => return Err(self.error("unterminated string")),
+            }
+        }
+    }
+
+    // Decode the escape sequence after a backslash
+    fn escape(&mut self, output: &mut Vec<u8>) -> Result<(), Error> {
+        let byte = match self.json.get(self.pos) {
+            Some(b'"') => b'"',
+            Some(b'\\') => b'\\',
+            Some(b'/') => b'/',
+            Some(b'b') => 0x08,
+            Some(b'f') => 0x0c,
+            Some(b'n') => b'\n',
+            Some(b'r') => b'\r',
+            Some(b't') => b'\t',
+            Some(b'u') => {
+                self.pos += 1;
+                let mut c = self.hex4()? as u32;
+                if (0xD800..0xDC00).contains(&c) {
+                    // A high surrogate must be followed by an escaped low surrogate
+                    if self.json.get(self.pos..self.pos + 2) != Some(b"\\u") {
+                        return Err(self.error("unpaired surrogate"));
+                    }
+                    self.pos += 2;
+                    let low = self.hex4()? as u32;
+                    if !(0xDC00..0xE000).contains(&low) {
+                        return Err(self.error("unpaired surrogate"));
+                    }
+                    c = 0x10000 + ((c - 0xD800) << 10) + (low - 0xDC00);
+                }
+                let c = char::from_u32(c).ok_or_else(|| self.error("unpaired surrogate"))?;
+                let mut buf = [0_u8; 4];
+                output.extend_from_slice(c.encode_utf8(&mut buf).as_bytes());
+                return Ok(());
+            }
+            _ => return Err(self.error("invalid escape")),
+        };
+        output.push(byte);
+        self.pos += 1;
+        Ok(())
+    }
+
+    fn hex4(&mut self) -> Result<u16, Error> {
+        let mut bytes = [0_u8; 2];
+        match self.json.get(self.pos..self.pos + 4) {
+            Some(hex) if hex::decode_to_slice(hex, &mut bytes).is_ok() => {
+                self.pos += 4;
+                Ok(u16::from_be_bytes(bytes))
+            }
+            _ => Err(self.error("invalid unicode escape")),
+        }
+    }
+
+    fn optional_string(&mut self) -> Result<Option<String>, Error> {
+        if self.peek() == Some(b'n') {
+            self.literal(b"null")?;
+            Ok(None)
+        } else {
+            Ok(Some(self.string()?))
+        }
+    }
+
+    fn literal(&mut self, literal: &'static [u8]) -> Result<(), Error> {
+        if self.json.get(self.pos..self.pos + literal.len()) == Some(literal) {
+            self.pos += literal.len();
+            Ok(())
+        } else {
+            Err(self.error("invalid literal"))
+        }
+    }
+
+    fn tags(&mut self) -> Result<Vec<Tag>, Error> {
+        let mut tags: Vec<Tag> = Vec::new();
+        self.expect(b'[', "expected an array of tags")?;
+        if self.peek() == Some(b']') {
+            self.pos += 1;
+            return Ok(tags);
+        }
+        loop {
+            tags.push(self.tag()?);
+            match self.peek() {
+                Some(b',') => self.pos += 1,
+                Some(b']') => {
+                    self.pos += 1;
+                    return Ok(tags);
+                }
+                _ => return Err(self.error("expected ',' or ']'")),
+            }
+        }
+    }
+
+    // Decode a single tag with serde_json, directly from the input
+    fn tag(&mut self) -> Result<Tag, Error> {
+        if self.peek() != Some(b'[') {
+            return Err(self.error("expected a tag"));
+        }
+        let mut stream =
+            serde_json::Deserializer::from_slice(&self.json[self.pos..]).into_iter::<Tag>();
+        match stream.next() {
+            Some(Ok(tag)) => {
+                self.pos += stream.byte_offset();
+                Ok(tag)
+            }
+            _ => Err(self.error("invalid tag")),
+        }
+    }
+
+    // Check that tags are an array of arrays, without decoding them, returning
+    // how many there are
+    fn skip_tags(&mut self) -> Result<usize, Error> {
+        self.expect(b'[', "expected an array of tags")?;
+        if self.peek() == Some(b']') {
+            self.pos += 1;
+            return Ok(0);
+        }
+        let mut count = 0;
+        loop {
+            if self.peek() != Some(b'[') {
+                return Err(self.error("expected a tag"));
+            }
+            self.skip_value(1)?;
+            count += 1;
+            match self.peek() {
+                Some(b',') => self.pos += 1,
+                Some(b']') => {
+                    self.pos += 1;
+                    return Ok(count);
+                }
+                _ => return Err(self.error("expected ',' or ']'")),
+            }
+        }
+    }
+
+    // Skip over a value of an unknown field
+    fn skip_value(&mut self, depth: usize) -> Result<(), Error> {
+        if depth > 64 {
+            return Err(self.error("nested too deeply"));
+        }
+        match self.peek() {
+            Some(b'"') => {
+                let _ = self.skip_string()?;
+            }
+            Some(b'{') | Some(b'[') => {
+                let close = if self.json[self.pos] == b'{' {
+                    b'}'
+                } else {
+                    b']'
+                };
+                self.pos += 1;
+                if self.peek() == Some(close) {
+                    self.pos += 1;
+                    return Ok(());
+                }
+                loop {
+                    if close == b'}' {
+                        let _ = self.skip_string()?;
+                        self.expect(b':', "expected ':'")?;
+                    }
+                    self.skip_value(depth + 1)?;
+                    match self.peek() {
+                        Some(b',') => self.pos += 1,
+                        Some(c) if c == close => {
+                            self.pos += 1;
+                            break;
+                        }
+                        _ => return Err(self.error("unterminated object or array")),
+                    }
+                }
+            }
+            Some(b't') => self.literal(b"true")?,
+            Some(b'f') => self.literal(b"false")?,
+            Some(b'n') => self.literal(b"null")?,
+            Some(b'-' | b'0'..=b'9') => {
+                while let Some(b'-' | b'+' | b'.' | b'e' | b'E' | b'0'..=b'9') =
+                    self.json.get(self.pos)
+                {
+                    self.pos += 1;
+                }
+            }
+            _ => return Err(self.error("expected a value")),
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{EventBuilder, PrivateKey};
+
+    #[test]
+    fn test_parse_event_matches_serde() {
+        let private_key = PrivateKey::generate();
+        let event = EventBuilder::new()
+            .content("Hello \"world\"\n\u{1F600} \\o/")
+            .tag(Tag::Hashtag("nostr".to_owned()))
+            .tag(Tag::Event {
+                id: Id::mock(),
+                recommended_relay_url: None,
+                marker: Some("root".to_owned()),
+            })
+            .tag(Tag::Protected)
+            .expiration(Unixtime(1_700_000_000))
+            .sign(&private_key)
+            .unwrap();
+        let json = serde_json::to_string(&event).unwrap();
+        let slow: Event = serde_json::from_str(&json).unwrap();
+        assert_eq!(Event::from_json_fast(json.as_bytes()).unwrap(), slow);
+
+        // Whitespace, reordered fields, unknown fields and unicode escapes
+        let json = format!(
+            r#" {{ "content" : "\ud83d\ude00 caf\u00e9", "extra": {{"a":[1, -2.5e3, true, null]}},
+            "tags": [ ], "kind": 1, "created_at": 1700000000, "pubkey": "{}",
+            "sig": "{}", "id": "{}" }} "#,
+            event.pubkey.as_hex_string(),
+            event.sig.as_hex_string(),
+            event.id.as_hex_string()
+        );
+        let fast = Event::from_json_fast(json.as_bytes()).unwrap();
+        let slow: Event = serde_json::from_str(&json).unwrap();
+        assert_eq!(fast, slow);
+        assert_eq!(fast.content, "\u{1F600} café");
+    }
+
+    #[test]
+    fn test_parse_event_rejects_malformed() {
+        let private_key = PrivateKey::generate();
+        let event = EventBuilder::new().sign(&private_key).unwrap();
+        let json = serde_json::to_string(&event).unwrap();
+
+        let bad = [
+            json.replace(r#""kind":1"#, r#""kind":"1""#),
+            json.replace(r#""kind":1"#, r#""kind":1,"kind":1"#),
+            json.replace(r#""tags":[]"#, r#""tags":[[1]]"#),
+            json.replace(r#""content":"""#, r#""content":"\x""#),
+            json.replace(&event.id.as_hex_string(), "abcd"),
+            json.replace(r#""sig""#, r#""unsigned""#),
+            format!("{json}x"),
+            json.strip_suffix('}').unwrap().to_owned(),
+        ];
+        for bad in bad.iter() {
+            assert!(
+                Event::from_json_fast(bad.as_bytes()).is_err(),
+                "accepted {bad}"
+            );
+        }
+        assert!(Event::from_json_fast(b"").is_err());
+        assert!(Event::from_json_fast(&[b'"', 0xff, b'"']).is_err());
+    }
+
+    #[test]
+    fn test_summarize_event() {
+        let private_key = PrivateKey::generate();
+        let event = EventBuilder::new()
+            .content("caf\u{e9} \"au lait\"")
+            .tag(Tag::Hashtag("coffee".to_owned()))
+            .expiration(Unixtime(1_700_000_000))
+            .sign(&private_key)
+            .unwrap();
+        let json = serde_json::to_string(&event).unwrap();
+
+        let summary = Event::quick_check_json(&json).unwrap();
+        assert_eq!(summary.id, event.id);
+        assert_eq!(summary.pubkey, event.pubkey.into());
+        assert_eq!(summary.created_at, event.created_at);
+        assert_eq!(summary.kind, event.kind);
+        assert_eq!(summary.tag_count, 2);
+        assert_eq!(summary.content_length, 14);
+
+        let mut limits = RelayLimitation {
+            max_content_length: Some(14),
+            max_event_tags: Some(2),
+            max_message_length: Some(json.len()),
+            ..Default::default()
+        };
+        assert!(Event::quick_check_json_with_limits(&json, &limits).is_ok());
+        limits.max_content_length = Some(13);
+        assert!(matches!(
+            Event::quick_check_json_with_limits(&json, &limits),
+            Err(Error::EventExceedsLimit("max_content_length"))
+        ));
+        limits.max_event_tags = Some(1);
+        limits.max_message_length = Some(100);
+        assert!(matches!(
+            Event::quick_check_json_with_limits(&json, &limits),
+            Err(Error::EventExceedsLimit("max_message_length"))
+        ));
+
+        let bad = [
+            json.replace(r#""tags":["#, r#""tags":["t","#),
+            json.replace(&event.sig.as_hex_string(), "abcd"),
+            json.replace(r#""id""#, r#""di""#),
+        ];
+        for bad in bad.iter() {
+            assert!(Event::quick_check_json(bad).is_err(), "accepted {bad}");
+        }
+    }
+}
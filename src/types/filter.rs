@@ -1,4 +1,7 @@
-use super::{EventKind, IdHex, IdHexPrefix, PublicKeyHex, PublicKeyHexPrefix, Unixtime};
+use super::{
+    CompiledFilter, Event, EventKind, IdHex, IdHexPrefix, PublicKeyHex, PublicKeyHexPrefix, Tag,
+    TagFilter, Unixtime,
+};
 use serde::{Deserialize, Serialize};
 #[cfg(feature = "speedy")]
 use speedy::{Readable, Writable};
@@ -10,6 +13,12 @@ use std::ops::Deref;
 #[cfg_attr(feature = "speedy", derive(Readable, Writable))]
 pub struct Filter {
     /// Events which match these ids
+    ///
+    /// Ids and authors are hex, which may come in either case (and, from older
+    /// clients, as short prefixes). They are lowercased once, as the filter is
+    /// built (see `add_id()` and `add_author()`) or deserialized, so that
+    /// `event_matches()`, `FilterMatchMode::Prefix` and `CompiledFilter` all
+    /// compare them the same way.
     #[serde(skip_serializing_if = "Vec::is_empty")]
     #[serde(default)]
     pub ids: Vec<IdHexPrefix>, // ID as hex, or prefix thereof
@@ -24,47 +33,10 @@ pub struct Filter {
     #[serde(default)]
     pub kinds: Vec<EventKind>,
 
-    /// Events which refer to this naddr in an 'a' tag
-    #[serde(skip_serializing_if = "Vec::is_empty")]
-    #[serde(rename = "#a")]
-    #[serde(default)]
-    pub a: Vec<String>,
-
-    /// Events which refer to this parameter in a 'd' tag
-    #[serde(skip_serializing_if = "Vec::is_empty")]
-    #[serde(rename = "#d")]
-    #[serde(default)]
-    pub d: Vec<String>,
-
-    /// Events which refer to these other events in an 'e' tag
-    #[serde(skip_serializing_if = "Vec::is_empty")]
-    #[serde(rename = "#e")]
-    #[serde(default)]
-    pub e: Vec<IdHex>,
-
-    /// Events which refer to this geohash in a 'g' tag
-    #[serde(skip_serializing_if = "Vec::is_empty")]
-    #[serde(rename = "#g")]
-    #[serde(default)]
-    pub g: Vec<String>,
-
-    /// Events which refer to these public keys in a 'p' tag
-    #[serde(skip_serializing_if = "Vec::is_empty")]
-    #[serde(rename = "#p")]
-    #[serde(default)]
-    pub p: Vec<PublicKeyHex>,
-
-    /// Events which refer to this URL reference in an 'r' tag
-    #[serde(skip_serializing_if = "Vec::is_empty")]
-    #[serde(rename = "#r")]
-    #[serde(default)]
-    pub r: Vec<String>,
-
-    /// Events which refer to this hashtag in a 't' tag
-    #[serde(skip_serializing_if = "Vec::is_empty")]
-    #[serde(rename = "#t")]
-    #[serde(default)]
-    pub t: Vec<String>,
+    /// Events which have tags with these values, by single-letter tag name
+    /// (the `"#<letter>"` keys)
+    #[serde(flatten)]
+    pub tags: TagFilter,
 
     /// Events occuring after this date
     #[serde(skip_serializing_if = "Option::is_none")]
@@ -80,6 +52,51 @@ pub struct Filter {
     #[serde(skip_serializing_if = "Option::is_none")]
     #[serde(default)]
     pub limit: Option<usize>,
+
+    /// Events matching this search query (NIP-50)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(default)]
+    pub search: Option<String>,
+}
+
+/// How the ids and authors of a `Filter` are matched against an event
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum FilterMatchMode {
+    /// Ids and authors must be given in full, and match exactly (NIP-01)
+    #[default]
+    Exact,
+
+    /// Ids and authors match if they begin with the given hex prefix, as older
+    /// relays and tooling allowed
+    Prefix,
+}
+
+impl FilterMatchMode {
+    fn matches(&self, prefix: &str, hex: &str) -> bool {
+        match self {
+            FilterMatchMode::Exact => prefix == hex,
+            FilterMatchMode::Prefix => hex.starts_with(prefix),
+        }
+    }
+}
+
+/// How the `search` query of a `Filter` (NIP-50) is matched against events
+/// held locally. Relays interpret search queries as they see fit, so stores
+/// which mirror relay-side search can plug in their own matching here.
+pub trait SearchMatcher {
+    /// Whether the event matches the search query
+    fn matches(&self, query: &str, event: &Event) -> bool;
+}
+
+/// The default `SearchMatcher`: an event matches if its content contains the
+/// query, ignoring case
+#[derive(Clone, Copy, Debug, Default)]
+pub struct SubstringSearch;
+
+impl SearchMatcher for SubstringSearch {
+    fn matches(&self, query: &str, event: &Event) -> bool {
+        event.content.to_lowercase().contains(&query.to_lowercase())
+    }
 }
 
 #[derive(Debug, PartialEq, Eq)]
@@ -153,7 +170,9 @@ impl Filter {
     /// Add an Id (or prefix) to the filter.
     /// `prefix_length` is measured in hex characters
     pub fn add_id<T: Into<IdHexPrefix>>(&mut self, id_hex_prefix: T) {
-        add_substr(&mut self.ids, id_hex_prefix.into());
+        let mut id_hex_prefix = id_hex_prefix.into();
+        id_hex_prefix.as_mut().make_ascii_lowercase();
+        add_substr(&mut self.ids, id_hex_prefix);
     }
 
     /// Delete an Id (or prefix) from the filter
@@ -165,7 +184,9 @@ impl Filter {
     /// Add a PublicKey (or prefix) to the filter
     /// `prefix_length` is measured in hex characters
     pub fn add_author<T: Into<PublicKeyHexPrefix>>(&mut self, public_key_hex_prefix: T) {
-        add_substr(&mut self.authors, public_key_hex_prefix.into());
+        let mut public_key_hex_prefix = public_key_hex_prefix.into();
+        public_key_hex_prefix.as_mut().make_ascii_lowercase();
+        add_substr(&mut self.authors, public_key_hex_prefix);
     }
 
     /// Delete a PublicKey (or prefix) from the filter
@@ -189,57 +210,361 @@ impl Filter {
         }
     }
 
+    /// Add a value to query for in tags of a single-letter name
+    pub fn add_tag_value(&mut self, letter: char, value: String) {
+        self.tags.add(letter, value);
+    }
+
+    /// Delete a value queried for in tags of a single-letter name
+    pub fn del_tag_value(&mut self, letter: char, value: &str) {
+        self.tags.del(letter, value);
+    }
+
     /// Add an e-tag Id to the filter
     pub fn add_e_tag_ids(&mut self, id_hex: IdHex) {
-        if self.e.contains(&id_hex) {
-            return;
-        }
-        self.e.push(id_hex);
+        self.tags.add('e', id_hex.into_string());
     }
 
     /// Delete an e-tag Id from the filter
     pub fn del_e_tag_ids(&mut self, id_hex: &IdHex) {
-        if let Some(position) = self.e.iter().position(|x| x == id_hex) {
-            let _ = self.e.swap_remove(position);
-        }
+        self.tags.del('e', id_hex.as_str());
     }
 
     /// Add a PublicKey to the filter
     pub fn add_p_tag_public_key(&mut self, public_key_hex: PublicKeyHex) {
-        if self.p.contains(&public_key_hex) {
-            return;
-        }
-        self.p.push(public_key_hex);
+        self.tags.add('p', public_key_hex.into_string());
     }
 
     /// Delete a PublicKey from the filter
     pub fn del_p_tag_public_key(&mut self, public_key_hex: &PublicKeyHex) {
-        if let Some(position) = self.p.iter().position(|x| x == public_key_hex) {
-            let _ = self.p.swap_remove(position);
+        self.tags.del('p', public_key_hex.as_str());
+    }
+
+    /// Whether an event matches the filter (NIP-01). Every condition that is
+    /// set must hold: the id, author and kind must each be one of those listed,
+    /// for each tag query the event must have a tag of that name whose value is
+    /// one of those listed, and the event must be created within `since` and
+    /// `until` (inclusive). Ids and authors must match exactly; see
+    /// `event_matches_with()` to match them as prefixes.
+    ///
+    /// `limit` applies to a query as a whole, so it is ignored here. A `search`
+    /// query is matched with `SubstringSearch`.
+    pub fn event_matches(&self, event: &Event) -> bool {
+        self.event_matches_with(event, FilterMatchMode::Exact, &SubstringSearch)
+    }
+
+    /// Whether an event matches the filter, as `event_matches()`, but matching
+    /// any `search` query with the given `SearchMatcher`
+    pub fn event_matches_with_search<M: SearchMatcher + ?Sized>(
+        &self,
+        event: &Event,
+        matcher: &M,
+    ) -> bool {
+        self.event_matches_with(event, FilterMatchMode::Exact, matcher)
+    }
+
+    /// Whether an event matches the filter, as `event_matches()`, but matching
+    /// ids and authors by the given `FilterMatchMode`, and any `search` query
+    /// with the given `SearchMatcher`
+    pub fn event_matches_with<M: SearchMatcher + ?Sized>(
+        &self,
+        event: &Event,
+        mode: FilterMatchMode,
+        matcher: &M,
+    ) -> bool {
+        if !self.ids.is_empty() {
+            let id = event.id.as_hex_string();
+            if !self.ids.iter().any(|prefix| mode.matches(prefix, &id)) {
+                return false;
+            }
+        }
+
+        if !self.authors.is_empty() {
+            let author = event.pubkey.as_hex_string();
+            if !self
+                .authors
+                .iter()
+                .any(|prefix| mode.matches(prefix, &author))
+            {
+                return false;
+            }
+        }
+
+        if !self.kinds.is_empty() && !self.kinds.contains(&event.kind) {
+            return false;
+        }
+
+        if self.since.is_some_and(|since| event.created_at < since)
+            || self.until.is_some_and(|until| event.created_at > until)
+        {
+            return false;
+        }
+
+        if let Some(query) = &self.search {
+            if !matcher.matches(query, event) {
+                return false;
+            }
+        }
+
+        self.tags
+            .iter()
+            .all(|(letter, values)| tag_matches(event, letter, values))
+    }
+
+    /// Split the filter into filters which together match the same events, each
+    /// listing at most `max_values` ids, authors, kinds, and values for each tag
+    /// name. Relays limit the size of filters, and some silently drop values
+    /// past their limit, so large lists (such as the authors of a contact list)
+    /// are better queried in parts.
+    ///
+    /// Any `limit` applies to each of the filters separately.
+    pub fn split(&self, max_values: usize) -> Vec<Filter> {
+        let max_values = max_values.max(1);
+        let mut filters = vec![self.clone()];
+        filters = split_list(filters, max_values, |f| &mut f.ids);
+        filters = split_list(filters, max_values, |f| &mut f.authors);
+        filters = split_list(filters, max_values, |f| &mut f.kinds);
+        for (letter, values) in self.tags.iter() {
+            if values.len() <= max_values {
+                continue;
+            }
+            filters = filters
+                .into_iter()
+                .flat_map(|filter| {
+                    values.chunks(max_values).map(move |chunk| {
+                        let mut part = filter.clone();
+                        part.tags.set(letter, chunk.to_vec());
+                        part
+                    })
+                })
+                .collect();
+        }
+        filters
+    }
+
+    /// Compile the filter for matching many events quickly (see
+    /// `CompiledFilter`)
+    pub fn compile(&self) -> CompiledFilter {
+        CompiledFilter::new(self)
+    }
+
+    /// Whether the filter cannot match any event, because its `since` is after
+    /// its `until`. (A `limit` of zero asks for no stored events, but still
+    /// matches new ones, so it does not count.)
+    pub fn matches_nothing(&self) -> bool {
+        matches!((self.since, self.until), (Some(since), Some(until)) if since > until)
+    }
+
+    /// Simplify the filter, removing duplicate values. This gives `None` if the
+    /// filter cannot match any event (see `matches_nothing()`).
+    pub fn simplify(&self) -> Option<Filter> {
+        if self.matches_nothing() {
+            return None;
+        }
+        let mut filter = self.clone();
+        dedup(&mut filter.ids);
+        dedup(&mut filter.authors);
+        dedup(&mut filter.kinds);
+        for (letter, values) in self.tags.iter() {
+            let mut values = values.to_vec();
+            dedup(&mut values);
+            filter.tags.set(letter, values);
+        }
+        Some(filter)
+    }
+
+    /// Combine two filters into one which matches the events that either
+    /// matches, if possible. This is possible when they differ in the values of
+    /// at most one of their ids, authors, kinds or tag queries, have the same
+    /// `since`, `until` and `search`, and neither has a `limit` (as one limit
+    /// over both would not give the events of each). Otherwise this gives
+    /// `None`, and the filters must be kept separate.
+    pub fn merge(&self, other: &Filter) -> Option<Filter> {
+        if self.since != other.since
+            || self.until != other.until
+            || self.limit.is_some()
+            || other.limit.is_some()
+            || self.search != other.search
+        {
+            return None;
+        }
+
+        let mut merged = self.clone();
+        let mut differences = 0;
+        if !same_set(&self.ids, &other.ids) {
+            differences += 1;
+            merged.ids = union(&self.ids, &other.ids);
+        }
+        if !same_set(&self.authors, &other.authors) {
+            differences += 1;
+            merged.authors = union(&self.authors, &other.authors);
+        }
+        if !same_set(&self.kinds, &other.kinds) {
+            differences += 1;
+            merged.kinds = union(&self.kinds, &other.kinds);
+        }
+        for letter in tag_letters(self, other) {
+            let ours = self.tags.get(letter).unwrap_or_default();
+            let theirs = other.tags.get(letter).unwrap_or_default();
+            if !same_set(ours, theirs) {
+                differences += 1;
+                merged.tags.set(letter, union(ours, theirs));
+            }
+        }
+
+        if differences > 1 {
+            None
+        } else {
+            Some(merged)
+        }
+    }
+
+    /// Combine two filters into one which matches only the events that both
+    /// match. This gives `None` if no event could match both, or if both have
+    /// a different `search` query, as those cannot be combined. The `limit` of
+    /// the result is the smaller of the two.
+    pub fn intersect(&self, other: &Filter) -> Option<Filter> {
+        let search = match (&self.search, &other.search) {
+            (Some(a), Some(b)) if a != b => return None,
+            (a, b) => a.as_ref().or(b.as_ref()).cloned(),
+        };
+        let mut filter = Filter {
+            ids: intersection(&self.ids, &other.ids)?,
+            authors: intersection(&self.authors, &other.authors)?,
+            kinds: intersection(&self.kinds, &other.kinds)?,
+            tags: TagFilter::new(),
+            since: self.since.max(other.since),
+            until: match (self.until, other.until) {
+                (Some(a), Some(b)) => Some(a.min(b)),
+                (a, b) => a.or(b),
+            },
+            limit: match (self.limit, other.limit) {
+                (Some(a), Some(b)) => Some(a.min(b)),
+                (a, b) => a.or(b),
+            },
+            search,
+        };
+        for letter in tag_letters(self, other) {
+            let values = intersection(
+                self.tags.get(letter).unwrap_or_default(),
+                other.tags.get(letter).unwrap_or_default(),
+            )?;
+            filter.tags.set(letter, values);
         }
+        filter.simplify()
     }
 
     // Mock data for testing
     #[allow(dead_code)]
     pub(crate) fn mock() -> Filter {
-        Filter {
+        let mut filter = Filter {
             ids: vec![IdHexPrefix::try_from_str("21345b").unwrap()],
             authors: vec![],
             kinds: vec![EventKind::TextNote, EventKind::Metadata],
-            e: vec![IdHex::mock()],
-            p: vec![PublicKeyHex::try_from_str(
-                "221115830ced1ca94352002485fcc7a75dcfe30d1b07f5f6fbe9c0407cfa59a1",
-            )
-            .unwrap()],
             since: Some(Unixtime(1668572286)),
             ..Default::default()
+        };
+        filter.add_e_tag_ids(IdHex::mock());
+        filter.add_p_tag_public_key(
+            PublicKeyHex::try_from_str(
+                "221115830ced1ca94352002485fcc7a75dcfe30d1b07f5f6fbe9c0407cfa59a1",
+            )
+            .unwrap(),
+        );
+        filter
+    }
+}
+
+// Split each filter into parts listing at most `max_values` of a list
+fn split_list<T: Clone>(
+    filters: Vec<Filter>,
+    max_values: usize,
+    list: fn(&mut Filter) -> &mut Vec<T>,
+) -> Vec<Filter> {
+    let mut parts = Vec::with_capacity(filters.len());
+    for mut filter in filters {
+        if list(&mut filter).len() <= max_values {
+            parts.push(filter);
+            continue;
+        }
+        let values = std::mem::take(list(&mut filter));
+        for chunk in values.chunks(max_values) {
+            let mut part = filter.clone();
+            *list(&mut part) = chunk.to_vec();
+            parts.push(part);
         }
     }
+    parts
+}
+
+// Remove later duplicates, keeping the order
+fn dedup<T: PartialEq>(values: &mut Vec<T>) {
+    let mut i = 0;
+    while i < values.len() {
+        if values[..i].contains(&values[i]) {
+            let _ = values.remove(i);
+        } else {
+            i += 1;
+        }
+    }
+}
+
+// Whether two lists of values hold the same values, in any order
+fn same_set<T: PartialEq>(a: &[T], b: &[T]) -> bool {
+    a.iter().all(|v| b.contains(v)) && b.iter().all(|v| a.contains(v))
+}
+
+// The values of a condition matching either list. An empty list does not
+// restrict, so neither does the union with one.
+fn union<T: Clone + PartialEq>(a: &[T], b: &[T]) -> Vec<T> {
+    if a.is_empty() || b.is_empty() {
+        return vec![];
+    }
+    let mut values = a.to_vec();
+    values.extend(b.iter().filter(|v| !a.contains(v)).cloned());
+    values
+}
+
+// The values of a condition matching both lists, or None if nothing can match
+// both. An empty list does not restrict.
+fn intersection<T: Clone + PartialEq>(a: &[T], b: &[T]) -> Option<Vec<T>> {
+    if a.is_empty() {
+        return Some(b.to_vec());
+    }
+    if b.is_empty() {
+        return Some(a.to_vec());
+    }
+    let values: Vec<T> = a.iter().filter(|v| b.contains(v)).cloned().collect();
+    if values.is_empty() {
+        None
+    } else {
+        Some(values)
+    }
+}
+
+// The tag names queried by either filter
+fn tag_letters(a: &Filter, b: &Filter) -> Vec<char> {
+    let mut letters: Vec<char> = a.tags.iter().chain(b.tags.iter()).map(|(l, _)| l).collect();
+    letters.sort();
+    letters.dedup();
+    letters
+}
+
+// Whether the event has a tag with this single-letter name and one of these values
+fn tag_matches(event: &Event, letter: char, values: &[String]) -> bool {
+    let mut name = [0_u8; 4];
+    let name: &str = letter.encode_utf8(&mut name);
+    event.tags.iter().any(|tag| {
+        !matches!(tag, Tag::Empty)
+            && tag.tagname() == name
+            && tag.value().is_some_and(|value| values.contains(&value))
+    })
 }
 
 #[cfg(test)]
 mod test {
     use super::*;
+    use crate::{EventBuilder, Id, PrivateKey};
 
     test_serde! {Filter, test_filters_serde}
 
@@ -251,6 +576,294 @@ mod test {
         );
     }
 
+    #[test]
+    fn test_filter_req_json() {
+        // A REQ filter using every field round-trips exactly
+        let json = r##"{"ids":["5df64b33303d62afc799bdc36d178c07b2e1f0d824f31b7dc812219440affab6"],"authors":["221115830ced1ca94352002485fcc7a75dcfe30d1b07f5f6fbe9c0407cfa59a1"],"kinds":[1,7],"#a":["30023:221115830ced1ca94352002485fcc7a75dcfe30d1b07f5f6fbe9c0407cfa59a1:abc"],"#d":["abc"],"#e":["5df64b33303d62afc799bdc36d178c07b2e1f0d824f31b7dc812219440affab6"],"#g":["u4pruydqqvj"],"#p":["221115830ced1ca94352002485fcc7a75dcfe30d1b07f5f6fbe9c0407cfa59a1"],"#r":["https://example.com/"],"#t":["nostr"],"since":1668572286,"until":1668572386,"limit":10}"##;
+        let filter: Filter = serde_json::from_str(json).unwrap();
+        assert_eq!(filter.kinds, vec![EventKind::TextNote, EventKind::Reaction]);
+        assert_eq!(
+            filter.tags.get('e'),
+            Some(&[IdHex::mock().into_string()][..])
+        );
+        assert_eq!(filter.tags.get('t'), Some(&["nostr".to_owned()][..]));
+        assert_eq!(filter.limit, Some(10));
+        assert_eq!(serde_json::to_string(&filter).unwrap(), json);
+
+        // An empty filter is an empty object
+        assert_eq!(serde_json::to_string(&Filter::new()).unwrap(), "{}");
+        assert_eq!(serde_json::from_str::<Filter>("{}").unwrap(), Filter::new());
+    }
+
+    #[test]
+    fn test_event_matches() {
+        let private_key = PrivateKey::generate();
+        let event = EventBuilder::new()
+            .content("Hello")
+            .created_at(Unixtime(1_700_000_000))
+            .tag(Tag::Hashtag("nostr".to_owned()))
+            .tag(Tag::Event {
+                id: Id::mock(),
+                recommended_relay_url: None,
+                marker: None,
+            })
+            .sign(&private_key)
+            .unwrap();
+
+        let matches = |f: fn(&mut Filter)| {
+            let mut filter = Filter::new();
+            f(&mut filter);
+            filter.event_matches(&event)
+        };
+
+        assert!(matches(|_| {}));
+
+        let id: IdHex = event.id.into();
+        let author: PublicKeyHex = event.pubkey.into();
+        let mut filter = Filter::new();
+        filter.add_id(id.clone());
+        filter.add_author(author.clone());
+        filter.add_event_kind(EventKind::TextNote);
+        assert!(filter.event_matches(&event));
+        filter.ids = vec![IdHex::mock().into()];
+        assert!(!filter.event_matches(&event));
+
+        // Exact matching only
+        let mut filter = Filter::new();
+        filter.add_id(id.prefix(10));
+        assert!(!filter.event_matches(&event));
+        let mut filter = Filter::new();
+        filter.add_author(author.prefix(10));
+        assert!(!filter.event_matches(&event));
+
+        // Unless prefix matching is asked for
+        let prefix = |filter: &Filter| {
+            filter.event_matches_with(&event, FilterMatchMode::Prefix, &SubstringSearch)
+        };
+        assert!(prefix(&filter));
+        let mut filter: Filter = serde_json::from_str(&format!(
+            r#"{{"ids":["{}"]}}"#,
+            id.prefix(8).as_str().to_uppercase()
+        ))
+        .unwrap();
+        assert!(prefix(&filter));
+        filter.add_id(IdHex::mock().prefix(8));
+        assert!(prefix(&filter));
+        filter.ids = vec![IdHex::mock().prefix(8)];
+        assert!(!prefix(&filter));
+        assert!(serde_json::from_str::<Filter>(r#"{"authors":["abcx"]}"#).is_err());
+        assert!(serde_json::from_str::<Filter>(&format!(r#"{{"ids":["{id}0"]}}"#)).is_err());
+
+        // Hex in either case matches, whether deserialized or added
+        let upper = author.as_str().to_uppercase();
+        let filter: Filter =
+            serde_json::from_str(&format!(r#"{{"authors":["{upper}"]}}"#)).unwrap();
+        assert_eq!(filter.authors, vec![author.clone().into()]);
+        assert!(filter.event_matches(&event));
+        let mut filter = Filter::new();
+        filter.add_author(PublicKeyHexPrefix::from(upper));
+        assert!(filter.event_matches(&event));
+
+        assert!(!matches(|f| f.kinds = vec![EventKind::Metadata]));
+        assert!(matches(|f| {
+            f.kinds = vec![EventKind::Metadata, EventKind::TextNote]
+        }));
+
+        // Bounds are inclusive
+        assert!(matches(|f| f.since = Some(Unixtime(1_700_000_000))));
+        assert!(!matches(|f| f.since = Some(Unixtime(1_700_000_001))));
+        assert!(matches(|f| f.until = Some(Unixtime(1_700_000_000))));
+        assert!(!matches(|f| f.until = Some(Unixtime(1_699_999_999))));
+
+        // Tag queries
+        assert!(matches(|f| {
+            f.add_tag_value('t', "other".to_owned());
+            f.add_tag_value('t', "nostr".to_owned());
+        }));
+        assert!(!matches(|f| f.add_tag_value('t', "other".to_owned())));
+        assert!(matches(|f| f.add_e_tag_ids(IdHex::mock())));
+        assert!(!matches(|f| f.add_p_tag_public_key(PublicKeyHex::mock())));
+        assert!(!matches(|f| {
+            f.add_tag_value('t', "nostr".to_owned());
+            f.add_tag_value('d', "nostr".to_owned());
+        }));
+
+        // Tags of letters this crate does not know about
+        let other = EventBuilder::new()
+            .tag(Tag::Other {
+                tag: "x".to_owned(),
+                data: vec!["value".to_owned()],
+            })
+            .sign(&private_key)
+            .unwrap();
+        let filter: Filter = serde_json::from_str(r##"{"#x":["value"]}"##).unwrap();
+        assert!(filter.event_matches(&other));
+        assert!(!filter.event_matches(&event));
+    }
+
+    #[test]
+    fn test_filter_search() {
+        let json = r#"{"kinds":[1],"search":"best nostr apps"}"#;
+        let filter: Filter = serde_json::from_str(json).unwrap();
+        assert_eq!(filter.search.as_deref(), Some("best nostr apps"));
+        assert_eq!(serde_json::to_string(&filter).unwrap(), json);
+
+        let private_key = PrivateKey::generate();
+        let event = EventBuilder::new()
+            .content("Hello Nostr")
+            .sign(&private_key)
+            .unwrap();
+        let mut filter = Filter::new();
+        filter.search = Some("nostr".to_owned());
+        assert!(filter.event_matches(&event));
+        filter.search = Some("bitcoin".to_owned());
+        assert!(!filter.event_matches(&event));
+
+        // A matcher of our own, which matches any of the words
+        struct AnyWord;
+        impl SearchMatcher for AnyWord {
+            fn matches(&self, query: &str, event: &Event) -> bool {
+                query
+                    .split_whitespace()
+                    .any(|word| SubstringSearch.matches(word, event))
+            }
+        }
+        filter.search = Some("bitcoin nostr".to_owned());
+        assert!(!filter.event_matches(&event));
+        assert!(filter.event_matches_with_search(&event, &AnyWord));
+
+        // Differing searches neither merge nor intersect
+        let mut other = filter.clone();
+        other.search = Some("other".to_owned());
+        assert!(filter.merge(&other).is_none());
+        assert!(filter.intersect(&other).is_none());
+        assert_eq!(filter.intersect(&Filter::new()).unwrap(), filter);
+    }
+
+    #[test]
+    fn test_filter_algebra() {
+        let alice = PublicKeyHex::mock();
+        let bob: PublicKeyHex = PrivateKey::generate().public_key().into();
+
+        let mut notes = Filter::new();
+        notes.add_event_kind(EventKind::TextNote);
+        notes.add_author(alice.clone());
+        notes.since = Some(Unixtime(1000));
+
+        // Differing in one condition merges
+        let mut bobs = notes.clone();
+        bobs.authors = vec![bob.clone().into()];
+        let merged = notes.merge(&bobs).unwrap();
+        assert_eq!(
+            merged.authors,
+            vec![alice.clone().into(), bob.clone().into()]
+        );
+        assert_eq!(merged.kinds, vec![EventKind::TextNote]);
+
+        // Differing in two does not
+        let mut bobs_reactions = bobs.clone();
+        bobs_reactions.kinds = vec![EventKind::Reaction];
+        assert!(notes.merge(&bobs_reactions).is_none());
+        let mut later = notes.clone();
+        later.since = Some(Unixtime(2000));
+        assert!(notes.merge(&later).is_none());
+
+        // Filters with a limit do not merge, even with the same limit
+        let mut limited = notes.clone();
+        limited.limit = Some(10);
+        assert!(notes.merge(&limited).is_none());
+        let mut bobs_limited = bobs.clone();
+        bobs_limited.limit = Some(10);
+        assert!(limited.merge(&bobs_limited).is_none());
+
+        // Merging with an unrestricted condition drops the restriction
+        let mut tagged = notes.clone();
+        tagged.add_tag_value('t', "nostr".to_owned());
+        assert_eq!(notes.merge(&tagged).unwrap(), notes);
+
+        // Intersections
+        let both = merged.intersect(&bobs_reactions);
+        assert!(both.is_none());
+        let mut until = Filter::new();
+        until.until = Some(Unixtime(1500));
+        until.limit = Some(10);
+        let both = merged.intersect(&until).unwrap();
+        assert_eq!(both.since, Some(Unixtime(1000)));
+        assert_eq!(both.until, Some(Unixtime(1500)));
+        assert_eq!(both.limit, Some(10));
+        assert_eq!(both.authors, merged.authors);
+        until.until = Some(Unixtime(500));
+        assert!(merged.intersect(&until).is_none());
+        let both = merged.intersect(&tagged).unwrap();
+        assert_eq!(both.authors, vec![alice.clone().into()]);
+        assert_eq!(both.tags.get('t'), Some(&["nostr".to_owned()][..]));
+
+        // Simplify
+        let mut dups = notes.clone();
+        dups.kinds = vec![
+            EventKind::TextNote,
+            EventKind::Reaction,
+            EventKind::TextNote,
+        ];
+        dups.tags.set('t', vec!["a".to_owned(), "a".to_owned()]);
+        let simple = dups.simplify().unwrap();
+        assert_eq!(simple.kinds, vec![EventKind::TextNote, EventKind::Reaction]);
+        assert_eq!(simple.tags.get('t'), Some(&["a".to_owned()][..]));
+        dups.since = Some(Unixtime(2000));
+        dups.until = Some(Unixtime(1500));
+        assert!(dups.matches_nothing());
+        assert!(dups.simplify().is_none());
+
+        // A limit of zero asks for live events only, which still match
+        let mut live = notes.clone();
+        live.limit = Some(0);
+        assert!(!live.matches_nothing());
+        assert_eq!(live.simplify().unwrap(), live);
+        assert_eq!(live.intersect(&notes).unwrap().limit, Some(0));
+    }
+
+    #[test]
+    fn test_filter_split() {
+        let mut filter = Filter::new();
+        let authors: Vec<PublicKeyHex> = (0..5)
+            .map(|_| PrivateKey::generate().public_key().into())
+            .collect();
+        for author in authors.iter() {
+            filter.add_author(author.clone());
+        }
+        filter.kinds = vec![EventKind::TextNote, EventKind::Repost, EventKind::Reaction];
+        filter.add_tag_value('t', "nostr".to_owned());
+        filter.limit = Some(10);
+
+        assert_eq!(filter.split(5), vec![filter.clone()]);
+
+        let parts = filter.split(2);
+        // Authors in 3 parts, times kinds in 2
+        assert_eq!(parts.len(), 6);
+        assert!(parts
+            .iter()
+            .all(|p| p.authors.len() <= 2 && p.kinds.len() <= 2 && p.limit == Some(10)));
+        for author in authors.iter() {
+            for kind in filter.kinds.iter() {
+                let covering = parts
+                    .iter()
+                    .filter(|p| {
+                        p.authors.contains(&author.clone().into()) && p.kinds.contains(kind)
+                    })
+                    .count();
+                assert_eq!(covering, 1);
+            }
+        }
+
+        let mut tagged = Filter::new();
+        tagged
+            .tags
+            .set('p', (0..7).map(|i| i.to_string()).collect());
+        let parts = tagged.split(3);
+        assert_eq!(parts.len(), 3);
+        assert_eq!(parts[2].tags.get('p'), Some(&["6".to_owned()][..]));
+    }
+
     #[test]
     fn test_prefix_match() {
         assert_eq!(prefix_match("1234", "123"), PrefixMatch::Shorter);
@@ -0,0 +1,355 @@
+use super::{EventKind, Filter, FilterMatchMode};
+
+/// The SQL dialect to generate for (see `Filter::to_sql_where()`)
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SqlDialect {
+    /// SQLite, with `?` placeholders
+    Sqlite,
+
+    /// PostgreSQL, with `$1`, `$2`, ... placeholders
+    Postgres,
+}
+
+/// A parameter to bind to a placeholder of a generated SQL clause
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum SqlParam {
+    /// A text value
+    Text(String),
+
+    /// An integer value
+    Integer(i64),
+}
+
+// Builds a clause, numbering placeholders as parameters are added
+struct SqlBuilder {
+    dialect: SqlDialect,
+    conditions: Vec<String>,
+    params: Vec<SqlParam>,
+}
+
+impl SqlBuilder {
+    fn placeholder(&mut self, param: SqlParam) -> String {
+        self.params.push(param);
+        match self.dialect {
+            SqlDialect::Sqlite => "?".to_owned(),
+            SqlDialect::Postgres => format!("${}", self.params.len()),
+        }
+    }
+
+    fn placeholders(&mut self, params: impl Iterator<Item = SqlParam>) -> String {
+        let mut list = String::new();
+        for param in params {
+            if !list.is_empty() {
+                list.push_str(", ");
+            }
+            let placeholder = self.placeholder(param);
+            list.push_str(&placeholder);
+        }
+        list
+    }
+
+    fn in_list(&mut self, column: &str, params: impl Iterator<Item = SqlParam>) {
+        let list = self.placeholders(params);
+        self.conditions.push(format!("{column} IN ({list})"));
+    }
+
+    // Ids and authors, matched by `mode`. Prefixes are hex, so they need no
+    // escaping in a LIKE pattern.
+    fn hex_list(
+        &mut self,
+        column: &str,
+        mode: FilterMatchMode,
+        values: impl Iterator<Item = String>,
+    ) {
+        match mode {
+            FilterMatchMode::Exact => self.in_list(column, values.map(SqlParam::Text)),
+            FilterMatchMode::Prefix => {
+                let likes: Vec<String> = values
+                    .map(|v| {
+                        let p = self.placeholder(SqlParam::Text(format!("{v}%")));
+                        format!("{column} LIKE {p}")
+                    })
+                    .collect();
+                self.conditions.push(format!("({})", likes.join(" OR ")));
+            }
+        }
+    }
+}
+
+impl Filter {
+    /// Translate the filter into an SQL `WHERE` condition (without the `WHERE`
+    /// keyword), and the parameters to bind to its placeholders, in order.
+    ///
+    /// The condition is written for this schema, with ids and public keys
+    /// stored as lowercase hex, and a row in `tags` for each tag of an event
+    /// with a single-letter name, holding its name and first value:
+    ///
+    /// ```sql
+    /// CREATE TABLE events (
+    ///     id         TEXT PRIMARY KEY,
+    ///     pubkey     TEXT NOT NULL,
+    ///     created_at INTEGER NOT NULL,
+    ///     kind       INTEGER NOT NULL,
+    ///     tags       TEXT NOT NULL,
+    ///     content    TEXT NOT NULL,
+    ///     sig        TEXT NOT NULL
+    /// );
+    /// CREATE TABLE tags (
+    ///     event_id TEXT NOT NULL REFERENCES events (id),
+    ///     name     TEXT NOT NULL,
+    ///     value    TEXT NOT NULL
+    /// );
+    /// ```
+    ///
+    /// It matches as `event_matches()` does, except that a `search` query is
+    /// matched as a case-insensitive substring of the content by the database
+    /// (so only ASCII letters are case-folded by SQLite). The `limit` is not
+    /// part of the condition; apply it with `ORDER BY created_at DESC LIMIT`.
+    pub fn to_sql_where(&self, dialect: SqlDialect) -> (String, Vec<SqlParam>) {
+        self.to_sql_where_with(dialect, FilterMatchMode::Exact)
+    }
+
+    /// As `to_sql_where()`, but matching ids and authors by the given
+    /// `FilterMatchMode`, as `event_matches_with()` does
+    pub fn to_sql_where_with(
+        &self,
+        dialect: SqlDialect,
+        mode: FilterMatchMode,
+    ) -> (String, Vec<SqlParam>) {
+        let mut sql = SqlBuilder {
+            dialect,
+            conditions: Vec::new(),
+            params: Vec::new(),
+        };
+
+        if !self.ids.is_empty() {
+            sql.hex_list("events.id", mode, self.ids.iter().map(|id| id.to_string()));
+        }
+        if !self.authors.is_empty() {
+            sql.hex_list(
+                "events.pubkey",
+                mode,
+                self.authors.iter().map(|author| author.to_string()),
+            );
+        }
+        if !self.kinds.is_empty() {
+            // Kinds beyond i64 cannot be stored, so cannot match
+            let kind = |kind: &EventKind| i64::try_from(u64::from(*kind)).unwrap_or(-1);
+            sql.in_list(
+                "events.kind",
+                self.kinds.iter().map(|k| SqlParam::Integer(kind(k))),
+            );
+        }
+        if let Some(since) = self.since {
+            let p = sql.placeholder(SqlParam::Integer(since.0));
+            sql.conditions.push(format!("events.created_at >= {p}"));
+        }
+        if let Some(until) = self.until {
+            let p = sql.placeholder(SqlParam::Integer(until.0));
+            sql.conditions.push(format!("events.created_at <= {p}"));
+        }
+        for (letter, values) in self.tags.iter() {
+            let name = sql.placeholder(SqlParam::Text(letter.to_string()));
+            let list = sql.placeholders(values.iter().map(|v| SqlParam::Text(v.clone())));
+            sql.conditions.push(format!(
+                "EXISTS (SELECT 1 FROM tags WHERE tags.event_id = events.id \
+                 AND tags.name = {name} AND tags.value IN ({list}))"
+            ));
+        }
+        if let Some(search) = &self.search {
+            let mut pattern = String::from("%");
+            for c in search.chars() {
+                if matches!(c, '%' | '_' | '\\') {
+                    pattern.push('\\');
+                }
+                pattern.push(c);
+            }
+            pattern.push('%');
+            let p = sql.placeholder(SqlParam::Text(pattern));
+            let like = match dialect {
+                SqlDialect::Sqlite => "LIKE",
+                SqlDialect::Postgres => "ILIKE",
+            };
+            sql.conditions
+                .push(format!("events.content {like} {p} ESCAPE '\\'"));
+        }
+
+        let clause = if sql.conditions.is_empty() {
+            "TRUE".to_owned()
+        } else {
+            sql.conditions.join(" AND ")
+        };
+        (clause, sql.params)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{
+        Event, EventBuilder, IdHex, IdHexPrefix, PrivateKey, PublicKeyHex, PublicKeyHexPrefix,
+        SubstringSearch, Tag, Unixtime,
+    };
+
+    #[test]
+    fn test_filter_to_sql_where() {
+        assert_eq!(
+            Filter::new().to_sql_where(SqlDialect::Sqlite),
+            ("TRUE".to_owned(), vec![])
+        );
+
+        let mut filter = Filter::new();
+        filter.add_id(IdHex::mock());
+        filter.kinds = vec![EventKind::TextNote, EventKind::Reaction];
+        filter.since = Some(Unixtime(1000));
+        filter.add_tag_value('t', "nostr".to_owned());
+        filter.add_tag_value('t', "rust".to_owned());
+        filter.search = Some("100%".to_owned());
+
+        let (clause, params) = filter.to_sql_where(SqlDialect::Postgres);
+        assert_eq!(
+            clause,
+            "events.id IN ($1) AND events.kind IN ($2, $3) AND events.created_at >= $4 \
+             AND EXISTS (SELECT 1 FROM tags WHERE tags.event_id = events.id \
+             AND tags.name = $5 AND tags.value IN ($6, $7)) \
+             AND events.content ILIKE $8 ESCAPE '\\'"
+        );
+        assert_eq!(
+            params,
+            vec![
+                SqlParam::Text(IdHex::mock().into_string()),
+                SqlParam::Integer(1),
+                SqlParam::Integer(7),
+                SqlParam::Integer(1000),
+                SqlParam::Text("t".to_owned()),
+                SqlParam::Text("nostr".to_owned()),
+                SqlParam::Text("rust".to_owned()),
+                SqlParam::Text("%100\\%%".to_owned()),
+            ]
+        );
+
+        let (clause, sqlite_params) = filter.to_sql_where(SqlDialect::Sqlite);
+        assert!(clause.starts_with("events.id IN (?) AND events.kind IN (?, ?)"));
+        assert!(clause.contains("LIKE ? ESCAPE"));
+        assert_eq!(sqlite_params, params);
+    }
+
+    #[test]
+    fn test_filter_to_sql_where_prefix() {
+        let mut filter = Filter::new();
+        filter.ids = vec![IdHexPrefix::try_from_str("5df6").unwrap()];
+        filter.add_author(PublicKeyHexPrefix::try_from_str("ee11").unwrap());
+        let (clause, params) =
+            filter.to_sql_where_with(SqlDialect::Sqlite, FilterMatchMode::Prefix);
+        assert_eq!(clause, "(events.id LIKE ?) AND (events.pubkey LIKE ?)");
+        assert_eq!(
+            params,
+            vec![
+                SqlParam::Text("5df6%".to_owned()),
+                SqlParam::Text("ee11%".to_owned()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_filter_to_sql_where_sqlite() {
+        let db = rusqlite::Connection::open_in_memory().unwrap();
+        db.execute_batch(
+            "CREATE TABLE events (id TEXT PRIMARY KEY, pubkey TEXT NOT NULL, \
+             created_at INTEGER NOT NULL, kind INTEGER NOT NULL, tags TEXT NOT NULL, \
+             content TEXT NOT NULL, sig TEXT NOT NULL); \
+             CREATE TABLE tags (event_id TEXT NOT NULL REFERENCES events (id), \
+             name TEXT NOT NULL, value TEXT NOT NULL);",
+        )
+        .unwrap();
+
+        let private_key = PrivateKey::generate();
+        let events: Vec<Event> = [
+            (EventKind::TextNote, 1000, "Hello nostr", "nostr"),
+            (EventKind::TextNote, 2000, "100% rust", "rust"),
+            (EventKind::Reaction, 3000, "+", "nostr"),
+        ]
+        .into_iter()
+        .map(|(kind, created_at, content, hashtag)| {
+            EventBuilder::new()
+                .kind(kind)
+                .created_at(Unixtime(created_at))
+                .content(content)
+                .tag(Tag::Hashtag(hashtag.to_owned()))
+                .sign(&private_key)
+                .unwrap()
+        })
+        .collect();
+        for event in events.iter() {
+            let _ = db
+                .execute(
+                    "INSERT INTO events VALUES (?, ?, ?, ?, ?, ?, ?)",
+                    rusqlite::params![
+                        event.id.as_hex_string(),
+                        event.pubkey.as_hex_string(),
+                        event.created_at.0,
+                        u64::from(event.kind) as i64,
+                        serde_json::to_string(&event.tags).unwrap(),
+                        event.content,
+                        event.sig.as_hex_string(),
+                    ],
+                )
+                .unwrap();
+            for tag in event.tags.iter() {
+                let _ = db
+                    .execute(
+                        "INSERT INTO tags VALUES (?, ?, ?)",
+                        rusqlite::params![event.id.as_hex_string(), tag.tagname(), tag.value()],
+                    )
+                    .unwrap();
+            }
+        }
+
+        let query = |filter: &Filter, mode: FilterMatchMode| -> Vec<String> {
+            let (clause, params) = filter.to_sql_where_with(SqlDialect::Sqlite, mode);
+            let params: Vec<rusqlite::types::Value> = params
+                .into_iter()
+                .map(|param| match param {
+                    SqlParam::Text(s) => s.into(),
+                    SqlParam::Integer(i) => i.into(),
+                })
+                .collect();
+            let mut stmt = db
+                .prepare(&format!(
+                    "SELECT id FROM events WHERE {clause} ORDER BY created_at"
+                ))
+                .unwrap();
+            stmt.query_map(rusqlite::params_from_iter(params), |row| row.get(0))
+                .unwrap()
+                .map(|id| id.unwrap())
+                .collect()
+        };
+        // The database gives the same events as event_matches_with()
+        let check = |filter: Filter, mode: FilterMatchMode| {
+            let expected: Vec<String> = events
+                .iter()
+                .filter(|e| filter.event_matches_with(e, mode, &SubstringSearch))
+                .map(|e| e.id.as_hex_string())
+                .collect();
+            assert!(!expected.is_empty(), "{filter:?}");
+            assert_eq!(query(&filter, mode), expected, "{filter:?}");
+        };
+
+        let mut filter = Filter::new();
+        filter.kinds = vec![EventKind::TextNote];
+        filter.add_tag_value('t', "rust".to_owned());
+        filter.search = Some("100%".to_owned());
+        check(filter, FilterMatchMode::Exact);
+
+        let mut filter = Filter::new();
+        filter.add_author(PublicKeyHex::from(private_key.public_key()));
+        filter.since = Some(Unixtime(1500));
+        check(filter, FilterMatchMode::Exact);
+
+        let mut filter = Filter::new();
+        let id = events[0].id.as_hex_string();
+        filter.ids = vec![IdHexPrefix::try_from_str(id.get(..8).unwrap()).unwrap()];
+        check(filter.clone(), FilterMatchMode::Prefix);
+        assert!(query(&filter, FilterMatchMode::Exact).is_empty());
+    }
+}
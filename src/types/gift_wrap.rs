@@ -0,0 +1,309 @@
+use super::{Event, EventKind, Id, PreEvent, PrivateKey, PublicKey, Signer, Tag, Unixtime};
+use crate::Error;
+use rand_core::{OsRng, RngCore};
+use serde::{Deserialize, Serialize};
+#[cfg(feature = "speedy")]
+use speedy::{Readable, Writable};
+
+// Seals and gift wraps are backdated by a random amount up to this many
+// seconds, so that their created_at does not reveal when the rumor was sent
+const MAX_TIMESTAMP_TWEAK: u64 = 2 * 24 * 60 * 60;
+
+// A random time in the recent past
+fn tweaked_now() -> Result<Unixtime, Error> {
+    let tweak = OsRng.next_u64() % MAX_TIMESTAMP_TWEAK;
+    Ok(Unixtime(Unixtime::now()?.0 - tweak as i64))
+}
+
+/// A rumor is an event without a signature (NIP-59). It has an id, so it can be
+/// referred to, but it is deniable as it is not signed.
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+#[cfg_attr(feature = "speedy", derive(Readable, Writable))]
+pub struct Rumor {
+    /// The Id of the event, generated as a SHA256 of the inner event data
+    pub id: Id,
+
+    /// The public key of the actor who created the event
+    pub pubkey: PublicKey,
+
+    /// The (unverified) time at which the event was created
+    pub created_at: Unixtime,
+
+    /// The kind of event
+    pub kind: EventKind,
+
+    /// A set of tags that apply to the event
+    pub tags: Vec<Tag>,
+
+    /// The content of the event
+    pub content: String,
+}
+
+impl Rumor {
+    /// Create a new rumor, computing its Id
+    pub fn new(input: PreEvent) -> Result<Rumor, Error> {
+        let id = Event::hash(&input)?;
+        Ok(Rumor {
+            id,
+            pubkey: input.pubkey,
+            created_at: input.created_at,
+            kind: input.kind,
+            tags: input.tags,
+            content: input.content,
+        })
+    }
+
+    /// Check that the Id matches the rumor's data
+    pub fn verify(&self) -> Result<(), Error> {
+        let id = Event::hash(&self.to_pre_event())?;
+        if id != self.id {
+            Err(Error::HashMismatch)
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Sign the rumor, turning it into an event. The signer must be the rumor's
+    /// author.
+    pub fn sign<S: Signer + ?Sized>(&self, signer: &S) -> Result<Event, Error> {
+        let event = signer.sign_event(self.to_pre_event())?;
+        if event.id != self.id {
+            return Err(Error::HashMismatch);
+        }
+        Ok(event)
+    }
+
+    fn to_pre_event(&self) -> PreEvent {
+        PreEvent {
+            pubkey: self.pubkey,
+            created_at: self.created_at,
+            kind: self.kind,
+            tags: self.tags.clone(),
+            content: self.content.clone(),
+            ots: None,
+        }
+    }
+}
+
+impl From<Event> for Rumor {
+    /// Strip the signature from an event
+    fn from(event: Event) -> Rumor {
+        Rumor {
+            id: event.id,
+            pubkey: event.pubkey,
+            created_at: event.created_at,
+            kind: event.kind,
+            tags: event.tags,
+            content: event.content,
+        }
+    }
+}
+
+/// A seal is a kind 13 event with a rumor encrypted (NIP-44) to a receiver in its
+/// content, signed by the author of the rumor (NIP-59)
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+#[serde(transparent)]
+pub struct Seal(pub Event);
+
+impl Seal {
+    /// Seal a rumor authored by `sender` for `receiver`
+    pub fn new(rumor: &Rumor, sender: &PrivateKey, receiver: &PublicKey) -> Result<Seal, Error> {
+        if rumor.pubkey != sender.public_key() {
+            return Err(Error::InvalidGiftWrap(
+                "The rumor is not authored by the sender".to_owned(),
+            ));
+        }
+
+        let content = sender.nip44_encrypt(receiver, &serde_json::to_string(rumor)?)?;
+        let pre_event = PreEvent {
+            pubkey: sender.public_key(),
+            created_at: tweaked_now()?,
+            kind: EventKind::Seal,
+            tags: vec![],
+            content,
+            ots: None,
+        };
+        Ok(Seal(Event::new(pre_event, sender)?))
+    }
+
+    /// Verify and decrypt the seal, returning the rumor within. This fails if the
+    /// rumor was not authored by the signer of the seal.
+    pub fn open(&self, receiver: &PrivateKey) -> Result<Rumor, Error> {
+        self.0.verify(None)?;
+        let json = receiver.nip44_decrypt(&self.0.pubkey, &self.0.content)?;
+        let rumor: Rumor = serde_json::from_str(&json)?;
+        if rumor.pubkey != self.0.pubkey {
+            return Err(Error::InvalidGiftWrap(
+                "The rumor is not authored by the signer of the seal".to_owned(),
+            ));
+        }
+        rumor.verify()?;
+        Ok(rumor)
+    }
+}
+
+impl TryFrom<Event> for Seal {
+    type Error = Error;
+
+    fn try_from(event: Event) -> Result<Seal, Error> {
+        if event.kind != EventKind::Seal {
+            return Err(Error::WrongEventKind);
+        }
+        Ok(Seal(event))
+    }
+}
+
+/// A gift wrap is a kind 1059 event with a seal encrypted (NIP-44) to a receiver in
+/// its content, signed by a random one-time key (NIP-59)
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+#[serde(transparent)]
+pub struct GiftWrap(pub Event);
+
+impl GiftWrap {
+    /// Seal a rumor authored by `sender` for `receiver`, and wrap it with a random
+    /// one-time key
+    pub fn wrap(
+        rumor: &Rumor,
+        sender: &PrivateKey,
+        receiver: &PublicKey,
+    ) -> Result<GiftWrap, Error> {
+        let seal = Seal::new(rumor, sender, receiver)?;
+
+        let wrapper = PrivateKey::generate();
+        let content = wrapper.nip44_encrypt(receiver, &serde_json::to_string(&seal)?)?;
+        let pre_event = PreEvent {
+            pubkey: wrapper.public_key(),
+            created_at: tweaked_now()?,
+            kind: EventKind::GiftWrap,
+            tags: vec![Tag::Pubkey {
+                pubkey: (*receiver).into(),
+                recommended_relay_url: None,
+                petname: None,
+            }],
+            content,
+            ots: None,
+        };
+        Ok(GiftWrap(Event::new(pre_event, &wrapper)?))
+    }
+
+    /// Verify and decrypt the gift wrap and the seal within, returning the rumor
+    pub fn unwrap(&self, receiver: &PrivateKey) -> Result<Rumor, Error> {
+        self.0.verify(None)?;
+        let json = receiver.nip44_decrypt(&self.0.pubkey, &self.0.content)?;
+        let seal: Seal = Seal::try_from(serde_json::from_str::<Event>(&json)?)?;
+        seal.open(receiver)
+    }
+
+    /// The receiver the gift wrap is addressed to, from its 'p' tag
+    pub fn receiver(&self) -> Option<PublicKey> {
+        self.0.tags.iter().find_map(|tag| match tag {
+            Tag::Pubkey { pubkey, .. } => PublicKey::try_from_hex_string(pubkey).ok(),
+            _ => None,
+        })
+    }
+}
+
+impl TryFrom<Event> for GiftWrap {
+    type Error = Error;
+
+    fn try_from(event: Event) -> Result<GiftWrap, Error> {
+        if event.kind != EventKind::GiftWrap {
+            return Err(Error::WrongEventKind);
+        }
+        Ok(GiftWrap(event))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn rumor(author: &PublicKey) -> Rumor {
+        Rumor::new(PreEvent {
+            pubkey: *author,
+            created_at: Unixtime::now().unwrap(),
+            kind: EventKind::TextNote,
+            tags: vec![],
+            content: "Are you going to the party tonight?".to_owned(),
+            ots: None,
+        })
+        .unwrap()
+    }
+
+    #[test]
+    fn test_gift_wrap_roundtrip() {
+        let sender = PrivateKey::generate();
+        let receiver = PrivateKey::generate();
+        let rumor = rumor(&sender.public_key());
+        assert!(rumor.verify().is_ok());
+
+        let gift_wrap = GiftWrap::wrap(&rumor, &sender, &receiver.public_key()).unwrap();
+        assert_eq!(gift_wrap.0.kind, EventKind::GiftWrap);
+        assert_ne!(gift_wrap.0.pubkey, sender.public_key());
+        assert_eq!(gift_wrap.receiver(), Some(receiver.public_key()));
+        assert!(gift_wrap.0.created_at <= Unixtime::now().unwrap());
+
+        // It survives the trip through JSON
+        let json = serde_json::to_string(&gift_wrap).unwrap();
+        let event: Event = serde_json::from_str(&json).unwrap();
+        let gift_wrap = GiftWrap::try_from(event).unwrap();
+
+        assert_eq!(gift_wrap.unwrap(&receiver).unwrap(), rumor);
+
+        // Nobody else can unwrap it
+        assert!(gift_wrap.unwrap(&PrivateKey::generate()).is_err());
+    }
+
+    #[test]
+    fn test_rumor_sign() {
+        let author = PrivateKey::generate();
+        let rumor = rumor(&author.public_key());
+
+        let event = rumor.sign(&author).unwrap();
+        assert!(event.verify(None).is_ok());
+        assert_eq!(event.id, rumor.id);
+        assert_eq!(Rumor::from(event), rumor);
+
+        // Only the author can sign it
+        assert!(rumor.sign(&PrivateKey::generate()).is_err());
+
+        // A tampered rumor cannot be signed
+        let mut tampered = rumor;
+        tampered.content = "Changed".to_owned();
+        assert!(tampered.verify().is_err());
+        assert!(tampered.sign(&author).is_err());
+    }
+
+    #[test]
+    fn test_seal_rejects_impersonation() {
+        let sender = PrivateKey::generate();
+        let receiver = PrivateKey::generate();
+        let victim = PrivateKey::generate();
+        let rumor = rumor(&victim.public_key());
+
+        assert!(Seal::new(&rumor, &sender, &receiver.public_key()).is_err());
+
+        // Build a seal by hand that claims someone else authored the rumor
+        let content = sender
+            .nip44_encrypt(
+                &receiver.public_key(),
+                &serde_json::to_string(&rumor).unwrap(),
+            )
+            .unwrap();
+        let seal = Seal(
+            Event::new(
+                PreEvent {
+                    pubkey: sender.public_key(),
+                    created_at: Unixtime::now().unwrap(),
+                    kind: EventKind::Seal,
+                    tags: vec![],
+                    content,
+                    ots: None,
+                },
+                &sender,
+            )
+            .unwrap(),
+        );
+        assert!(seal.open(&receiver).is_err());
+    }
+}
@@ -0,0 +1,121 @@
+use super::{RelayInformationDocument, RelayUrl};
+use crate::Error;
+use std::future::Future;
+use std::time::Duration;
+
+/// The largest relay information document that `fetch_relay_information()`
+/// will accept
+pub const RELAY_INFORMATION_MAX_BYTES: usize = 64 * 1024;
+
+/// An HTTP GET request, made through the HTTP client the caller supplies (this
+/// crate has none of its own). The client returns the body of a successful
+/// response, and:
+/// * must not follow redirects,
+/// * must send the `accept` header, if there is one,
+/// * must stop reading (and fail) once more than `max_bytes` have arrived,
+/// * must give up after `timeout`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct HttpRequest {
+    /// The URL to fetch
+    pub url: String,
+
+    /// The value of the `Accept` header to send
+    pub accept: Option<&'static str>,
+
+    /// The most bytes of response body to read
+    pub max_bytes: usize,
+
+    /// How long to wait for the whole response
+    pub timeout: Duration,
+}
+
+// Make the request with the caller's client, checking the size limit again in
+// case the client did not
+pub(crate) async fn get<F, Fut>(request: HttpRequest, fetch: F) -> Result<Vec<u8>, Error>
+where
+    F: FnOnce(HttpRequest) -> Fut,
+    Fut: Future<Output = Result<Vec<u8>, Error>>,
+{
+    let max_bytes = request.max_bytes;
+    let body = fetch(request).await?;
+    if body.len() > max_bytes {
+        return Err(Error::HttpResponseTooLarge(body.len()));
+    }
+    Ok(body)
+}
+
+/// Fetch the NIP-11 relay information document of a relay, with `fetch` making
+/// the `HttpRequest` (see there). The document is requested from the relay URL
+/// with its scheme changed to https (or http, for ws).
+pub async fn fetch_relay_information<F, Fut>(
+    relay_url: &RelayUrl,
+    fetch: F,
+) -> Result<RelayInformationDocument, Error>
+where
+    F: FnOnce(HttpRequest) -> Fut,
+    Fut: Future<Output = Result<Vec<u8>, Error>>,
+{
+    let mut url = url::Url::parse(relay_url.as_str())?;
+    let scheme = match url.scheme() {
+        "wss" => "https",
+        "ws" => "http",
+        other => return Err(Error::InvalidUrlScheme(other.to_owned())),
+    };
+    url.set_scheme(scheme)
+        .map_err(|_| Error::InvalidUrlScheme(scheme.to_owned()))?;
+
+    let request = HttpRequest {
+        url: url.into(),
+        accept: Some("application/nostr+json"),
+        max_bytes: RELAY_INFORMATION_MAX_BYTES,
+        timeout: Duration::from_secs(10),
+    };
+    let body = get(request, fetch).await?;
+    Ok(serde_json::from_slice(&body)?)
+}
+
+// The fetchers in tests are ready at once, so one poll completes the future
+#[cfg(test)]
+pub(crate) fn block_on<T>(future: impl Future<Output = T>) -> T {
+    use std::pin::pin;
+    use std::task::{Context, Poll, Waker};
+
+    match pin!(future).poll(&mut Context::from_waker(Waker::noop())) {
+        Poll::Ready(output) => output,
+        Poll::Pending => panic!("future not ready"),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_fetch_relay_information() {
+        let body = serde_json::to_vec(&RelayInformationDocument::mock()).unwrap();
+        let relay_url = RelayUrl::try_from_str("wss://relay.example.com/path").unwrap();
+        let fetch = |request: HttpRequest| {
+            assert_eq!(request.url, "https://relay.example.com/path");
+            assert_eq!(request.accept, Some("application/nostr+json"));
+            async move { Ok(body) }
+        };
+        let rid = block_on(fetch_relay_information(&relay_url, fetch)).unwrap();
+        assert_eq!(rid, RelayInformationDocument::mock());
+
+        let relay_url = RelayUrl::try_from_str("ws://relay.example.com:8080").unwrap();
+        let fetch = |request: HttpRequest| {
+            assert_eq!(request.url, "http://relay.example.com:8080/");
+            async { Ok(b"{}".to_vec()) }
+        };
+        let rid = block_on(fetch_relay_information(&relay_url, fetch)).unwrap();
+        assert_eq!(rid, RelayInformationDocument::default());
+
+        let huge = |_| async { Ok(vec![b' '; RELAY_INFORMATION_MAX_BYTES + 1]) };
+        assert!(matches!(
+            block_on(fetch_relay_information(&relay_url, huge)),
+            Err(Error::HttpResponseTooLarge(_))
+        ));
+        let failed = |_| async { Err(Error::Url("connection refused".to_owned())) };
+        assert!(block_on(fetch_relay_information(&relay_url, failed)).is_err());
+    }
+}
@@ -1,3 +1,5 @@
+use super::abbrev::abbreviate_bech32;
+use super::{fast_hex, EventKind, EventPointer, PublicKey, UncheckedUrl};
 use crate::Error;
 use bech32::{FromBase32, ToBase32};
 use derive_more::{AsMut, AsRef, Deref, Display, From, FromStr, Into};
@@ -5,14 +7,13 @@ use serde::de::{Deserializer, Visitor};
 use serde::ser::Serializer;
 use serde::{Deserialize, Serialize};
 #[cfg(feature = "speedy")]
-use speedy::{Readable, Writable};
+use speedy::{Context, Readable, Reader, Writable, Writer};
 use std::fmt;
 
 /// An event identifier, constructed as a SHA256 hash of the event fields according to NIP-01
 #[derive(
     AsMut, AsRef, Clone, Copy, Debug, Deref, Eq, From, Hash, Into, Ord, PartialEq, PartialOrd,
 )]
-#[cfg_attr(feature = "speedy", derive(Readable, Writable))]
 pub struct Id(pub [u8; 32]);
 
 impl Id {
@@ -20,11 +21,19 @@ impl Id {
     ///
     /// Consider converting `.into()` an `IdHex` which is a wrapped type rather than a naked `String`
     pub fn as_hex_string(&self) -> String {
-        hex::encode(self.0)
+        fast_hex::encode(&self.0)
+    }
+
+    /// Write as a hexadecimal string, without allocating
+    pub fn write_hex<W: fmt::Write + ?Sized>(&self, w: &mut W) -> fmt::Result {
+        fast_hex::write_hex(&self.0, w)
     }
 
     /// Create from a hexadecimal string
     pub fn try_from_hex_string(v: &str) -> Result<Id, Error> {
+        if let Some(bytes) = fast_hex::decode_array(v) {
+            return Ok(Id(bytes));
+        }
         let vec: Vec<u8> = hex::decode(v)?;
         Ok(Id(vec
             .try_into()
@@ -36,6 +45,18 @@ impl Id {
         bech32::encode("note", self.0.to_vec().to_base32(), bech32::Variant::Bech32).unwrap()
     }
 
+    /// Export as a bech32 encoded string ("note"). This is the same as
+    /// `as_bech32_string()`, named for symmetry with `as_nevent()`.
+    pub fn as_note_string(&self) -> String {
+        self.as_bech32_string()
+    }
+
+    /// Abbreviate the bech32 "note" for display, keeping `n` characters of the
+    /// data at each end, like `note1abcd…wxyz`
+    pub fn abbrev(&self, n: usize) -> String {
+        abbreviate_bech32(&self.as_bech32_string(), n)
+    }
+
     /// Import from a bech32 encoded string ("note")
     pub fn try_from_bech32_string(s: &str) -> Result<Id, Error> {
         let data = bech32::decode(s)?;
@@ -54,6 +75,38 @@ impl Id {
         }
     }
 
+    /// Export as a bech32 encoded string ("nevent"), along with some relays
+    /// where the event may be found, and optionally its author and kind
+    pub fn as_nevent(
+        &self,
+        relays: Vec<UncheckedUrl>,
+        author: Option<PublicKey>,
+        kind: Option<EventKind>,
+    ) -> String {
+        EventPointer {
+            id: *self,
+            relays,
+            author,
+            kind,
+        }
+        .as_bech32_string()
+    }
+
+    /// The number of leading zero bits, which is the proof-of-work difficulty
+    /// of the event with this Id (NIP-13). An all-zero Id counts as 255.
+    pub fn leading_zero_bits(&self) -> u8 {
+        let mut res = 0_u8;
+        for b in self.0.iter() {
+            if *b == 0 {
+                res = res.saturating_add(8);
+            } else {
+                res += b.leading_zeros() as u8;
+                return res;
+            }
+        }
+        res
+    }
+
     // Mock data for testing
     #[allow(dead_code)]
     pub(crate) fn mock() -> Id {
@@ -62,12 +115,67 @@ impl Id {
     }
 }
 
+/// Displays as hex, or with the alternate flag (`{:#}`) as an abbreviated
+/// "note" (see `abbrev()`)
+impl fmt::Display for Id {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if f.alternate() {
+            write!(f, "{}", self.abbrev(4))
+        } else {
+            self.write_hex(f)
+        }
+    }
+}
+
+impl TryFrom<&str> for Id {
+    type Error = Error;
+
+    /// Accepts hex, a bech32 "note", or a bech32 "nevent" (taking its id)
+    fn try_from(s: &str) -> Result<Id, Error> {
+        if s.starts_with("note1") {
+            Id::try_from_bech32_string(s)
+        } else if s.starts_with("nevent1") {
+            Ok(EventPointer::try_from_bech32_string(s)?.id)
+        } else {
+            Id::try_from_hex_string(s)
+        }
+    }
+}
+
+#[cfg(feature = "speedy")]
+impl<'a, C: Context> Readable<'a, C> for Id {
+    #[inline]
+    fn read_from<R: Reader<'a, C>>(reader: &mut R) -> Result<Self, C::Error> {
+        let mut bytes = [0_u8; 32];
+        reader.read_bytes(&mut bytes)?;
+        Ok(Id(bytes))
+    }
+
+    #[inline]
+    fn minimum_bytes_needed() -> usize {
+        32
+    }
+}
+
+#[cfg(feature = "speedy")]
+impl<C: Context> Writable<C> for Id {
+    #[inline]
+    fn write_to<T: ?Sized + Writer<C>>(&self, writer: &mut T) -> Result<(), C::Error> {
+        writer.write_bytes(&self.0)
+    }
+
+    #[inline]
+    fn bytes_needed(&self) -> Result<usize, C::Error> {
+        Ok(32)
+    }
+}
+
 impl Serialize for Id {
     fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
     where
         S: Serializer,
     {
-        serializer.serialize_str(&hex::encode(self.0))
+        fast_hex::with_hex(&self.0, |hex| serializer.serialize_str(hex))
     }
 }
 
@@ -93,6 +201,9 @@ impl Visitor<'_> for IdVisitor {
     where
         E: serde::de::Error,
     {
+        if let Some(bytes) = fast_hex::decode_array(v) {
+            return Ok(Id(bytes));
+        }
         let vec: Vec<u8> = hex::decode(v).map_err(|e| serde::de::Error::custom(format!("{e}")))?;
 
         Ok(Id(vec.try_into().map_err(|e: Vec<u8>| {
@@ -138,8 +249,8 @@ impl IdHex {
         Self::try_from_string(s.to_owned())
     }
 
-    /// Try from String
-    pub fn try_from_string(s: String) -> Result<IdHex, Error> {
+    /// Try from String. The hex is lowercased.
+    pub fn try_from_string(mut s: String) -> Result<IdHex, Error> {
         if s.len() != 64 {
             return Err(Error::InvalidId);
         }
@@ -147,6 +258,7 @@ impl IdHex {
         if vec.len() != 32 {
             return Err(Error::InvalidId);
         }
+        s.make_ascii_lowercase();
         Ok(IdHex(s))
     }
 
@@ -192,20 +304,7 @@ impl From<IdHex> for Id {
 
 /// An event identifier prefix, constructed as a SHA256 hash of the event fields according to NIP-01, as a hex string
 #[derive(
-    AsMut,
-    AsRef,
-    Clone,
-    Debug,
-    Deref,
-    Deserialize,
-    Display,
-    Eq,
-    From,
-    FromStr,
-    Hash,
-    Into,
-    PartialEq,
-    Serialize,
+    AsMut, AsRef, Clone, Debug, Deref, Display, Eq, From, FromStr, Hash, Into, PartialEq, Serialize,
 )]
 #[cfg_attr(feature = "speedy", derive(Readable, Writable))]
 pub struct IdHexPrefix(String);
@@ -222,18 +321,19 @@ impl IdHexPrefix {
         Self::try_from_string(s.to_owned())
     }
 
-    /// Try from String
-    pub fn try_from_string(s: String) -> Result<IdHexPrefix, Error> {
+    /// Try from String. The hex is lowercased.
+    pub fn try_from_string(mut s: String) -> Result<IdHexPrefix, Error> {
         if s.len() > 64 {
             return Err(Error::InvalidIdPrefix);
         }
         if s.chars().any(|c| !c.is_ascii_hexdigit()) {
-            return Err(Error::InvalidPublicKeyPrefix);
+            return Err(Error::InvalidIdPrefix);
         }
         // let vec: Vec<u8> = hex::decode(&s)?;
         // if vec.len() > 32 {
         //     return Err(Error::InvalidIdPrefix);
         // }
+        s.make_ascii_lowercase();
         Ok(IdHexPrefix(s))
     }
 
@@ -267,10 +367,70 @@ impl TryFrom<&str> for IdHexPrefix {
     }
 }
 
+impl<'de> Deserialize<'de> for IdHexPrefix {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        deserializer.deserialize_str(IdHexPrefixVisitor)
+    }
+}
+
+struct IdHexPrefixVisitor;
+
+impl Visitor<'_> for IdHexPrefixVisitor {
+    type Value = IdHexPrefix;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "a hexadecimal string of at most 64 characters, an event id or a prefix of one"
+        )
+    }
+
+    fn visit_str<E>(self, v: &str) -> Result<IdHexPrefix, E>
+    where
+        E: serde::de::Error,
+    {
+        IdHexPrefix::try_from_str(v).map_err(|e| E::custom(format!("{e}")))
+    }
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
 
+    #[test]
+    fn test_id_abbrev() {
+        let id = Id::mock();
+        let note = id.as_bech32_string();
+        let abbrev = id.abbrev(4);
+        assert_eq!(abbrev.chars().count(), 5 + 4 + 1 + 4);
+        assert!(abbrev.starts_with(note.get(..9).unwrap()));
+        assert!(abbrev.ends_with(note.get(note.len() - 4..).unwrap()));
+        assert_eq!(id.abbrev(100), note);
+
+        assert_eq!(format!("{id}"), id.as_hex_string());
+        assert_eq!(format!("{id:#}"), abbrev);
+    }
+
+    #[test]
+    fn test_id_try_from_str() {
+        let id = Id::mock();
+        assert_eq!(Id::try_from(id.as_hex_string().as_str()).unwrap(), id);
+        assert_eq!(Id::try_from(id.as_note_string().as_str()).unwrap(), id);
+        let nevent = id.as_nevent(
+            vec![UncheckedUrl::from_str("wss://r.example.com")],
+            None,
+            None,
+        );
+        assert_eq!(Id::try_from(nevent.as_str()).unwrap(), id);
+
+        assert!(Id::try_from("note1abc").is_err());
+        assert!(Id::try_from("abcd").is_err());
+        assert!(Id::try_from(PublicKey::mock().as_bech32_string().as_str()).is_err());
+    }
+
     test_serde! {Id, test_id_serde}
     test_serde! {IdHex, test_id_hex_serde}
     test_serde! {IdHexPrefix, test_id_hex_prefix_serde}
@@ -281,4 +441,33 @@ mod test {
         println!("{bech32}");
         assert_eq!(Id::mock(), Id::try_from_bech32_string(&bech32).unwrap());
     }
+
+    #[test]
+    fn test_id_leading_zero_bits() {
+        let mut id = Id([0xff; 32]);
+        assert_eq!(id.leading_zero_bits(), 0);
+        id.0[0] = 0;
+        id.0[1] = 0x1f;
+        assert_eq!(id.leading_zero_bits(), 11);
+        assert_eq!(Id([0; 32]).leading_zero_bits(), 0xff);
+    }
+
+    #[test]
+    fn test_id_hex() {
+        let id = Id::mock();
+        let mut hex = String::new();
+        id.write_hex(&mut hex).unwrap();
+        assert_eq!(hex, id.as_hex_string());
+        assert_eq!(Id::try_from_hex_string(&hex).unwrap(), id);
+        assert_eq!(Id::try_from_hex_string(&hex.to_uppercase()).unwrap(), id);
+        let short: String = hex.chars().skip(2).collect();
+        assert!(matches!(
+            Id::try_from_hex_string(&short),
+            Err(Error::WrongLengthHexString)
+        ));
+        assert!(matches!(
+            Id::try_from_hex_string(&format!("zz{short}")),
+            Err(Error::HexDecode(_))
+        ));
+    }
 }
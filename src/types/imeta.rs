@@ -0,0 +1,241 @@
+use super::{Tag, UncheckedUrl};
+use crate::Error;
+use serde::{Deserialize, Serialize};
+#[cfg(feature = "speedy")]
+use speedy::{Readable, Writable};
+
+/// A media attachment (NIP-92), described by an 'imeta' tag. The tag holds
+/// "key value" entries, with the key being everything up to the first space:
+///
+/// `["imeta", "url https://example.com/a.jpg", "m image/jpeg", "dim 640x480"]`
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+#[cfg_attr(feature = "speedy", derive(Readable, Writable))]
+pub struct IMeta {
+    /// The URL of the media, as it appears in the content
+    pub url: UncheckedUrl,
+
+    /// The MIME type (m)
+    pub mime_type: Option<String>,
+
+    /// A blurhash, for showing while the media loads
+    pub blurhash: Option<String>,
+
+    /// The width and height in pixels (dim)
+    pub dim: Option<(u32, u32)>,
+
+    /// The hex SHA-256 hash of the media (x)
+    pub sha256: Option<String>,
+
+    /// A description for accessibility
+    pub alt: Option<String>,
+
+    /// Other URLs where the media can be found
+    pub fallbacks: Vec<UncheckedUrl>,
+
+    /// Any other entries, in order, as (key, value)
+    pub other: Vec<(String, String)>,
+}
+
+impl IMeta {
+    /// Describe the media at `url`, with nothing else known about it yet
+    pub fn new(url: UncheckedUrl) -> IMeta {
+        IMeta {
+            url,
+            mime_type: None,
+            blurhash: None,
+            dim: None,
+            sha256: None,
+            alt: None,
+            fallbacks: Vec::new(),
+            other: Vec::new(),
+        }
+    }
+
+    /// The width in pixels
+    pub fn width(&self) -> Option<u32> {
+        self.dim.map(|(width, _)| width)
+    }
+
+    /// The height in pixels
+    pub fn height(&self) -> Option<u32> {
+        self.dim.map(|(_, height)| height)
+    }
+
+    /// The value of the first other entry with this key
+    pub fn get(&self, key: &str) -> Option<&str> {
+        self.other
+            .iter()
+            .find(|(k, _)| k == key)
+            .map(|(_, v)| v.as_str())
+    }
+
+    /// Read from an 'imeta' tag. Unknown keys (and repeats of the single
+    /// valued ones, and a `dim` which does not parse) are kept in `other`.
+    /// Entries without a value are skipped. Only a missing url is an error.
+    pub fn try_from_tag(tag: &Tag) -> Result<IMeta, Error> {
+        let data = match tag {
+            Tag::Other { tag, data } if tag == "imeta" => data,
+            _ => return Err(Error::InvalidIMeta("Not an imeta tag".to_owned())),
+        };
+
+        let mut url: Option<UncheckedUrl> = None;
+        let mut imeta = IMeta::new(UncheckedUrl(String::new()));
+        for entry in data.iter() {
+            let Some((key, value)) = entry.split_once(' ') else {
+                continue;
+            };
+            match key {
+                "url" if url.is_none() => url = Some(UncheckedUrl(value.to_owned())),
+                "m" if imeta.mime_type.is_none() => imeta.mime_type = Some(value.to_owned()),
+                "blurhash" if imeta.blurhash.is_none() => imeta.blurhash = Some(value.to_owned()),
+                "dim" if imeta.dim.is_none() => match parse_dim(value) {
+                    Some(dim) => imeta.dim = Some(dim),
+                    None => imeta.other.push((key.to_owned(), value.to_owned())),
+                },
+                "x" if imeta.sha256.is_none() => imeta.sha256 = Some(value.to_owned()),
+                "alt" if imeta.alt.is_none() => imeta.alt = Some(value.to_owned()),
+                "fallback" => imeta.fallbacks.push(UncheckedUrl(value.to_owned())),
+                _ => imeta.other.push((key.to_owned(), value.to_owned())),
+            }
+        }
+
+        imeta.url = url.ok_or_else(|| Error::InvalidIMeta("Missing url".to_owned()))?;
+        Ok(imeta)
+    }
+
+    /// Write as an 'imeta' tag
+    pub fn to_tag(&self) -> Tag {
+        let mut data = vec![format!("url {}", self.url)];
+        let fields = [
+            ("m", &self.mime_type),
+            ("blurhash", &self.blurhash),
+            ("x", &self.sha256),
+            ("alt", &self.alt),
+        ];
+        for (key, value) in fields {
+            if let Some(value) = value {
+                data.push(format!("{key} {value}"));
+            }
+        }
+        if let Some((width, height)) = self.dim {
+            data.push(format!("dim {width}x{height}"));
+        }
+        for fallback in self.fallbacks.iter() {
+            data.push(format!("fallback {fallback}"));
+        }
+        for (key, value) in self.other.iter() {
+            data.push(format!("{key} {value}"));
+        }
+        Tag::Other {
+            tag: "imeta".to_owned(),
+            data,
+        }
+    }
+
+    #[allow(dead_code)]
+    pub(crate) fn mock() -> IMeta {
+        IMeta {
+            url: UncheckedUrl("https://example.com/cat.jpg".to_owned()),
+            mime_type: Some("image/jpeg".to_owned()),
+            blurhash: Some("eVF$^OI:${M{o#*0-nNFxakD-?xVM}WEWB%iNKxvR-oetmo#R-aen$".to_owned()),
+            dim: Some((3024, 4032)),
+            sha256: Some(
+                "a5d6c3a1e5f0b2c9d8e7f6a5b4c3d2e1f0a9b8c7d6e5f4a3b2c1d0e9f8a7b6c5".to_owned(),
+            ),
+            alt: Some("A cat on a sofa".to_owned()),
+            fallbacks: vec![UncheckedUrl(
+                "https://mirror.example.com/cat.jpg".to_owned(),
+            )],
+            other: vec![("size".to_owned(), "1048576".to_owned())],
+        }
+    }
+}
+
+fn parse_dim(value: &str) -> Option<(u32, u32)> {
+    let (width, height) = value.split_once('x')?;
+    Some((width.parse().ok()?, height.parse().ok()?))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{EventBuilder, EventKind, PrivateKey};
+
+    test_serde! {IMeta, test_imeta_serde}
+
+    #[test]
+    fn test_imeta_tag() {
+        let imeta = IMeta::mock();
+        let tag = imeta.to_tag();
+        assert_eq!(IMeta::try_from_tag(&tag).unwrap(), imeta);
+        assert_eq!(imeta.width(), Some(3024));
+        assert_eq!(imeta.height(), Some(4032));
+        assert_eq!(imeta.get("size"), Some("1048576"));
+
+        let tag = Tag::Other {
+            tag: "imeta".to_owned(),
+            data: vec![
+                "url https://example.com/a.png".to_owned(),
+                "alt A picture with spaces".to_owned(),
+                "m image/png".to_owned(),
+                "m image/webp".to_owned(),
+                "fallback https://a.example.com/a.png".to_owned(),
+                "fallback https://b.example.com/a.png".to_owned(),
+            ],
+        };
+        let imeta = IMeta::try_from_tag(&tag).unwrap();
+        assert_eq!(imeta.url.0, "https://example.com/a.png");
+        assert_eq!(imeta.alt.as_deref(), Some("A picture with spaces"));
+        assert_eq!(imeta.mime_type.as_deref(), Some("image/png"));
+        assert_eq!(imeta.get("m"), Some("image/webp"));
+        assert_eq!(imeta.fallbacks.len(), 2);
+        assert_eq!(imeta.dim, None);
+
+        // Bad entries are dropped or kept aside, rather than losing the rest
+        let tag = Tag::Other {
+            tag: "imeta".to_owned(),
+            data: vec![
+                "url https://example.com/a.png".to_owned(),
+                "blurhash".to_owned(),
+                "dim 640".to_owned(),
+                "dim 640x480".to_owned(),
+                "m image/png".to_owned(),
+            ],
+        };
+        let imeta = IMeta::try_from_tag(&tag).unwrap();
+        assert_eq!(imeta.blurhash, None);
+        assert_eq!(imeta.dim, Some((640, 480)));
+        assert_eq!(imeta.get("dim"), Some("640"));
+        assert_eq!(imeta.mime_type.as_deref(), Some("image/png"));
+
+        // but a url is required
+        let tag = Tag::Other {
+            tag: "imeta".to_owned(),
+            data: vec!["m image/png".to_owned()],
+        };
+        assert!(IMeta::try_from_tag(&tag).is_err());
+        assert!(IMeta::try_from_tag(&Tag::Hashtag("imeta".to_owned())).is_err());
+    }
+
+    #[test]
+    fn test_attach_media() {
+        let private_key = PrivateKey::generate();
+        let cat = IMeta::mock();
+        let mut dog = IMeta::new(UncheckedUrl("https://example.com/dog.png".to_owned()));
+        dog.mime_type = Some("image/png".to_owned());
+        let mut unused = IMeta::new(UncheckedUrl("https://example.com/unused.png".to_owned()));
+        unused.mime_type = Some("image/png".to_owned());
+
+        let event = EventBuilder::new()
+            .kind(EventKind::TextNote)
+            .content("My pets https://example.com/dog.png and https://example.com/cat.jpg https://example.com/dog.png")
+            .attach_media(&[cat.clone(), unused, dog.clone()])
+            .attach_media(std::slice::from_ref(&cat))
+            .sign(&private_key)
+            .unwrap();
+        assert_eq!(event.tags, vec![dog.to_tag(), cat.to_tag()]);
+        assert_eq!(event.imeta(), vec![dog, cat.clone()]);
+        assert_eq!(event.imeta_for("https://example.com/cat.jpg"), Some(cat));
+        assert_eq!(event.imeta_for("https://example.com/unused.png"), None);
+    }
+}
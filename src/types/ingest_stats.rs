@@ -0,0 +1,187 @@
+use super::EventKind;
+use crate::Error;
+use serde_json::error::Category;
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::RwLock;
+
+/// A collector of event ingestion statistics, using cheap atomic counters.
+///
+/// A single `IngestStats` can be shared (e.g. in an `Arc`) by all the threads
+/// handling incoming events, and fed via `Event::parse_with_stats()` and
+/// `Event::verify_with_stats()`, or directly via the `record_*()` functions.
+#[derive(Debug, Default)]
+pub struct IngestStats {
+    bytes_processed: AtomicU64,
+    events_parsed: AtomicU64,
+    parse_failures_syntax: AtomicU64,
+    parse_failures_data: AtomicU64,
+    parse_failures_eof: AtomicU64,
+    parse_failures_io: AtomicU64,
+    verified: AtomicU64,
+    verification_failures_hash: AtomicU64,
+    verification_failures_signature: AtomicU64,
+    verification_failures_in_future: AtomicU64,
+    verification_failures_other: AtomicU64,
+    kinds: RwLock<HashMap<u64, AtomicU64>>,
+}
+
+/// A point-in-time copy of the counters in an `IngestStats`
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct IngestStatsSnapshot {
+    /// Bytes of JSON processed, whether or not they parsed
+    pub bytes_processed: u64,
+
+    /// Events successfully parsed
+    pub events_parsed: u64,
+
+    /// Parse failures due to JSON syntax errors
+    pub parse_failures_syntax: u64,
+
+    /// Parse failures due to valid JSON that is not a valid event
+    pub parse_failures_data: u64,
+
+    /// Parse failures due to truncated input
+    pub parse_failures_eof: u64,
+
+    /// Parse failures due to I/O errors
+    pub parse_failures_io: u64,
+
+    /// Events successfully verified
+    pub verified: u64,
+
+    /// Verification failures because the id did not match the event data
+    pub verification_failures_hash: u64,
+
+    /// Verification failures because the signature was invalid
+    pub verification_failures_signature: u64,
+
+    /// Verification failures because the event was too far in the future
+    pub verification_failures_in_future: u64,
+
+    /// Verification failures for any other reason
+    pub verification_failures_other: u64,
+
+    /// Number of events parsed, by kind
+    pub kinds: HashMap<u64, u64>,
+}
+
+impl IngestStats {
+    /// Create a new collector with all counters at zero
+    pub fn new() -> IngestStats {
+        IngestStats::default()
+    }
+
+    /// Record a successfully parsed event of `bytes` length
+    pub fn record_event(&self, kind: EventKind, bytes: usize) {
+        let _ = self
+            .bytes_processed
+            .fetch_add(bytes as u64, Ordering::Relaxed);
+        let _ = self.events_parsed.fetch_add(1, Ordering::Relaxed);
+
+        let kind: u64 = kind.into();
+        {
+            let kinds = self.kinds.read().unwrap();
+            if let Some(counter) = kinds.get(&kind) {
+                let _ = counter.fetch_add(1, Ordering::Relaxed);
+                return;
+            }
+        }
+        let mut kinds = self.kinds.write().unwrap();
+        let _ = kinds
+            .entry(kind)
+            .or_insert_with(|| AtomicU64::new(0))
+            .fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Record a failure to parse `bytes` of JSON
+    pub fn record_parse_failure(&self, error: &serde_json::Error, bytes: usize) {
+        let _ = self
+            .bytes_processed
+            .fetch_add(bytes as u64, Ordering::Relaxed);
+        let counter = match error.classify() {
+            Category::Syntax => &self.parse_failures_syntax,
+            Category::Data => &self.parse_failures_data,
+            Category::Eof => &self.parse_failures_eof,
+            Category::Io => &self.parse_failures_io,
+        };
+        let _ = counter.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Record the result of an event verification
+    pub fn record_verification(&self, result: &Result<(), Error>) {
+        let counter = match result {
+            Ok(()) => &self.verified,
+            Err(Error::HashMismatch) => &self.verification_failures_hash,
+            Err(Error::Signature(_)) => &self.verification_failures_signature,
+            Err(Error::EventInFuture) => &self.verification_failures_in_future,
+            Err(_) => &self.verification_failures_other,
+        };
+        let _ = counter.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Take a snapshot of the current counter values
+    pub fn snapshot(&self) -> IngestStatsSnapshot {
+        IngestStatsSnapshot {
+            bytes_processed: self.bytes_processed.load(Ordering::Relaxed),
+            events_parsed: self.events_parsed.load(Ordering::Relaxed),
+            parse_failures_syntax: self.parse_failures_syntax.load(Ordering::Relaxed),
+            parse_failures_data: self.parse_failures_data.load(Ordering::Relaxed),
+            parse_failures_eof: self.parse_failures_eof.load(Ordering::Relaxed),
+            parse_failures_io: self.parse_failures_io.load(Ordering::Relaxed),
+            verified: self.verified.load(Ordering::Relaxed),
+            verification_failures_hash: self.verification_failures_hash.load(Ordering::Relaxed),
+            verification_failures_signature: self
+                .verification_failures_signature
+                .load(Ordering::Relaxed),
+            verification_failures_in_future: self
+                .verification_failures_in_future
+                .load(Ordering::Relaxed),
+            verification_failures_other: self.verification_failures_other.load(Ordering::Relaxed),
+            kinds: self
+                .kinds
+                .read()
+                .unwrap()
+                .iter()
+                .map(|(k, v)| (*k, v.load(Ordering::Relaxed)))
+                .collect(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{Event, Id};
+
+    #[test]
+    fn test_ingest_stats() {
+        let stats = IngestStats::new();
+
+        let json = serde_json::to_string(&Event::mock()).unwrap();
+        let event = Event::parse_with_stats(&json, &stats).unwrap();
+        assert!(event.verify_with_stats(None, &stats).is_ok());
+
+        let mut tampered = event.clone();
+        tampered.content = "tampered".to_owned();
+        assert!(tampered.verify_with_stats(None, &stats).is_err());
+        let mut tampered = event.clone();
+        tampered.id = Id([0; 32]);
+        assert!(tampered.verify_with_stats(None, &stats).is_err());
+
+        assert!(Event::parse_with_stats("{\"id\":", &stats).is_err());
+        assert!(Event::parse_with_stats("[1,2,3]", &stats).is_err());
+        assert!(Event::parse_with_stats("{{", &stats).is_err());
+
+        let snapshot = stats.snapshot();
+        assert_eq!(snapshot.events_parsed, 1);
+        assert_eq!(snapshot.bytes_processed, json.len() as u64 + 6 + 7 + 2);
+        assert_eq!(snapshot.parse_failures_eof, 1);
+        assert_eq!(snapshot.parse_failures_data, 1);
+        assert_eq!(snapshot.parse_failures_syntax, 1);
+        assert_eq!(snapshot.verified, 1);
+        assert_eq!(snapshot.verification_failures_signature, 1);
+        assert_eq!(snapshot.verification_failures_hash, 1);
+        assert_eq!(snapshot.kinds.get(&u64::from(event.kind)), Some(&1));
+    }
+}
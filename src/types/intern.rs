@@ -0,0 +1,284 @@
+use super::{Id, PublicKey, XOnlyPublicKey};
+use serde::{Deserialize, Serialize};
+#[cfg(feature = "speedy")]
+use speedy::{Readable, Writable};
+use std::collections::hash_map::RandomState;
+use std::collections::HashMap;
+use std::hash::BuildHasher;
+
+/// A small handle standing in for a `PublicKey` interned in a `PublicKeyTable`
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[cfg_attr(feature = "speedy", derive(Readable, Writable))]
+pub struct PublicKeyHandle(u32);
+
+impl PublicKeyHandle {
+    /// The index of the key within its table
+    pub fn as_u32(&self) -> u32 {
+        self.0
+    }
+}
+
+/// A table of interned public keys. Each distinct key is stored once and
+/// assigned a 4-byte handle, so that applications holding many events can store
+/// handles in place of the keys, and compare them cheaply.
+///
+/// Keys are held as their 32 x-only bytes, and turned back into `PublicKey`s
+/// when they are read out. Handles are only meaningful to the table that issued
+/// them. Keys are never removed.
+#[derive(Clone, Debug, Default)]
+pub struct PublicKeyTable {
+    keys: Interner<XOnlyPublicKey>,
+}
+
+impl PublicKeyTable {
+    /// Create an empty table
+    pub fn new() -> PublicKeyTable {
+        PublicKeyTable::default()
+    }
+
+    /// Create an empty table with room for `capacity` keys
+    pub fn with_capacity(capacity: usize) -> PublicKeyTable {
+        PublicKeyTable {
+            keys: Interner::with_capacity(capacity),
+        }
+    }
+
+    /// Get the handle for a key, adding it to the table if it is new
+    ///
+    /// # Panics
+    ///
+    /// Panics if the table already holds `u32::MAX` keys
+    pub fn intern(&mut self, key: PublicKey) -> PublicKeyHandle {
+        let key = XOnlyPublicKey::from(key);
+        let hash = self.keys.hash(&key.0);
+        PublicKeyHandle(self.keys.intern(hash, key))
+    }
+
+    /// Get the handle for a key, if it is in the table
+    pub fn handle(&self, key: &PublicKey) -> Option<PublicKeyHandle> {
+        let key = XOnlyPublicKey::from(*key);
+        let hash = self.keys.hash(&key.0);
+        self.keys.find(hash, &key).map(PublicKeyHandle)
+    }
+
+    /// Get the key for a handle, or `None` if it did not come from this table
+    pub fn get(&self, handle: PublicKeyHandle) -> Option<PublicKey> {
+        let key = self.keys.values.get(handle.0 as usize)?;
+        PublicKey::try_from(*key).ok()
+    }
+
+    /// The number of keys in the table
+    pub fn len(&self) -> usize {
+        self.keys.values.len()
+    }
+
+    /// Whether the table is empty
+    pub fn is_empty(&self) -> bool {
+        self.keys.values.is_empty()
+    }
+
+    /// Iterate over the handles and keys in the table, in the order they were added
+    pub fn iter(&self) -> impl Iterator<Item = (PublicKeyHandle, PublicKey)> + '_ {
+        self.keys.values.iter().enumerate().filter_map(|(i, key)| {
+            Some((PublicKeyHandle(i as u32), PublicKey::try_from(*key).ok()?))
+        })
+    }
+}
+
+/// A small handle standing in for an `Id` interned in an `IdTable`
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[cfg_attr(feature = "speedy", derive(Readable, Writable))]
+pub struct IdHandle(u32);
+
+impl IdHandle {
+    /// The index of the id within its table
+    pub fn as_u32(&self) -> u32 {
+        self.0
+    }
+}
+
+/// A table of interned event ids. Each distinct id is stored once and assigned
+/// a 4-byte handle, so that applications holding many events (and many
+/// references between them) can store handles in place of the ids.
+///
+/// Handles are only meaningful to the table that issued them. Ids are never
+/// removed.
+#[derive(Clone, Debug, Default)]
+pub struct IdTable {
+    ids: Interner<Id>,
+}
+
+impl IdTable {
+    /// Create an empty table
+    pub fn new() -> IdTable {
+        IdTable::default()
+    }
+
+    /// Create an empty table with room for `capacity` ids
+    pub fn with_capacity(capacity: usize) -> IdTable {
+        IdTable {
+            ids: Interner::with_capacity(capacity),
+        }
+    }
+
+    /// Get the handle for an id, adding it to the table if it is new
+    ///
+    /// # Panics
+    ///
+    /// Panics if the table already holds `u32::MAX` ids
+    pub fn intern(&mut self, id: Id) -> IdHandle {
+        let hash = self.ids.hash(&id.0);
+        IdHandle(self.ids.intern(hash, id))
+    }
+
+    /// Get the handle for an id, if it is in the table
+    pub fn handle(&self, id: &Id) -> Option<IdHandle> {
+        let hash = self.ids.hash(&id.0);
+        self.ids.find(hash, id).map(IdHandle)
+    }
+
+    /// Get the id for a handle, or `None` if it did not come from this table
+    pub fn get(&self, handle: IdHandle) -> Option<Id> {
+        self.ids.values.get(handle.0 as usize).copied()
+    }
+
+    /// The number of ids in the table
+    pub fn len(&self) -> usize {
+        self.ids.values.len()
+    }
+
+    /// Whether the table is empty
+    pub fn is_empty(&self) -> bool {
+        self.ids.values.is_empty()
+    }
+
+    /// Iterate over the handles and ids in the table, in the order they were added
+    pub fn iter(&self) -> impl Iterator<Item = (IdHandle, Id)> + '_ {
+        self.ids
+            .values
+            .iter()
+            .enumerate()
+            .map(|(i, id)| (IdHandle(i as u32), *id))
+    }
+}
+
+// Marks the end of a chain in `Interner::next`
+const END: u32 = u32::MAX;
+
+// The storage behind both tables. Each value is stored once, in `values`, at
+// the index which is its handle. The map goes from the hash of a value to the
+// handle of the last value added with that hash, rather than holding the value
+// again, and values whose hashes collide are chained through `next`, which
+// holds for each value the handle of the one added before it with the same
+// hash (or END).
+#[derive(Clone, Debug)]
+struct Interner<T> {
+    values: Vec<T>,
+    next: Vec<u32>,
+    heads: HashMap<u64, u32>,
+    hasher: RandomState,
+}
+
+impl<T> Default for Interner<T> {
+    fn default() -> Self {
+        Interner {
+            values: Vec::new(),
+            next: Vec::new(),
+            heads: HashMap::new(),
+            hasher: RandomState::new(),
+        }
+    }
+}
+
+impl<T: Eq> Interner<T> {
+    fn with_capacity(capacity: usize) -> Interner<T> {
+        Interner {
+            values: Vec::with_capacity(capacity),
+            next: Vec::with_capacity(capacity),
+            heads: HashMap::with_capacity(capacity),
+            hasher: RandomState::new(),
+        }
+    }
+
+    // Hash the bytes of a value
+    fn hash(&self, bytes: &[u8]) -> u64 {
+        self.hasher.hash_one(bytes)
+    }
+
+    fn find(&self, hash: u64, value: &T) -> Option<u32> {
+        let mut handle = *self.heads.get(&hash)?;
+        while handle != END {
+            if self.values[handle as usize] == *value {
+                return Some(handle);
+            }
+            handle = self.next[handle as usize];
+        }
+        None
+    }
+
+    fn intern(&mut self, hash: u64, value: T) -> u32 {
+        if let Some(handle) = self.find(hash, &value) {
+            return handle;
+        }
+        let handle = u32::try_from(self.values.len())
+            .ok()
+            .filter(|handle| *handle != END)
+            .expect("table is full");
+        let next = self.heads.insert(hash, handle).unwrap_or(END);
+        self.values.push(value);
+        self.next.push(next);
+        handle
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::PrivateKey;
+
+    #[test]
+    fn test_public_key_table() {
+        let alice = PrivateKey::generate().public_key();
+        let bob = PrivateKey::generate().public_key();
+
+        let mut table = PublicKeyTable::new();
+        assert!(table.is_empty());
+        let a = table.intern(alice);
+        let b = table.intern(bob);
+        assert_ne!(a, b);
+        assert_eq!(table.intern(alice), a);
+        assert_eq!(table.len(), 2);
+        assert_eq!(table.handle(&bob), Some(b));
+        assert_eq!(table.get(a), Some(alice));
+        assert_eq!(table.get(PublicKeyHandle(7)), None);
+        assert_eq!(table.iter().collect::<Vec<_>>(), vec![(a, alice), (b, bob)]);
+    }
+
+    #[test]
+    fn test_id_table() {
+        let mut table = IdTable::with_capacity(2);
+        let a = table.intern(Id([1; 32]));
+        let b = table.intern(Id([2; 32]));
+        assert_eq!(table.intern(Id([1; 32])), a);
+        assert_eq!(table.len(), 2);
+        assert_eq!(table.handle(&Id([2; 32])), Some(b));
+        assert_eq!(table.handle(&Id([3; 32])), None);
+        assert_eq!(table.get(b), Some(Id([2; 32])));
+        assert_eq!(b.as_u32(), 1);
+    }
+
+    #[test]
+    fn test_interner_collisions() {
+        // Values whose hashes collide are told apart
+        let mut interner: Interner<u8> = Interner::default();
+        let handles: Vec<u32> = (0..5).map(|v| interner.intern(7, v)).collect();
+        assert_eq!(handles, vec![0, 1, 2, 3, 4]);
+        for v in 0..5 {
+            assert_eq!(interner.intern(7, v), u32::from(v));
+            assert_eq!(interner.find(7, &v), Some(u32::from(v)));
+        }
+        assert_eq!(interner.find(7, &5), None);
+        assert_eq!(interner.find(8, &0), None);
+        assert_eq!(interner.heads.len(), 1);
+    }
+}
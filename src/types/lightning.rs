@@ -0,0 +1,271 @@
+use super::nip05::domain_to_ascii;
+use crate::Error;
+use bech32::{FromBase32, ToBase32};
+use serde::de::Error as DeError;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::fmt;
+
+/// A lightning address (LUD-16), `user@domain`, as in the lud16 field of
+/// `Metadata`. Both parts are case-insensitive, and are kept in lowercase.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct LightningAddress {
+    user: String,
+    domain: String,
+}
+
+impl LightningAddress {
+    /// Parse a lightning address
+    pub fn try_from_str(s: &str) -> Result<LightningAddress, Error> {
+        let invalid = || Error::InvalidLightningAddress(s.to_owned());
+        let (user, domain) = s.trim().split_once('@').ok_or_else(invalid)?;
+        let valid_user = !user.is_empty()
+            && user
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.' | '+'));
+        if !valid_user {
+            return Err(invalid());
+        }
+        Ok(LightningAddress {
+            user: user.to_ascii_lowercase(),
+            domain: domain_to_ascii(domain).ok_or_else(invalid)?,
+        })
+    }
+
+    /// The user
+    pub fn user(&self) -> &str {
+        &self.user
+    }
+
+    /// The domain
+    pub fn domain(&self) -> &str {
+        &self.domain
+    }
+
+    /// The URL of the LNURL-pay endpoint to resolve the address with. This is
+    /// https, except for onion domains.
+    pub fn lnurlp_endpoint(&self) -> String {
+        let scheme = if self.domain.ends_with(".onion") {
+            "http"
+        } else {
+            "https"
+        };
+        format!(
+            "{scheme}://{}/.well-known/lnurlp/{}",
+            self.domain, self.user
+        )
+    }
+}
+
+/// An LNURL (LUD-01): a URL encoded in bech32 as `lnurl1...`, as in the lud06
+/// field of `Metadata`
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct LnUrl(String);
+
+impl LnUrl {
+    /// Create from the URL it encodes, which must be https (or http, for an
+    /// onion domain)
+    pub fn try_from_url(s: &str) -> Result<LnUrl, Error> {
+        let url = url::Url::parse(s.trim())?;
+        let onion = url.host_str().is_some_and(|host| host.ends_with(".onion"));
+        match url.scheme() {
+            "https" => {}
+            "http" if onion => {}
+            _ => return Err(Error::InvalidLnUrl(s.to_owned())),
+        }
+        // Kept as given, so that it encodes back to the same bech32
+        Ok(LnUrl(s.trim().to_owned()))
+    }
+
+    /// Import from a bech32 encoded string, in either case
+    pub fn try_from_bech32_string(s: &str) -> Result<LnUrl, Error> {
+        let s = s.trim();
+        let s = match s.get(..10) {
+            Some(prefix) if prefix.eq_ignore_ascii_case("lightning:") => s.get(10..).unwrap(),
+            _ => s,
+        };
+        let data = bech32::decode(s)?;
+        if data.0 != "lnurl" {
+            return Err(Error::WrongBech32("lnurl".to_string(), data.0));
+        }
+        let bytes = Vec::<u8>::from_base32(&data.1)?;
+        let url = String::from_utf8(bytes).map_err(|_| Error::InvalidLnUrl(s.to_owned()))?;
+        LnUrl::try_from_url(&url)
+    }
+
+    /// Export as a bech32 encoded string, in lowercase
+    pub fn as_bech32_string(&self) -> String {
+        bech32::encode(
+            "lnurl",
+            self.0.as_bytes().to_base32(),
+            bech32::Variant::Bech32,
+        )
+        .unwrap()
+    }
+
+    /// The URL it encodes, which for the lud06 field is the LNURL-pay endpoint
+    pub fn lnurlp_endpoint(&self) -> String {
+        self.0.clone()
+    }
+
+    /// The lightning address with this endpoint, if the URL is of the form
+    /// that lightning addresses resolve to
+    pub fn lightning_address(&self) -> Option<LightningAddress> {
+        let url = url::Url::parse(&self.0).ok()?;
+        if url.query().is_some() || url.fragment().is_some() {
+            return None;
+        }
+        let user = url.path().strip_prefix("/.well-known/lnurlp/")?;
+        let address =
+            LightningAddress::try_from_str(&format!("{user}@{}", url.host_str()?)).ok()?;
+        // Only if it resolves back to the same URL
+        (address.lnurlp_endpoint() == self.0).then_some(address)
+    }
+}
+
+impl From<&LightningAddress> for LnUrl {
+    fn from(address: &LightningAddress) -> LnUrl {
+        LnUrl(address.lnurlp_endpoint())
+    }
+}
+
+impl TryFrom<&LnUrl> for LightningAddress {
+    type Error = Error;
+
+    fn try_from(lnurl: &LnUrl) -> Result<LightningAddress, Error> {
+        lnurl
+            .lightning_address()
+            .ok_or_else(|| Error::InvalidLightningAddress(lnurl.0.clone()))
+    }
+}
+
+impl TryFrom<&str> for LightningAddress {
+    type Error = Error;
+
+    fn try_from(s: &str) -> Result<LightningAddress, Error> {
+        LightningAddress::try_from_str(s)
+    }
+}
+
+impl TryFrom<&str> for LnUrl {
+    type Error = Error;
+
+    fn try_from(s: &str) -> Result<LnUrl, Error> {
+        LnUrl::try_from_bech32_string(s)
+    }
+}
+
+impl fmt::Display for LightningAddress {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}@{}", self.user, self.domain)
+    }
+}
+
+impl fmt::Display for LnUrl {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.as_bech32_string())
+    }
+}
+
+impl Serialize for LightningAddress {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serializer.collect_str(self)
+    }
+}
+
+impl<'de> Deserialize<'de> for LightningAddress {
+    fn deserialize<D>(deserializer: D) -> Result<LightningAddress, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let s = String::deserialize(deserializer)?;
+        LightningAddress::try_from_str(&s).map_err(DeError::custom)
+    }
+}
+
+impl Serialize for LnUrl {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serializer.collect_str(self)
+    }
+}
+
+impl<'de> Deserialize<'de> for LnUrl {
+    fn deserialize<D>(deserializer: D) -> Result<LnUrl, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let s = String::deserialize(deserializer)?;
+        LnUrl::try_from_bech32_string(&s).map_err(DeError::custom)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_lightning_address() {
+        let address = LightningAddress::try_from_str(" DecentBun13@WalletOfSatoshi.com").unwrap();
+        assert_eq!(address.user(), "decentbun13");
+        assert_eq!(address.domain(), "walletofsatoshi.com");
+        assert_eq!(
+            address.lnurlp_endpoint(),
+            "https://walletofsatoshi.com/.well-known/lnurlp/decentbun13"
+        );
+        assert_eq!(format!("{address}"), "decentbun13@walletofsatoshi.com");
+        let onion = LightningAddress::try_from_str("bob@abcdef.onion").unwrap();
+        assert!(onion.lnurlp_endpoint().starts_with("http://"));
+
+        for bad in [
+            "bob",
+            "@example.com",
+            "bob@",
+            "b b@example.com",
+            "bob@x.com/p",
+        ] {
+            assert!(LightningAddress::try_from_str(bad).is_err(), "{bad}");
+        }
+    }
+
+    #[test]
+    fn test_lnurl() {
+        let lud06 = "lnurl1dp68gurn8ghj7ampd3kx2ar0veekzar0wd5xjtnrdakj7tnhv4kxctttdehhwm30d3h82unvwqhkgetrv4h8gcn4dccnxv563ep";
+        let lnurl = LnUrl::try_from_bech32_string(lud06).unwrap();
+        let endpoint = "https://walletofsatoshi.com/.well-known/lnurlp/decentbun13";
+        assert_eq!(lnurl.lnurlp_endpoint(), endpoint);
+        assert_eq!(lnurl.as_bech32_string(), lud06);
+        assert_eq!(
+            LnUrl::try_from_bech32_string(&format!("lightning:{}", lud06.to_uppercase())).unwrap(),
+            lnurl
+        );
+
+        // Conversion to and from the lightning address
+        let address = LightningAddress::try_from(&lnurl).unwrap();
+        assert_eq!(format!("{address}"), "decentbun13@walletofsatoshi.com");
+        assert_eq!(LnUrl::from(&address), lnurl);
+        let other = LnUrl::try_from_url("https://example.com/lnurlp?user=bob").unwrap();
+        assert_eq!(other.lightning_address(), None);
+
+        assert!(LnUrl::try_from_url("http://example.com/pay").is_err());
+        assert!(LnUrl::try_from_url("http://abcdef.onion/pay").is_ok());
+        let npub = "npub1sn0wdenkukak0d9dfczzeacvhkrgz92ak56egt7vdgzn8pv2wfqqhrjdv9";
+        assert!(matches!(
+            LnUrl::try_from_bech32_string(npub),
+            Err(Error::WrongBech32(_, _))
+        ));
+
+        let json = serde_json::to_string(&lnurl).unwrap();
+        assert_eq!(json, format!("\"{lud06}\""));
+        assert_eq!(serde_json::from_str::<LnUrl>(&json).unwrap(), lnurl);
+        let json = serde_json::to_string(&address).unwrap();
+        assert_eq!(
+            serde_json::from_str::<LightningAddress>(&json).unwrap(),
+            address
+        );
+    }
+}
@@ -0,0 +1,307 @@
+use super::{Event, EventAddr, EventBuilder, EventKind, Signer, Tag, UncheckedUrl, Unixtime};
+use crate::Error;
+use serde::Serialize;
+
+/// Long-form content (NIP-23): an article in markdown, published as kind 30023
+/// or kept as a draft as kind 30024. It is addressed by its 'd' identifier, so
+/// that edits replace it.
+///
+/// Like `VerifiedEvent`, it wraps the event and serializes as it, but does not
+/// deserialize: deserialize an `Event` and use `try_from_event()`.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+#[serde(transparent)]
+pub struct LongFormContent(Event);
+
+impl LongFormContent {
+    /// Wrap a long-form content event
+    pub fn try_from_event(event: Event) -> Result<LongFormContent, Error> {
+        match event.kind {
+            EventKind::LongFormContent | EventKind::DraftLongFormContent => {
+                Ok(LongFormContent(event))
+            }
+            _ => Err(Error::WrongEventKind),
+        }
+    }
+
+    /// Get the inner event
+    pub fn as_event(&self) -> &Event {
+        &self.0
+    }
+
+    /// Unwrap the inner event
+    pub fn into_inner(self) -> Event {
+        self.0
+    }
+
+    /// Whether this is a draft (kind 30024)
+    pub fn is_draft(&self) -> bool {
+        self.0.kind == EventKind::DraftLongFormContent
+    }
+
+    /// The identifier in the 'd' tag (empty if there is none)
+    pub fn identifier(&self) -> &str {
+        self.0
+            .tags
+            .iter()
+            .find_map(|tag| match tag {
+                Tag::Identifier(d) => Some(d.as_str()),
+                _ => None,
+            })
+            .unwrap_or("")
+    }
+
+    /// The address of the article
+    pub fn addr(&self) -> EventAddr {
+        EventAddr {
+            d: self.identifier().to_owned(),
+            relays: vec![],
+            kind: self.0.kind,
+            author: self.0.pubkey,
+        }
+    }
+
+    /// The article itself, in markdown
+    pub fn markdown(&self) -> &str {
+        &self.0.content
+    }
+
+    /// The title
+    pub fn title(&self) -> Option<&str> {
+        self.0.tags.iter().find_map(|tag| match tag {
+            Tag::Title(title) => Some(title.as_str()),
+            _ => None,
+        })
+    }
+
+    /// The summary
+    pub fn summary(&self) -> Option<&str> {
+        self.other_tag("summary")
+    }
+
+    /// The URL of an image to show with the title
+    pub fn image(&self) -> Option<UncheckedUrl> {
+        self.other_tag("image").map(UncheckedUrl::from_str)
+    }
+
+    /// When the article was first published. Unlike `created_at`, this does
+    /// not change when the article is edited.
+    pub fn published_at(&self) -> Option<Unixtime> {
+        self.other_tag("published_at")?
+            .parse::<i64>()
+            .ok()
+            .map(Unixtime)
+    }
+
+    /// The topics, from the 't' tags
+    pub fn topics(&self) -> Vec<&str> {
+        self.0
+            .tags
+            .iter()
+            .filter_map(|tag| match tag {
+                Tag::Hashtag(topic) => Some(topic.as_str()),
+                _ => None,
+            })
+            .collect()
+    }
+
+    // The first value of the first tag with this name
+    fn other_tag(&self, name: &str) -> Option<&str> {
+        self.0.tags.iter().find_map(|tag| match tag {
+            Tag::Other { tag, data } if tag == name => data.first().map(|s| s.as_str()),
+            _ => None,
+        })
+    }
+}
+
+impl AsRef<Event> for LongFormContent {
+    fn as_ref(&self) -> &Event {
+        &self.0
+    }
+}
+
+impl From<LongFormContent> for Event {
+    fn from(long_form: LongFormContent) -> Event {
+        long_form.0
+    }
+}
+
+impl TryFrom<Event> for LongFormContent {
+    type Error = Error;
+
+    fn try_from(event: Event) -> Result<LongFormContent, Error> {
+        LongFormContent::try_from_event(event)
+    }
+}
+
+/// A builder for `LongFormContent`, producing the event with its NIP-23 tags
+///
+/// ```
+/// # use nostr_types::{LongFormContentBuilder, PrivateKey, Unixtime};
+/// let private_key = PrivateKey::generate();
+/// let article = LongFormContentBuilder::new("my-article", "# Hello\n\nWorld")
+///     .title("Hello")
+///     .topic("greetings")
+///     .published_at(Unixtime(1_700_000_000))
+///     .sign(&private_key)
+///     .unwrap();
+/// assert_eq!(article.title(), Some("Hello"));
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct LongFormContentBuilder {
+    identifier: String,
+    markdown: String,
+    title: Option<String>,
+    summary: Option<String>,
+    image: Option<UncheckedUrl>,
+    published_at: Option<Unixtime>,
+    topics: Vec<String>,
+    draft: bool,
+}
+
+impl LongFormContentBuilder {
+    /// Start building an article with this 'd' identifier and markdown. To
+    /// edit an article, use the identifier it already has.
+    pub fn new(identifier: &str, markdown: &str) -> LongFormContentBuilder {
+        LongFormContentBuilder {
+            identifier: identifier.to_owned(),
+            markdown: markdown.to_owned(),
+            ..Default::default()
+        }
+    }
+
+    /// Set the title
+    pub fn title(mut self, title: &str) -> LongFormContentBuilder {
+        self.title = Some(title.to_owned());
+        self
+    }
+
+    /// Set the summary
+    pub fn summary(mut self, summary: &str) -> LongFormContentBuilder {
+        self.summary = Some(summary.to_owned());
+        self
+    }
+
+    /// Set the image to show with the title
+    pub fn image(mut self, image: UncheckedUrl) -> LongFormContentBuilder {
+        self.image = Some(image);
+        self
+    }
+
+    /// Set when the article was first published. Keep this when editing it.
+    pub fn published_at(mut self, published_at: Unixtime) -> LongFormContentBuilder {
+        self.published_at = Some(published_at);
+        self
+    }
+
+    /// Add a topic. Topics are kept in lowercase without a leading '#', and
+    /// each only once.
+    pub fn topic(mut self, topic: &str) -> LongFormContentBuilder {
+        let topic = topic.trim().trim_start_matches('#').to_lowercase();
+        if !topic.is_empty() && !self.topics.contains(&topic) {
+            self.topics.push(topic);
+        }
+        self
+    }
+
+    /// Make it a draft (kind 30024) rather than a published article
+    pub fn draft(mut self, draft: bool) -> LongFormContentBuilder {
+        self.draft = draft;
+        self
+    }
+
+    /// An `EventBuilder` for the event, to set anything else on (such as
+    /// further tags, or when it was created) before signing it
+    pub fn to_event_builder(&self) -> EventBuilder {
+        let kind = if self.draft {
+            EventKind::DraftLongFormContent
+        } else {
+            EventKind::LongFormContent
+        };
+        let mut builder = EventBuilder::new()
+            .kind(kind)
+            .content(&self.markdown)
+            .tag(Tag::Identifier(self.identifier.clone()));
+        if let Some(title) = &self.title {
+            builder = builder.tag(Tag::Title(title.clone()));
+        }
+        let others = [
+            ("summary", self.summary.clone()),
+            ("image", self.image.as_ref().map(|url| url.0.clone())),
+            ("published_at", self.published_at.map(|t| t.0.to_string())),
+        ];
+        for (tag, value) in others {
+            if let Some(value) = value {
+                builder = builder.tag(Tag::Other {
+                    tag: tag.to_owned(),
+                    data: vec![value],
+                });
+            }
+        }
+        builder.tags(self.topics.iter().cloned().map(Tag::Hashtag))
+    }
+
+    /// Finish building, producing the article signed by `signer`
+    pub fn sign<S: Signer + ?Sized>(&self, signer: &S) -> Result<LongFormContent, Error> {
+        Ok(LongFormContent(self.to_event_builder().sign(signer)?))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::PrivateKey;
+
+    #[test]
+    fn test_long_form_content() {
+        let private_key = PrivateKey::generate();
+        let image = UncheckedUrl::from_str("https://example.com/header.png");
+        let article = LongFormContentBuilder::new("nip-23", "# Long form\n\nIn *markdown*.")
+            .title("Long form")
+            .summary("About long form content")
+            .image(image.clone())
+            .published_at(Unixtime(1_700_000_000))
+            .topic("#Nostr")
+            .topic("nostr")
+            .topic("markdown")
+            .to_event_builder()
+            .created_at(Unixtime(1_700_000_500))
+            .sign(&private_key)
+            .unwrap();
+        let article = LongFormContent::try_from_event(article).unwrap();
+        assert!(article.as_event().verify(None).is_ok());
+        assert!(!article.is_draft());
+        assert_eq!(article.as_event().kind, EventKind::LongFormContent);
+        assert_eq!(article.identifier(), "nip-23");
+        assert_eq!(article.markdown(), "# Long form\n\nIn *markdown*.");
+        assert_eq!(article.title(), Some("Long form"));
+        assert_eq!(article.summary(), Some("About long form content"));
+        assert_eq!(article.image(), Some(image));
+        assert_eq!(article.published_at(), Some(Unixtime(1_700_000_000)));
+        assert_eq!(article.as_event().created_at, Unixtime(1_700_000_500));
+        assert_eq!(article.topics(), vec!["nostr", "markdown"]);
+        assert_eq!(Some(article.addr()), article.as_event().addr());
+
+        // It reads back from the event
+        let event: Event = article.clone().into();
+        assert_eq!(LongFormContent::try_from(event).unwrap(), article);
+
+        let draft = LongFormContentBuilder::new("nip-23", "Not done")
+            .draft(true)
+            .sign(&private_key)
+            .unwrap();
+        assert!(draft.is_draft());
+        assert_eq!(draft.as_event().kind, EventKind::DraftLongFormContent);
+        assert_eq!(draft.title(), None);
+        assert_eq!(draft.published_at(), None);
+        assert!(draft.topics().is_empty());
+        assert_eq!(
+            draft.as_event().tags,
+            vec![Tag::Identifier("nip-23".to_owned())]
+        );
+
+        assert!(matches!(
+            LongFormContent::try_from_event(Event::mock()),
+            Err(Error::WrongEventKind)
+        ));
+    }
+}
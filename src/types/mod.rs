@@ -11,77 +11,194 @@ macro_rules! test_serde {
     };
 }
 
+mod abbrev;
+
+mod app_data;
+pub use app_data::AppData;
+
+#[cfg(feature = "speedy")]
+mod binary;
+#[cfg(feature = "speedy")]
+pub use binary::{from_binary, to_binary, BINARY_FORMAT_VERSION};
+
+mod auth_challenge;
+pub use auth_challenge::AuthChallengeManager;
+
+mod batch;
+#[cfg(feature = "rayon")]
+pub use batch::{verify_all, verify_all_until_failure};
+pub use batch::{verify_events_batch, verify_signatures_batch};
+
+mod chess;
+pub use chess::{ChessGame, ChessResult};
+
 mod client_message;
 pub use client_message::ClientMessage;
 
+mod compiled_filter;
+pub use compiled_filter::CompiledFilter;
+
 mod content;
-pub use content::{ContentSegment, ShatteredContent, Span};
+pub use content::{
+    compose_mentions, resolve_tag_reference, rewrite_legacy_mentions, ContentSegment,
+    ShatteredContent, Span,
+};
+
+mod content_policy;
+pub use content_policy::{ContentPolicy, ContentPolicyViolation};
+
+mod content_encryption;
+pub use content_encryption::{ContentEncryptionAlgorithm, ConversationKeyCache, EncryptedContent};
 
 mod delegation;
-pub use delegation::{DelegationConditions, EventDelegation};
+pub use delegation::{
+    DelegationConditionFailure, DelegationConditions, EventDelegation, GrantedDelegation,
+};
 
 mod event;
-pub use event::{Event, PreEvent};
+pub use event::{Event, PreEvent, PreEventValidationFailure};
+
+mod event_addr;
+pub use event_addr::{EventAddr, NAddr};
+
+mod event_builder;
+pub use event_builder::EventBuilder;
+
+mod event_flat;
+pub use event_flat::EventFlat;
 
 mod event_kind;
 pub use event_kind::{EventKind, EventKindIterator, EventKindOrRange};
 
+mod fast_hex;
+
+mod fast_parse;
+pub use fast_parse::EventSummary;
+
 mod filter;
-pub use filter::Filter;
+pub use filter::{Filter, FilterMatchMode, SearchMatcher, SubstringSearch};
+
+#[cfg(feature = "sql")]
+mod filter_sql;
+#[cfg(feature = "sql")]
+pub use filter_sql::{SqlDialect, SqlParam};
+
+mod gift_wrap;
+pub use gift_wrap::{GiftWrap, Rumor, Seal};
 
 mod id;
 pub use id::{Id, IdHex, IdHexPrefix};
 
+mod imeta;
+pub use imeta::IMeta;
+
 mod event_pointer;
-pub use event_pointer::EventPointer;
+pub use event_pointer::{EventPointer, NEvent};
+
+mod ingest_stats;
+pub use ingest_stats::{IngestStats, IngestStatsSnapshot};
+
+mod intern;
+pub use intern::{IdHandle, IdTable, PublicKeyHandle, PublicKeyTable};
+
+mod long_form;
+pub use long_form::{LongFormContent, LongFormContentBuilder};
 
 mod metadata;
-pub use metadata::Metadata;
+pub use metadata::{Birthday, Metadata, MetadataChange, SanitizedField};
+
+mod lightning;
+pub use lightning::{LightningAddress, LnUrl};
 
 mod nip05;
-pub use nip05::Nip05;
+pub use nip05::{Nip05, Nip05Builder, Nip05Identifier, Nip05Limits};
+
+#[cfg(feature = "http")]
+mod http;
+#[cfg(feature = "http")]
+pub use http::{fetch_relay_information, HttpRequest, RELAY_INFORMATION_MAX_BYTES};
+
+#[cfg(feature = "nip05-verify")]
+mod nip05_verify;
+#[cfg(feature = "nip05-verify")]
+pub use nip05_verify::{verify_nip05_body, Nip05Status, NIP05_MAX_RESPONSE_BYTES};
+
+mod nip51;
+pub use nip51::{Bookmarks, FollowSet, ListBuilder, MuteList, PinList};
+
+mod nip44;
 
 mod nostr_url;
-pub use nostr_url::{find_nostr_bech32_pos, find_nostr_url_pos, NostrBech32, NostrUrl};
+pub use nostr_url::{
+    find_nostr_bech32_pos, find_nostr_entities, find_nostr_url_pos, NostrBech32, NostrUri, NostrUrl,
+};
 
 mod pay_request_data;
 pub use pay_request_data::PayRequestData;
 
+mod poll;
+pub use poll::{Poll, PollOption, PollResponse, PollTally, PollType};
+
 mod private_key;
-pub use private_key::{EncryptedPrivateKey, KeySecurity, PrivateKey};
+pub use private_key::{EncryptedPrivateKey, KeySecurity, PrivateKey, PrivateKeyInput};
 
 mod profile;
-pub use profile::Profile;
+pub use profile::{NProfile, Profile, ProfilePointer};
 
 mod public_key;
-pub use public_key::{PublicKey, PublicKeyHex, PublicKeyHexPrefix};
+pub use public_key::{PublicKey, PublicKeyHex, PublicKeyHexPrefix, XOnlyPublicKey};
+
+mod relay_discovery;
+pub use relay_discovery::{
+    RelayDiscovery, RelayMonitorAnnouncement, RelayMonitorTimeout, RelayNetwork, RelayRtt,
+};
 
 mod relay_message;
-pub use relay_message::RelayMessage;
+pub use relay_message::{CountResult, Reason, ReasonPrefix, RelayMessage, RelayMessageLimits};
+
+mod relay_message_ref;
+pub use relay_message_ref::RelayMessageRef;
 
 mod relay_information_document;
 pub use relay_information_document::{
-    Fee, RelayFees, RelayInformationDocument, RelayLimitation, RelayRetention,
+    Fee, RelayCapabilities, RelayFees, RelayInformationDocument, RelayLimitation, RelayRetention,
 };
 
 mod signature;
 pub use signature::{Signature, SignatureHex};
 
+mod signer;
+pub use signer::Signer;
+
 mod relay_list;
 pub use relay_list::{SimpleRelayList, SimpleRelayUsage};
 
+mod subscription;
+pub use subscription::{Subscription, SubscriptionState};
+
 mod subscription_id;
 pub use subscription_id::SubscriptionId;
 
 mod tag;
 pub use tag::Tag;
 
+mod tag_filter;
+pub use tag_filter::TagFilter;
+
+#[cfg(any(test, feature = "test-util"))]
+mod test_signer;
+#[cfg(any(test, feature = "test-util"))]
+pub use test_signer::TestSigner;
+
 mod unixtime;
 pub use unixtime::Unixtime;
 
 mod url;
 pub use self::url::{RelayUrl, UncheckedUrl, Url};
 
+mod verified_event;
+pub use verified_event::VerifiedEvent;
+
 #[cfg(test)]
 mod test {
     use crate::*;
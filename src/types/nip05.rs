@@ -1,12 +1,18 @@
-use super::{PublicKeyHex, UncheckedUrl};
-use serde::{Deserialize, Serialize};
+use super::{ProfilePointer, PublicKey, PublicKeyHex, UncheckedUrl};
+use crate::Error;
+use serde::de::{DeserializeSeed, Error as DeError, IgnoredAny, MapAccess, SeqAccess, Visitor};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
 #[cfg(feature = "speedy")]
 use speedy::{Readable, Writable};
-use std::collections::HashMap;
+use std::cell::Cell;
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
 
 /// The content of a webserver's /.well-known/nostr.json file used in NIP-05 and NIP-35
 /// This allows lookup and verification of a nostr user via a `user@domain` style identifier.
-#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq)]
+///
+/// Deserializing it applies the default `Nip05Limits`.
+#[derive(Clone, Debug, Serialize, PartialEq, Eq)]
 #[cfg_attr(feature = "speedy", derive(Readable, Writable))]
 pub struct Nip05 {
     /// DNS names mapped to public keys
@@ -14,11 +20,83 @@ pub struct Nip05 {
 
     /// Public keys mapped to arrays of relays where they post
     #[serde(skip_serializing_if = "HashMap::is_empty")]
-    #[serde(default)]
     pub relays: HashMap<PublicKeyHex, Vec<UncheckedUrl>>,
 }
 
+/// Limits on the size of a nostr.json, checked while it is read so that a
+/// hostile server cannot make us allocate without bound
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Nip05Limits {
+    /// The most names accepted, and the most public keys with relays
+    pub max_names: usize,
+
+    /// The longest name accepted, in bytes
+    pub max_name_length: usize,
+
+    /// The most relays accepted for one public key
+    pub max_relays_per_key: usize,
+
+    /// The longest relay URL accepted, in bytes
+    pub max_relay_url_length: usize,
+}
+
+impl Default for Nip05Limits {
+    fn default() -> Nip05Limits {
+        Nip05Limits {
+            max_names: 10_000,
+            max_name_length: 128,
+            max_relays_per_key: 50,
+            max_relay_url_length: 2048,
+        }
+    }
+}
+
 impl Nip05 {
+    /// Parse a nostr.json, failing with `Error::Nip05LimitExceeded` as soon as
+    /// it goes over one of the limits
+    pub fn from_json_with_limits(json: &[u8], limits: &Nip05Limits) -> Result<Nip05, Error> {
+        let exceeded = Cell::new(None);
+        let mut deserializer = serde_json::Deserializer::from_slice(json);
+        let result = Limited {
+            limits,
+            exceeded: &exceeded,
+        }
+        .deserialize(&mut deserializer)
+        .and_then(|nip05| deserializer.end().map(|()| nip05));
+        result.map_err(|e| match exceeded.take() {
+            Some(limit) => Error::Nip05LimitExceeded(limit),
+            None => Error::SerdeJson(e),
+        })
+    }
+
+    /// The public key for a name. Names are matched case-insensitively, as
+    /// NIP-05 local parts are.
+    pub fn pubkey_for(&self, name: &str) -> Option<&PublicKeyHex> {
+        self.names.get(name).or_else(|| {
+            self.names
+                .iter()
+                .find(|(n, _)| n.eq_ignore_ascii_case(name))
+                .map(|(_, pubkey)| pubkey)
+        })
+    }
+
+    /// The relays listed for the public key of a name (empty if there are
+    /// none, or the name is not listed)
+    pub fn relays_for(&self, name: &str) -> &[UncheckedUrl] {
+        self.pubkey_for(name)
+            .and_then(|pubkey| self.relays.get(pubkey))
+            .map(|relays| relays.as_slice())
+            .unwrap_or_default()
+    }
+
+    /// The public key of a name, with the relays listed for it
+    pub fn profile_pointer_for(&self, name: &str) -> Option<ProfilePointer> {
+        Some(ProfilePointer {
+            pubkey: PublicKey::try_from(self.pubkey_for(name)?.clone()).ok()?,
+            relays: self.relays_for(name).to_vec(),
+        })
+    }
+
     // Mock data for testing
     #[allow(dead_code)]
     pub(crate) fn mock() -> Nip05 {
@@ -43,6 +121,434 @@ impl Nip05 {
     }
 }
 
+impl<'de> Deserialize<'de> for Nip05 {
+    fn deserialize<D>(deserializer: D) -> Result<Nip05, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        Limited {
+            limits: &Nip05Limits::default(),
+            exceeded: &Cell::new(None),
+        }
+        .deserialize(deserializer)
+    }
+}
+
+// Reads a nostr.json within limits, without allocating for anything over them,
+// and records which limit was exceeded (if one was) so that it can be reported
+// as such rather than as a JSON error
+#[derive(Clone, Copy)]
+struct Limited<'a> {
+    limits: &'a Nip05Limits,
+    exceeded: &'a Cell<Option<String>>,
+}
+
+impl Limited<'_> {
+    fn exceeded<E: DeError>(self, limit: String) -> E {
+        let e = E::custom(&limit);
+        self.exceeded.set(Some(limit));
+        e
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(field_identifier, rename_all = "lowercase")]
+enum Nip05Field {
+    Names,
+    Relays,
+    #[serde(other)]
+    Other,
+}
+
+impl<'de> DeserializeSeed<'de> for Limited<'_> {
+    type Value = Nip05;
+
+    fn deserialize<D: Deserializer<'de>>(self, deserializer: D) -> Result<Nip05, D::Error> {
+        deserializer.deserialize_map(self)
+    }
+}
+
+impl<'de> Visitor<'de> for Limited<'_> {
+    type Value = Nip05;
+
+    fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "a nostr.json object")
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<Nip05, A::Error> {
+        let mut names = None;
+        let mut relays = None;
+        while let Some(field) = map.next_key()? {
+            match field {
+                Nip05Field::Names if names.is_some() => {
+                    return Err(DeError::duplicate_field("names"))
+                }
+                Nip05Field::Names => names = Some(map.next_value_seed(Names(self))?),
+                Nip05Field::Relays if relays.is_some() => {
+                    return Err(DeError::duplicate_field("relays"))
+                }
+                Nip05Field::Relays => relays = Some(map.next_value_seed(Relays(self))?),
+                Nip05Field::Other => {
+                    let _ = map.next_value::<IgnoredAny>()?;
+                }
+            }
+        }
+        Ok(Nip05 {
+            names: names.ok_or_else(|| DeError::missing_field("names"))?,
+            relays: relays.unwrap_or_default(),
+        })
+    }
+}
+
+// The "names" object
+struct Names<'a>(Limited<'a>);
+
+impl<'de> DeserializeSeed<'de> for Names<'_> {
+    type Value = HashMap<String, PublicKeyHex>;
+
+    fn deserialize<D: Deserializer<'de>>(self, deserializer: D) -> Result<Self::Value, D::Error> {
+        deserializer.deserialize_map(self)
+    }
+}
+
+impl<'de> Visitor<'de> for Names<'_> {
+    type Value = HashMap<String, PublicKeyHex>;
+
+    fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "an object of names to public keys")
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<Self::Value, A::Error> {
+        let Names(limited) = self;
+        let mut names = HashMap::new();
+        let name = BoundedString(limited, limited.limits.max_name_length, "name");
+        while let Some(name) = map.next_key_seed(name)? {
+            if names.len() == limited.limits.max_names {
+                return Err(
+                    limited.exceeded(format!("more than {} names", limited.limits.max_names))
+                );
+            }
+            let pubkey = map.next_value_seed(PubkeyHexSeed)?;
+            let _ = names.insert(name, pubkey);
+        }
+        Ok(names)
+    }
+}
+
+// The "relays" object
+struct Relays<'a>(Limited<'a>);
+
+impl<'de> DeserializeSeed<'de> for Relays<'_> {
+    type Value = HashMap<PublicKeyHex, Vec<UncheckedUrl>>;
+
+    fn deserialize<D: Deserializer<'de>>(self, deserializer: D) -> Result<Self::Value, D::Error> {
+        deserializer.deserialize_map(self)
+    }
+}
+
+impl<'de> Visitor<'de> for Relays<'_> {
+    type Value = HashMap<PublicKeyHex, Vec<UncheckedUrl>>;
+
+    fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "an object of public keys to relays")
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<Self::Value, A::Error> {
+        let Relays(limited) = self;
+        let mut relays = HashMap::new();
+        while let Some(pubkey) = map.next_key_seed(PubkeyHexSeed)? {
+            if relays.len() == limited.limits.max_names {
+                return Err(limited.exceeded(format!(
+                    "relays for more than {} public keys",
+                    limited.limits.max_names
+                )));
+            }
+            let urls = map.next_value_seed(RelayList(limited))?;
+            let _ = relays.insert(pubkey, urls);
+        }
+        Ok(relays)
+    }
+}
+
+// The relays of one public key
+struct RelayList<'a>(Limited<'a>);
+
+impl<'de> DeserializeSeed<'de> for RelayList<'_> {
+    type Value = Vec<UncheckedUrl>;
+
+    fn deserialize<D: Deserializer<'de>>(self, deserializer: D) -> Result<Self::Value, D::Error> {
+        deserializer.deserialize_seq(self)
+    }
+}
+
+impl<'de> Visitor<'de> for RelayList<'_> {
+    type Value = Vec<UncheckedUrl>;
+
+    fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "an array of relay URLs")
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Self::Value, A::Error> {
+        let RelayList(limited) = self;
+        let mut urls = Vec::new();
+        let url = BoundedString(limited, limited.limits.max_relay_url_length, "relay URL");
+        while let Some(url) = seq.next_element_seed(url)? {
+            if urls.len() == limited.limits.max_relays_per_key {
+                return Err(limited.exceeded(format!(
+                    "more than {} relays for a public key",
+                    limited.limits.max_relays_per_key
+                )));
+            }
+            urls.push(UncheckedUrl::from_string(url));
+        }
+        Ok(urls)
+    }
+}
+
+// A string of at most this many bytes, described as this
+#[derive(Clone, Copy)]
+struct BoundedString<'a>(Limited<'a>, usize, &'static str);
+
+impl<'de> DeserializeSeed<'de> for BoundedString<'_> {
+    type Value = String;
+
+    fn deserialize<D: Deserializer<'de>>(self, deserializer: D) -> Result<String, D::Error> {
+        deserializer.deserialize_str(self)
+    }
+}
+
+impl Visitor<'_> for BoundedString<'_> {
+    type Value = String;
+
+    fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "a {}", self.2)
+    }
+
+    fn visit_str<E: DeError>(self, v: &str) -> Result<String, E> {
+        let BoundedString(limited, max, what) = self;
+        if v.len() > max {
+            return Err(limited.exceeded(format!("a {what} is longer than {max} bytes")));
+        }
+        Ok(v.to_owned())
+    }
+}
+
+// A public key in hex, checked before it is copied
+struct PubkeyHexSeed;
+
+impl<'de> DeserializeSeed<'de> for PubkeyHexSeed {
+    type Value = PublicKeyHex;
+
+    fn deserialize<D: Deserializer<'de>>(self, deserializer: D) -> Result<PublicKeyHex, D::Error> {
+        deserializer.deserialize_str(self)
+    }
+}
+
+impl Visitor<'_> for PubkeyHexSeed {
+    type Value = PublicKeyHex;
+
+    fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "a hex public key")
+    }
+
+    fn visit_str<E: DeError>(self, v: &str) -> Result<PublicKeyHex, E> {
+        PublicKeyHex::try_from_str(v).map_err(DeError::custom)
+    }
+}
+
+/// A NIP-05 identifier, `local@domain`, naming a nostr user at a domain. The
+/// root identifier `_@domain` may be written as just `domain`.
+///
+/// The local part may contain only `a-z`, `0-9`, `-`, `_` and `.`; both parts are
+/// case-insensitive, and are kept in lowercase. An internationalized domain is
+/// kept in its ASCII (punycode) form, so `bücher.example` is kept as
+/// `xn--bcher-kva.example`.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct Nip05Identifier {
+    local: String,
+    domain: String,
+}
+
+impl Nip05Identifier {
+    /// Parse an identifier, `local@domain` or a bare `domain`
+    pub fn try_from_str(s: &str) -> Result<Nip05Identifier, Error> {
+        let invalid = || Error::InvalidNip05Identifier(s.to_owned());
+        let (local, domain) = s.split_once('@').unwrap_or(("_", s));
+        if !valid_local_part(local) {
+            return Err(invalid());
+        }
+        let domain = domain_to_ascii(domain).ok_or_else(invalid)?;
+        Ok(Nip05Identifier {
+            local: local.to_ascii_lowercase(),
+            domain,
+        })
+    }
+
+    /// The local part (the name), `_` for the root identifier
+    pub fn local_part(&self) -> &str {
+        &self.local
+    }
+
+    /// The domain
+    pub fn domain(&self) -> &str {
+        &self.domain
+    }
+
+    /// Whether this is the root identifier of the domain, `_@domain`
+    pub fn is_root(&self) -> bool {
+        self.local == "_"
+    }
+
+    /// The URL of the nostr.json to look the name up in
+    pub fn well_known_url(&self) -> String {
+        format!(
+            "https://{}/.well-known/nostr.json?name={}",
+            self.domain, self.local
+        )
+    }
+}
+
+// Normalize a domain to lowercase ASCII, converting internationalized labels to
+// punycode. Returns None if it is not a plain domain name.
+pub(crate) fn domain_to_ascii(domain: &str) -> Option<String> {
+    // Anything which would make the URL more than a host is rejected up front
+    if domain.is_empty() || domain.contains(['/', '\\', '?', '#', '@', ':', '%']) {
+        return None;
+    }
+    let url = url::Url::parse(&format!("https://{domain}/")).ok()?;
+    let ascii = url.host_str()?.to_owned();
+    let valid = ascii.contains('.')
+        && ascii.split('.').all(|label| {
+            !label.is_empty() && label.chars().all(|c| c.is_ascii_alphanumeric() || c == '-')
+        });
+    valid.then_some(ascii)
+}
+
+// Whether a name is allowed as the local part of an identifier
+fn valid_local_part(name: &str) -> bool {
+    !name.is_empty()
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.'))
+}
+
+/// Builds the nostr.json file a domain serves for NIP-05, from (name, public
+/// key, relays) entries, with its JSON in a stable order.
+#[derive(Clone, Debug, Default)]
+pub struct Nip05Builder {
+    names: BTreeMap<String, PublicKeyHex>,
+    relays: BTreeMap<String, Vec<UncheckedUrl>>,
+}
+
+impl Nip05Builder {
+    /// Create an empty builder
+    pub fn new() -> Nip05Builder {
+        Nip05Builder::default()
+    }
+
+    /// Add a name for a public key, with relays where it may be found. Names
+    /// are case-insensitive, and are kept in lowercase; adding a name twice is
+    /// an error. Relays given for a key under more than one name are merged.
+    pub fn add(
+        &mut self,
+        name: &str,
+        pubkey: PublicKey,
+        relays: Vec<UncheckedUrl>,
+    ) -> Result<&mut Nip05Builder, Error> {
+        if !valid_local_part(name) {
+            return Err(Error::InvalidNip05Identifier(name.to_owned()));
+        }
+        let name = name.to_ascii_lowercase();
+        if self.names.contains_key(&name) {
+            return Err(Error::DuplicateNip05Name(name));
+        }
+        let pubkey = PublicKeyHex::from(pubkey);
+        if !relays.is_empty() {
+            let known = self.relays.entry(pubkey.as_str().to_owned()).or_default();
+            for relay in relays {
+                if !known.contains(&relay) {
+                    known.push(relay);
+                }
+            }
+        }
+        let _ = self.names.insert(name, pubkey);
+        Ok(self)
+    }
+
+    /// Build the `Nip05`
+    pub fn build(&self) -> Nip05 {
+        Nip05 {
+            names: self
+                .names
+                .iter()
+                .map(|(name, pubkey)| (name.clone(), pubkey.clone()))
+                .collect(),
+            relays: self
+                .relays
+                .iter()
+                .filter_map(|(pubkey, relays)| {
+                    PublicKeyHex::try_from_str(pubkey)
+                        .ok()
+                        .map(|pubkey| (pubkey, relays.clone()))
+                })
+                .collect(),
+        }
+    }
+
+    /// The nostr.json file, with names and keys in sorted order so that the
+    /// same entries always give the same file
+    pub fn to_json(&self) -> String {
+        #[derive(Serialize)]
+        struct Nip05Json<'a> {
+            names: &'a BTreeMap<String, PublicKeyHex>,
+            #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+            relays: &'a BTreeMap<String, Vec<UncheckedUrl>>,
+        }
+        serde_json::to_string_pretty(&Nip05Json {
+            names: &self.names,
+            relays: &self.relays,
+        })
+        .unwrap_or_default()
+    }
+}
+
+impl TryFrom<&str> for Nip05Identifier {
+    type Error = Error;
+
+    fn try_from(s: &str) -> Result<Nip05Identifier, Error> {
+        Nip05Identifier::try_from_str(s)
+    }
+}
+
+impl fmt::Display for Nip05Identifier {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.is_root() {
+            write!(f, "{}", self.domain)
+        } else {
+            write!(f, "{}@{}", self.local, self.domain)
+        }
+    }
+}
+
+impl Serialize for Nip05Identifier {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serializer.collect_str(self)
+    }
+}
+
+impl<'de> Deserialize<'de> for Nip05Identifier {
+    fn deserialize<D>(deserializer: D) -> Result<Nip05Identifier, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let s = String::deserialize(deserializer)?;
+        Nip05Identifier::try_from_str(&s).map_err(DeError::custom)
+    }
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
@@ -78,4 +584,190 @@ mod test {
             ]
         );
     }
+
+    #[test]
+    fn test_nip05_lookup() {
+        let nip05 = Nip05::mock();
+        let bob = nip05.names.get("bob").unwrap();
+        assert_eq!(nip05.pubkey_for("bob"), Some(bob));
+        assert_eq!(nip05.pubkey_for("BoB"), Some(bob));
+        assert_eq!(nip05.relays_for("Bob").len(), 2);
+        assert_eq!(nip05.pubkey_for("alice"), None);
+        assert!(nip05.relays_for("alice").is_empty());
+        assert_eq!(
+            nip05.profile_pointer_for("bob"),
+            Some(ProfilePointer::mock())
+        );
+        assert_eq!(nip05.profile_pointer_for("alice"), None);
+    }
+
+    #[test]
+    fn test_nip05_limits() {
+        let bob = "b0635d6a9851d3aed0cd6c495b282167acf761729078d975fc341b22650b07b9";
+        let json = serde_json::to_vec(&Nip05::mock()).unwrap();
+        let limits = Nip05Limits {
+            max_names: 1,
+            max_name_length: 3,
+            max_relays_per_key: 2,
+            max_relay_url_length: 30,
+        };
+        assert_eq!(
+            Nip05::from_json_with_limits(&json, &limits).unwrap(),
+            Nip05::mock()
+        );
+
+        let exceeds = |json: String, limits: &Nip05Limits| {
+            matches!(
+                Nip05::from_json_with_limits(json.as_bytes(), limits),
+                Err(Error::Nip05LimitExceeded(_))
+            )
+        };
+        let two_names = format!(r#"{{"names":{{"bob":"{bob}","al":"{bob}"}}}}"#);
+        assert!(exceeds(two_names.clone(), &limits));
+        assert!(!exceeds(two_names, &Nip05Limits::default()));
+        assert!(exceeds(
+            format!(r#"{{"names":{{"bobby":"{bob}"}}}}"#),
+            &limits
+        ));
+        let three_relays = format!(
+            r#"{{"names":{{}},"relays":{{"{bob}":["wss://a.com","wss://b.com","wss://c.com"]}}}}"#
+        );
+        assert!(exceeds(three_relays, &limits));
+        let long_url = format!(
+            r#"{{"names":{{}},"relays":{{"{bob}":["wss://{}.com"]}}}}"#,
+            "a".repeat(30)
+        );
+        assert!(exceeds(long_url, &limits));
+
+        // Plain deserialization applies the default limits
+        let many: String = (0..10_001)
+            .map(|n| format!(r#""n{n}":"{bob}""#))
+            .collect::<Vec<_>>()
+            .join(",");
+        let many = format!(r#"{{"names":{{{many}}}}}"#);
+        assert!(serde_json::from_str::<Nip05>(&many).is_err());
+        assert!(exceeds(many, &Nip05Limits::default()));
+
+        // Other mistakes are JSON errors
+        for bad in [
+            r#"{"relays":{}}"#,
+            r#"{"names":{"bob":"nothex"}}"#,
+            r#"{"names":{}}x"#,
+            r#"{"names":{},"names":{}}"#,
+        ] {
+            assert!(matches!(
+                Nip05::from_json_with_limits(bad.as_bytes(), &limits),
+                Err(Error::SerdeJson(_))
+            ));
+        }
+        let extra = r#"{"names":{},"comment":["anything"]}"#;
+        assert!(Nip05::from_json_with_limits(extra.as_bytes(), &limits).is_ok());
+    }
+
+    #[test]
+    fn test_nip05_identifier() {
+        let bob = Nip05Identifier::try_from_str("Bob.Smith@Example.com").unwrap();
+        assert_eq!(bob.local_part(), "bob.smith");
+        assert_eq!(bob.domain(), "example.com");
+        assert_eq!(
+            bob.well_known_url(),
+            "https://example.com/.well-known/nostr.json?name=bob.smith"
+        );
+        assert_eq!(bob.to_string(), "bob.smith@example.com");
+
+        let root = Nip05Identifier::try_from_str("_@example.com").unwrap();
+        assert!(root.is_root());
+        assert_eq!(root.to_string(), "example.com");
+        assert_eq!(Nip05Identifier::try_from_str("example.com").unwrap(), root);
+
+        for bad in [
+            "bob@",
+            "@example.com",
+            "bob@localhost",
+            "bob@x.com/evil",
+            "b b@x.com",
+            "bob+x@x.com",
+            "bob@x.com:8080",
+            "bob@exa mple.com",
+            "bob@x..com",
+        ] {
+            assert!(Nip05Identifier::try_from_str(bad).is_err(), "{bad}");
+        }
+
+        // Internationalized domains are compared and looked up in punycode
+        let idn = Nip05Identifier::try_from_str("bob@Bücher.example").unwrap();
+        assert_eq!(idn.domain(), "xn--bcher-kva.example");
+        assert_eq!(
+            idn.well_known_url(),
+            "https://xn--bcher-kva.example/.well-known/nostr.json?name=bob"
+        );
+        assert_eq!(
+            Nip05Identifier::try_from_str("bob@xn--bcher-kva.example").unwrap(),
+            idn
+        );
+
+        let json = serde_json::to_string(&bob).unwrap();
+        assert_eq!(json, r#""bob.smith@example.com""#);
+        assert_eq!(serde_json::from_str::<Nip05Identifier>(&json).unwrap(), bob);
+        assert!(serde_json::from_str::<Nip05Identifier>(r#""bob@""#).is_err());
+    }
+
+    #[test]
+    fn test_nip05_builder() {
+        let bob = PublicKey::try_from_hex_string(
+            "b0635d6a9851d3aed0cd6c495b282167acf761729078d975fc341b22650b07b9",
+        )
+        .unwrap();
+        let alice = PublicKey::try_from_hex_string(
+            "3bf0c63fcb93463407af97a5e5ee64fa883d107ef9e558472c4eb9aaaefa459d",
+        )
+        .unwrap();
+        let relay = |s: &str| UncheckedUrl::from_str(s);
+
+        let mut builder = Nip05Builder::new();
+        let _ = builder
+            .add("bob", bob, vec![relay("wss://relay.example.com")])
+            .unwrap()
+            .add("Alice", alice, vec![])
+            .unwrap()
+            .add(
+                "_",
+                bob,
+                vec![
+                    relay("wss://relay.example.com"),
+                    relay("wss://relay2.example.com"),
+                ],
+            )
+            .unwrap();
+        assert!(matches!(
+            builder.add("ALICE", bob, vec![]),
+            Err(Error::DuplicateNip05Name(_))
+        ));
+        assert!(builder.add("bob smith", bob, vec![]).is_err());
+
+        let json = builder.to_json();
+        let expected = format!(
+            r#"{{
+  "names": {{
+    "_": "{bob}",
+    "alice": "{alice}",
+    "bob": "{bob}"
+  }},
+  "relays": {{
+    "{bob}": [
+      "wss://relay.example.com",
+      "wss://relay2.example.com"
+    ]
+  }}
+}}"#,
+            bob = bob.as_hex_string(),
+            alice = alice.as_hex_string()
+        );
+        assert_eq!(json, expected);
+
+        let nip05: Nip05 = serde_json::from_str(&json).unwrap();
+        assert_eq!(nip05, builder.build());
+        assert_eq!(nip05.relays_for("_").len(), 2);
+        assert!(nip05.relays_for("alice").is_empty());
+    }
 }
@@ -0,0 +1,75 @@
+use super::{Nip05, Nip05Identifier, Nip05Limits, PublicKey, PublicKeyHex};
+use crate::Error;
+
+/// The largest nostr.json response that `verify_nip05_body()` will accept
+pub const NIP05_MAX_RESPONSE_BYTES: usize = 256 * 1024;
+
+/// The outcome of verifying a NIP-05 identifier against a public key
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Nip05Status {
+    /// The identifier maps to the expected public key
+    Valid,
+
+    /// The identifier maps to a different public key
+    Mismatch(PublicKeyHex),
+
+    /// The server does not list the name
+    NotFound,
+}
+
+/// Verify that a NIP-05 identifier belongs to the `expected` public key, from
+/// the body of the domain's nostr.json, which the caller has fetched from
+/// `Nip05Identifier::well_known_url()`. Names are matched case-insensitively.
+///
+/// This crate has no HTTP client, so making that request safely is up to the
+/// caller: NIP-05 forbids following redirects, the request should time out,
+/// and reading should stop once the body is over `NIP05_MAX_RESPONSE_BYTES`.
+/// A body over that size is rejected here too.
+pub fn verify_nip05_body(
+    identifier: &Nip05Identifier,
+    expected: &PublicKey,
+    body: &[u8],
+) -> Result<Nip05Status, Error> {
+    if body.len() > NIP05_MAX_RESPONSE_BYTES {
+        return Err(Error::HttpResponseTooLarge(body.len()));
+    }
+    let nip05 = Nip05::from_json_with_limits(body, &Nip05Limits::default())?;
+
+    Ok(match nip05.pubkey_for(identifier.local_part()) {
+        None => Nip05Status::NotFound,
+        Some(pubkey) if *pubkey == PublicKeyHex::from(*expected) => Nip05Status::Valid,
+        Some(pubkey) => Nip05Status::Mismatch(pubkey.clone()),
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_verify_nip05_body() {
+        let bob = PublicKey::try_from_hex_string(
+            "b0635d6a9851d3aed0cd6c495b282167acf761729078d975fc341b22650b07b9",
+        )
+        .unwrap();
+        let body = serde_json::to_vec(&Nip05::mock()).unwrap();
+        let id = |s: &str| Nip05Identifier::try_from_str(s).unwrap();
+
+        let status = verify_nip05_body(&id("Bob@Example.com"), &bob, &body).unwrap();
+        assert_eq!(status, Nip05Status::Valid);
+
+        let other = PublicKey::mock();
+        let status = verify_nip05_body(&id("bob@example.com"), &other, &body).unwrap();
+        assert_eq!(status, Nip05Status::Mismatch(bob.into()));
+
+        let status = verify_nip05_body(&id("example.com"), &bob, &body).unwrap();
+        assert_eq!(status, Nip05Status::NotFound);
+
+        let huge = vec![b' '; NIP05_MAX_RESPONSE_BYTES + 1];
+        assert!(matches!(
+            verify_nip05_body(&id("bob@example.com"), &bob, &huge),
+            Err(Error::HttpResponseTooLarge(_))
+        ));
+        assert!(verify_nip05_body(&id("bob@example.com"), &bob, b"<html>").is_err());
+    }
+}
@@ -0,0 +1,384 @@
+// NIP-44 version 2 encryption
+//
+// conversation_key = HKDF-extract(salt = "nip44-v2", ikm = shared_x)
+// (chacha_key, chacha_nonce, hmac_key) = HKDF-expand(conversation_key, info = nonce, L = 76)
+// padded = u16be(len) + plaintext + zeroes, to calc_padded_len(len)
+// ciphertext = ChaCha20(chacha_key, chacha_nonce, padded)
+// mac = HMAC-SHA256(hmac_key, nonce + ciphertext)
+// payload = base64(0x02 + nonce + ciphertext + mac)
+
+use crate::Error;
+use base64::Engine;
+use chacha20::cipher::{KeyIvInit, StreamCipher};
+use chacha20::ChaCha20;
+use hkdf::Hkdf;
+use hmac::{Hmac, Mac};
+use rand_core::{OsRng, RngCore};
+use serde::{Deserialize, Serialize};
+use sha2::Sha256;
+use zeroize::Zeroize;
+
+pub(crate) const VERSION: u8 = 2;
+const MIN_PLAINTEXT_LEN: usize = 1;
+const MAX_PLAINTEXT_LEN: usize = 65535;
+
+// The decoded payload lengths: a version byte, 32-byte nonce, at least 34 bytes
+// of padded ciphertext and a 32-byte mac
+pub(crate) const MIN_PAYLOAD_LEN: usize = 99;
+const MAX_PAYLOAD_LEN: usize = 65603;
+
+// Compute the conversation key from the x coordinate of the ECDH shared point
+pub(crate) fn conversation_key(shared_x: &[u8]) -> [u8; 32] {
+    let (prk, _) = Hkdf::<Sha256>::extract(Some(b"nip44-v2"), shared_x);
+    prk.into()
+}
+
+// Derive the per-message keys from the conversation key and nonce
+fn message_keys(conversation_key: &[u8; 32], nonce: &[u8; 32]) -> ([u8; 32], [u8; 12], [u8; 32]) {
+    let hkdf = Hkdf::<Sha256>::from_prk(conversation_key).unwrap(); // 32 bytes is a valid PRK length
+    let mut okm = [0u8; 76];
+    hkdf.expand(nonce, &mut okm).unwrap(); // 76 bytes is a valid output length
+    let mut chacha_key = [0u8; 32];
+    let mut chacha_nonce = [0u8; 12];
+    let mut hmac_key = [0u8; 32];
+    chacha_key.copy_from_slice(&okm[0..32]);
+    chacha_nonce.copy_from_slice(&okm[32..44]);
+    hmac_key.copy_from_slice(&okm[44..76]);
+    okm.zeroize();
+    (chacha_key, chacha_nonce, hmac_key)
+}
+
+// The padded length of a plaintext of the given length
+fn calc_padded_len(len: usize) -> usize {
+    if len <= 32 {
+        return 32;
+    }
+    let next_power = 1 << (usize::BITS - (len - 1).leading_zeros());
+    let chunk = if next_power <= 256 {
+        32
+    } else {
+        next_power / 8
+    };
+    chunk * ((len - 1) / chunk + 1)
+}
+
+fn hmac_aad(hmac_key: &[u8; 32], nonce: &[u8; 32], ciphertext: &[u8]) -> [u8; 32] {
+    let mut mac = Hmac::<Sha256>::new_from_slice(hmac_key).unwrap(); // any key length works
+    mac.update(nonce);
+    mac.update(ciphertext);
+    mac.finalize().into_bytes().into()
+}
+
+// Encrypt with a caller-supplied nonce, which must be random
+pub(crate) fn encrypt(
+    conversation_key: &[u8; 32],
+    plaintext: &str,
+    nonce: &[u8; 32],
+) -> Result<String, Error> {
+    let len = plaintext.len();
+    if !(MIN_PLAINTEXT_LEN..=MAX_PLAINTEXT_LEN).contains(&len) {
+        return Err(Error::Encryption);
+    }
+
+    let (mut chacha_key, chacha_nonce, mut hmac_key) = message_keys(conversation_key, nonce);
+
+    let mut buffer: Vec<u8> = Vec::with_capacity(2 + calc_padded_len(len));
+    buffer.extend((len as u16).to_be_bytes());
+    buffer.extend(plaintext.as_bytes());
+    buffer.resize(2 + calc_padded_len(len), 0);
+
+    let mut cipher = ChaCha20::new(&chacha_key.into(), &chacha_nonce.into());
+    cipher.apply_keystream(&mut buffer);
+
+    let mac = hmac_aad(&hmac_key, nonce, &buffer);
+    chacha_key.zeroize();
+    hmac_key.zeroize();
+
+    let mut payload: Vec<u8> = Vec::with_capacity(1 + 32 + buffer.len() + 32);
+    payload.push(VERSION);
+    payload.extend(nonce);
+    payload.extend(buffer);
+    payload.extend(mac);
+    Ok(base64::engine::general_purpose::STANDARD.encode(payload))
+}
+
+pub(crate) fn decrypt(conversation_key: &[u8; 32], payload: &str) -> Result<String, Error> {
+    // '#' signals a future, non-base64 encoding
+    if payload.is_empty() || payload.starts_with('#') {
+        return Err(Error::BadEncryptedMessage);
+    }
+    if payload.len() < 132 || payload.len() > 87472 {
+        return Err(Error::BadEncryptedMessage);
+    }
+    let data = base64::engine::general_purpose::STANDARD.decode(payload)?;
+    if data.len() < MIN_PAYLOAD_LEN || data.len() > MAX_PAYLOAD_LEN || data[0] != VERSION {
+        return Err(Error::BadEncryptedMessage);
+    }
+
+    let nonce: [u8; 32] = data[1..33].try_into()?;
+    let ciphertext = &data[33..data.len() - 32];
+    let mac = &data[data.len() - 32..];
+
+    let (mut chacha_key, chacha_nonce, mut hmac_key) = message_keys(conversation_key, &nonce);
+
+    let calculated_mac = hmac_aad(&hmac_key, &nonce, ciphertext);
+    hmac_key.zeroize();
+    // constant time comparison
+    if calculated_mac
+        .iter()
+        .zip(mac)
+        .fold(0, |acc, (a, b)| acc | (a ^ b))
+        != 0
+    {
+        chacha_key.zeroize();
+        return Err(Error::Encryption);
+    }
+
+    let mut padded = ciphertext.to_vec();
+    let mut cipher = ChaCha20::new(&chacha_key.into(), &chacha_nonce.into());
+    cipher.apply_keystream(&mut padded);
+    chacha_key.zeroize();
+
+    let len = u16::from_be_bytes([padded[0], padded[1]]) as usize;
+    if len < MIN_PLAINTEXT_LEN || padded.len() != 2 + calc_padded_len(len) {
+        padded.zeroize();
+        return Err(Error::BadEncryptedMessage);
+    }
+    let plaintext = std::str::from_utf8(&padded[2..2 + len]).map(|s| s.to_owned());
+    padded.zeroize();
+    Ok(plaintext?)
+}
+
+// Bytes of data carried by each chunk. Base64 encoded (4/3 larger) and wrapped
+// in the chunk envelope this stays within MAX_PLAINTEXT_LEN.
+const CHUNK_DATA_LEN: usize = 48_000;
+
+// The envelope around each chunk of a chunked payload
+#[derive(Debug, Deserialize, Serialize)]
+struct Chunk {
+    // Random id shared by all chunks of one payload
+    id: String,
+
+    // Index of this chunk, from 0
+    i: usize,
+
+    // Total number of chunks
+    n: usize,
+
+    // Base64 encoded data
+    d: String,
+}
+
+// Encrypt data of any length as a sequence of NIP-44 payloads
+pub(crate) fn encrypt_chunked(
+    conversation_key: &[u8; 32],
+    data: &[u8],
+) -> Result<Vec<String>, Error> {
+    let mut id: [u8; 16] = [0; 16];
+    OsRng.fill_bytes(&mut id);
+    let id = hex::encode(id);
+
+    let n = data.len().div_ceil(CHUNK_DATA_LEN).max(1);
+    let mut output: Vec<String> = Vec::with_capacity(n);
+    for i in 0..n {
+        let start = i * CHUNK_DATA_LEN;
+        let end = data.len().min(start + CHUNK_DATA_LEN);
+        let chunk = Chunk {
+            id: id.clone(),
+            i,
+            n,
+            d: base64::engine::general_purpose::STANDARD.encode(&data[start..end]),
+        };
+        let mut nonce: [u8; 32] = [0; 32];
+        OsRng.fill_bytes(&mut nonce);
+        output.push(encrypt(
+            conversation_key,
+            &serde_json::to_string(&chunk)?,
+            &nonce,
+        )?);
+    }
+    Ok(output)
+}
+
+// Decrypt and reassemble the payloads produced by encrypt_chunked(), which may
+// be given in any order
+pub(crate) fn decrypt_chunked(
+    conversation_key: &[u8; 32],
+    payloads: &[String],
+) -> Result<Vec<u8>, Error> {
+    let mut chunks: Vec<Chunk> = Vec::with_capacity(payloads.len());
+    for payload in payloads {
+        let json = decrypt(conversation_key, payload)?;
+        chunks.push(serde_json::from_str(&json)?);
+    }
+    chunks.sort_by_key(|c| c.i);
+
+    let (id, n) = match chunks.first() {
+        Some(c) => (c.id.clone(), c.n),
+        None => return Err(Error::InvalidEncryptedChunks("No chunks".to_owned())),
+    };
+    if chunks.len() != n {
+        return Err(Error::InvalidEncryptedChunks(format!(
+            "Expected {} chunks, found {}",
+            n,
+            chunks.len()
+        )));
+    }
+
+    let mut output: Vec<u8> = Vec::new();
+    for (expected, chunk) in chunks.iter().enumerate() {
+        if chunk.id != id || chunk.n != n {
+            return Err(Error::InvalidEncryptedChunks(
+                "Chunks belong to different payloads".to_owned(),
+            ));
+        }
+        if chunk.i != expected {
+            return Err(Error::InvalidEncryptedChunks(format!(
+                "Chunk {expected} is missing"
+            )));
+        }
+        output.extend(base64::engine::general_purpose::STANDARD.decode(&chunk.d)?);
+    }
+    Ok(output)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{PrivateKey, PublicKey};
+
+    #[test]
+    fn test_calc_padded_len() {
+        for (len, padded) in [
+            (16, 32),
+            (32, 32),
+            (33, 64),
+            (37, 64),
+            (45, 64),
+            (49, 64),
+            (64, 64),
+            (65, 96),
+            (100, 128),
+            (111, 128),
+            (200, 224),
+            (250, 256),
+            (320, 320),
+            (383, 384),
+            (384, 384),
+            (400, 448),
+            (500, 512),
+            (512, 512),
+            (515, 640),
+            (700, 768),
+            (800, 896),
+            (900, 1024),
+            (1020, 1024),
+            (65536, 65536),
+        ] {
+            assert_eq!(calc_padded_len(len), padded);
+        }
+    }
+
+    #[test]
+    fn test_nip44_vector() {
+        // Test vector from NIP-44
+        let sec1 = PrivateKey::try_from_hex_string(
+            "0000000000000000000000000000000000000000000000000000000000000001",
+        )
+        .unwrap();
+        let sec2 = PrivateKey::try_from_hex_string(
+            "0000000000000000000000000000000000000000000000000000000000000002",
+        )
+        .unwrap();
+        let conversation_key = sec1.nip44_conversation_key(&sec2.public_key());
+        assert_eq!(
+            hex::encode(conversation_key),
+            "c41c775356fd92eadc63ff5a0dc1da211b268cbea22316767095b2871ea1412d"
+        );
+
+        let mut nonce = [0; 32];
+        nonce[31] = 1;
+        let payload = encrypt(&conversation_key, "a", &nonce).unwrap();
+        assert_eq!(
+            payload,
+            "AgAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAABee0G5VSK0/9YypIObAtDKfYEAjD35uVkHyB0F4DwrcNaCXlCWZKaArsGrY6M9wnuTMxWfp1RTN9Xga8no+kF5Vsb"
+        );
+        assert_eq!(decrypt(&conversation_key, &payload).unwrap(), "a");
+    }
+
+    #[test]
+    fn test_nip44_roundtrip() {
+        let sender = PrivateKey::generate();
+        let receiver = PrivateKey::generate();
+        let receiver_pubkey: PublicKey = receiver.public_key();
+
+        for message in ["hello", "üñîçøðé 🎉", &"x".repeat(65535)] {
+            let payload = sender.nip44_encrypt(&receiver_pubkey, message).unwrap();
+            let decrypted = receiver
+                .nip44_decrypt(&sender.public_key(), &payload)
+                .unwrap();
+            assert_eq!(decrypted, message);
+        }
+
+        assert!(sender.nip44_encrypt(&receiver_pubkey, "").is_err());
+        assert!(sender
+            .nip44_encrypt(&receiver_pubkey, &"x".repeat(65536))
+            .is_err());
+
+        // Tampering is detected
+        let payload = sender.nip44_encrypt(&receiver_pubkey, "hello").unwrap();
+        let mut data = base64::engine::general_purpose::STANDARD
+            .decode(&payload)
+            .unwrap();
+        data[40] ^= 1;
+        let tampered = base64::engine::general_purpose::STANDARD.encode(data);
+        assert!(receiver
+            .nip44_decrypt(&sender.public_key(), &tampered)
+            .is_err());
+    }
+
+    #[test]
+    fn test_nip44_chunked() {
+        let sender = PrivateKey::generate();
+        let receiver = PrivateKey::generate();
+
+        let data: Vec<u8> = (0..200_000u32).map(|i| (i % 251) as u8).collect();
+        let mut payloads = sender
+            .nip44_encrypt_chunked(&receiver.public_key(), &data)
+            .unwrap();
+        assert_eq!(payloads.len(), 5);
+        assert!(payloads.iter().all(|p| p.len() <= 87472));
+
+        // Order does not matter
+        payloads.reverse();
+        let decrypted = receiver
+            .nip44_decrypt_chunked(&sender.public_key(), &payloads)
+            .unwrap();
+        assert_eq!(decrypted, data);
+
+        // Missing or foreign chunks are detected
+        let _ = payloads.pop();
+        assert!(receiver
+            .nip44_decrypt_chunked(&sender.public_key(), &payloads)
+            .is_err());
+        let other = sender
+            .nip44_encrypt_chunked(&receiver.public_key(), &data)
+            .unwrap();
+        payloads.push(other[0].clone());
+        assert!(receiver
+            .nip44_decrypt_chunked(&sender.public_key(), &payloads)
+            .is_err());
+
+        // Small and empty payloads take one chunk
+        for data in [&b""[..], &b"hello"[..]] {
+            let payloads = sender
+                .nip44_encrypt_chunked(&receiver.public_key(), data)
+                .unwrap();
+            assert_eq!(payloads.len(), 1);
+            let decrypted = receiver
+                .nip44_decrypt_chunked(&sender.public_key(), &payloads)
+                .unwrap();
+            assert_eq!(decrypted, data);
+        }
+    }
+}
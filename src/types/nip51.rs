@@ -0,0 +1,467 @@
+use super::{ContentEncryptionAlgorithm, Event, EventBuilder, EventKind, PrivateKey, Tag};
+use crate::Error;
+use serde::Serialize;
+
+// The tags of a set (rather than a list) which describe it, rather than being
+// entries
+const SET_METADATA_TAGS: [&str; 4] = ["d", "title", "description", "image"];
+
+// The entries in the tags of a list event. Only sets (which are parameterized
+// replaceable) have tags describing them, so only theirs are left out.
+fn public_entries(event: &Event) -> Vec<Tag> {
+    let is_set = event.kind.is_parameterized_replaceable();
+    event
+        .tags
+        .iter()
+        .filter(|tag| match tag {
+            Tag::Empty => false,
+            tag => !is_set || !SET_METADATA_TAGS.contains(&tag.tagname().as_str()),
+        })
+        .cloned()
+        .collect()
+}
+
+// The entries encrypted in the content of a list event, which only its author
+// can read. These are NIP-44 encrypted to the author themselves, though older
+// clients used NIP-04, which is also accepted.
+fn private_entries(event: &Event, private_key: &PrivateKey) -> Result<Vec<Tag>, Error> {
+    if event.content.trim().is_empty() {
+        return Ok(vec![]);
+    }
+    if private_key.public_key() != event.pubkey {
+        return Err(Error::InvalidList(
+            "Only the author can read the private entries".to_owned(),
+        ));
+    }
+    let json = private_key.decrypt_auto(&event.pubkey, &event.content)?;
+    Ok(serde_json::from_str(&json)?)
+}
+
+macro_rules! nip51_list {
+    ($(#[$meta:meta])* $name:ident, $kind:ident) => {
+        $(#[$meta])*
+        ///
+        /// Like `VerifiedEvent`, it wraps the event and serializes as it, but
+        /// does not deserialize: deserialize an `Event` and use
+        /// `try_from_event()`.
+        #[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+        #[serde(transparent)]
+        pub struct $name(Event);
+
+        impl $name {
+            /// Wrap an event of this kind of list
+            pub fn try_from_event(event: Event) -> Result<$name, Error> {
+                if event.kind != EventKind::$kind {
+                    return Err(Error::WrongEventKind);
+                }
+                Ok($name(event))
+            }
+
+            /// Get the inner event
+            pub fn as_event(&self) -> &Event {
+                &self.0
+            }
+
+            /// Unwrap the inner event
+            pub fn into_inner(self) -> Event {
+                self.0
+            }
+
+            /// The public entries, which are the tags of the event
+            pub fn public_entries(&self) -> Vec<Tag> {
+                public_entries(&self.0)
+            }
+
+            /// The private entries, decrypted from the content with the
+            /// author's private key
+            pub fn private_entries(&self, private_key: &PrivateKey) -> Result<Vec<Tag>, Error> {
+                private_entries(&self.0, private_key)
+            }
+        }
+
+        impl AsRef<Event> for $name {
+            fn as_ref(&self) -> &Event {
+                &self.0
+            }
+        }
+
+        impl From<$name> for Event {
+            fn from(list: $name) -> Event {
+                list.0
+            }
+        }
+
+        impl TryFrom<Event> for $name {
+            type Error = Error;
+
+            fn try_from(event: Event) -> Result<$name, Error> {
+                $name::try_from_event(event)
+            }
+        }
+    };
+}
+
+nip51_list! {
+    /// A mute list (kind 10000, NIP-51): people ('p'), hashtags ('t'), words
+    /// ('word') and threads ('e') the author does not want to see
+    MuteList, MuteList
+}
+
+nip51_list! {
+    /// Pinned notes (kind 10001, NIP-51): events ('e') the author wants shown
+    /// on their profile
+    PinList, PinList
+}
+
+nip51_list! {
+    /// Bookmarks (kind 10003, NIP-51): events ('e'), addresses ('a'), hashtags
+    /// ('t') and URLs ('r') the author saved
+    Bookmarks, Bookmarks
+}
+
+nip51_list! {
+    /// A follow set (kind 30000, NIP-51): a named set of people ('p'), such as
+    /// for a custom feed, of which an author may have many
+    FollowSet, FollowSets
+}
+
+impl FollowSet {
+    /// The identifier in the 'd' tag (empty if there is none)
+    pub fn identifier(&self) -> &str {
+        self.set_tag("d").unwrap_or("")
+    }
+
+    /// The title
+    pub fn title(&self) -> Option<&str> {
+        self.set_tag("title")
+    }
+
+    /// The description
+    pub fn description(&self) -> Option<&str> {
+        self.set_tag("description")
+    }
+
+    // The value of the first tag with this name
+    fn set_tag(&self, name: &str) -> Option<&str> {
+        self.0.tags.iter().find_map(|tag| match tag {
+            Tag::Identifier(d) if name == "d" => Some(d.as_str()),
+            Tag::Title(title) if name == "title" => Some(title.as_str()),
+            Tag::Other { tag, data } if tag == name => data.first().map(|s| s.as_str()),
+            _ => None,
+        })
+    }
+}
+
+/// A builder for NIP-51 lists, putting public entries in the tags and private
+/// entries, encrypted to the author (NIP-44), in the content.
+///
+/// Each kind of list only takes the kinds of entry NIP-51 lists for it (see
+/// the list types), and `sign()` fails with `Error::InvalidList` for any other.
+///
+/// ```
+/// # use nostr_types::{ListBuilder, MuteList, PrivateKey, Tag};
+/// let private_key = PrivateKey::generate();
+/// let event = ListBuilder::mute_list()
+///     .public(Tag::Hashtag("spam".to_owned()))
+///     .private(Tag::Other { tag: "word".to_owned(), data: vec!["secret".to_owned()] })
+///     .sign(&private_key)
+///     .unwrap();
+/// let mute_list = MuteList::try_from_event(event).unwrap();
+/// assert_eq!(mute_list.public_entries().len(), 1);
+/// assert_eq!(mute_list.private_entries(&private_key).unwrap().len(), 1);
+/// ```
+#[derive(Clone, Debug)]
+pub struct ListBuilder {
+    kind: EventKind,
+    set_tags: Vec<Tag>,
+    public: Vec<Tag>,
+    private: Vec<Tag>,
+}
+
+impl ListBuilder {
+    fn new(kind: EventKind) -> ListBuilder {
+        ListBuilder {
+            kind,
+            set_tags: vec![],
+            public: vec![],
+            private: vec![],
+        }
+    }
+
+    /// Start building a mute list
+    pub fn mute_list() -> ListBuilder {
+        ListBuilder::new(EventKind::MuteList)
+    }
+
+    /// Start building a list of pinned notes
+    pub fn pin_list() -> ListBuilder {
+        ListBuilder::new(EventKind::PinList)
+    }
+
+    /// Start building a list of bookmarks
+    pub fn bookmarks() -> ListBuilder {
+        ListBuilder::new(EventKind::Bookmarks)
+    }
+
+    /// Start building a follow set with this 'd' identifier. To edit a set,
+    /// use the identifier it already has.
+    pub fn follow_set(identifier: &str) -> ListBuilder {
+        let mut builder = ListBuilder::new(EventKind::FollowSets);
+        builder
+            .set_tags
+            .push(Tag::Identifier(identifier.to_owned()));
+        builder
+    }
+
+    /// Set the title of a set
+    pub fn title(mut self, title: &str) -> ListBuilder {
+        self.set_tags.retain(|t| !matches!(t, Tag::Title(_)));
+        self.set_tags.push(Tag::Title(title.to_owned()));
+        self
+    }
+
+    /// Set the description of a set
+    pub fn description(mut self, description: &str) -> ListBuilder {
+        self.set_tags
+            .retain(|t| !matches!(t, Tag::Other { tag, .. } if tag == "description"));
+        self.set_tags.push(Tag::Other {
+            tag: "description".to_owned(),
+            data: vec![description.to_owned()],
+        });
+        self
+    }
+
+    /// Add a public entry, unless it is in the list already
+    pub fn public(mut self, entry: Tag) -> ListBuilder {
+        if !self.public.contains(&entry) {
+            self.public.push(entry);
+        }
+        self
+    }
+
+    /// Add a private entry, unless it is in the list already
+    pub fn private(mut self, entry: Tag) -> ListBuilder {
+        if !self.private.contains(&entry) {
+            self.private.push(entry);
+        }
+        self
+    }
+
+    /// Add entries, each with whether it is private
+    pub fn entries<I: IntoIterator<Item = (Tag, bool)>>(mut self, entries: I) -> ListBuilder {
+        for (entry, private) in entries {
+            self = if private {
+                self.private(entry)
+            } else {
+                self.public(entry)
+            };
+        }
+        self
+    }
+
+    /// Finish building, producing the list signed (and its private entries
+    /// encrypted) by `private_key`
+    pub fn sign(&self, private_key: &PrivateKey) -> Result<Event, Error> {
+        self.to_event_builder(private_key)?.sign(private_key)
+    }
+
+    /// An `EventBuilder` for the list, with its private entries encrypted by
+    /// `private_key`, to set anything else on (such as when it was created)
+    /// before signing it with the same key
+    pub fn to_event_builder(&self, private_key: &PrivateKey) -> Result<EventBuilder, Error> {
+        let allowed: &[&str] = match self.kind {
+            EventKind::MuteList => &["p", "t", "word", "e"],
+            EventKind::PinList => &["e"],
+            EventKind::Bookmarks => &["e", "a", "t", "r"],
+            _ => &["p"],
+        };
+        for entry in self.public.iter().chain(self.private.iter()) {
+            let allowed = match entry {
+                Tag::Empty => false,
+                entry => allowed.contains(&entry.tagname().as_str()),
+            };
+            if !allowed {
+                return Err(Error::InvalidList(format!(
+                    "Kind {} cannot have the entry {}",
+                    u64::from(self.kind),
+                    serde_json::to_string(entry)?
+                )));
+            }
+        }
+
+        let content = if self.private.is_empty() {
+            String::new()
+        } else {
+            private_key.encrypt(
+                &private_key.public_key(),
+                &serde_json::to_string(&self.private)?,
+                ContentEncryptionAlgorithm::Nip44v2,
+            )?
+        };
+
+        Ok(EventBuilder::new()
+            .kind(self.kind)
+            .content(&content)
+            .tags(self.set_tags.iter().cloned())
+            .tags(self.public.iter().cloned()))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{Id, PublicKey, PublicKeyHex, Unixtime};
+
+    fn p_tag(pubkey: PublicKey) -> Tag {
+        Tag::Pubkey {
+            pubkey: PublicKeyHex::from(pubkey),
+            recommended_relay_url: None,
+            petname: None,
+        }
+    }
+
+    fn e_tag(id: Id) -> Tag {
+        Tag::Event {
+            id,
+            recommended_relay_url: None,
+            marker: None,
+        }
+    }
+
+    #[test]
+    fn test_mute_list() {
+        let private_key = PrivateKey::generate();
+        let public = p_tag(PublicKey::mock());
+        let secret = Tag::Other {
+            tag: "word".to_owned(),
+            data: vec!["spoilers".to_owned()],
+        };
+        let event = ListBuilder::mute_list()
+            .entries([(public.clone(), false), (secret.clone(), true)])
+            .public(public.clone())
+            .sign(&private_key)
+            .unwrap();
+        assert_eq!(event.kind, EventKind::MuteList);
+        assert!(event.verify(None).is_ok());
+
+        let mute_list = MuteList::try_from_event(event).unwrap();
+        assert_eq!(mute_list.public_entries(), vec![public]);
+        assert_eq!(
+            mute_list.private_entries(&private_key).unwrap(),
+            vec![secret]
+        );
+        assert!(mute_list.private_entries(&PrivateKey::generate()).is_err());
+        assert!(!mute_list.as_event().content.contains("spoilers"));
+
+        // Entries of another kind are refused
+        assert!(matches!(
+            ListBuilder::pin_list()
+                .private(p_tag(PublicKey::mock()))
+                .sign(&private_key),
+            Err(Error::InvalidList(_))
+        ));
+    }
+
+    #[test]
+    fn test_lists_of_events() {
+        let private_key = PrivateKey::generate();
+        let pinned = e_tag(Id::mock());
+        let event = ListBuilder::pin_list()
+            .public(pinned.clone())
+            .sign(&private_key)
+            .unwrap();
+        assert_eq!(event.content, "");
+        let pin_list = PinList::try_from(event.clone()).unwrap();
+        assert_eq!(pin_list.public_entries(), vec![pinned.clone()]);
+        assert!(pin_list.private_entries(&private_key).unwrap().is_empty());
+        assert!(matches!(
+            Bookmarks::try_from_event(event),
+            Err(Error::WrongEventKind)
+        ));
+
+        let url = Tag::Reference {
+            url: crate::UncheckedUrl::from_str("https://example.com"),
+            marker: None,
+        };
+        let event = ListBuilder::bookmarks()
+            .public(url.clone())
+            .private(pinned.clone())
+            .sign(&private_key)
+            .unwrap();
+        let bookmarks = Bookmarks::try_from_event(event).unwrap();
+        assert_eq!(bookmarks.public_entries(), vec![url]);
+        assert_eq!(
+            bookmarks.private_entries(&private_key).unwrap(),
+            vec![pinned.clone()]
+        );
+
+        // Older clients encrypted private entries with NIP-04
+        let json = serde_json::to_string(&vec![e_tag(Id::mock())]).unwrap();
+        let content = private_key
+            .encrypt(
+                &private_key.public_key(),
+                &json,
+                ContentEncryptionAlgorithm::Nip04,
+            )
+            .unwrap();
+        let event = EventBuilder::new()
+            .kind(EventKind::Bookmarks)
+            .content(&content)
+            .sign(&private_key)
+            .unwrap();
+        let bookmarks = Bookmarks::try_from_event(event).unwrap();
+        assert_eq!(bookmarks.private_entries(&private_key).unwrap().len(), 1);
+
+        // Lists which are not sets have no describing tags to leave out
+        let title = Tag::Title("Favourites".to_owned());
+        let event = EventBuilder::new()
+            .kind(EventKind::PinList)
+            .tag(pinned.clone())
+            .tag(title.clone())
+            .sign(&private_key)
+            .unwrap();
+        let pin_list = PinList::try_from_event(event).unwrap();
+        assert_eq!(pin_list.public_entries(), vec![pinned, title]);
+    }
+
+    #[test]
+    fn test_follow_set() {
+        let private_key = PrivateKey::generate();
+        let alice = p_tag(PrivateKey::generate().public_key());
+        let bob = p_tag(PrivateKey::generate().public_key());
+        let carol = p_tag(PrivateKey::generate().public_key());
+        let dave = p_tag(PrivateKey::generate().public_key());
+        let event = ListBuilder::follow_set("friends")
+            .title("Old title")
+            .title("Friends")
+            .description("People I know")
+            .public(carol.clone())
+            .public(alice.clone())
+            .public(carol.clone())
+            .private(bob.clone())
+            .private(dave.clone())
+            .private(bob.clone())
+            .sign(&private_key)
+            .unwrap();
+        assert!(event.kind.is_parameterized_replaceable());
+        assert_eq!(event.parameter().as_deref(), Some("friends"));
+
+        let set = FollowSet::try_from_event(event).unwrap();
+        assert_eq!(set.identifier(), "friends");
+        assert_eq!(set.title(), Some("Friends"));
+        assert_eq!(set.description(), Some("People I know"));
+        assert_eq!(set.public_entries(), vec![carol, alice.clone()]);
+        assert_eq!(set.private_entries(&private_key).unwrap(), vec![bob, dave]);
+
+        // Anything else is set on the EventBuilder
+        let event = ListBuilder::follow_set("friends")
+            .public(alice)
+            .to_event_builder(&private_key)
+            .unwrap()
+            .created_at(Unixtime(1_700_000_000))
+            .sign(&private_key)
+            .unwrap();
+        assert_eq!(event.created_at, Unixtime(1_700_000_000));
+        assert!(FollowSet::try_from_event(event).is_ok());
+    }
+}
@@ -1,18 +1,49 @@
-use super::{EventPointer, Id, Profile, PublicKey};
+use super::{
+    EncryptedPrivateKey, EventAddr, EventPointer, Id, PrivateKey, ProfilePointer, PublicKey,
+};
+use crate::Error;
 use lazy_static::lazy_static;
+use serde::de::Error as DeError;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::ops::Range;
 
 /// A bech32 sequence representing a nostr object (or set of objects)
 // note, internally we store them as the object the sequence represents
-#[derive(Clone, Debug)]
+#[derive(Clone)]
 pub enum NostrBech32 {
     /// npub - a NostrBech32 representing a public key
     Pubkey(PublicKey),
     /// nprofile - a NostrBech32 representing a public key and a set of relay URLs
-    Profile(Profile),
+    Profile(ProfilePointer),
     /// note - a NostrBech32 representing an event
     Id(Id),
     /// nevent - a NostrBech32 representing an event and a set of relay URLs
     EventPointer(EventPointer),
+    /// naddr - a NostrBech32 representing the address of a replaceable event and a
+    /// set of relay URLs
+    EventAddr(EventAddr),
+    /// nsec - a NostrBech32 representing a private key. As `PrivateKey` cannot be
+    /// cloned, this keeps the (checked) bech32 string; use
+    /// `PrivateKey::try_from_bech32_string()` on it. It is not shown by `Debug`.
+    PrivateKey(String),
+    /// ncryptsec - a NostrBech32 representing an encrypted private key
+    EncryptedPrivateKey(EncryptedPrivateKey),
+}
+
+impl std::fmt::Debug for NostrBech32 {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> Result<(), std::fmt::Error> {
+        match self {
+            NostrBech32::Pubkey(pk) => f.debug_tuple("Pubkey").field(pk).finish(),
+            NostrBech32::Profile(p) => f.debug_tuple("Profile").field(p).finish(),
+            NostrBech32::Id(i) => f.debug_tuple("Id").field(i).finish(),
+            NostrBech32::EventPointer(ep) => f.debug_tuple("EventPointer").field(ep).finish(),
+            NostrBech32::EventAddr(ea) => f.debug_tuple("EventAddr").field(ea).finish(),
+            NostrBech32::PrivateKey(_) => write!(f, "PrivateKey(..)"),
+            NostrBech32::EncryptedPrivateKey(epk) => {
+                f.debug_tuple("EncryptedPrivateKey").field(epk).finish()
+            }
+        }
+    }
 }
 
 impl std::fmt::Display for NostrBech32 {
@@ -22,6 +53,14 @@ impl std::fmt::Display for NostrBech32 {
             NostrBech32::Profile(p) => write!(f, "{}", p.as_bech32_string()),
             NostrBech32::Id(i) => write!(f, "{}", i.as_bech32_string()),
             NostrBech32::EventPointer(ep) => write!(f, "{}", ep.as_bech32_string()),
+            // An address which cannot be encoded (see `EventAddr::check_bech32()`)
+            // displays as nothing, rather than failing
+            NostrBech32::EventAddr(ea) => match ea.as_bech32_string() {
+                Ok(s) => write!(f, "{s}"),
+                Err(_) => Ok(()),
+            },
+            NostrBech32::PrivateKey(nsec) => write!(f, "{nsec}"),
+            NostrBech32::EncryptedPrivateKey(epk) => write!(f, "{}", epk.0),
         }
     }
 }
@@ -32,8 +71,8 @@ impl NostrBech32 {
         NostrBech32::Pubkey(pubkey)
     }
 
-    /// Create from a `Profile`
-    pub fn new_profile(profile: Profile) -> NostrBech32 {
+    /// Create from a `ProfilePointer`
+    pub fn new_profile(profile: ProfilePointer) -> NostrBech32 {
         NostrBech32::Profile(profile)
     }
 
@@ -47,27 +86,53 @@ impl NostrBech32 {
         NostrBech32::EventPointer(ep)
     }
 
-    /// Try to convert a string into a NostrBech32. Must not have leading or trailing
-    /// junk for this to work.
+    /// Create from an `EventAddr`. This fails if the address cannot be encoded
+    /// (see `EventAddr::check_bech32()`).
+    pub fn new_event_addr(ea: EventAddr) -> Result<NostrBech32, Error> {
+        ea.check_bech32()?;
+        Ok(NostrBech32::EventAddr(ea))
+    }
+
+    /// Create from a `PrivateKey`
+    ///
+    /// WARNING: This weakens the security of your key. Your key will be marked
+    /// with `KeySecurity::Weak` if you execute this.
+    pub fn new_private_key(private_key: &mut PrivateKey) -> NostrBech32 {
+        NostrBech32::PrivateKey(private_key.as_bech32_string())
+    }
+
+    /// Create from an `EncryptedPrivateKey`
+    pub fn new_encrypted_private_key(epk: EncryptedPrivateKey) -> NostrBech32 {
+        NostrBech32::EncryptedPrivateKey(epk)
+    }
+
+    /// Try to convert a string into a NostrBech32, of whichever kind its
+    /// human-readable part names. Must not have leading or trailing junk for this
+    /// to work.
     pub fn try_from_string(s: &str) -> Option<NostrBech32> {
-        if s.get(..5) == Some("npub1") {
-            if let Ok(pk) = PublicKey::try_from_bech32_string(s) {
-                return Some(NostrBech32::Pubkey(pk));
-            }
-        } else if s.get(..9) == Some("nprofile1") {
-            if let Ok(p) = Profile::try_from_bech32_string(s) {
-                return Some(NostrBech32::Profile(p));
-            }
-        } else if s.get(..5) == Some("note1") {
-            if let Ok(id) = Id::try_from_bech32_string(s) {
-                return Some(NostrBech32::Id(id));
-            }
-        } else if s.get(..7) == Some("nevent1") {
-            if let Ok(ep) = EventPointer::try_from_bech32_string(s) {
-                return Some(NostrBech32::EventPointer(ep));
-            }
+        let (hrp, _) = s.split_once('1')?;
+        match hrp {
+            "npub" => PublicKey::try_from_bech32_string(s)
+                .ok()
+                .map(NostrBech32::Pubkey),
+            "nprofile" => ProfilePointer::try_from_bech32_string(s)
+                .ok()
+                .map(NostrBech32::Profile),
+            "note" => Id::try_from_bech32_string(s).ok().map(NostrBech32::Id),
+            "nevent" => EventPointer::try_from_bech32_string(s)
+                .ok()
+                .map(NostrBech32::EventPointer),
+            "naddr" => EventAddr::try_from_bech32_string(s)
+                .ok()
+                .map(NostrBech32::EventAddr),
+            "nsec" => PrivateKey::try_from_bech32_string(s)
+                .ok()
+                .map(|_| NostrBech32::PrivateKey(s.to_owned())),
+            "ncryptsec" => EncryptedPrivateKey::try_from_bech32_string(s)
+                .ok()
+                .map(NostrBech32::EncryptedPrivateKey),
+            _ => None,
         }
-        None
     }
 
     /// Find all `NostrBech32`s in a string, returned in the order found
@@ -90,6 +155,9 @@ impl NostrBech32 {
 #[derive(Clone, Debug)]
 pub struct NostrUrl(pub NostrBech32);
 
+/// A Nostr URL, by its NIP-21 name
+pub type NostrUri = NostrUrl;
+
 impl std::fmt::Display for NostrUrl {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> Result<(), std::fmt::Error> {
         write!(f, "nostr:")?;
@@ -105,11 +173,21 @@ impl NostrUrl {
 
     /// Try to convert a string into a NostrUrl. Must not have leading or trailing
     /// junk for this to work.
+    ///
+    /// The scheme is matched case-insensitively, a `//` after it is tolerated, the
+    /// rest is percent-decoded, and an all-uppercase bech32 sequence (as put in QR
+    /// codes) is accepted.
     pub fn try_from_string(s: &str) -> Option<NostrUrl> {
-        if s.get(..6) != Some("nostr:") {
+        if !s.get(..6)?.eq_ignore_ascii_case("nostr:") {
             return None;
         }
-        NostrBech32::try_from_string(s.get(6..).unwrap()).map(NostrUrl)
+        let rest = s.get(6..)?;
+        let rest = rest.strip_prefix("//").unwrap_or(rest);
+        let mut bech32 = percent_decode(rest)?;
+        if !bech32.chars().any(|c| c.is_ascii_lowercase()) {
+            bech32.make_ascii_lowercase();
+        }
+        NostrBech32::try_from_string(&bech32).map(NostrUrl)
     }
 
     /// Find all `NostrUrl`s in a string, returned in the order found
@@ -149,6 +227,78 @@ impl NostrUrl {
     }
 }
 
+impl From<NostrBech32> for NostrUrl {
+    fn from(bech32: NostrBech32) -> NostrUrl {
+        NostrUrl(bech32)
+    }
+}
+
+impl From<NostrUrl> for NostrBech32 {
+    fn from(url: NostrUrl) -> NostrBech32 {
+        url.0
+    }
+}
+
+impl Serialize for NostrUrl {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serializer.collect_str(self)
+    }
+}
+
+impl<'de> Deserialize<'de> for NostrUrl {
+    fn deserialize<D>(deserializer: D) -> Result<NostrUrl, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let s = String::deserialize(deserializer)?;
+        NostrUrl::try_from_string(&s)
+            .ok_or_else(|| DeError::custom(format!("not a nostr: URL: {s}")))
+    }
+}
+
+// Decode %XX escapes. Returns None for a malformed escape, or if the decoded
+// bytes are not UTF-8.
+fn percent_decode(s: &str) -> Option<String> {
+    if !s.contains('%') {
+        return Some(s.to_owned());
+    }
+    let mut bytes: Vec<u8> = Vec::with_capacity(s.len());
+    let mut iter = s.bytes();
+    while let Some(b) = iter.next() {
+        if b == b'%' {
+            let hex = [iter.next()?, iter.next()?];
+            let hex = std::str::from_utf8(&hex).ok()?;
+            bytes.push(u8::from_str_radix(hex, 16).ok()?);
+        } else {
+            bytes.push(b);
+        }
+    }
+    String::from_utf8(bytes).ok()
+}
+
+/// Find all nostr entities in a string, whether bare or as `nostr:` URLs,
+/// returned in the order found with the byte range each one covers (including the
+/// `nostr:` prefix, where it has one)
+pub fn find_nostr_entities(s: &str) -> Vec<(Range<usize>, NostrBech32)> {
+    let mut output: Vec<(Range<usize>, NostrBech32)> = Vec::new();
+    let mut cursor = 0;
+    while let Some((relstart, relend)) = find_nostr_bech32_pos(s.get(cursor..).unwrap()) {
+        let (start, end) = (cursor + relstart, cursor + relend);
+        if let Some(entity) = NostrBech32::try_from_string(s.get(start..end).unwrap()) {
+            let prefixed = start >= 6
+                && s.get(start - 6..start)
+                    .is_some_and(|p| p.eq_ignore_ascii_case("nostr:"));
+            let start = if prefixed { start - 6 } else { start };
+            output.push((start..end, entity));
+        }
+        cursor = end;
+    }
+    output
+}
+
 /// Returns start and end position of next valid NostrBech32
 pub fn find_nostr_bech32_pos(s: &str) -> Option<(usize, usize)> {
     // BECH32 Alphabet:
@@ -157,7 +307,7 @@ pub fn find_nostr_bech32_pos(s: &str) -> Option<(usize, usize)> {
     use regex::Regex;
     lazy_static! {
         static ref BECH32_RE: Regex = Regex::new(
-            r#"(?:^|[^a-zA-Z0-9])((?:note|nevent|nprofile|npub)1[ac-hj-np-z02-9]{58,})(?:$|[^a-zA-Z0-9])"#
+            r#"(?:^|[^a-zA-Z0-9])((?:note|nevent|naddr|nprofile|npub)1[ac-hj-np-z02-9]{58,})(?:$|[^a-zA-Z0-9])"#
         ).expect("Could not compile nostr URL regex");
     }
     BECH32_RE.captures(s).map(|cap| {
@@ -174,7 +324,7 @@ pub fn find_nostr_url_pos(s: &str) -> Option<(usize, usize)> {
     use regex::Regex;
     lazy_static! {
         static ref NOSTRURL_RE: Regex = Regex::new(
-            r#"(?:^|[^a-zA-Z0-9])(nostr:(?:note|nevent|nprofile|npub)1[ac-hj-np-z02-9]{58,})(?:$|[^a-zA-Z0-9])"#
+            r#"(?:^|[^a-zA-Z0-9])(nostr:(?:note|nevent|naddr|nprofile|npub)1[ac-hj-np-z02-9]{58,})(?:$|[^a-zA-Z0-9])"#
         ).expect("Could not compile nostr URL regex");
     }
     NOSTRURL_RE.captures(s).map(|cap| {
@@ -186,6 +336,7 @@ pub fn find_nostr_url_pos(s: &str) -> Option<(usize, usize)> {
 #[cfg(test)]
 mod test {
     use super::*;
+    use crate::{EventKind, UncheckedUrl};
 
     #[test]
     fn test_nostr_bech32_try_from_string() {
@@ -205,6 +356,26 @@ mod test {
         let nurl = NostrBech32::try_from_string(d).unwrap();
         assert!(matches!(nurl, NostrBech32::EventPointer(..)));
 
+        let e = EventAddr::mock().as_bech32_string().unwrap();
+        let nurl = NostrBech32::try_from_string(&e).unwrap();
+        assert!(matches!(nurl, NostrBech32::EventAddr(..)));
+        assert_eq!(nurl.to_string(), e);
+
+        let mut private_key = PrivateKey::generate();
+        let nsec = NostrBech32::new_private_key(&mut private_key).to_string();
+        let nurl = NostrBech32::try_from_string(&nsec).unwrap();
+        assert!(matches!(nurl, NostrBech32::PrivateKey(..)));
+        assert_eq!(format!("{nurl:?}"), "PrivateKey(..)");
+        assert_eq!(nurl.to_string(), nsec);
+
+        let ncryptsec = "ncryptsec1qgg9947rlpvqu76pj5ecreduf9jxhselq2nae2kghhvd5g7dgjtcxfqtd67p9m0w57lspw8gsq6yphnm8623nsl8xn9j4jdzz84zm3frztj3z7s35vpzmqf6ksu8r89qk5z2zxfmu5gv8th8wclt0h4p";
+        let nurl = NostrBech32::try_from_string(ncryptsec).unwrap();
+        assert!(matches!(nurl, NostrBech32::EncryptedPrivateKey(..)));
+        assert_eq!(nurl.to_string(), ncryptsec);
+
+        // the human-readable part must match the content
+        assert!(NostrBech32::try_from_string(&nsec.replacen("nsec", "npub", 1)).is_none());
+
         // too short
         let short = "npub1sn0wdenkukak0d9dfczzeacvhkrgz92ak56egt7vdgzn8pv2wfqqhrjdv";
         assert!(NostrBech32::try_from_string(short).is_none());
@@ -218,6 +389,73 @@ mod test {
         assert!(NostrBech32::try_from_string(unknown).is_none());
     }
 
+    #[test]
+    fn test_nostr_bech32_long() {
+        // NIP-19 entities with several relays are well beyond the 90 character
+        // limit of BIP-173, and must still work
+        let relays: Vec<UncheckedUrl> = (0..20)
+            .map(|i| {
+                UncheckedUrl::from_str(&format!("wss://relay{i}.some-long-domain.example.com"))
+            })
+            .collect();
+
+        let profile = ProfilePointer {
+            pubkey: PublicKey::mock(),
+            relays: relays.clone(),
+        };
+        let event_pointer = EventPointer {
+            id: Id([7; 32]),
+            relays: relays.clone(),
+            author: Some(PublicKey::mock()),
+            kind: Some(EventKind::TextNote),
+        };
+        let event_addr = EventAddr {
+            d: "x".repeat(200),
+            relays,
+            kind: EventKind::LongFormContent,
+            author: PublicKey::mock(),
+        };
+
+        let entities = [
+            NostrBech32::new_profile(profile),
+            NostrBech32::new_event_pointer(event_pointer),
+            NostrBech32::new_event_addr(event_addr).unwrap(),
+        ];
+        for entity in entities {
+            let bech32 = entity.to_string();
+            assert!(bech32.len() > 1023, "{}", bech32.len());
+            let parsed = NostrBech32::try_from_string(&bech32).unwrap();
+            assert_eq!(parsed.to_string(), bech32);
+
+            // and are found in content
+            let content = format!("see nostr:{bech32} here");
+            let found = NostrUrl::find_all_in_string(&content);
+            assert_eq!(found.len(), 1);
+            assert_eq!(found[0].0.to_string(), bech32);
+        }
+    }
+
+    #[test]
+    fn test_find_nostr_entities() {
+        let npub = "npub1sn0wdenkukak0d9dfczzeacvhkrgz92ak56egt7vdgzn8pv2wfqqhrjdv9";
+        let note = "note1fntxtkcy9pjwucqwa9mddn7v03wwwsu9j330jj350nvhpky2tuaspk6nqc";
+        let text = format!("Hi nostr:{npub}, have you seen {note}? (not npub1abc)");
+
+        let found = find_nostr_entities(&text);
+        assert_eq!(found.len(), 2);
+        assert_eq!(
+            text.get(found[0].0.clone()),
+            Some(format!("nostr:{npub}").as_str())
+        );
+        assert!(matches!(found[0].1, NostrBech32::Pubkey(..)));
+        assert_eq!(text.get(found[1].0.clone()), Some(note));
+        assert!(matches!(found[1].1, NostrBech32::Id(..)));
+
+        assert!(find_nostr_entities("🌝 nothing here").is_empty());
+        let found = find_nostr_entities(&format!("🌝 {note}"));
+        assert_eq!(found[0].0, 5..5 + note.len());
+    }
+
     #[test]
     fn test_nostr_urlize() {
         let sample = r#"This is now the offical Gossip Client account.  Please follow it.  I will be reposting it's messages for some time until it catches on.
@@ -246,6 +484,63 @@ note10ttnuuvcs29y3k23gwrcurw2ksvgd7c2rrqlfx7urmt5m963vhss8nja90
         assert!(fixed.len() > sample3.len());
     }
 
+    #[test]
+    fn test_nostr_url_try_from_string() {
+        let npub = "npub1sn0wdenkukak0d9dfczzeacvhkrgz92ak56egt7vdgzn8pv2wfqqhrjdv9";
+        let url = NostrUri::try_from_string(&format!("nostr:{npub}")).unwrap();
+        assert!(matches!(url.0, NostrBech32::Pubkey(..)));
+        assert_eq!(url.to_string(), format!("nostr:{npub}"));
+
+        for variant in [
+            format!("NOSTR:{npub}"),
+            format!("nostr://{npub}"),
+            format!("nostr:%6E{}", npub.get(1..).unwrap()),
+            format!("nostr:%6e{}", npub.get(1..).unwrap()),
+            format!("NOSTR:{}", npub.to_uppercase()),
+        ] {
+            let url = NostrUrl::try_from_string(&variant).unwrap();
+            assert_eq!(url.to_string(), format!("nostr:{npub}"));
+        }
+
+        for bad in [
+            npub.to_owned(),
+            format!("nostr:{npub}%"),
+            format!("nostr:{npub}%2"),
+            format!("nostr:{npub}%zz"),
+            format!("nostr:%FF{npub}"),
+            format!("nostr:{}", npub.replacen('s', "S", 1)),
+            "nostr:".to_owned(),
+        ] {
+            assert!(NostrUrl::try_from_string(&bad).is_none(), "{bad}");
+        }
+
+        // Conversions and serde
+        let bech32: NostrBech32 = url.into();
+        let url = NostrUrl::from(bech32);
+        let json = serde_json::to_string(&url).unwrap();
+        assert_eq!(json, format!(r#""nostr:{npub}""#));
+        let url2: NostrUrl = serde_json::from_str(&json).unwrap();
+        assert_eq!(url2.to_string(), url.to_string());
+        assert!(serde_json::from_str::<NostrUrl>(&format!(r#""{npub}""#)).is_err());
+    }
+
+    #[test]
+    fn test_nostr_bech32_unencodable_event_addr() {
+        // A 'd' longer than 255 bytes cannot be put in an naddr
+        let event_addr = EventAddr {
+            d: "x".repeat(300),
+            relays: vec![],
+            kind: EventKind::LongFormContent,
+            author: PublicKey::mock(),
+        };
+        assert!(NostrBech32::new_event_addr(event_addr.clone()).is_err());
+
+        // but if built anyway, it must not fail to display
+        let url = NostrUrl(NostrBech32::EventAddr(event_addr));
+        assert_eq!(url.to_string(), "nostr:");
+        assert!(serde_json::to_string(&url).is_ok());
+    }
+
     #[test]
     fn test_nostr_url_unicode_issues() {
         let sample = r#"🌝🐸note1fntxtkcy9pjwucqwa9mddn7v03wwwsu9j330jj350nvhpky2tuaspk6nqc"#;
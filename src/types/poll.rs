@@ -0,0 +1,449 @@
+use super::{Event, EventKind, Id, PreEvent, PublicKey, Tag, UncheckedUrl, Unixtime};
+use crate::Error;
+use serde::{Deserialize, Serialize};
+#[cfg(feature = "speedy")]
+use speedy::{Readable, Writable};
+use std::collections::{HashMap, HashSet};
+
+/// Whether a poll allows one or several options to be chosen (NIP-88)
+#[derive(Clone, Copy, Debug, Default, Deserialize, Eq, PartialEq, Serialize)]
+#[cfg_attr(feature = "speedy", derive(Readable, Writable))]
+pub enum PollType {
+    /// Only one option may be chosen. This is the default.
+    #[default]
+    SingleChoice,
+
+    /// Any number of options may be chosen
+    MultipleChoice,
+}
+
+impl PollType {
+    /// The string used in the 'polltype' tag
+    pub fn as_str(&self) -> &'static str {
+        match *self {
+            PollType::SingleChoice => "singlechoice",
+            PollType::MultipleChoice => "multiplechoice",
+        }
+    }
+}
+
+/// An option in a poll
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+#[cfg_attr(feature = "speedy", derive(Readable, Writable))]
+pub struct PollOption {
+    /// The option id, which responses refer to
+    pub id: String,
+
+    /// The option label shown to the user
+    pub label: String,
+}
+
+/// A poll (kind 1068, NIP-88)
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+#[cfg_attr(feature = "speedy", derive(Readable, Writable))]
+pub struct Poll {
+    /// The id of the poll event
+    pub id: Id,
+
+    /// The author of the poll
+    pub pubkey: PublicKey,
+
+    /// The question (the content of the poll event)
+    pub question: String,
+
+    /// The options that may be chosen
+    pub options: Vec<PollOption>,
+
+    /// Whether one or several options may be chosen
+    pub poll_type: PollType,
+
+    /// When the poll stops accepting responses
+    pub ends_at: Option<Unixtime>,
+
+    /// Relays where responses should be published and looked for
+    pub relays: Vec<UncheckedUrl>,
+}
+
+/// A response to a poll (kind 1018, NIP-88)
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+#[cfg_attr(feature = "speedy", derive(Readable, Writable))]
+pub struct PollResponse {
+    /// The author of the response
+    pub pubkey: PublicKey,
+
+    /// When the response was made
+    pub created_at: Unixtime,
+
+    /// The id of the poll event being responded to
+    pub poll_id: Id,
+
+    /// The ids of the chosen options
+    pub choices: Vec<String>,
+}
+
+/// The result of counting responses to a poll
+#[derive(Clone, Debug, Default, Deserialize, Eq, PartialEq, Serialize)]
+pub struct PollTally {
+    /// Number of votes for each option id, in the order the poll lists its options
+    pub counts: Vec<(String, u64)>,
+
+    /// Number of distinct pubkeys whose vote was counted
+    pub voters: u64,
+}
+
+impl Poll {
+    // Mock data for testing
+    #[allow(dead_code)]
+    pub(crate) fn mock() -> Poll {
+        Poll {
+            id: Id::mock(),
+            pubkey: PublicKey::mock(),
+            question: "Pineapple on pizza?".to_owned(),
+            options: vec![
+                PollOption {
+                    id: "yes".to_owned(),
+                    label: "Yes".to_owned(),
+                },
+                PollOption {
+                    id: "no".to_owned(),
+                    label: "No".to_owned(),
+                },
+            ],
+            poll_type: PollType::SingleChoice,
+            ends_at: Some(Unixtime(1_700_000_000)),
+            relays: vec![UncheckedUrl::mock()],
+        }
+    }
+
+    /// Create a PreEvent for a new poll
+    pub fn new_pre_event(
+        pubkey: PublicKey,
+        question: &str,
+        options: &[PollOption],
+        poll_type: PollType,
+        ends_at: Option<Unixtime>,
+        relays: &[UncheckedUrl],
+    ) -> Result<PreEvent, Error> {
+        let mut tags: Vec<Tag> = Vec::new();
+        for option in options {
+            tags.push(Tag::Other {
+                tag: "option".to_owned(),
+                data: vec![option.id.clone(), option.label.clone()],
+            });
+        }
+        for relay in relays {
+            tags.push(Tag::Other {
+                tag: "relay".to_owned(),
+                data: vec![relay.0.clone()],
+            });
+        }
+        tags.push(Tag::Other {
+            tag: "polltype".to_owned(),
+            data: vec![poll_type.as_str().to_owned()],
+        });
+        if let Some(ends_at) = ends_at {
+            tags.push(Tag::Other {
+                tag: "endsAt".to_owned(),
+                data: vec![format!("{}", ends_at.0)],
+            });
+        }
+
+        Ok(PreEvent {
+            pubkey,
+            created_at: Unixtime::now()?,
+            kind: EventKind::Poll,
+            tags,
+            content: question.to_owned(),
+            ots: None,
+        })
+    }
+
+    /// Read a poll from an event
+    pub fn try_from_event(event: &Event) -> Result<Poll, Error> {
+        if event.kind != EventKind::Poll {
+            return Err(Error::WrongEventKind);
+        }
+
+        let mut options: Vec<PollOption> = Vec::new();
+        let mut poll_type = PollType::SingleChoice;
+        let mut ends_at: Option<Unixtime> = None;
+        let mut relays: Vec<UncheckedUrl> = Vec::new();
+
+        for tag in event.tags.iter() {
+            if let Tag::Other { tag, data } = tag {
+                match (tag.as_str(), data.len()) {
+                    ("option", n) if n >= 2 => {
+                        if options.iter().any(|o| o.id == data[0]) {
+                            return Err(Error::InvalidPoll(format!(
+                                "Duplicate option id {}",
+                                data[0]
+                            )));
+                        }
+                        options.push(PollOption {
+                            id: data[0].clone(),
+                            label: data[1].clone(),
+                        });
+                    }
+                    ("relay", n) if n >= 1 => relays.push(UncheckedUrl(data[0].clone())),
+                    ("polltype", n) if n >= 1 => {
+                        poll_type = match data[0].as_str() {
+                            "singlechoice" => PollType::SingleChoice,
+                            "multiplechoice" => PollType::MultipleChoice,
+                            other => {
+                                return Err(Error::InvalidPoll(format!(
+                                    "Unknown poll type {other}"
+                                )))
+                            }
+                        };
+                    }
+                    ("endsAt", n) if n >= 1 => {
+                        let t = data[0].parse::<i64>()?;
+                        ends_at = Some(Unixtime(t));
+                    }
+                    _ => {}
+                }
+            }
+        }
+
+        if options.is_empty() {
+            return Err(Error::InvalidPoll("Poll has no options".to_owned()));
+        }
+
+        Ok(Poll {
+            id: event.id,
+            pubkey: event.pubkey,
+            question: event.content.clone(),
+            options,
+            poll_type,
+            ends_at,
+            relays,
+        })
+    }
+
+    /// Whether the poll has ended as of `now`
+    pub fn has_ended(&self, now: Unixtime) -> bool {
+        match self.ends_at {
+            Some(ends_at) => now > ends_at,
+            None => false,
+        }
+    }
+
+    /// Count the responses to this poll.
+    ///
+    /// Only one vote is counted per pubkey: their latest response made before the
+    /// poll ended. Responses to other polls and choices that are not options of
+    /// this poll are ignored. For single choice polls only the first valid choice
+    /// of a response is counted.
+    pub fn tally(&self, responses: &[PollResponse]) -> PollTally {
+        let valid_ids: HashSet<&str> = self.options.iter().map(|o| o.id.as_str()).collect();
+
+        // Find the latest eligible response for each pubkey
+        let mut latest: HashMap<PublicKey, &PollResponse> = HashMap::new();
+        for response in responses {
+            if response.poll_id != self.id {
+                continue;
+            }
+            if let Some(ends_at) = self.ends_at {
+                if response.created_at > ends_at {
+                    continue;
+                }
+            }
+            match latest.get(&response.pubkey) {
+                Some(existing) if existing.created_at >= response.created_at => {}
+                _ => {
+                    let _ = latest.insert(response.pubkey, response);
+                }
+            }
+        }
+
+        let mut counts: HashMap<&str, u64> = HashMap::new();
+        let mut voters: u64 = 0;
+        for response in latest.values() {
+            let mut chosen: Vec<&str> = Vec::new();
+            for choice in response.choices.iter() {
+                if valid_ids.contains(choice.as_str()) && !chosen.contains(&choice.as_str()) {
+                    chosen.push(choice);
+                }
+            }
+            if self.poll_type == PollType::SingleChoice {
+                chosen.truncate(1);
+            }
+            if chosen.is_empty() {
+                continue;
+            }
+            voters += 1;
+            for choice in chosen {
+                *counts.entry(choice).or_insert(0) += 1;
+            }
+        }
+
+        PollTally {
+            counts: self
+                .options
+                .iter()
+                .map(|o| {
+                    (
+                        o.id.clone(),
+                        counts.get(o.id.as_str()).copied().unwrap_or(0),
+                    )
+                })
+                .collect(),
+            voters,
+        }
+    }
+}
+
+impl PollResponse {
+    // Mock data for testing
+    #[allow(dead_code)]
+    pub(crate) fn mock() -> PollResponse {
+        PollResponse {
+            pubkey: PublicKey::mock(),
+            created_at: Unixtime::mock(),
+            poll_id: Id::mock(),
+            choices: vec!["yes".to_owned()],
+        }
+    }
+
+    /// Create a PreEvent for this response
+    pub fn to_pre_event(&self) -> PreEvent {
+        let mut tags: Vec<Tag> = vec![Tag::Event {
+            id: self.poll_id,
+            recommended_relay_url: None,
+            marker: None,
+        }];
+        for choice in self.choices.iter() {
+            tags.push(Tag::Other {
+                tag: "response".to_owned(),
+                data: vec![choice.clone()],
+            });
+        }
+
+        PreEvent {
+            pubkey: self.pubkey,
+            created_at: self.created_at,
+            kind: EventKind::PollResponse,
+            tags,
+            content: "".to_owned(),
+            ots: None,
+        }
+    }
+
+    /// Read a poll response from an event
+    pub fn try_from_event(event: &Event) -> Result<PollResponse, Error> {
+        if event.kind != EventKind::PollResponse {
+            return Err(Error::WrongEventKind);
+        }
+
+        let mut poll_id: Option<Id> = None;
+        let mut choices: Vec<String> = Vec::new();
+        for tag in event.tags.iter() {
+            match tag {
+                Tag::Event { id, .. } if poll_id.is_none() => {
+                    poll_id = Some(*id);
+                }
+                Tag::Other { tag, data } if tag == "response" && !data.is_empty() => {
+                    choices.push(data[0].clone());
+                }
+                _ => {}
+            }
+        }
+
+        let poll_id = match poll_id {
+            Some(id) => id,
+            None => {
+                return Err(Error::InvalidPoll(
+                    "Poll response does not reference a poll".to_owned(),
+                ))
+            }
+        };
+
+        Ok(PollResponse {
+            pubkey: event.pubkey,
+            created_at: event.created_at,
+            poll_id,
+            choices,
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::PrivateKey;
+
+    test_serde! {Poll, test_poll_serde}
+    test_serde! {PollResponse, test_poll_response_serde}
+
+    fn response(pubkey: PublicKey, poll_id: Id, at: i64, choices: &[&str]) -> PollResponse {
+        PollResponse {
+            pubkey,
+            created_at: Unixtime(at),
+            poll_id,
+            choices: choices.iter().map(|c| c.to_string()).collect(),
+        }
+    }
+
+    #[test]
+    fn test_poll_event_round_trip() {
+        let private_key = PrivateKey::mock();
+        let mock = Poll::mock();
+        let pre_event = Poll::new_pre_event(
+            private_key.public_key(),
+            &mock.question,
+            &mock.options,
+            PollType::MultipleChoice,
+            mock.ends_at,
+            &mock.relays,
+        )
+        .unwrap();
+        let event = Event::new(pre_event, &private_key).unwrap();
+        let poll = Poll::try_from_event(&event).unwrap();
+        assert_eq!(poll.options, mock.options);
+        assert_eq!(poll.poll_type, PollType::MultipleChoice);
+        assert_eq!(poll.ends_at, mock.ends_at);
+        assert_eq!(poll.relays, mock.relays);
+
+        let response = response(private_key.public_key(), poll.id, 1_600_000_000, &["no"]);
+        let event = Event::new(response.to_pre_event(), &private_key).unwrap();
+        assert_eq!(PollResponse::try_from_event(&event).unwrap(), response);
+    }
+
+    #[test]
+    fn test_poll_tally() {
+        let poll = Poll::mock();
+        let alice = PrivateKey::generate().public_key();
+        let bob = PrivateKey::generate().public_key();
+        let carol = PrivateKey::generate().public_key();
+        let dave = PrivateKey::generate().public_key();
+
+        let responses = vec![
+            // alice changed her mind; only the latest counts
+            response(alice, poll.id, 1_600_000_000, &["no"]),
+            response(alice, poll.id, 1_600_000_100, &["yes"]),
+            // bob chose two, but this is single choice
+            response(bob, poll.id, 1_600_000_000, &["no", "yes"]),
+            // carol voted too late
+            response(carol, poll.id, 1_800_000_000, &["no"]),
+            // dave chose something that isn't an option
+            response(dave, poll.id, 1_600_000_000, &["maybe"]),
+            // a response to another poll
+            response(dave, Id([1; 32]), 1_600_000_000, &["yes"]),
+        ];
+
+        let tally = poll.tally(&responses);
+        assert_eq!(tally.voters, 2);
+        assert_eq!(
+            tally.counts,
+            vec![("yes".to_owned(), 1), ("no".to_owned(), 1)]
+        );
+
+        let mut poll = poll;
+        poll.poll_type = PollType::MultipleChoice;
+        let tally = poll.tally(&responses);
+        assert_eq!(
+            tally.counts,
+            vec![("yes".to_owned(), 2), ("no".to_owned(), 1)]
+        );
+    }
+}
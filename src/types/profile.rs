@@ -1,14 +1,16 @@
-use super::{PublicKey, UncheckedUrl};
+use super::{PublicKey, Tag, UncheckedUrl};
 use crate::Error;
 use bech32::{FromBase32, ToBase32};
 use serde::{Deserialize, Serialize};
 #[cfg(feature = "speedy")]
 use speedy::{Readable, Writable};
 
-/// A person's profile on nostr which consists of the data needed in order to follow someone.
+/// A person on nostr, and where to find them: the data needed in order to
+/// follow someone. This is what an "nprofile" encodes (NIP-19), what a 'p' tag
+/// with a relay hint refers to, and what a NIP-05 lookup finds.
 #[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq)]
 #[cfg_attr(feature = "speedy", derive(Readable, Writable))]
-pub struct Profile {
+pub struct ProfilePointer {
     /// Their public key
     pub pubkey: PublicKey,
 
@@ -16,8 +18,15 @@ pub struct Profile {
     pub relays: Vec<UncheckedUrl>,
 }
 
-impl Profile {
-    /// Export as a bech32 encoded string ("nprofile")
+/// The former name of `ProfilePointer`
+pub type Profile = ProfilePointer;
+
+/// The NIP-19 name for a `ProfilePointer`
+pub type NProfile = ProfilePointer;
+
+impl ProfilePointer {
+    /// Export as a bech32 encoded string ("nprofile"). Relay URLs longer than
+    /// 255 bytes cannot be encoded, and are left out.
     pub fn as_bech32_string(&self) -> String {
         // Compose
         let mut tlv: Vec<u8> = Vec::new();
@@ -29,8 +38,11 @@ impl Profile {
 
         // Push relays
         for relay in &self.relays {
+            let Ok(len) = u8::try_from(relay.0.len()) else {
+                continue;
+            };
             tlv.push(1); // type 'relay'
-            tlv.push(relay.0.len() as u8); // the length of the string
+            tlv.push(len); // the length of the string
             tlv.extend(relay.0.as_bytes());
         }
 
@@ -38,7 +50,7 @@ impl Profile {
     }
 
     /// Import from a bech32 encoded string ("nprofile")
-    pub fn try_from_bech32_string(s: &str) -> Result<Profile, Error> {
+    pub fn try_from_bech32_string(s: &str) -> Result<ProfilePointer, Error> {
         let data = bech32::decode(s)?;
         if data.0 != "nprofile" {
             Err(Error::WrongBech32("nprofile".to_string(), data.0))
@@ -49,7 +61,7 @@ impl Profile {
             let mut pos = 0;
             loop {
                 // we need at least 2 more characters for anything meaningful
-                if pos > tlv.len() - 2 {
+                if pos + 2 > tlv.len() {
                     break;
                 }
                 let ty = tlv[pos];
@@ -78,22 +90,47 @@ impl Profile {
                 pos += len;
             }
             if let Some(pubkey) = pubkey {
-                Ok(Profile { pubkey, relays })
+                Ok(ProfilePointer { pubkey, relays })
             } else {
                 Err(Error::InvalidProfile)
             }
         }
     }
 
+    /// Convert into a 'p' tag, with the first relay (if any) as the
+    /// recommended relay URL
+    pub fn to_tag(&self) -> Tag {
+        Tag::Pubkey {
+            pubkey: self.pubkey.into(),
+            recommended_relay_url: self.relays.first().cloned(),
+            petname: None,
+        }
+    }
+
+    /// Convert from a 'p' tag
+    pub fn try_from_tag(tag: &Tag) -> Result<ProfilePointer, Error> {
+        match tag {
+            Tag::Pubkey {
+                pubkey,
+                recommended_relay_url,
+                ..
+            } => Ok(ProfilePointer {
+                pubkey: PublicKey::try_from(pubkey.clone())?,
+                relays: recommended_relay_url.iter().cloned().collect(),
+            }),
+            _ => Err(Error::InvalidProfile),
+        }
+    }
+
     // Mock data for testing
     #[allow(dead_code)]
-    pub(crate) fn mock() -> Profile {
+    pub(crate) fn mock() -> ProfilePointer {
         let pubkey = PublicKey::try_from_hex_string(
             "b0635d6a9851d3aed0cd6c495b282167acf761729078d975fc341b22650b07b9",
         )
         .unwrap();
 
-        Profile {
+        ProfilePointer {
             pubkey,
             relays: vec![
                 UncheckedUrl::from_str("wss://relay.example.com"),
@@ -103,6 +140,15 @@ impl Profile {
     }
 }
 
+impl From<PublicKey> for ProfilePointer {
+    fn from(pubkey: PublicKey) -> ProfilePointer {
+        ProfilePointer {
+            pubkey,
+            relays: vec![],
+        }
+    }
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
@@ -119,6 +165,41 @@ mod test {
         );
     }
 
+    #[test]
+    fn test_profile_pointer_tag() {
+        let pointer = ProfilePointer::mock();
+        let tag = pointer.to_tag();
+        let from_tag = ProfilePointer::try_from_tag(&tag).unwrap();
+        assert_eq!(from_tag.pubkey, pointer.pubkey);
+        assert_eq!(from_tag.relays, pointer.relays[..1]);
+        assert!(ProfilePointer::try_from_tag(&Tag::Hashtag("nostr".to_owned())).is_err());
+
+        let bare = ProfilePointer::from(pointer.pubkey);
+        assert_eq!(ProfilePointer::try_from_tag(&bare.to_tag()).unwrap(), bare);
+        assert_eq!(
+            NProfile::try_from_bech32_string(&bare.as_bech32_string()).unwrap(),
+            bare
+        );
+    }
+
+    #[test]
+    fn test_profile_bech32_malformed() {
+        let encode = |tlv: &[u8]| {
+            bech32::encode("nprofile", tlv.to_base32(), bech32::Variant::Bech32).unwrap()
+        };
+        assert!(Profile::try_from_bech32_string(&encode(&[])).is_err());
+        assert!(Profile::try_from_bech32_string(&encode(&[0])).is_err());
+        assert!(Profile::try_from_bech32_string(&encode(&[0, 32, 1, 2])).is_err());
+
+        // Relays too long to encode are left out
+        let mut profile = Profile::mock();
+        profile
+            .relays
+            .push(UncheckedUrl(format!("wss://{}.com", "x".repeat(300))));
+        let decoded = Profile::try_from_bech32_string(&profile.as_bech32_string()).unwrap();
+        assert_eq!(decoded, Profile::mock());
+    }
+
     #[test]
     fn test_nip19_example() {
         let profile = Profile {
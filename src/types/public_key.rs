@@ -1,7 +1,10 @@
-use crate::{Error, PrivateKey, Signature};
+use super::abbrev::abbreviate_bech32;
+use super::fast_hex;
+use crate::{Error, Id, PrivateKey, ProfilePointer, Signature, UncheckedUrl};
 use bech32::{FromBase32, ToBase32};
 use derive_more::{AsMut, AsRef, Deref, Display, From, FromStr, Into};
 use k256::ecdsa::signature::Verifier;
+use k256::schnorr::signature::hazmat::PrehashVerifier;
 use k256::schnorr::VerifyingKey;
 use serde::de::{Deserializer, Visitor};
 use serde::ser::Serializer;
@@ -20,17 +23,31 @@ impl PublicKey {
     ///
     /// Consider converting `.into()` a `PublicKeyHex` which is a wrapped type rather than a naked `String`
     pub fn as_hex_string(&self) -> String {
-        hex::encode(self.0.to_bytes())
+        fast_hex::encode(&self.0.to_bytes())
+    }
+
+    /// Write as a hexadecimal string, without allocating
+    pub fn write_hex<W: fmt::Write + ?Sized>(&self, w: &mut W) -> fmt::Result {
+        fast_hex::write_hex(&self.0.to_bytes(), w)
     }
 
     /// Create from a hexadecimal string
+    ///
+    /// This also accepts 33-byte compressed keys (see `try_from_compressed_bytes()`)
     pub fn try_from_hex_string(v: &str) -> Result<PublicKey, Error> {
+        if let Some(bytes) = fast_hex::decode_array::<32>(v) {
+            return PublicKey::from_bytes(&bytes);
+        }
         let vec: Vec<u8> = hex::decode(v)?;
-        // if it's not 32 bytes, dont even try because k256 code has panics in it
-        if vec.len() != 32 {
-            Err(Error::InvalidPublicKey)
-        } else {
-            Ok(PublicKey(VerifyingKey::from_bytes(&vec)?))
+        PublicKey::from_bytes(&vec)
+    }
+
+    /// Create from a 33-byte compressed key (with a 02 or 03 parity prefix),
+    /// normalizing it to the x-only key used by nostr
+    pub fn try_from_compressed_bytes(bytes: &[u8]) -> Result<PublicKey, Error> {
+        match bytes.split_first() {
+            Some((0x02 | 0x03, x)) if x.len() == 32 => Ok(PublicKey(VerifyingKey::from_bytes(x)?)),
+            _ => Err(Error::InvalidPublicKey),
         }
     }
 
@@ -44,6 +61,22 @@ impl PublicKey {
         .unwrap()
     }
 
+    /// Abbreviate the bech32 "npub" for display, keeping `n` characters of the
+    /// data at each end, like `npub1abcd…wxyz`
+    pub fn abbrev(&self, n: usize) -> String {
+        abbreviate_bech32(&self.as_bech32_string(), n)
+    }
+
+    /// Export as a bech32 encoded "nprofile" string, with some relays the key
+    /// may be found at (NIP-19). See `ProfilePointer`.
+    pub fn as_nprofile(&self, relays: Vec<UncheckedUrl>) -> String {
+        ProfilePointer {
+            pubkey: *self,
+            relays,
+        }
+        .as_bech32_string()
+    }
+
     /// Import from a bech32 encoded string
     pub fn try_from_bech32_string(s: &str) -> Result<PublicKey, Error> {
         let data = bech32::decode(s)?;
@@ -60,11 +93,14 @@ impl PublicKey {
     }
 
     /// Import from raw bytes
+    ///
+    /// This also accepts 33-byte compressed keys (see `try_from_compressed_bytes()`)
     pub fn from_bytes(bytes: &[u8]) -> Result<PublicKey, Error> {
-        if bytes.len() != 32 {
-            Err(Error::InvalidPublicKey)
-        } else {
-            Ok(PublicKey(VerifyingKey::from_bytes(bytes)?))
+        // if it's not 32 bytes, dont even try because k256 code has panics in it
+        match bytes.len() {
+            32 => Ok(PublicKey(VerifyingKey::from_bytes(bytes)?)),
+            33 => PublicKey::try_from_compressed_bytes(bytes),
+            _ => Err(Error::InvalidPublicKey),
         }
     }
 
@@ -78,6 +114,11 @@ impl PublicKey {
         Ok(self.0.verify(message, &signature.0)?)
     }
 
+    /// Verify a signature over a 32-byte hash, such as an event Id
+    pub fn verify_id(&self, id: Id, signature: Signature) -> Result<(), Error> {
+        Ok(self.0.verify_prehash(&id.0, &signature.0)?)
+    }
+
     // Mock data for testing
     #[allow(dead_code)]
     pub(crate) fn mock() -> PublicKey {
@@ -85,12 +126,39 @@ impl PublicKey {
     }
 }
 
+/// Displays as hex, or with the alternate flag (`{:#}`) as an abbreviated
+/// "npub" (see `abbrev()`)
+impl fmt::Display for PublicKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if f.alternate() {
+            write!(f, "{}", self.abbrev(4))
+        } else {
+            self.write_hex(f)
+        }
+    }
+}
+
+impl TryFrom<&str> for PublicKey {
+    type Error = Error;
+
+    /// Accepts hex, a bech32 "npub", or a bech32 "nprofile" (taking its key)
+    fn try_from(s: &str) -> Result<PublicKey, Error> {
+        if s.starts_with("npub1") {
+            PublicKey::try_from_bech32_string(s)
+        } else if s.starts_with("nprofile1") {
+            Ok(ProfilePointer::try_from_bech32_string(s)?.pubkey)
+        } else {
+            PublicKey::try_from_hex_string(s)
+        }
+    }
+}
+
 impl Serialize for PublicKey {
     fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
     where
         S: Serializer,
     {
-        serializer.serialize_str(&format!("{:x}", self.0.to_bytes()))
+        fast_hex::with_hex(&self.0.to_bytes(), |hex| serializer.serialize_str(hex))
     }
 }
 
@@ -109,30 +177,34 @@ impl Visitor<'_> for PublicKeyVisitor {
     type Value = PublicKey;
 
     fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        write!(f, "a hexadecimal string representing 32 bytes")
+        write!(f, "a hexadecimal string representing 32 or 33 bytes")
     }
 
     fn visit_str<E>(self, v: &str) -> Result<PublicKey, E>
     where
         E: serde::de::Error,
     {
+        if let Some(bytes) = fast_hex::decode_array::<32>(v) {
+            return PublicKey::from_bytes(&bytes)
+                .map_err(|e| serde::de::Error::custom(format!("{e}")));
+        }
         let vec: Vec<u8> = hex::decode(v).map_err(|e| serde::de::Error::custom(format!("{e}")))?;
 
         // If we don't catch this ourselves, the below from_bytes will panic when it
         // gets into an assertion within generic-array
-        if vec.len() != 32 {
-            return Err(serde::de::Error::custom("Public key is not 32 bytes long"));
+        if vec.len() != 32 && vec.len() != 33 {
+            return Err(serde::de::Error::custom(
+                "Public key is not 32 or 33 bytes long",
+            ));
         }
 
-        Ok(PublicKey(
-            VerifyingKey::from_bytes(&vec).map_err(|e| serde::de::Error::custom(format!("{e}")))?,
-        ))
+        PublicKey::from_bytes(&vec).map_err(|e| serde::de::Error::custom(format!("{e}")))
     }
 }
 
 impl Hash for PublicKey {
     fn hash<H: Hasher>(&self, state: &mut H) {
-        self.as_hex_string().hash(state);
+        fast_hex::with_hex(&self.0.to_bytes(), |hex| hex.hash(state));
     }
 }
 
@@ -140,9 +212,11 @@ impl Hash for PublicKey {
 impl<'a, C: Context> Readable<'a, C> for PublicKey {
     #[inline]
     fn read_from<R: Reader<'a, C>>(reader: &mut R) -> Result<Self, C::Error> {
-        let bytes = <[u8; 32]>::read_from(reader)?;
-        let vk = VerifyingKey::from_bytes(&bytes).map_err(|e| speedy::Error::custom(e))?;
-        Ok(PublicKey(vk))
+        let mut bytes = [0_u8; 32];
+        reader.read_bytes(&mut bytes)?;
+        Ok(PublicKey(
+            VerifyingKey::from_bytes(&bytes).map_err(|e| speedy::Error::custom(e))?,
+        ))
     }
 
     #[inline]
@@ -155,7 +229,7 @@ impl<'a, C: Context> Readable<'a, C> for PublicKey {
 impl<C: Context> Writable<C> for PublicKey {
     #[inline]
     fn write_to<T: ?Sized + Writer<C>>(&self, writer: &mut T) -> Result<(), C::Error> {
-        self.0.to_bytes().write_to(writer)
+        writer.write_bytes(&self.0.to_bytes())
     }
 
     #[inline]
@@ -204,8 +278,8 @@ impl PublicKeyHex {
         Self::try_from_string(s.to_owned())
     }
 
-    /// Try from String
-    pub fn try_from_string(s: String) -> Result<PublicKeyHex, Error> {
+    /// Try from String. The hex is lowercased.
+    pub fn try_from_string(mut s: String) -> Result<PublicKeyHex, Error> {
         if s.len() != 64 {
             return Err(Error::InvalidPublicKey);
         }
@@ -213,6 +287,7 @@ impl PublicKeyHex {
         if vec.len() != 32 {
             return Err(Error::InvalidPublicKey);
         }
+        s.make_ascii_lowercase();
         Ok(PublicKeyHex(s))
     }
 
@@ -257,23 +332,148 @@ impl TryFrom<PublicKeyHex> for PublicKey {
     }
 }
 
+/// This is a public key, which identifies an actor (usually a person) and is shared,
+/// stored as its raw 32 x-only bytes.
+///
+/// Unlike `PublicKey`, this is not checked to be a valid curve point when it is
+/// created or deserialized, which makes it cheap to handle in bulk. Convert it to
+/// a `PublicKey` with `TryFrom`/`TryInto` when you need to verify with it.
+#[derive(
+    AsMut, AsRef, Clone, Copy, Debug, Deref, Eq, From, Hash, Into, Ord, PartialEq, PartialOrd,
+)]
+pub struct XOnlyPublicKey(pub [u8; 32]);
+
+impl XOnlyPublicKey {
+    /// Render into a hexadecimal string
+    pub fn as_hex_string(&self) -> String {
+        fast_hex::encode(&self.0)
+    }
+
+    /// Write as a hexadecimal string, without allocating
+    pub fn write_hex<W: fmt::Write + ?Sized>(&self, w: &mut W) -> fmt::Result {
+        fast_hex::write_hex(&self.0, w)
+    }
+
+    /// Create from a hexadecimal string
+    pub fn try_from_hex_string(v: &str) -> Result<XOnlyPublicKey, Error> {
+        if let Some(bytes) = fast_hex::decode_array(v) {
+            return Ok(XOnlyPublicKey(bytes));
+        }
+        let vec: Vec<u8> = hex::decode(v)?;
+        Ok(XOnlyPublicKey(
+            vec.try_into().map_err(|_| Error::WrongLengthHexString)?,
+        ))
+    }
+
+    /// Export as a bech32 encoded string
+    pub fn as_bech32_string(&self) -> String {
+        bech32::encode("npub", self.0.to_vec().to_base32(), bech32::Variant::Bech32).unwrap()
+    }
+
+    // Mock data for testing
+    #[allow(dead_code)]
+    pub(crate) fn mock() -> XOnlyPublicKey {
+        From::from(PublicKey::mock())
+    }
+}
+
+#[cfg(feature = "speedy")]
+impl<'a, C: Context> Readable<'a, C> for XOnlyPublicKey {
+    #[inline]
+    fn read_from<R: Reader<'a, C>>(reader: &mut R) -> Result<Self, C::Error> {
+        let mut bytes = [0_u8; 32];
+        reader.read_bytes(&mut bytes)?;
+        Ok(XOnlyPublicKey(bytes))
+    }
+
+    #[inline]
+    fn minimum_bytes_needed() -> usize {
+        32
+    }
+}
+
+#[cfg(feature = "speedy")]
+impl<C: Context> Writable<C> for XOnlyPublicKey {
+    #[inline]
+    fn write_to<T: ?Sized + Writer<C>>(&self, writer: &mut T) -> Result<(), C::Error> {
+        writer.write_bytes(&self.0)
+    }
+
+    #[inline]
+    fn bytes_needed(&self) -> Result<usize, C::Error> {
+        Ok(32)
+    }
+}
+
+impl From<PublicKey> for XOnlyPublicKey {
+    fn from(pk: PublicKey) -> XOnlyPublicKey {
+        XOnlyPublicKey(pk.0.to_bytes().into())
+    }
+}
+
+impl TryFrom<XOnlyPublicKey> for PublicKey {
+    type Error = Error;
+
+    fn try_from(xonly: XOnlyPublicKey) -> Result<PublicKey, Error> {
+        PublicKey::from_bytes(&xonly.0)
+    }
+}
+
+impl From<XOnlyPublicKey> for PublicKeyHex {
+    fn from(xonly: XOnlyPublicKey) -> PublicKeyHex {
+        PublicKeyHex(xonly.as_hex_string())
+    }
+}
+
+impl Serialize for XOnlyPublicKey {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        fast_hex::with_hex(&self.0, |hex| serializer.serialize_str(hex))
+    }
+}
+
+impl<'de> Deserialize<'de> for XOnlyPublicKey {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        deserializer.deserialize_str(XOnlyPublicKeyVisitor)
+    }
+}
+
+struct XOnlyPublicKeyVisitor;
+
+impl Visitor<'_> for XOnlyPublicKeyVisitor {
+    type Value = XOnlyPublicKey;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "a hexadecimal string representing 32 bytes")
+    }
+
+    fn visit_str<E>(self, v: &str) -> Result<XOnlyPublicKey, E>
+    where
+        E: serde::de::Error,
+    {
+        if let Some(bytes) = fast_hex::decode_array(v) {
+            return Ok(XOnlyPublicKey(bytes));
+        }
+        let vec: Vec<u8> = hex::decode(v).map_err(|e| serde::de::Error::custom(format!("{e}")))?;
+
+        Ok(XOnlyPublicKey(vec.try_into().map_err(|e: Vec<u8>| {
+            E::custom(format!(
+                "Public key is not 32 bytes long. Was {} bytes long",
+                e.len()
+            ))
+        })?))
+    }
+}
+
 /// This is a public key prefix, which identifies an actor (usually a person) and is shared, as a hex string
 ///
 #[derive(
-    AsMut,
-    AsRef,
-    Clone,
-    Debug,
-    Deref,
-    Deserialize,
-    Display,
-    Eq,
-    From,
-    FromStr,
-    Hash,
-    Into,
-    PartialEq,
-    Serialize,
+    AsMut, AsRef, Clone, Debug, Deref, Display, Eq, From, FromStr, Hash, Into, PartialEq, Serialize,
 )]
 #[cfg_attr(feature = "speedy", derive(Readable, Writable))]
 pub struct PublicKeyHexPrefix(String);
@@ -290,8 +490,8 @@ impl PublicKeyHexPrefix {
         Self::try_from_string(s.to_owned())
     }
 
-    /// Try from String
-    pub fn try_from_string(s: String) -> Result<PublicKeyHexPrefix, Error> {
+    /// Try from String. The hex is lowercased.
+    pub fn try_from_string(mut s: String) -> Result<PublicKeyHexPrefix, Error> {
         if s.len() > 64 {
             return Err(Error::InvalidPublicKeyPrefix);
         }
@@ -302,6 +502,7 @@ impl PublicKeyHexPrefix {
         // if vec.len() > 32 {
         //    return Err(Error::InvalidPublicKeyPrefix);
         // }
+        s.make_ascii_lowercase();
         Ok(PublicKeyHexPrefix(s))
     }
 
@@ -335,13 +536,77 @@ impl TryFrom<&str> for PublicKeyHexPrefix {
     }
 }
 
+impl<'de> Deserialize<'de> for PublicKeyHexPrefix {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        deserializer.deserialize_str(PublicKeyHexPrefixVisitor)
+    }
+}
+
+struct PublicKeyHexPrefixVisitor;
+
+impl Visitor<'_> for PublicKeyHexPrefixVisitor {
+    type Value = PublicKeyHexPrefix;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "a hexadecimal string of at most 64 characters, a public key or a prefix of one"
+        )
+    }
+
+    fn visit_str<E>(self, v: &str) -> Result<PublicKeyHexPrefix, E>
+    where
+        E: serde::de::Error,
+    {
+        PublicKeyHexPrefix::try_from_str(v).map_err(|e| E::custom(format!("{e}")))
+    }
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
 
+    #[test]
+    fn test_pubkey_abbrev() {
+        let pk = PublicKey::mock();
+        let npub = pk.as_bech32_string();
+        let abbrev = pk.abbrev(6);
+        assert_eq!(abbrev.chars().count(), 5 + 6 + 1 + 6);
+        assert!(abbrev.starts_with(npub.get(..11).unwrap()));
+        assert!(abbrev.ends_with(npub.get(npub.len() - 6..).unwrap()));
+        assert_eq!(pk.abbrev(0), "npub1…");
+        assert_eq!(pk.abbrev(29), npub);
+
+        assert_eq!(format!("{pk}"), pk.as_hex_string());
+        assert_eq!(format!("{pk:#}"), pk.abbrev(4));
+    }
+
+    #[test]
+    fn test_pubkey_try_from_str() {
+        let pk = PublicKey::mock();
+        assert_eq!(
+            PublicKey::try_from(pk.as_hex_string().as_str()).unwrap(),
+            pk
+        );
+        assert_eq!(
+            PublicKey::try_from(pk.as_bech32_string().as_str()).unwrap(),
+            pk
+        );
+        let nprofile = pk.as_nprofile(vec![UncheckedUrl::from_str("wss://r.example.com")]);
+        assert_eq!(PublicKey::try_from(nprofile.as_str()).unwrap(), pk);
+
+        assert!(PublicKey::try_from("npub1abc").is_err());
+        assert!(PublicKey::try_from("abcd").is_err());
+        assert!(PublicKey::try_from(Id::mock().as_bech32_string().as_str()).is_err());
+    }
+
     test_serde! {PublicKey, test_public_key_serde}
     test_serde! {PublicKeyHex, test_public_key_hex_serde}
     test_serde! {PublicKeyHexPrefix, test_public_key_hex_prefix_serde}
+    test_serde! {XOnlyPublicKey, test_xonly_public_key_serde}
 
     #[test]
     fn test_pubkey_bech32() {
@@ -354,4 +619,55 @@ mod test {
 
         assert_eq!(pk, decoded);
     }
+
+    #[test]
+    fn test_pubkey_nprofile() {
+        let profile = ProfilePointer::mock();
+        let nprofile = profile.pubkey.as_nprofile(profile.relays.clone());
+        assert!(nprofile.starts_with("nprofile1"));
+        assert_eq!(
+            ProfilePointer::try_from_bech32_string(&nprofile).unwrap(),
+            profile
+        );
+    }
+
+    #[test]
+    fn test_pubkey_compressed() {
+        let pk = PublicKey::mock();
+        let x_only = pk.as_hex_string();
+
+        for prefix in ["02", "03"] {
+            let compressed = format!("{prefix}{x_only}");
+            assert_eq!(PublicKey::try_from_hex_string(&compressed).unwrap(), pk);
+            let bytes = hex::decode(&compressed).unwrap();
+            assert_eq!(PublicKey::try_from_compressed_bytes(&bytes).unwrap(), pk);
+            let json = format!("\"{compressed}\"");
+            assert_eq!(serde_json::from_str::<PublicKey>(&json).unwrap(), pk);
+        }
+
+        assert!(PublicKey::try_from_hex_string(&format!("04{x_only}")).is_err());
+        assert!(PublicKey::try_from_compressed_bytes(&pk.as_bytes()).is_err());
+        assert!(PublicKey::try_from_hex_string(&format!("0202{x_only}")).is_err());
+    }
+
+    #[test]
+    fn test_xonly_public_key() {
+        let pk = PublicKey::mock();
+        let xonly: XOnlyPublicKey = pk.into();
+        assert_eq!(xonly.as_hex_string(), pk.as_hex_string());
+        assert_eq!(xonly.as_bech32_string(), pk.as_bech32_string());
+        assert_eq!(PublicKey::try_from(xonly).unwrap(), pk);
+        assert_eq!(
+            serde_json::to_string(&xonly).unwrap(),
+            serde_json::to_string(&pk).unwrap()
+        );
+
+        // Not a valid x coordinate (it exceeds the field prime), but that is only
+        // checked on conversion
+        let invalid = XOnlyPublicKey::try_from_hex_string(
+            "ffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffff",
+        )
+        .unwrap();
+        assert!(PublicKey::try_from(invalid).is_err());
+    }
 }
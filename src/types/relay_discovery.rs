@@ -0,0 +1,499 @@
+use super::{Event, EventKind, PreEvent, PublicKey, RelayInformationDocument, Tag, Unixtime};
+use crate::Error;
+use serde::{Deserialize, Serialize};
+
+/// The network a relay is reached over (NIP-66)
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub enum RelayNetwork {
+    /// The public internet
+    Clearnet,
+
+    /// Tor
+    Tor,
+
+    /// I2P
+    I2p,
+
+    /// Lokinet
+    Loki,
+
+    /// Some other network
+    Other(String),
+}
+
+impl RelayNetwork {
+    /// The string used in the 'n' tag
+    pub fn as_str(&self) -> &str {
+        match self {
+            RelayNetwork::Clearnet => "clearnet",
+            RelayNetwork::Tor => "tor",
+            RelayNetwork::I2p => "i2p",
+            RelayNetwork::Loki => "loki",
+            RelayNetwork::Other(network) => network,
+        }
+    }
+}
+
+impl From<&str> for RelayNetwork {
+    fn from(s: &str) -> RelayNetwork {
+        match s {
+            "clearnet" => RelayNetwork::Clearnet,
+            "tor" => RelayNetwork::Tor,
+            "i2p" => RelayNetwork::I2p,
+            "loki" => RelayNetwork::Loki,
+            other => RelayNetwork::Other(other.to_owned()),
+        }
+    }
+}
+
+/// Round-trip times measured to a relay, in milliseconds
+#[derive(Clone, Copy, Debug, Default, Deserialize, Eq, PartialEq, Serialize)]
+pub struct RelayRtt {
+    /// Time to open a websocket connection
+    pub open: Option<u64>,
+
+    /// Time to read an event
+    pub read: Option<u64>,
+
+    /// Time to write an event
+    pub write: Option<u64>,
+}
+
+/// A relay discovery event (kind 30166, NIP-66): what a monitor observed of a
+/// relay
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub struct RelayDiscovery {
+    /// The monitor which published the event
+    pub monitor: PublicKey,
+
+    /// When the relay was observed
+    pub created_at: Unixtime,
+
+    /// The relay, by its normalized URL (or its hex public key, for a relay
+    /// without one). This is the 'd' tag.
+    pub relay: String,
+
+    /// Round-trip times measured to the relay
+    pub rtt: RelayRtt,
+
+    /// The network the relay is on
+    pub network: Option<RelayNetwork>,
+
+    /// The type of relay, in PascalCase, e.g. `PrivateInbox`
+    pub relay_type: Option<String>,
+
+    /// NIPs the relay supports
+    pub supported_nips: Vec<u32>,
+
+    /// Requirements of the relay, e.g. `auth` or `payment`, or `!auth` for one
+    /// it does not have
+    pub requirements: Vec<String>,
+
+    /// Topics of the relay
+    pub topics: Vec<String>,
+
+    /// Kinds the relay accepts
+    pub accepted_kinds: Vec<EventKind>,
+
+    /// Kinds the relay rejects
+    pub rejected_kinds: Vec<EventKind>,
+
+    /// Where the relay is
+    pub geohash: Option<String>,
+
+    /// The relay's NIP-11 information document, if the monitor included it
+    pub information: Option<RelayInformationDocument>,
+}
+
+impl RelayDiscovery {
+    // Mock data for testing
+    #[allow(dead_code)]
+    pub(crate) fn mock() -> RelayDiscovery {
+        RelayDiscovery {
+            monitor: PublicKey::mock(),
+            created_at: Unixtime(1_700_000_000),
+            relay: "wss://relay.example.com/".to_owned(),
+            rtt: RelayRtt {
+                open: Some(234),
+                read: Some(150),
+                write: None,
+            },
+            network: Some(RelayNetwork::Clearnet),
+            relay_type: Some("PublicOutbox".to_owned()),
+            supported_nips: vec![1, 11, 42],
+            requirements: vec!["!payment".to_owned(), "auth".to_owned()],
+            topics: vec!["nostr".to_owned()],
+            accepted_kinds: vec![EventKind::TextNote],
+            rejected_kinds: vec![EventKind::EncryptedDirectMessage],
+            geohash: Some("ww8p1r4t8".to_owned()),
+            information: Some(RelayInformationDocument::mock()),
+        }
+    }
+
+    /// Whether the relay has a requirement (`Some(true)`), is listed as not
+    /// having it (`Some(false)`), or neither
+    pub fn requirement(&self, name: &str) -> Option<bool> {
+        self.requirements
+            .iter()
+            .find_map(|r| match r.strip_prefix('!') {
+                Some(r) if r == name => Some(false),
+                None if r == name => Some(true),
+                _ => None,
+            })
+    }
+
+    /// Create a PreEvent for this discovery event
+    pub fn to_pre_event(&self) -> PreEvent {
+        let other = |tag: &str, value: String| Tag::Other {
+            tag: tag.to_owned(),
+            data: vec![value],
+        };
+        let mut tags: Vec<Tag> = vec![Tag::Identifier(self.relay.clone())];
+        for (tag, rtt) in [
+            ("rtt-open", self.rtt.open),
+            ("rtt-read", self.rtt.read),
+            ("rtt-write", self.rtt.write),
+        ] {
+            if let Some(rtt) = rtt {
+                tags.push(other(tag, rtt.to_string()));
+            }
+        }
+        if let Some(network) = &self.network {
+            tags.push(other("n", network.as_str().to_owned()));
+        }
+        if let Some(relay_type) = &self.relay_type {
+            tags.push(other("T", relay_type.clone()));
+        }
+        for nip in &self.supported_nips {
+            tags.push(other("N", nip.to_string()));
+        }
+        for requirement in &self.requirements {
+            tags.push(other("R", requirement.clone()));
+        }
+        for topic in &self.topics {
+            tags.push(Tag::Hashtag(topic.clone()));
+        }
+        for kind in &self.accepted_kinds {
+            tags.push(other("k", u64::from(*kind).to_string()));
+        }
+        for kind in &self.rejected_kinds {
+            tags.push(other("k", format!("!{}", u64::from(*kind))));
+        }
+        if let Some(geohash) = &self.geohash {
+            tags.push(Tag::Geohash(geohash.clone()));
+        }
+
+        PreEvent {
+            pubkey: self.monitor,
+            created_at: self.created_at,
+            kind: EventKind::RelayDiscovery,
+            tags,
+            content: self
+                .information
+                .as_ref()
+                .and_then(|information| serde_json::to_string(information).ok())
+                .unwrap_or_default(),
+            ots: None,
+        }
+    }
+
+    /// Read a discovery event. Its content is read as the relay's information
+    /// document if it is one, and otherwise ignored. Tags whose values do not
+    /// parse are skipped.
+    pub fn try_from_event(event: &Event) -> Result<RelayDiscovery, Error> {
+        if event.kind != EventKind::RelayDiscovery {
+            return Err(Error::WrongEventKind);
+        }
+
+        let mut relay: Option<String> = None;
+        let mut discovery = RelayDiscovery {
+            monitor: event.pubkey,
+            created_at: event.created_at,
+            relay: String::new(),
+            rtt: RelayRtt::default(),
+            network: None,
+            relay_type: None,
+            supported_nips: Vec::new(),
+            requirements: Vec::new(),
+            topics: Vec::new(),
+            accepted_kinds: Vec::new(),
+            rejected_kinds: Vec::new(),
+            geohash: None,
+            information: serde_json::from_str(&event.content).ok(),
+        };
+
+        for tag in event.tags.iter() {
+            match tag {
+                Tag::Identifier(d) => relay = Some(d.clone()),
+                Tag::Hashtag(topic) => discovery.topics.push(topic.clone()),
+                Tag::Geohash(geohash) => discovery.geohash = Some(geohash.clone()),
+                Tag::Other { tag, data } if !data.is_empty() => {
+                    let value = data[0].as_str();
+                    match tag.as_str() {
+                        "rtt-open" => {
+                            discovery.rtt.open = value.parse().ok().or(discovery.rtt.open)
+                        }
+                        "rtt-read" => {
+                            discovery.rtt.read = value.parse().ok().or(discovery.rtt.read)
+                        }
+                        "rtt-write" => {
+                            discovery.rtt.write = value.parse().ok().or(discovery.rtt.write)
+                        }
+                        "n" => discovery.network = Some(value.into()),
+                        "T" => discovery.relay_type = Some(value.to_owned()),
+                        "N" => discovery.supported_nips.extend(value.parse::<u32>().ok()),
+                        "R" => discovery.requirements.push(value.to_owned()),
+                        "k" => match value.strip_prefix('!') {
+                            Some(kind) => discovery
+                                .rejected_kinds
+                                .extend(kind.parse::<u64>().ok().map(EventKind::from)),
+                            None => discovery
+                                .accepted_kinds
+                                .extend(value.parse::<u64>().ok().map(EventKind::from)),
+                        },
+                        _ => {}
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        discovery.relay = relay.ok_or_else(|| {
+            Error::InvalidRelayDiscovery("Discovery event has no 'd' tag".to_owned())
+        })?;
+        Ok(discovery)
+    }
+}
+
+/// How long a monitor waits for one of its checks, or for all of them
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub struct RelayMonitorTimeout {
+    /// The timeout, in milliseconds
+    pub ms: u64,
+
+    /// The check it applies to, e.g. `open`, or `None` for every check
+    pub check: Option<String>,
+}
+
+/// A relay monitor announcement (kind 10166, NIP-66): how a monitor checks
+/// relays, and so what its discovery events contain
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub struct RelayMonitorAnnouncement {
+    /// The monitor
+    pub monitor: PublicKey,
+
+    /// When the announcement was made
+    pub created_at: Unixtime,
+
+    /// How often the monitor publishes discovery events, in seconds
+    pub frequency: Option<u64>,
+
+    /// The timeouts of its checks
+    pub timeouts: Vec<RelayMonitorTimeout>,
+
+    /// The checks it makes, e.g. `open`, `read`, `write`, `auth`, `nip11`,
+    /// `dns` or `geo`
+    pub checks: Vec<String>,
+
+    /// Where the monitor is
+    pub geohash: Option<String>,
+}
+
+impl RelayMonitorAnnouncement {
+    // Mock data for testing
+    #[allow(dead_code)]
+    pub(crate) fn mock() -> RelayMonitorAnnouncement {
+        RelayMonitorAnnouncement {
+            monitor: PublicKey::mock(),
+            created_at: Unixtime(1_700_000_000),
+            frequency: Some(3600),
+            timeouts: vec![
+                RelayMonitorTimeout {
+                    ms: 5000,
+                    check: None,
+                },
+                RelayMonitorTimeout {
+                    ms: 2000,
+                    check: Some("open".to_owned()),
+                },
+            ],
+            checks: vec!["open".to_owned(), "read".to_owned(), "nip11".to_owned()],
+            geohash: Some("ww8p1r4t8".to_owned()),
+        }
+    }
+
+    /// The timeout of a check, in milliseconds: its own, or else the one for
+    /// every check
+    pub fn timeout_for(&self, check: &str) -> Option<u64> {
+        let timeout = |c: Option<&str>| {
+            self.timeouts
+                .iter()
+                .find(|t| t.check.as_deref() == c)
+                .map(|t| t.ms)
+        };
+        timeout(Some(check)).or_else(|| timeout(None))
+    }
+
+    /// Create a PreEvent for this announcement
+    pub fn to_pre_event(&self) -> PreEvent {
+        let other = |tag: &str, data: Vec<String>| Tag::Other {
+            tag: tag.to_owned(),
+            data,
+        };
+        let mut tags: Vec<Tag> = Vec::new();
+        if let Some(frequency) = self.frequency {
+            tags.push(other("frequency", vec![frequency.to_string()]));
+        }
+        for timeout in &self.timeouts {
+            let mut data = vec![timeout.ms.to_string()];
+            data.extend(timeout.check.clone());
+            tags.push(other("timeout", data));
+        }
+        for check in &self.checks {
+            tags.push(other("c", vec![check.clone()]));
+        }
+        if let Some(geohash) = &self.geohash {
+            tags.push(Tag::Geohash(geohash.clone()));
+        }
+
+        PreEvent {
+            pubkey: self.monitor,
+            created_at: self.created_at,
+            kind: EventKind::RelayMonitorAnnouncement,
+            tags,
+            content: "".to_owned(),
+            ots: None,
+        }
+    }
+
+    /// Read a monitor announcement. Tags whose values do not parse are skipped.
+    pub fn try_from_event(event: &Event) -> Result<RelayMonitorAnnouncement, Error> {
+        if event.kind != EventKind::RelayMonitorAnnouncement {
+            return Err(Error::WrongEventKind);
+        }
+
+        let mut announcement = RelayMonitorAnnouncement {
+            monitor: event.pubkey,
+            created_at: event.created_at,
+            frequency: None,
+            timeouts: Vec::new(),
+            checks: Vec::new(),
+            geohash: None,
+        };
+
+        for tag in event.tags.iter() {
+            match tag {
+                Tag::Geohash(geohash) => announcement.geohash = Some(geohash.clone()),
+                Tag::Other { tag, data } if !data.is_empty() => match tag.as_str() {
+                    "frequency" => {
+                        announcement.frequency = data[0].parse().ok().or(announcement.frequency)
+                    }
+                    "timeout" => {
+                        if let Ok(ms) = data[0].parse() {
+                            announcement.timeouts.push(RelayMonitorTimeout {
+                                ms,
+                                check: data.get(1).cloned(),
+                            })
+                        }
+                    }
+                    "c" => announcement.checks.push(data[0].clone()),
+                    _ => {}
+                },
+                _ => {}
+            }
+        }
+
+        Ok(announcement)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::TestSigner;
+
+    test_serde! {RelayDiscovery, test_relay_discovery_serde}
+    test_serde! {RelayMonitorAnnouncement, test_relay_monitor_announcement_serde}
+
+    #[test]
+    fn test_relay_discovery_event() {
+        let signer = TestSigner::new(b"monitor");
+        let mut discovery = RelayDiscovery::mock();
+        discovery.monitor = signer.public_key();
+        let event = signer.sign_event(discovery.to_pre_event()).unwrap();
+        assert_eq!(event.kind, EventKind::RelayDiscovery);
+        assert!(event.kind.is_parameterized_replaceable());
+        let read = RelayDiscovery::try_from_event(&event).unwrap();
+        assert_eq!(read, discovery);
+        assert_eq!(read.requirement("auth"), Some(true));
+        assert_eq!(read.requirement("payment"), Some(false));
+        assert_eq!(read.requirement("writes"), None);
+
+        // Content which is not an information document is ignored, but the
+        // relay must be named
+        let tag = |tag: &str, value: &str| Tag::Other {
+            tag: tag.to_owned(),
+            data: vec![value.to_owned()],
+        };
+        let mut pre_event = discovery.to_pre_event();
+        pre_event.tags = vec![
+            Tag::Identifier("wss://relay.example.com/".to_owned()),
+            tag("n", "i2p"),
+            tag("rtt-open", "12"),
+            tag("k", "!1"),
+            // Malformed tags are skipped, rather than rejecting the event
+            tag("rtt-read", "fast"),
+            tag("rtt-open", "-"),
+            tag("N", "eleven"),
+            tag("N", "11"),
+            tag("k", "!text"),
+        ];
+        pre_event.content = "not json".to_owned();
+        let mut event = signer.sign_event(pre_event).unwrap();
+        let read = RelayDiscovery::try_from_event(&event).unwrap();
+        assert_eq!(read.network, Some(RelayNetwork::I2p));
+        assert_eq!(read.rtt.open, Some(12));
+        assert_eq!(read.rtt.read, None);
+        assert_eq!(read.supported_nips, vec![11]);
+        assert_eq!(read.rejected_kinds, vec![EventKind::TextNote]);
+        assert_eq!(read.information, None);
+        let _ = event.tags.remove(0);
+        assert!(RelayDiscovery::try_from_event(&event).is_err());
+        event.kind = EventKind::TextNote;
+        assert!(matches!(
+            RelayDiscovery::try_from_event(&event),
+            Err(Error::WrongEventKind)
+        ));
+    }
+
+    #[test]
+    fn test_relay_monitor_announcement_event() {
+        let signer = TestSigner::new(b"monitor");
+        let mut announcement = RelayMonitorAnnouncement::mock();
+        announcement.monitor = signer.public_key();
+        let event = signer.sign_event(announcement.to_pre_event()).unwrap();
+        assert!(event.kind.is_replaceable());
+        let read = RelayMonitorAnnouncement::try_from_event(&event).unwrap();
+        assert_eq!(read, announcement);
+        assert_eq!(read.timeout_for("open"), Some(2000));
+        assert_eq!(read.timeout_for("read"), Some(5000));
+
+        // Malformed tags are skipped
+        let mut pre_event = announcement.to_pre_event();
+        pre_event.tags.push(Tag::Other {
+            tag: "frequency".to_owned(),
+            data: vec!["hourly".to_owned()],
+        });
+        pre_event.tags.push(Tag::Other {
+            tag: "timeout".to_owned(),
+            data: vec!["soon".to_owned(), "write".to_owned()],
+        });
+        let event = signer.sign_event(pre_event).unwrap();
+        let read = RelayMonitorAnnouncement::try_from_event(&event).unwrap();
+        assert_eq!(read, announcement);
+        assert!(matches!(
+            RelayDiscovery::try_from_event(&event),
+            Err(Error::WrongEventKind)
+        ));
+    }
+}
@@ -1,5 +1,4 @@
 use super::{EventKind, EventKindOrRange, PublicKeyHexPrefix, Url};
-use serde::de::Error as DeError;
 use serde::de::{Deserializer, MapAccess, Visitor};
 use serde::ser::{SerializeMap, Serializer};
 use serde::{Deserialize, Serialize};
@@ -9,7 +8,7 @@ use speedy::{Readable, Writable};
 use std::fmt;
 
 /// Relay limitations
-#[derive(Clone, Copy, Debug, Deserialize, Eq, PartialEq, Serialize)]
+#[derive(Clone, Copy, Debug, Default, Deserialize, Eq, PartialEq, Serialize)]
 #[cfg_attr(feature = "speedy", derive(Readable, Writable))]
 pub struct RelayLimitation {
     /// max message length
@@ -66,6 +65,11 @@ pub struct RelayLimitation {
     #[serde(skip_serializing_if = "Option::is_none")]
     #[serde(default)]
     pub payment_required: Option<bool>,
+
+    /// restricted writes
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(default)]
+    pub restricted_writes: Option<bool>,
 }
 
 /// Relay retention
@@ -144,7 +148,8 @@ pub struct RelayInformationDocument {
     /// An administrative contact for the relay. Should be a URI.
     pub contact: Option<String>,
 
-    /// A list of NIPs supported by the relay
+    /// A list of NIPs supported by the relay. Entries which are not NIP
+    /// numbers are kept in `other`, under "supported_nips".
     pub supported_nips: Vec<u32>,
 
     /// The software running the relay
@@ -177,7 +182,20 @@ pub struct RelayInformationDocument {
     /// fees
     pub fees: Option<RelayFees>,
 
-    /// Additional fields not specified in NIP-11
+    /// An icon for the relay, ideally square
+    pub icon: Option<Url>,
+
+    /// A banner image for the relay
+    pub banner: Option<Url>,
+
+    /// The privacy policy of the relay
+    pub privacy_policy: Option<Url>,
+
+    /// The terms of service of the relay
+    pub terms_of_service: Option<Url>,
+
+    /// Additional fields not specified in NIP-11, and specified fields whose
+    /// values could not be understood
     pub other: Map<String, Value>,
 }
 
@@ -199,6 +217,10 @@ impl Default for RelayInformationDocument {
             posting_policy: None,
             payments_url: None,
             fees: None,
+            icon: None,
+            banner: None,
+            privacy_policy: None,
+            terms_of_service: None,
             other: Map::new(),
         }
     }
@@ -210,6 +232,11 @@ impl RelayInformationDocument {
         self.supported_nips.contains(&nip)
     }
 
+    /// What the relay can do and requires, as far as this document says
+    pub fn capabilities(&self) -> RelayCapabilities {
+        RelayCapabilities::from(self)
+    }
+
     #[allow(dead_code)]
     pub(crate) fn mock() -> RelayInformationDocument {
         let mut m = Map::new();
@@ -241,6 +268,7 @@ impl RelayInformationDocument {
                 min_pow_difficulty: Some(30),
                 auth_required: Some(true),
                 payment_required: Some(true),
+                restricted_writes: None,
             }),
             retention: vec![
                 RelayRetention {
@@ -269,7 +297,7 @@ impl RelayInformationDocument {
                 },
                 RelayRetention {
                     kinds: vec![EventKindOrRange::Range(vec![
-                        EventKind::Other(30000),
+                        EventKind::FollowSets,
                         EventKind::Other(39999),
                     ])],
                     time: None,
@@ -308,11 +336,79 @@ impl RelayInformationDocument {
                     period: None,
                 }],
             }),
+            icon: Some(Url::try_from_str("https://example.com/icon.png").unwrap()),
+            banner: None,
+            privacy_policy: None,
+            terms_of_service: Some(Url::try_from_str("https://example.com/tos.html").unwrap()),
             other: m,
         }
     }
 }
 
+/// What a relay can do and requires, from its information document, for
+/// deciding how to use it. Limits the document does not give are `None`, and
+/// requirements it does not state are taken to be absent.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct RelayCapabilities {
+    supported_nips: Vec<u32>,
+
+    /// Whether the relay requires NIP-42 authentication before anything else
+    pub auth_required: bool,
+
+    /// Whether the relay requires payment before it can be used
+    pub payment_required: bool,
+
+    /// Whether the relay restricts who may write to it
+    pub restricted_writes: bool,
+
+    /// The most filters the relay accepts in one subscription
+    pub max_filters: Option<usize>,
+
+    /// The most subscriptions the relay allows open on one connection
+    pub max_subscriptions: Option<usize>,
+
+    /// The longest message the relay accepts, in bytes
+    pub max_message_length: Option<usize>,
+
+    /// The most events the relay returns for one filter
+    pub max_limit: Option<usize>,
+
+    /// The proof of work the relay requires of events (NIP-13)
+    pub min_pow_difficulty: Option<usize>,
+}
+
+impl RelayCapabilities {
+    /// If the relay supports the queried `nip`
+    pub fn supports_nip(&self, nip: u32) -> bool {
+        self.supported_nips.binary_search(&nip).is_ok()
+    }
+
+    /// The NIPs the relay supports, in order
+    pub fn supported_nips(&self) -> &[u32] {
+        &self.supported_nips
+    }
+}
+
+impl From<&RelayInformationDocument> for RelayCapabilities {
+    fn from(rid: &RelayInformationDocument) -> RelayCapabilities {
+        let mut supported_nips = rid.supported_nips.clone();
+        supported_nips.sort_unstable();
+        supported_nips.dedup();
+        let limitation = rid.limitation.unwrap_or_default();
+        RelayCapabilities {
+            supported_nips,
+            auth_required: limitation.auth_required.unwrap_or(false),
+            payment_required: limitation.payment_required.unwrap_or(false),
+            restricted_writes: limitation.restricted_writes.unwrap_or(false),
+            max_filters: limitation.max_filters,
+            max_subscriptions: limitation.max_subscriptions,
+            max_message_length: limitation.max_message_length,
+            max_limit: limitation.max_limit,
+            min_pow_difficulty: limitation.min_pow_difficulty,
+        }
+    }
+}
+
 impl fmt::Display for RelayInformationDocument {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         write!(f, "Relay Information:")?;
@@ -362,7 +458,19 @@ impl Serialize for RelayInformationDocument {
         if self.contact.is_some() {
             map.serialize_entry("contact", &json!(&self.contact))?;
         }
-        map.serialize_entry("supported_nips", &json!(&self.supported_nips))?;
+        // Entries of supported_nips which are not NIP numbers are kept in `other`,
+        // and written back after those which are
+        match self.other.get("supported_nips") {
+            Some(Value::Array(kept)) => {
+                let mut nips: Vec<Value> = self.supported_nips.iter().map(|n| json!(n)).collect();
+                nips.extend(kept.iter().cloned());
+                map.serialize_entry("supported_nips", &nips)?;
+            }
+            Some(kept) if self.supported_nips.is_empty() => {
+                map.serialize_entry("supported_nips", kept)?;
+            }
+            _ => map.serialize_entry("supported_nips", &json!(&self.supported_nips))?,
+        }
         if self.software.is_some() {
             map.serialize_entry("software", &json!(&self.software))?;
         }
@@ -393,8 +501,22 @@ impl Serialize for RelayInformationDocument {
         if self.fees.is_some() {
             map.serialize_entry("fees", &json!(&self.fees))?;
         }
+        if self.icon.is_some() {
+            map.serialize_entry("icon", &json!(&self.icon))?;
+        }
+        if self.banner.is_some() {
+            map.serialize_entry("banner", &json!(&self.banner))?;
+        }
+        if self.privacy_policy.is_some() {
+            map.serialize_entry("privacy_policy", &json!(&self.privacy_policy))?;
+        }
+        if self.terms_of_service.is_some() {
+            map.serialize_entry("terms_of_service", &json!(&self.terms_of_service))?;
+        }
         for (k, v) in &self.other {
-            map.serialize_entry(&k, &v)?;
+            if k != "supported_nips" {
+                map.serialize_entry(&k, &v)?;
+            }
         }
         map.end()
     }
@@ -427,90 +549,71 @@ impl<'de> Visitor<'de> for RidVisitor {
             let _ = map.insert(key, value);
         }
 
-        let mut rid: RelayInformationDocument = Default::default();
-
-        if let Some(Value::String(s)) = map.remove("name") {
-            rid.name = Some(s);
-        }
-        if let Some(Value::String(s)) = map.remove("description") {
-            rid.description = Some(s);
-        }
-        if let Some(Value::String(s)) = map.remove("pubkey") {
-            rid.pubkey = match PublicKeyHexPrefix::try_from_string(s) {
-                Ok(pkh) => Some(pkh),
-                Err(e) => return Err(DeError::custom(format!("{e}"))),
-            };
-        }
-        if let Some(Value::String(s)) = map.remove("contact") {
-            rid.contact = Some(s);
-        }
-        if let Some(Value::Array(vec)) = map.remove("supported_nips") {
-            for elem in vec.iter() {
-                if let Value::Number(num) = elem {
-                    if let Some(u) = num.as_u64() {
-                        rid.supported_nips.push(u as u32);
-                    }
+        // Relays get fields wrong in all sorts of ways. Fields that cannot be
+        // understood are kept in `other` rather than failing the whole document.
+        let mut pubkey = None;
+        if let Some(s) = take::<String>(&mut map, "pubkey") {
+            match PublicKeyHexPrefix::try_from_str(&s) {
+                Ok(pkh) => pubkey = Some(pkh),
+                Err(_) => {
+                    let _ = map.insert("pubkey".to_owned(), Value::String(s));
                 }
             }
         }
-        if let Some(Value::String(s)) = map.remove("software") {
-            rid.software = Some(s);
-        }
-        if let Some(Value::String(s)) = map.remove("version") {
-            rid.version = Some(s);
-        }
-        if let Some(v) = map.remove("limitation") {
-            rid.limitation = match serde_json::from_value::<Option<RelayLimitation>>(v) {
-                Ok(x) => x,
-                Err(e) => return Err(DeError::custom(format!("{e}"))),
-            }
-        }
-        if let Some(v) = map.remove("retention") {
-            rid.retention = match serde_json::from_value::<Vec<RelayRetention>>(v) {
-                Ok(x) => x,
-                Err(e) => return Err(DeError::custom(format!("{e}"))),
-            };
-        }
-        if let Some(v) = map.remove("relay_countries") {
-            rid.relay_countries = match serde_json::from_value::<Vec<String>>(v) {
-                Ok(x) => x,
-                Err(e) => return Err(DeError::custom(format!("{e}"))),
-            }
-        }
-        if let Some(v) = map.remove("language_tags") {
-            rid.language_tags = match serde_json::from_value::<Vec<String>>(v) {
-                Ok(x) => x,
-                Err(e) => return Err(DeError::custom(format!("{e}"))),
-            }
-        }
-        if let Some(v) = map.remove("tags") {
-            rid.tags = match serde_json::from_value::<Vec<String>>(v) {
-                Ok(x) => x,
-                Err(e) => return Err(DeError::custom(format!("{e}"))),
-            }
-        }
-        if let Some(v) = map.remove("posting_policy") {
-            rid.posting_policy = match serde_json::from_value::<Option<Url>>(v) {
-                Ok(x) => x,
-                Err(e) => return Err(DeError::custom(format!("{e}"))),
+        let mut supported_nips = Vec::new();
+        if let Some(vec) = take::<Vec<Value>>(&mut map, "supported_nips") {
+            let mut kept: Vec<Value> = Vec::new();
+            for elem in vec.into_iter() {
+                let nip = match &elem {
+                    Value::Number(num) => num.as_u64(),
+                    Value::String(s) => s.parse::<u64>().ok(),
+                    _ => None,
+                };
+                match nip.and_then(|n| u32::try_from(n).ok()) {
+                    Some(nip) => supported_nips.push(nip),
+                    None => kept.push(elem),
+                }
             }
-        }
-        if let Some(v) = map.remove("payments_url") {
-            rid.payments_url = match serde_json::from_value::<Option<Url>>(v) {
-                Ok(x) => x,
-                Err(e) => return Err(DeError::custom(format!("{e}"))),
-            }
-        }
-        if let Some(v) = map.remove("fees") {
-            rid.fees = match serde_json::from_value::<Option<RelayFees>>(v) {
-                Ok(x) => x,
-                Err(e) => return Err(DeError::custom(format!("{e}"))),
+            if !kept.is_empty() {
+                let _ = map.insert("supported_nips".to_owned(), Value::Array(kept));
             }
         }
 
-        rid.other = map;
+        Ok(RelayInformationDocument {
+            name: take(&mut map, "name").flatten(),
+            description: take(&mut map, "description").flatten(),
+            pubkey,
+            contact: take(&mut map, "contact").flatten(),
+            supported_nips,
+            software: take(&mut map, "software").flatten(),
+            version: take(&mut map, "version").flatten(),
+            limitation: take(&mut map, "limitation").flatten(),
+            retention: take(&mut map, "retention").unwrap_or_default(),
+            relay_countries: take(&mut map, "relay_countries").unwrap_or_default(),
+            language_tags: take(&mut map, "language_tags").unwrap_or_default(),
+            tags: take(&mut map, "tags").unwrap_or_default(),
+            posting_policy: take(&mut map, "posting_policy").flatten(),
+            payments_url: take(&mut map, "payments_url").flatten(),
+            fees: take(&mut map, "fees").flatten(),
+            icon: take(&mut map, "icon").flatten(),
+            banner: take(&mut map, "banner").flatten(),
+            privacy_policy: take(&mut map, "privacy_policy").flatten(),
+            terms_of_service: take(&mut map, "terms_of_service").flatten(),
+            other: map,
+        })
+    }
+}
 
-        Ok(rid)
+// Take a field out of the map, if it is there and has the expected type. If
+// it does not, it is left in the map.
+fn take<T: serde::de::DeserializeOwned>(map: &mut Map<String, Value>, key: &str) -> Option<T> {
+    let value = map.remove(key)?;
+    match T::deserialize(&value) {
+        Ok(t) => Some(t),
+        Err(_) => {
+            let _ = map.insert(key.to_owned(), value);
+            None
+        }
     }
 }
 
@@ -542,4 +645,64 @@ mod test {
         let expected_json2 = r##"{"name":"A Relay","supported_nips":[11,12],"retention":[{"kinds":[0,1,[5,7],[40,49]],"time":3600},{"kinds":[[40000,49999]],"time":100},{"count":1000,"kinds":[[30000,39999]]},{"count":10000,"time":3600}],"myfield":[1,2]}"##;
         assert_eq!(json2, expected_json2);
     }
+
+    #[test]
+    fn test_relay_information_document_lenient() {
+        let json = r##"{ "name": "A Relay", "pubkey": "not a key", "supported_nips": [1, "11", "x", 4294967296],
+  "limitation": { "max_filters": "ten" }, "icon": "https://example.com/icon.png", "fees": 12 }"##;
+        let rid: RelayInformationDocument = serde_json::from_str(json).unwrap();
+        assert_eq!(rid.name.as_deref(), Some("A Relay"));
+        assert_eq!(rid.supported_nips, vec![1, 11]);
+        assert_eq!(
+            rid.icon.as_ref().map(|u| u.as_str()),
+            Some("https://example.com/icon.png")
+        );
+        assert!(rid.pubkey.is_none());
+        assert!(rid.limitation.is_none());
+        assert!(rid.fees.is_none());
+
+        // what could not be understood is kept
+        assert_eq!(rid.other.get("pubkey"), Some(&json!("not a key")));
+        assert_eq!(
+            rid.other.get("limitation"),
+            Some(&json!({ "max_filters": "ten" }))
+        );
+        assert_eq!(rid.other.get("fees"), Some(&json!(12)));
+        assert_eq!(
+            rid.other.get("supported_nips"),
+            Some(&json!(["x", 4294967296_u64]))
+        );
+        let json = serde_json::to_value(&rid).unwrap();
+        assert_eq!(json["supported_nips"], json!([1, 11, "x", 4294967296_u64]));
+        let rid2: RelayInformationDocument = serde_json::from_value(json).unwrap();
+        assert_eq!(rid2, rid);
+    }
+
+    #[test]
+    fn test_relay_capabilities() {
+        let caps = RelayInformationDocument::mock().capabilities();
+        assert!(caps.supports_nip(11));
+        assert!(!caps.supports_nip(42));
+        assert!(caps.auth_required);
+        assert!(caps.payment_required);
+        assert!(!caps.restricted_writes);
+        assert_eq!(caps.max_filters, Some(100));
+        assert_eq!(caps.max_subscriptions, Some(20));
+
+        let json = r#"{ "supported_nips": [42, 1, 11, 1],
+  "limitation": { "restricted_writes": true, "max_filters": 10 } }"#;
+        let rid: RelayInformationDocument = serde_json::from_str(json).unwrap();
+        let caps = rid.capabilities();
+        assert_eq!(caps.supported_nips(), &[1, 11, 42]);
+        assert!(caps.supports_nip(42));
+        assert!(caps.restricted_writes);
+        assert!(!caps.auth_required);
+        assert_eq!(caps.max_filters, Some(10));
+        assert_eq!(caps.max_subscriptions, None);
+
+        assert_eq!(
+            RelayInformationDocument::default().capabilities(),
+            RelayCapabilities::default()
+        );
+    }
 }
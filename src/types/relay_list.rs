@@ -1,4 +1,5 @@
-use super::UncheckedUrl;
+use super::{Event, EventKind, RelayUrl, Tag, UncheckedUrl};
+use crate::Error;
 use serde::de::{Deserializer, MapAccess, Visitor};
 use serde::ser::{SerializeMap, Serializer};
 use serde::{Deserialize, Serialize};
@@ -52,6 +53,108 @@ impl SimpleRelayList {
         );
         SimpleRelayList(map)
     }
+
+    /// Read the relays from the content of a legacy contact list (kind 3),
+    /// which clients used before relay lists (NIP-65).
+    ///
+    /// This is tolerant of what clients wrote there: content which is empty or
+    /// not a JSON object gives an empty list, flags may be booleans, `0`/`1`
+    /// or `"true"`/`"false"`, a missing flag takes its value from
+    /// `SimpleRelayUsage::default()`, and URLs which are not relay URLs are
+    /// skipped. URLs are normalized, and the flags of duplicates combined.
+    pub fn from_legacy_content(content: &str) -> SimpleRelayList {
+        let mut map: HashMap<UncheckedUrl, SimpleRelayUsage> = HashMap::new();
+        let object = match serde_json::from_str::<serde_json::Value>(content) {
+            Ok(serde_json::Value::Object(object)) => object,
+            _ => return SimpleRelayList(map),
+        };
+
+        for (url, value) in object.iter() {
+            let Ok(url) = RelayUrl::try_from_str(url.trim()) else {
+                continue;
+            };
+            let default = SimpleRelayUsage::default();
+            let usage = SimpleRelayUsage {
+                write: legacy_flag(value.get("write")).unwrap_or(default.write),
+                read: legacy_flag(value.get("read")).unwrap_or(default.read),
+            };
+            let entry = map
+                .entry(url.to_unchecked_url())
+                .or_insert(SimpleRelayUsage {
+                    write: false,
+                    read: false,
+                });
+            entry.write |= usage.write;
+            entry.read |= usage.read;
+        }
+
+        SimpleRelayList(map)
+    }
+
+    /// Read the relays from a legacy contact list (kind 3) event (see
+    /// `from_legacy_content()`)
+    pub fn try_from_event(event: &Event) -> Result<SimpleRelayList, Error> {
+        if event.kind != EventKind::ContactList {
+            return Err(Error::WrongEventKind);
+        }
+        Ok(SimpleRelayList::from_legacy_content(&event.content))
+    }
+
+    /// Write as the content of a legacy contact list (kind 3), with the relays
+    /// in order so that the content is the same each time
+    pub fn to_legacy_content(&self) -> String {
+        let mut relays: Vec<(&UncheckedUrl, &SimpleRelayUsage)> = self.0.iter().collect();
+        relays.sort_by(|a, b| a.0.as_str().cmp(b.0.as_str()));
+        let entries: Vec<String> = relays
+            .into_iter()
+            .map(|(url, usage)| {
+                format!(
+                    r#"{}:{{"write":{},"read":{}}}"#,
+                    serde_json::Value::from(url.as_str()),
+                    usage.write,
+                    usage.read
+                )
+            })
+            .collect();
+        format!("{{{}}}", entries.join(","))
+    }
+
+    /// The 'r' tags of a relay list (NIP-65) for these relays, in order of
+    /// URL, to migrate them to one. Relays neither read from nor written to
+    /// are left out.
+    pub fn to_nip65_tags(&self) -> Vec<Tag> {
+        let mut relays: Vec<(&UncheckedUrl, &SimpleRelayUsage)> = self.0.iter().collect();
+        relays.sort_by(|a, b| a.0.as_str().cmp(b.0.as_str()));
+        relays
+            .into_iter()
+            .filter_map(|(url, usage)| {
+                let marker = match (usage.read, usage.write) {
+                    (true, true) => None,
+                    (true, false) => Some("read".to_owned()),
+                    (false, true) => Some("write".to_owned()),
+                    (false, false) => return None,
+                };
+                Some(Tag::Reference {
+                    url: url.clone(),
+                    marker,
+                })
+            })
+            .collect()
+    }
+}
+
+// A read or write flag as written by various clients
+fn legacy_flag(value: Option<&serde_json::Value>) -> Option<bool> {
+    match value? {
+        serde_json::Value::Bool(b) => Some(*b),
+        serde_json::Value::Number(n) => n.as_u64().map(|n| n != 0),
+        serde_json::Value::String(s) => match s.trim().to_ascii_lowercase().as_str() {
+            "true" | "1" => Some(true),
+            "false" | "0" => Some(false),
+            _ => None,
+        },
+        _ => None,
+    }
 }
 
 impl Serialize for SimpleRelayList {
@@ -108,4 +211,79 @@ mod test {
         let serialized = r#"{"wss://nostr.oxtr.dev":{"write":true,"read":true},"wss://relay.damus.io":{"write":true,"read":true},"wss://nostr.fmt.wiz.biz":{"write":true,"read":true},"wss://nostr-relay.wlvs.space":{"write":true,"read":true}}"#;
         let _simple_relay_list: SimpleRelayList = serde_json::from_str(serialized).unwrap();
     }
+
+    #[test]
+    fn test_simple_relay_list_legacy_content() {
+        let content = r#"{
+            "wss://relay.damus.io": {"read": true, "write": true},
+            "wss://Nostr.Example.com/": {"read": "true", "write": 0},
+            "wss://nostr.example.com": {"write": true},
+            "wss://write.example.com": {"read": false, "write": "1"},
+            "wss://unused.example.com": {"read": false, "write": false},
+            "wss://flagless.example.com": null,
+            "https://not.a.relay.com": {"read": true, "write": true},
+            "not a url": {"read": true, "write": true}
+        }"#;
+        let list = SimpleRelayList::from_legacy_content(content);
+        assert_eq!(list.0.len(), 5);
+        let usage = |url: &str| list.0[&UncheckedUrl::from_str(url)];
+        assert_eq!(
+            usage("wss://nostr.example.com/"),
+            SimpleRelayUsage {
+                write: true,
+                read: true
+            }
+        );
+        assert_eq!(
+            usage("wss://flagless.example.com/"),
+            SimpleRelayUsage::default()
+        );
+
+        assert_eq!(
+            list.to_legacy_content(),
+            concat!(
+                r#"{"wss://flagless.example.com/":{"write":false,"read":true},"#,
+                r#""wss://nostr.example.com/":{"write":true,"read":true},"#,
+                r#""wss://relay.damus.io/":{"write":true,"read":true},"#,
+                r#""wss://unused.example.com/":{"write":false,"read":false},"#,
+                r#""wss://write.example.com/":{"write":true,"read":false}}"#
+            )
+        );
+        assert_eq!(
+            SimpleRelayList::from_legacy_content(&list.to_legacy_content()),
+            list
+        );
+
+        // URLs are escaped as JSON strings
+        let mut odd = SimpleRelayList::default();
+        let _ = odd.0.insert(
+            UncheckedUrl::from_str("wss://a.com/\"x\""),
+            SimpleRelayUsage::default(),
+        );
+        let content: serde_json::Value = serde_json::from_str(&odd.to_legacy_content()).unwrap();
+        assert!(content.get("wss://a.com/\"x\"").is_some());
+
+        let tags = list.to_nip65_tags();
+        let r = |url: &str, marker: Option<&str>| Tag::Reference {
+            url: UncheckedUrl::from_str(url),
+            marker: marker.map(|m| m.to_owned()),
+        };
+        assert_eq!(
+            tags,
+            vec![
+                r("wss://flagless.example.com/", Some("read")),
+                r("wss://nostr.example.com/", None),
+                r("wss://relay.damus.io/", None),
+                r("wss://write.example.com/", Some("write")),
+            ]
+        );
+
+        assert!(matches!(
+            SimpleRelayList::try_from_event(&Event::mock()),
+            Err(Error::WrongEventKind)
+        ));
+        for junk in ["", "[]", "\"relays\"", "{not json"] {
+            assert!(SimpleRelayList::from_legacy_content(junk).0.is_empty());
+        }
+    }
 }
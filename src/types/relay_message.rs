@@ -1,10 +1,14 @@
 use super::{Event, Id, SubscriptionId};
+use crate::Error;
 use serde::de::Error as DeError;
-use serde::de::{Deserialize, Deserializer, SeqAccess, Visitor};
-use serde::ser::{Serialize, SerializeSeq, Serializer};
+use serde::de::{Deserializer, SeqAccess, Visitor};
+use serde::ser::{SerializeSeq, Serializer};
+use serde::{Deserialize, Serialize};
 #[cfg(feature = "speedy")]
 use speedy::{Readable, Writable};
+use std::convert::Infallible;
 use std::fmt;
+use std::str::FromStr;
 
 /// A message from a relay to a client
 #[derive(Clone, Debug, Eq, PartialEq)]
@@ -20,13 +24,381 @@ pub enum RelayMessage {
     Eose(SubscriptionId),
 
     /// Used to notify clients if an event was successuful
-    Ok(Id, bool, String),
+    Ok(Id, bool, Reason),
 
     /// Used to send authentication challenges
     Auth(String),
+
+    /// A subscription was ended by the relay, with the reason why
+    Closed(SubscriptionId, Reason),
+
+    /// The number of events matching a COUNT request (NIP-45)
+    Count(SubscriptionId, CountResult),
+}
+
+/// The machine-readable prefix of the reason given in an OK or CLOSED message
+/// (NIP-01), which says why an event was rejected or a subscription closed
+#[derive(Clone, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "speedy", derive(Readable, Writable))]
+pub enum ReasonPrefix {
+    /// The event was already held (`duplicate`)
+    Duplicate,
+
+    /// The event lacked proof of work (`pow`)
+    Pow,
+
+    /// The client or author is blocked (`blocked`)
+    Blocked,
+
+    /// Too many requests were made (`rate-limited`)
+    RateLimited,
+
+    /// The event or request was invalid (`invalid`)
+    Invalid,
+
+    /// The client is not permitted, such as when not a paying member (`restricted`)
+    Restricted,
+
+    /// The event was accepted but will not be served (`mute`)
+    Mute,
+
+    /// The client must authenticate first (`auth-required`, NIP-42)
+    AuthRequired,
+
+    /// The relay failed (`error`)
+    Error,
+
+    /// A prefix not listed here
+    Other(String),
+}
+
+impl ReasonPrefix {
+    /// The prefix as it appears in a message
+    pub fn as_str(&self) -> &str {
+        match self {
+            ReasonPrefix::Duplicate => "duplicate",
+            ReasonPrefix::Pow => "pow",
+            ReasonPrefix::Blocked => "blocked",
+            ReasonPrefix::RateLimited => "rate-limited",
+            ReasonPrefix::Invalid => "invalid",
+            ReasonPrefix::Restricted => "restricted",
+            ReasonPrefix::Mute => "mute",
+            ReasonPrefix::AuthRequired => "auth-required",
+            ReasonPrefix::Error => "error",
+            ReasonPrefix::Other(prefix) => prefix,
+        }
+    }
+}
+
+impl From<&str> for ReasonPrefix {
+    fn from(s: &str) -> ReasonPrefix {
+        match s {
+            "duplicate" => ReasonPrefix::Duplicate,
+            "pow" => ReasonPrefix::Pow,
+            "blocked" => ReasonPrefix::Blocked,
+            "rate-limited" => ReasonPrefix::RateLimited,
+            "invalid" => ReasonPrefix::Invalid,
+            "restricted" => ReasonPrefix::Restricted,
+            "mute" => ReasonPrefix::Mute,
+            "auth-required" => ReasonPrefix::AuthRequired,
+            "error" => ReasonPrefix::Error,
+            other => ReasonPrefix::Other(other.to_owned()),
+        }
+    }
+}
+
+impl FromStr for ReasonPrefix {
+    type Err = Infallible;
+
+    fn from_str(s: &str) -> Result<ReasonPrefix, Infallible> {
+        Ok(s.into())
+    }
+}
+
+impl fmt::Display for ReasonPrefix {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+/// The reason given in an OK or CLOSED message: an optional machine-readable
+/// prefix, and a message for humans, written as `"<prefix>: <message>"`
+#[derive(Clone, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "speedy", derive(Readable, Writable))]
+pub struct Reason {
+    /// The machine-readable prefix, if there is one
+    pub prefix: Option<ReasonPrefix>,
+
+    /// The message for humans
+    pub message: String,
+}
+
+impl Reason {
+    /// Create a reason
+    pub fn new(prefix: Option<ReasonPrefix>, message: &str) -> Reason {
+        Reason {
+            prefix,
+            message: message.to_owned(),
+        }
+    }
+
+    /// Parse a reason. Text before the first colon is taken as the prefix only
+    /// if it is one of the known prefixes, so that a message such as
+    /// "https://example.com" is not split.
+    pub fn parse(s: &str) -> Reason {
+        if let Some((prefix, message)) = s.split_once(':') {
+            match ReasonPrefix::from(prefix) {
+                ReasonPrefix::Other(_) => {}
+                prefix => {
+                    return Reason {
+                        prefix: Some(prefix),
+                        message: message.strip_prefix(' ').unwrap_or(message).to_owned(),
+                    }
+                }
+            }
+        }
+        Reason {
+            prefix: None,
+            message: s.to_owned(),
+        }
+    }
+
+    // Mock data for testing
+    #[allow(dead_code)]
+    pub(crate) fn mock() -> Reason {
+        Reason::new(Some(ReasonPrefix::Duplicate), "already have this event")
+    }
+}
+
+impl fmt::Display for Reason {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.prefix {
+            Some(prefix) if self.message.is_empty() => write!(f, "{}:", prefix),
+            Some(prefix) => write!(f, "{}: {}", prefix, self.message),
+            None => write!(f, "{}", self.message),
+        }
+    }
+}
+
+impl From<&str> for Reason {
+    fn from(s: &str) -> Reason {
+        Reason::parse(s)
+    }
+}
+
+impl Serialize for Reason {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serializer.collect_str(self)
+    }
+}
+
+impl<'de> Deserialize<'de> for Reason {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let s = String::deserialize(deserializer)?;
+        Ok(Reason::parse(&s))
+    }
+}
+
+/// The result of a COUNT request (NIP-45)
+#[derive(Clone, Copy, Debug, Default, Deserialize, Eq, PartialEq, Serialize)]
+#[cfg_attr(feature = "speedy", derive(Readable, Writable))]
+pub struct CountResult {
+    /// The number of events matching the filters
+    pub count: u64,
+
+    /// Whether the count is an estimate
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(default)]
+    pub approximate: Option<bool>,
+}
+
+/// Limits on the relay messages accepted by `RelayMessage::parse_with_limits()`
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct RelayMessageLimits {
+    /// The longest message accepted, in bytes
+    pub max_message_length: usize,
+
+    /// The most elements accepted in any array of the message, including the
+    /// message itself and the tags of an event
+    pub max_array_length: usize,
+}
+
+impl Default for RelayMessageLimits {
+    fn default() -> RelayMessageLimits {
+        RelayMessageLimits {
+            max_message_length: 1024 * 1024,
+            max_array_length: 10_000,
+        }
+    }
+}
+
+const ORDINALS: [&str; 4] = ["first", "second", "third", "fourth"];
+
+// The JSON types of the elements of a relay message
+#[derive(Clone, Copy)]
+enum Expect {
+    String,
+    Boolean,
+    Object,
+}
+
+impl Expect {
+    fn matches(self, value: &serde_json::Value) -> bool {
+        match self {
+            Expect::String => value.is_string(),
+            Expect::Boolean => value.is_boolean(),
+            Expect::Object => value.is_object(),
+        }
+    }
+
+    fn describe(self) -> &'static str {
+        match self {
+            Expect::String => "a string",
+            Expect::Boolean => "a boolean",
+            Expect::Object => "an object",
+        }
+    }
+}
+
+// The elements of a relay message, with the checks and errors for reading them
+struct Frame<'a> {
+    word: &'a str,
+    elements: &'a [serde_json::Value],
+}
+
+impl Frame<'_> {
+    fn read<T: serde::de::DeserializeOwned>(
+        &self,
+        index: usize,
+        expect: Expect,
+        what: &str,
+    ) -> Result<T, Error> {
+        let invalid = |problem: String| {
+            Error::InvalidRelayMessage(
+                Some(index),
+                format!(
+                    "{} element of {} frame {problem}",
+                    ORDINALS[index], self.word
+                ),
+            )
+        };
+        let value = self.elements.get(index).ok_or_else(|| {
+            Error::InvalidRelayMessage(
+                Some(index),
+                format!("{} frame has no {} element", self.word, ORDINALS[index]),
+            )
+        })?;
+        if !expect.matches(value) {
+            return Err(invalid(format!("is not {}", expect.describe())));
+        }
+        T::deserialize(value).map_err(|e| invalid(format!("is not a valid {what}: {e}")))
+    }
+}
+
+// Find an array longer than the limit, anywhere in the value
+fn longest_array(value: &serde_json::Value) -> usize {
+    match value {
+        serde_json::Value::Array(elements) => elements
+            .iter()
+            .map(longest_array)
+            .fold(elements.len(), usize::max),
+        serde_json::Value::Object(map) => map.values().map(longest_array).max().unwrap_or(0),
+        _ => 0,
+    }
 }
 
 impl RelayMessage {
+    /// Parse a relay message from untrusted input, with the default
+    /// `RelayMessageLimits`.
+    ///
+    /// This never panics. Errors say which element of the message was
+    /// malformed, so that clients can log and skip bad messages.
+    pub fn parse(json: &str) -> Result<RelayMessage, Error> {
+        RelayMessage::parse_with_limits(json, &RelayMessageLimits::default())
+    }
+
+    /// Parse a relay message from untrusted input, rejecting it if it exceeds
+    /// the limits (see `parse()`)
+    pub fn parse_with_limits(
+        json: &str,
+        limits: &RelayMessageLimits,
+    ) -> Result<RelayMessage, Error> {
+        if json.len() > limits.max_message_length {
+            return Err(Error::InvalidRelayMessage(
+                None,
+                format!(
+                    "message is {} bytes, over the limit of {}",
+                    json.len(),
+                    limits.max_message_length
+                ),
+            ));
+        }
+        let value: serde_json::Value = serde_json::from_str(json)
+            .map_err(|e| Error::InvalidRelayMessage(None, format!("message is not JSON: {e}")))?;
+        let elements = value.as_array().ok_or_else(|| {
+            Error::InvalidRelayMessage(None, "message is not an array".to_owned())
+        })?;
+        let longest = longest_array(&value);
+        if longest > limits.max_array_length {
+            return Err(Error::InvalidRelayMessage(
+                None,
+                format!(
+                    "an array has {longest} elements, over the limit of {}",
+                    limits.max_array_length
+                ),
+            ));
+        }
+        let word = elements.first().and_then(|w| w.as_str()).ok_or_else(|| {
+            Error::InvalidRelayMessage(Some(0), "first element is not a string".to_owned())
+        })?;
+
+        let frame = Frame { word, elements };
+        match word {
+            "EVENT" => Ok(RelayMessage::Event(
+                frame.read(1, Expect::String, "subscription id")?,
+                Box::new(frame.read(2, Expect::Object, "event")?),
+            )),
+            "NOTICE" => Ok(RelayMessage::Notice(frame.read(
+                1,
+                Expect::String,
+                "message",
+            )?)),
+            "EOSE" => Ok(RelayMessage::Eose(frame.read(
+                1,
+                Expect::String,
+                "subscription id",
+            )?)),
+            "OK" => Ok(RelayMessage::Ok(
+                frame.read(1, Expect::String, "event id")?,
+                frame.read(2, Expect::Boolean, "status")?,
+                frame.read(3, Expect::String, "reason")?,
+            )),
+            "AUTH" => Ok(RelayMessage::Auth(frame.read(
+                1,
+                Expect::String,
+                "challenge",
+            )?)),
+            "CLOSED" => Ok(RelayMessage::Closed(
+                frame.read(1, Expect::String, "subscription id")?,
+                frame.read(2, Expect::String, "reason")?,
+            )),
+            "COUNT" => Ok(RelayMessage::Count(
+                frame.read(1, Expect::String, "subscription id")?,
+                frame.read(2, Expect::Object, "count")?,
+            )),
+            _ => Err(Error::InvalidRelayMessage(
+                Some(0),
+                format!("unknown message type {word:?}"),
+            )),
+        }
+    }
+
     // Mock data for testing
     #[allow(dead_code)]
     pub(crate) fn mock() -> RelayMessage {
@@ -73,6 +445,20 @@ impl Serialize for RelayMessage {
                 seq.serialize_element(&challenge)?;
                 seq.end()
             }
+            RelayMessage::Closed(id, reason) => {
+                let mut seq = serializer.serialize_seq(Some(3))?;
+                seq.serialize_element("CLOSED")?;
+                seq.serialize_element(&id)?;
+                seq.serialize_element(&reason)?;
+                seq.end()
+            }
+            RelayMessage::Count(id, result) => {
+                let mut seq = serializer.serialize_seq(Some(3))?;
+                seq.serialize_element("COUNT")?;
+                seq.serialize_element(&id)?;
+                seq.serialize_element(&result)?;
+                seq.end()
+            }
         }
     }
 }
@@ -127,15 +513,31 @@ impl<'de> Visitor<'de> for RelayMessageVisitor {
             let ok: bool = seq
                 .next_element()?
                 .ok_or_else(|| DeError::custom("Message missing ok field"))?;
-            let message: String = seq
+            let reason: Reason = seq
                 .next_element()?
                 .ok_or_else(|| DeError::custom("Message missing string field"))?;
-            Ok(RelayMessage::Ok(id, ok, message))
+            Ok(RelayMessage::Ok(id, ok, reason))
         } else if word == "AUTH" {
             let challenge: String = seq
                 .next_element()?
                 .ok_or_else(|| DeError::custom("Message missing challenge field"))?;
             Ok(RelayMessage::Auth(challenge))
+        } else if word == "CLOSED" {
+            let id: SubscriptionId = seq
+                .next_element()?
+                .ok_or_else(|| DeError::custom("Message missing id field"))?;
+            let reason: Reason = seq
+                .next_element()?
+                .ok_or_else(|| DeError::custom("Message missing string field"))?;
+            Ok(RelayMessage::Closed(id, reason))
+        } else if word == "COUNT" {
+            let id: SubscriptionId = seq
+                .next_element()?
+                .ok_or_else(|| DeError::custom("Message missing id field"))?;
+            let result: CountResult = seq
+                .next_element()?
+                .ok_or_else(|| DeError::custom("Message missing count field"))?;
+            Ok(RelayMessage::Count(id, result))
         } else {
             Err(DeError::custom(format!("Unknown Message: {word}")))
         }
@@ -147,4 +549,136 @@ mod test {
     use super::*;
 
     test_serde! {RelayMessage, test_relay_message_serde}
+    test_serde! {Reason, test_reason_serde}
+
+    #[test]
+    fn test_relay_message_wire_format() {
+        let id = Id::mock().as_hex_string();
+        for json in [
+            format!(r#"["OK","{id}",true,""]"#),
+            format!(r#"["OK","{id}",false,"pow: difficulty 25>=24"]"#),
+            r#"["CLOSED","sub1","auth-required: we only serve members"]"#.to_owned(),
+            r#"["CLOSED","sub1","shutting down"]"#.to_owned(),
+            r#"["COUNT","sub1",{"count":238}]"#.to_owned(),
+            r#"["COUNT","sub1",{"count":93412452,"approximate":true}]"#.to_owned(),
+            r#"["EOSE","sub1"]"#.to_owned(),
+            r#"["NOTICE","hello"]"#.to_owned(),
+        ] {
+            let message: RelayMessage = serde_json::from_str(&json).unwrap();
+            assert_eq!(serde_json::to_string(&message).unwrap(), json);
+        }
+
+        let message: RelayMessage =
+            serde_json::from_str(r#"["CLOSED","sub1","rate-limited: slow down"]"#).unwrap();
+        match message {
+            RelayMessage::Closed(_, reason) => {
+                assert_eq!(reason.prefix, Some(ReasonPrefix::RateLimited));
+                assert_eq!(reason.message, "slow down");
+            }
+            _ => panic!("expected CLOSED"),
+        }
+    }
+
+    #[test]
+    fn test_relay_message_parse() {
+        let event = serde_json::to_string(&Event::mock()).unwrap();
+        let json = format!(r#"["EVENT","sub1",{event}]"#);
+        assert_eq!(
+            RelayMessage::parse(&json).unwrap(),
+            serde_json::from_str::<RelayMessage>(&json).unwrap()
+        );
+        assert!(matches!(
+            RelayMessage::parse(r#"["OK","bad",true,""]"#),
+            Err(Error::InvalidRelayMessage(Some(1), _))
+        ));
+
+        let error = |json: &str| match RelayMessage::parse(json) {
+            Err(Error::InvalidRelayMessage(index, message)) => (index, message),
+            other => panic!("expected an error for {json}, got {other:?}"),
+        };
+        assert_eq!(
+            error(r#"["EVENT","sub1","event"]"#),
+            (
+                Some(2),
+                "third element of EVENT frame is not an object".to_owned()
+            )
+        );
+        assert_eq!(
+            error(r#"["EOSE"]"#),
+            (Some(1), "EOSE frame has no second element".to_owned())
+        );
+        assert_eq!(error(r#"[1,"sub1"]"#).0, Some(0));
+        assert_eq!(error(r#"["HELLO"]"#).0, Some(0));
+        assert_eq!(error(r#"{"EOSE":"sub1"}"#).0, None);
+        assert_eq!(error(r#"["EOSE","sub1""#).0, None);
+        assert_eq!(error(&"[".repeat(10_000)).0, None);
+        assert!(error(r#"["EVENT","sub1",{}]"#)
+            .1
+            .starts_with("third element of EVENT frame is not a valid event"));
+
+        let limits = RelayMessageLimits {
+            max_message_length: 100,
+            max_array_length: 3,
+        };
+        assert!(RelayMessage::parse_with_limits(r#"["EOSE","sub1"]"#, &limits).is_ok());
+        assert!(RelayMessage::parse_with_limits(&json, &limits).is_err());
+        assert!(RelayMessage::parse_with_limits(r#"["EOSE","sub1",[1,2,3,4]]"#, &limits).is_err());
+    }
+
+    #[test]
+    fn test_reason_parse() {
+        let reason = Reason::parse("blocked: you are banned from posting here");
+        assert_eq!(reason.prefix, Some(ReasonPrefix::Blocked));
+        assert_eq!(reason.message, "you are banned from posting here");
+
+        // Not a prefix
+        for s in [
+            "some-new-thing: details",
+            "Error: something happened",
+            "https://example.com/why",
+            "",
+        ] {
+            let reason = Reason::parse(s);
+            assert_eq!(reason.prefix, None);
+            assert_eq!(reason.message, s);
+        }
+    }
+
+    #[test]
+    fn test_reason_round_trip() {
+        for s in [
+            "blocked: you are banned from posting here",
+            "auth-required: we only accept events from registered users",
+            "error: see https://relay.example.com/errors",
+            "https://relay.example.com/errors",
+            "mute:",
+            "note: not a prefix",
+            "",
+        ] {
+            assert_eq!(Reason::parse(s).to_string(), s);
+            let reason = Reason::parse(s);
+            assert_eq!(Reason::parse(&reason.to_string()), reason);
+        }
+    }
+
+    #[test]
+    fn test_reason_prefix() {
+        for prefix in [
+            ReasonPrefix::Duplicate,
+            ReasonPrefix::Pow,
+            ReasonPrefix::Blocked,
+            ReasonPrefix::RateLimited,
+            ReasonPrefix::Invalid,
+            ReasonPrefix::Restricted,
+            ReasonPrefix::Mute,
+            ReasonPrefix::AuthRequired,
+            ReasonPrefix::Error,
+        ] {
+            assert_eq!(prefix.to_string().parse::<ReasonPrefix>(), Ok(prefix));
+        }
+        assert_eq!(
+            "auth-required".parse::<ReasonPrefix>(),
+            Ok(ReasonPrefix::AuthRequired)
+        );
+    }
 }
@@ -0,0 +1,153 @@
+use super::fast_parse::split_event_message;
+use super::{Event, EventSummary, RelayMessage, RelayMessageLimits, SubscriptionId};
+use crate::Error;
+use std::borrow::Cow;
+
+/// A message from a relay, read from a websocket text frame without copying
+/// the event it carries, if any.
+///
+/// Consumers of high-volume subscriptions can read each frame with
+/// `RelayMessageRef::parse()`, and then decide from the borrowed event JSON
+/// (for example with `quick_check()`) whether to parse and verify the event at
+/// all. Messages other than EVENT are small, and are parsed in full.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum RelayMessageRef<'a> {
+    /// An event matching a subscription
+    Event {
+        /// The subscription the event matches
+        subscription_id: Cow<'a, str>,
+
+        /// The JSON text of the event, borrowed from the frame. It is known to
+        /// be a well-formed JSON object, but has not been checked to be an
+        /// event.
+        event_json: &'a str,
+    },
+
+    /// Any other message
+    Other(RelayMessage),
+}
+
+impl<'a> RelayMessageRef<'a> {
+    /// Read a relay message from a websocket text frame, with the default
+    /// `RelayMessageLimits`
+    pub fn parse(frame: &'a str) -> Result<RelayMessageRef<'a>, Error> {
+        RelayMessageRef::parse_with_limits(frame, &RelayMessageLimits::default())
+    }
+
+    /// Read a relay message from a websocket text frame. Only the length of an
+    /// EVENT message is checked against the limits, as its event is not parsed;
+    /// other messages are parsed with `RelayMessage::parse_with_limits()`.
+    pub fn parse_with_limits(
+        frame: &'a str,
+        limits: &RelayMessageLimits,
+    ) -> Result<RelayMessageRef<'a>, Error> {
+        if frame.len() > limits.max_message_length {
+            return Err(Error::InvalidRelayMessage(
+                None,
+                format!(
+                    "message is {} bytes, over the limit of {}",
+                    frame.len(),
+                    limits.max_message_length
+                ),
+            ));
+        }
+        match split_event_message(frame)? {
+            Some((subscription_id, event_json)) => Ok(RelayMessageRef::Event {
+                subscription_id,
+                event_json,
+            }),
+            None => Ok(RelayMessageRef::Other(RelayMessage::parse_with_limits(
+                frame, limits,
+            )?)),
+        }
+    }
+
+    /// Check the structure of the event, if this is an EVENT message (see
+    /// `Event::quick_check_json()`)
+    pub fn quick_check(&self) -> Option<Result<EventSummary, Error>> {
+        match self {
+            RelayMessageRef::Event { event_json, .. } => Some(Event::quick_check_json(event_json)),
+            RelayMessageRef::Other(_) => None,
+        }
+    }
+
+    /// Parse the event, if this is an EVENT message. This does not verify it.
+    pub fn event(&self) -> Option<Result<Event, Error>> {
+        match self {
+            RelayMessageRef::Event { event_json, .. } => {
+                Some(Event::from_json_fast(event_json.as_bytes()))
+            }
+            RelayMessageRef::Other(_) => None,
+        }
+    }
+
+    /// Convert into an owned `RelayMessage`, parsing the event if there is one
+    pub fn into_owned(self) -> Result<RelayMessage, Error> {
+        match self {
+            RelayMessageRef::Event {
+                subscription_id,
+                event_json,
+            } => Ok(RelayMessage::Event(
+                SubscriptionId(subscription_id.into_owned()),
+                Box::new(Event::from_json_fast(event_json.as_bytes())?),
+            )),
+            RelayMessageRef::Other(message) => Ok(message),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_relay_message_ref() {
+        let event = Event::mock();
+        let event_json = serde_json::to_string(&event).unwrap();
+        let frame = format!(r#"[ "EVENT", "sub1", {event_json} ]"#);
+
+        let message = RelayMessageRef::parse(&frame).unwrap();
+        match &message {
+            RelayMessageRef::Event {
+                subscription_id,
+                event_json: borrowed,
+            } => {
+                assert!(matches!(subscription_id, Cow::Borrowed("sub1")));
+                assert_eq!(*borrowed, event_json);
+                // Borrowed from the frame, not copied
+                assert!(frame.as_bytes().as_ptr_range().contains(&borrowed.as_ptr()));
+            }
+            _ => panic!("expected EVENT"),
+        }
+        assert_eq!(message.quick_check().unwrap().unwrap().id, event.id);
+        assert_eq!(
+            message.into_owned().unwrap(),
+            serde_json::from_str::<RelayMessage>(&frame).unwrap()
+        );
+
+        // Escaped subscription ids are decoded
+        let frame = format!(r#"["EVENT","sub\"1",{event_json}]"#);
+        match RelayMessageRef::parse(&frame).unwrap() {
+            RelayMessageRef::Event {
+                subscription_id, ..
+            } => assert_eq!(subscription_id, "sub\"1"),
+            _ => panic!("expected EVENT"),
+        }
+
+        let message = RelayMessageRef::parse(r#"["EOSE","sub1"]"#).unwrap();
+        assert_eq!(
+            message,
+            RelayMessageRef::Other(RelayMessage::Eose(SubscriptionId("sub1".to_owned())))
+        );
+        assert!(message.event().is_none());
+
+        assert!(RelayMessageRef::parse(r#"["EVENT","sub1","event"]"#).is_err());
+        assert!(RelayMessageRef::parse(r#"["EVENT","sub1",{"id":]"#).is_err());
+        assert!(RelayMessageRef::parse(&format!(r#"["EVENT","sub1",{event_json}]x"#)).is_err());
+        assert!(RelayMessageRef::parse(r#"["EVENT","sub1",{}]"#)
+            .unwrap()
+            .event()
+            .unwrap()
+            .is_err());
+    }
+}
@@ -1,3 +1,4 @@
+use super::fast_hex;
 use crate::{Error, Event};
 use derive_more::{AsMut, AsRef, Deref, Display, From, FromStr, Into};
 use k256::schnorr::Signature as KSignature;
@@ -16,11 +17,19 @@ pub struct Signature(pub KSignature);
 impl Signature {
     /// Render into a hexadecimal string
     pub fn as_hex_string(&self) -> String {
-        hex::encode(self.0.to_bytes())
+        fast_hex::encode(&self.0.to_bytes())
+    }
+
+    /// Write as a hexadecimal string, without allocating
+    pub fn write_hex<W: fmt::Write + ?Sized>(&self, w: &mut W) -> fmt::Result {
+        fast_hex::write_hex(&self.0.to_bytes(), w)
     }
 
     /// Create from a hexadecimal string
     pub fn try_from_hex_string(v: &str) -> Result<Signature, Error> {
+        if let Some(bytes) = fast_hex::decode_array::<64>(v) {
+            return Ok(Signature(KSignature::try_from(&bytes[..])?));
+        }
         let vec: Vec<u8> = hex::decode(v)?;
         Ok(Signature(KSignature::try_from(&*vec)?))
     }
@@ -38,7 +47,7 @@ impl Se for Signature {
     where
         S: Serializer,
     {
-        serializer.serialize_str(&hex::encode(self.to_bytes()))
+        fast_hex::with_hex(&self.to_bytes(), |hex| serializer.serialize_str(hex))
     }
 }
 
@@ -62,14 +71,21 @@ impl Visitor<'_> for SignatureVisitor {
 
     fn visit_str<E>(self, v: &str) -> Result<Signature, E>
     where
-        E: serde::de::Error,
+        E: DeserializeError,
     {
-        let vec: Vec<u8> = hex::decode(v).map_err(|e| serde::de::Error::custom(format!("{e}")))?;
+        let vec: Vec<u8> = match fast_hex::decode_array::<64>(v) {
+            Some(bytes) => {
+                return KSignature::try_from(&bytes[..])
+                    .map(Signature)
+                    .map_err(|e| DeserializeError::custom(format!("{e}")));
+            }
+            None => hex::decode(v).map_err(|e| DeserializeError::custom(format!("{e}")))?,
+        };
 
         // If we don't catch this ourselves, the below from_bytes will panic when it
         // gets into an assertion within generic-array
         if vec.len() != 64 {
-            return Err(serde::de::Error::custom("Signature is not 64 bytes long"));
+            return Err(DeserializeError::custom("Signature is not 64 bytes long"));
         }
 
         let ksig: KSignature =
@@ -83,14 +99,16 @@ impl Visitor<'_> for SignatureVisitor {
 impl<'a, C: Context> Readable<'a, C> for Signature {
     #[inline]
     fn read_from<R: Reader<'a, C>>(reader: &mut R) -> Result<Self, C::Error> {
-        let bytes = <[u8; 32]>::read_from(reader)?;
-        let sig = KSignature::try_from(&bytes[..]).map_err(|e| speedy::Error::custom(e))?;
-        Ok(Signature(sig))
+        let mut bytes = [0_u8; 64];
+        reader.read_bytes(&mut bytes)?;
+        Ok(Signature(
+            KSignature::try_from(&bytes[..]).map_err(|e| speedy::Error::custom(e))?,
+        ))
     }
 
     #[inline]
     fn minimum_bytes_needed() -> usize {
-        32
+        64
     }
 }
 
@@ -98,12 +116,12 @@ impl<'a, C: Context> Readable<'a, C> for Signature {
 impl<C: Context> Writable<C> for Signature {
     #[inline]
     fn write_to<T: ?Sized + Writer<C>>(&self, writer: &mut T) -> Result<(), C::Error> {
-        self.0.to_bytes().write_to(writer)
+        writer.write_bytes(&self.0.to_bytes())
     }
 
     #[inline]
     fn bytes_needed(&self) -> Result<usize, C::Error> {
-        Ok(32)
+        Ok(64)
     }
 }
 
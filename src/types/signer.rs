@@ -0,0 +1,42 @@
+use super::{Event, Id, PreEvent, PrivateKey, PublicKey, Signature};
+use crate::Error;
+
+/// Something that signs events for a public key. This is implemented by
+/// `PrivateKey` and `TestSigner`, and may be implemented by applications for
+/// keys held elsewhere (e.g. a hardware device or a remote signer).
+pub trait Signer {
+    /// The public key that signatures are made for
+    fn public_key(&self) -> PublicKey;
+
+    /// Sign a 32-byte event id
+    fn sign_id(&self, id: Id) -> Result<Signature, Error>;
+
+    /// Sign a PreEvent, creating an Event
+    fn sign_event(&self, input: PreEvent) -> Result<Event, Error> {
+        if input.pubkey != self.public_key() {
+            return Err(Error::InvalidPublicKey);
+        }
+        let id = Event::hash(&input)?;
+        let sig = self.sign_id(id)?;
+        Ok(Event {
+            id,
+            pubkey: input.pubkey,
+            created_at: input.created_at,
+            kind: input.kind,
+            tags: input.tags,
+            content: input.content,
+            ots: input.ots,
+            sig,
+        })
+    }
+}
+
+impl Signer for PrivateKey {
+    fn public_key(&self) -> PublicKey {
+        PrivateKey::public_key(self)
+    }
+
+    fn sign_id(&self, id: Id) -> Result<Signature, Error> {
+        PrivateKey::sign_id(self, id)
+    }
+}
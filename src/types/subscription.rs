@@ -0,0 +1,161 @@
+use super::{ClientMessage, Filter, Reason, RelayMessage, SubscriptionId};
+
+/// Where a subscription is in its lifecycle
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum SubscriptionState {
+    /// The REQ has been sent, and the relay is sending the stored events
+    /// matching it
+    Requested,
+
+    /// The relay has sent all its stored events (EOSE), and now sends new
+    /// events as they arrive
+    Live,
+
+    /// The client closed the subscription (CLOSE)
+    ClosedByClient,
+
+    /// The relay closed the subscription (CLOSED), for this reason
+    ClosedByRelay(Reason),
+}
+
+/// A subscription to a relay, tracking its state as REQ, EOSE, CLOSE and
+/// CLOSED messages pass.
+///
+/// Feed each message from the relay to `handle()`, which updates the state if
+/// the message is for this subscription.
+#[derive(Clone, Debug)]
+pub struct Subscription {
+    id: SubscriptionId,
+    filters: Vec<Filter>,
+    state: SubscriptionState,
+    stored_events: usize,
+    live_events: usize,
+}
+
+impl Subscription {
+    /// Start a subscription, returning it with the REQ message to send
+    pub fn new(id: SubscriptionId, filters: Vec<Filter>) -> (Subscription, ClientMessage) {
+        let req = ClientMessage::Req(id.clone(), filters.clone());
+        let subscription = Subscription {
+            id,
+            filters,
+            state: SubscriptionState::Requested,
+            stored_events: 0,
+            live_events: 0,
+        };
+        (subscription, req)
+    }
+
+    /// The subscription id
+    pub fn id(&self) -> &SubscriptionId {
+        &self.id
+    }
+
+    /// The filters subscribed to
+    pub fn filters(&self) -> &[Filter] {
+        &self.filters
+    }
+
+    /// The state of the subscription
+    pub fn state(&self) -> &SubscriptionState {
+        &self.state
+    }
+
+    /// Whether the subscription is still open
+    pub fn is_open(&self) -> bool {
+        matches!(
+            self.state,
+            SubscriptionState::Requested | SubscriptionState::Live
+        )
+    }
+
+    /// The number of stored events received (those before EOSE)
+    pub fn stored_events(&self) -> usize {
+        self.stored_events
+    }
+
+    /// The number of new events received (those after EOSE)
+    pub fn live_events(&self) -> usize {
+        self.live_events
+    }
+
+    /// Close the subscription, returning the CLOSE message to send, or `None`
+    /// if it is already closed
+    pub fn close(&mut self) -> Option<ClientMessage> {
+        if !self.is_open() {
+            return None;
+        }
+        self.state = SubscriptionState::ClosedByClient;
+        Some(ClientMessage::Close(self.id.clone()))
+    }
+
+    /// Update the subscription with a message from the relay. This returns
+    /// whether the message was for this subscription while it was open; any
+    /// other message (including events arriving after it was closed) should be
+    /// ignored as far as this subscription is concerned.
+    pub fn handle(&mut self, message: &RelayMessage) -> bool {
+        if !self.is_open() {
+            return false;
+        }
+        match message {
+            RelayMessage::Event(id, _) if *id == self.id => {
+                if self.state == SubscriptionState::Live {
+                    self.live_events += 1;
+                } else {
+                    self.stored_events += 1;
+                }
+            }
+            RelayMessage::Eose(id) if *id == self.id => {
+                self.state = SubscriptionState::Live;
+            }
+            RelayMessage::Closed(id, reason) if *id == self.id => {
+                self.state = SubscriptionState::ClosedByRelay(reason.clone());
+            }
+            _ => return false,
+        }
+        true
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{Event, ReasonPrefix};
+
+    #[test]
+    fn test_subscription_lifecycle() {
+        let id = SubscriptionId("sub1".to_owned());
+        let other = SubscriptionId("sub2".to_owned());
+        let (mut subscription, req) = Subscription::new(id.clone(), vec![Filter::mock()]);
+        assert_eq!(req, ClientMessage::Req(id.clone(), vec![Filter::mock()]));
+        assert_eq!(subscription.state(), &SubscriptionState::Requested);
+
+        let event = |id: &SubscriptionId| RelayMessage::Event(id.clone(), Box::new(Event::mock()));
+        assert!(subscription.handle(&event(&id)));
+        assert!(!subscription.handle(&event(&other)));
+        assert!(!subscription.handle(&RelayMessage::Eose(other)));
+        assert!(subscription.handle(&RelayMessage::Eose(id.clone())));
+        assert_eq!(subscription.state(), &SubscriptionState::Live);
+        assert!(subscription.handle(&event(&id)));
+        assert!(subscription.handle(&event(&id)));
+        assert_eq!(subscription.stored_events(), 1);
+        assert_eq!(subscription.live_events(), 2);
+
+        let closed = RelayMessage::Closed(id.clone(), Reason::parse("rate-limited: slow down"));
+        assert!(subscription.handle(&closed));
+        match subscription.state() {
+            SubscriptionState::ClosedByRelay(reason) => {
+                assert_eq!(reason.prefix, Some(ReasonPrefix::RateLimited))
+            }
+            state => panic!("unexpected state {state:?}"),
+        }
+        assert!(!subscription.is_open());
+        assert!(!subscription.handle(&event(&id)));
+        assert_eq!(subscription.close(), None);
+
+        let (mut subscription, _) = Subscription::new(id.clone(), vec![]);
+        assert_eq!(subscription.close(), Some(ClientMessage::Close(id.clone())));
+        assert_eq!(subscription.state(), &SubscriptionState::ClosedByClient);
+        assert!(!subscription.handle(&RelayMessage::Eose(id)));
+    }
+}
@@ -1,8 +1,13 @@
-use crate::{DelegationConditions, Id, PublicKeyHex, SignatureHex, UncheckedUrl, Unixtime};
+use super::event_addr::split_tag_value;
+use crate::{
+    DelegationConditions, Error, Event, EventKind, Id, PrivateKey, PublicKey, PublicKeyHex,
+    Signature, SignatureHex, UncheckedUrl, Unixtime,
+};
 use serde::de::{Deserialize, Deserializer, SeqAccess, Visitor};
-use serde::ser::{Serialize, SerializeSeq, Serializer};
+use serde::ser::{Serialize, Serializer};
 #[cfg(feature = "speedy")]
 use speedy::{Readable, Writable};
+use std::borrow::Cow;
 use std::fmt;
 
 /// A tag on an Event
@@ -102,12 +107,37 @@ pub enum Tag {
 
     /// An empty array (kept so signature remains valid across ser/de)
     Empty,
+
+    // New variants go at the end, so as not to change the binary encoding of
+    // existing ones
+    /// 'a' A reference to a replaceable or parameterized replaceable event by its
+    /// address (see `EventAddr`)
+    Address {
+        /// The kind of the referenced event
+        kind: EventKind,
+
+        /// The author of the referenced event
+        pubkey: PublicKeyHex,
+
+        /// The 'd' tag of the referenced event (empty for replaceable events)
+        d: String,
+
+        /// A recommended relay URL to find that other event
+        relay_url: Option<UncheckedUrl>,
+
+        /// A marker (commonly things like 'reply')
+        marker: Option<String>,
+    },
+
+    /// '-' The event may only be published to relays by its author (NIP-70)
+    Protected,
 }
 
 impl Tag {
     /// Get the tag name for the tag (the first string in the array)a
     pub fn tagname(&self) -> String {
         match self {
+            Tag::Address { .. } => "a".to_string(),
             Tag::ContentWarning(_) => "content-warning".to_string(),
             Tag::Delegation { .. } => "delegation".to_string(),
             Tag::Event { .. } => "e".to_string(),
@@ -119,6 +149,7 @@ impl Tag {
             Tag::Identifier(_) => "d".to_string(),
             Tag::Subject(_) => "subject".to_string(),
             Tag::Nonce { .. } => "nonce".to_string(),
+            Tag::Protected => "-".to_string(),
             Tag::Parameter(_) => "parameter".to_string(),
             Tag::Title(_) => "title".to_string(),
             Tag::Other { tag, .. } => tag.clone(),
@@ -126,6 +157,115 @@ impl Tag {
         }
     }
 
+    /// Get the value of the tag (the second string in the array), which is what
+    /// filters match tags on. Tags without a value give `None`.
+    pub fn value(&self) -> Option<String> {
+        match self {
+            Tag::Address {
+                kind, pubkey, d, ..
+            } => Some(format!("{}:{}:{}", u64::from(*kind), pubkey, d)),
+            Tag::ContentWarning(msg) => Some(msg.clone()),
+            Tag::Delegation { pubkey, .. } => Some(pubkey.as_str().to_owned()),
+            Tag::Event { id, .. } => Some(id.as_hex_string()),
+            Tag::Expiration(time) => Some(time.0.to_string()),
+            Tag::Pubkey { pubkey, .. } => Some(pubkey.as_str().to_owned()),
+            Tag::Hashtag(hashtag) => Some(hashtag.clone()),
+            Tag::Reference { url, .. } => Some(url.as_str().to_owned()),
+            Tag::Geohash(geohash) => Some(geohash.clone()),
+            Tag::Identifier(d) => Some(d.clone()),
+            Tag::Subject(subject) => Some(subject.clone()),
+            Tag::Nonce { nonce, .. } => Some(nonce.clone()),
+            Tag::Protected => None,
+            Tag::Parameter(parameter) => Some(parameter.clone()),
+            Tag::Title(title) => Some(title.clone()),
+            Tag::Other { data, .. } => data.first().cloned(),
+            Tag::Empty => None,
+        }
+    }
+
+    /// Create a content warning tag (NIP-36), with an optional reason
+    pub fn new_content_warning(reason: Option<&str>) -> Tag {
+        match reason {
+            Some(reason) => Tag::ContentWarning(reason.to_owned()),
+            None => Tag::Other {
+                tag: "content-warning".to_owned(),
+                data: vec![],
+            },
+        }
+    }
+
+    /// Create a delegation tag (NIP-26), signed by the delegator, which allows the
+    /// delegatee to create events on their behalf under the given conditions
+    pub fn new_delegation(
+        delegator: &PrivateKey,
+        delegatee: &PublicKey,
+        conditions: &DelegationConditions,
+    ) -> Result<Tag, Error> {
+        let sig = conditions.sign(&delegatee.as_hex_string(), delegator)?;
+        Ok(Tag::Delegation {
+            pubkey: delegator.public_key().into(),
+            conditions: conditions.clone(),
+            sig,
+        })
+    }
+
+    /// If this is a delegation tag (NIP-26), get its parsed conditions
+    pub fn delegation_conditions(&self) -> Option<&DelegationConditions> {
+        match self {
+            Tag::Delegation { conditions, .. } => Some(conditions),
+            _ => None,
+        }
+    }
+
+    /// Validate this delegation tag (NIP-26) for an event, checking that the tag
+    /// is on the event, that the delegator signed it for the event's author, and
+    /// that the event's kind and created_at meet the conditions.
+    ///
+    /// Returns the delegator's public key.
+    pub fn validate_delegation_for_event(&self, event: &Event) -> Result<PublicKey, Error> {
+        let (pubkey, conditions, sig) = match self {
+            Tag::Delegation {
+                pubkey,
+                conditions,
+                sig,
+            } => (pubkey, conditions, sig),
+            _ => return Err(Error::InvalidDelegation("Not a delegation tag".to_owned())),
+        };
+
+        if !event.tags.contains(self) {
+            return Err(Error::InvalidDelegation(
+                "Delegation tag is not on the event".to_owned(),
+            ));
+        }
+
+        let signature = Signature::try_from_hex_string(sig)?;
+        let delegator = PublicKey::try_from_hex_string(pubkey)?;
+        conditions.verify_signature(&delegator, &event.pubkey, signature)?;
+        conditions.check(event.kind, event.created_at)?;
+
+        Ok(delegator)
+    }
+
+    /// Create a nonce tag for proof of work (NIP-13), committing to the target
+    /// difficulty in bits
+    pub fn new_nonce(nonce: u64, target: u8) -> Tag {
+        Tag::Nonce {
+            nonce: format!("{nonce}"),
+            target: Some(format!("{target}")),
+        }
+    }
+
+    /// If this is a nonce tag (NIP-13), get the target difficulty it commits to
+    pub fn nonce_target(&self) -> Option<u8> {
+        match self {
+            Tag::Nonce {
+                target: Some(target),
+                ..
+            } => target.parse::<u8>().ok(),
+            _ => None,
+        }
+    }
+
     // Mock data for testing
     #[allow(dead_code)]
     pub(crate) fn mock() -> Tag {
@@ -137,139 +277,144 @@ impl Tag {
     }
 }
 
-impl Serialize for Tag {
+// One element of a tag as it is written, borrowed from the tag where it can be
+pub(crate) enum TagElement<'a> {
+    Str(Cow<'a, str>),
+    Int(i64),
+}
+
+impl<'a> TagElement<'a> {
+    // The element as a string, as it appears in JSON
+    pub(crate) fn into_str(self) -> Cow<'a, str> {
+        match self {
+            TagElement::Str(s) => s,
+            TagElement::Int(i) => Cow::Owned(i.to_string()),
+        }
+    }
+}
+
+impl<'a, T: Into<Cow<'a, str>>> From<T> for TagElement<'a> {
+    fn from(s: T) -> TagElement<'a> {
+        TagElement::Str(s.into())
+    }
+}
+
+impl Serialize for TagElement<'_> {
     fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
     where
         S: Serializer,
     {
         match self {
-            Tag::ContentWarning(msg) => {
-                let mut seq = serializer.serialize_seq(None)?;
-                seq.serialize_element("content-warning")?;
-                seq.serialize_element(msg)?;
-                seq.end()
+            TagElement::Str(s) => serializer.serialize_str(s),
+            TagElement::Int(i) => serializer.serialize_i64(*i),
+        }
+    }
+}
+
+impl Tag {
+    // The elements of the tag, as it is serialized
+    pub(crate) fn elements(&self) -> Vec<TagElement<'_>> {
+        // An optional element, written as "" if it is missing but the one after
+        // it is present, followed by that one
+        fn optional_pair<'a>(
+            elements: &mut Vec<TagElement<'a>>,
+            first: Option<&'a str>,
+            second: Option<&'a str>,
+        ) {
+            match (first, second) {
+                (Some(first), _) => elements.push(first.into()),
+                (None, Some(_)) => elements.push("".into()),
+                (None, None) => {}
+            }
+            elements.extend(second.map(TagElement::from));
+        }
+
+        let mut elements: Vec<TagElement<'_>> = Vec::new();
+        match self {
+            Tag::Address {
+                kind,
+                pubkey,
+                d,
+                relay_url,
+                marker,
+            } => {
+                elements.push("a".into());
+                elements.push(format!("{}:{}:{}", u64::from(*kind), pubkey, d).into());
+                optional_pair(
+                    &mut elements,
+                    relay_url.as_ref().map(|url| url.as_str()),
+                    marker.as_deref(),
+                );
             }
+            Tag::ContentWarning(msg) => elements.extend(["content-warning".into(), msg.into()]),
             Tag::Delegation {
                 pubkey,
                 conditions,
                 sig,
-            } => {
-                let mut seq = serializer.serialize_seq(None)?;
-                seq.serialize_element("delegation")?;
-                seq.serialize_element(pubkey)?;
-                seq.serialize_element(conditions)?;
-                seq.serialize_element(sig)?;
-                seq.end()
-            }
+            } => elements.extend([
+                "delegation".into(),
+                pubkey.as_str().into(),
+                conditions.as_string().into(),
+                sig.0.as_str().into(),
+            ]),
             Tag::Event {
                 id,
                 recommended_relay_url,
                 marker,
             } => {
-                let mut seq = serializer.serialize_seq(None)?;
-                seq.serialize_element("e")?;
-                seq.serialize_element(id)?;
-                if let Some(rru) = recommended_relay_url {
-                    seq.serialize_element(rru)?;
-                } else if marker.is_some() {
-                    seq.serialize_element("")?;
-                }
-                if let Some(m) = marker {
-                    seq.serialize_element(m)?;
-                }
-                seq.end()
+                elements.extend(["e".into(), id.as_hex_string().into()]);
+                optional_pair(
+                    &mut elements,
+                    recommended_relay_url.as_ref().map(|url| url.as_str()),
+                    marker.as_deref(),
+                );
             }
             Tag::Expiration(time) => {
-                let mut seq = serializer.serialize_seq(None)?;
-                seq.serialize_element("expiration")?;
-                seq.serialize_element(time)?;
-                seq.end()
+                elements.extend(["expiration".into(), TagElement::Int(time.0)])
             }
             Tag::Pubkey {
                 pubkey,
                 recommended_relay_url,
                 petname,
             } => {
-                let mut seq = serializer.serialize_seq(None)?;
-                seq.serialize_element("p")?;
-                seq.serialize_element(pubkey)?;
-                if let Some(rru) = recommended_relay_url {
-                    seq.serialize_element(rru)?;
-                } else if petname.is_some() {
-                    seq.serialize_element("")?;
-                }
-                if let Some(pn) = petname {
-                    seq.serialize_element(pn)?;
-                }
-                seq.end()
-            }
-            Tag::Hashtag(hashtag) => {
-                let mut seq = serializer.serialize_seq(None)?;
-                seq.serialize_element("t")?;
-                seq.serialize_element(hashtag)?;
-                seq.end()
+                elements.extend(["p".into(), pubkey.as_str().into()]);
+                optional_pair(
+                    &mut elements,
+                    recommended_relay_url.as_ref().map(|url| url.as_str()),
+                    petname.as_deref(),
+                );
             }
+            Tag::Hashtag(hashtag) => elements.extend(["t".into(), hashtag.into()]),
             Tag::Reference { url, marker } => {
-                let mut seq = serializer.serialize_seq(None)?;
-                seq.serialize_element("r")?;
-                seq.serialize_element(url)?;
-                if let Some(m) = marker {
-                    seq.serialize_element(m)?
-                }
-                seq.end()
-            }
-            Tag::Geohash(geohash) => {
-                let mut seq = serializer.serialize_seq(None)?;
-                seq.serialize_element("g")?;
-                seq.serialize_element(geohash)?;
-                seq.end()
-            }
-            Tag::Identifier(id) => {
-                let mut seq = serializer.serialize_seq(None)?;
-                seq.serialize_element("d")?;
-                seq.serialize_element(id)?;
-                seq.end()
-            }
-            Tag::Subject(subject) => {
-                let mut seq = serializer.serialize_seq(None)?;
-                seq.serialize_element("subject")?;
-                seq.serialize_element(subject)?;
-                seq.end()
+                elements.extend(["r".into(), url.as_str().into()]);
+                elements.extend(marker.as_deref().map(TagElement::from));
             }
+            Tag::Geohash(geohash) => elements.extend(["g".into(), geohash.into()]),
+            Tag::Identifier(id) => elements.extend(["d".into(), id.into()]),
+            Tag::Subject(subject) => elements.extend(["subject".into(), subject.into()]),
             Tag::Nonce { nonce, target } => {
-                let mut seq = serializer.serialize_seq(None)?;
-                seq.serialize_element("nonce")?;
-                seq.serialize_element(nonce)?;
-                if let Some(t) = target {
-                    seq.serialize_element(t)?;
-                }
-                seq.end()
-            }
-            Tag::Parameter(parameter) => {
-                let mut seq = serializer.serialize_seq(None)?;
-                seq.serialize_element("parameter")?;
-                seq.serialize_element(parameter)?;
-                seq.end()
-            }
-            Tag::Title(title) => {
-                let mut seq = serializer.serialize_seq(None)?;
-                seq.serialize_element("title")?;
-                seq.serialize_element(title)?;
-                seq.end()
+                elements.extend(["nonce".into(), nonce.into()]);
+                elements.extend(target.as_deref().map(TagElement::from));
             }
+            Tag::Protected => elements.push("-".into()),
+            Tag::Parameter(parameter) => elements.extend(["parameter".into(), parameter.into()]),
+            Tag::Title(title) => elements.extend(["title".into(), title.into()]),
             Tag::Other { tag, data } => {
-                let mut seq = serializer.serialize_seq(None)?;
-                seq.serialize_element(tag)?;
-                for s in data.iter() {
-                    seq.serialize_element(s)?;
-                }
-                seq.end()
-            }
-            Tag::Empty => {
-                let seq = serializer.serialize_seq(Some(0))?;
-                seq.end()
+                elements.push(tag.into());
+                elements.extend(data.iter().map(TagElement::from));
             }
+            Tag::Empty => {}
         }
+        elements
+    }
+}
+
+impl Serialize for Tag {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serializer.collect_seq(self.elements())
     }
 }
 
@@ -299,7 +444,44 @@ impl<'de> Visitor<'de> for TagVisitor {
             Some(e) => e,
             None => return Ok(Tag::Empty),
         };
-        if tagname == "content-warning" {
+        if tagname == "a" {
+            let value: String = match seq.next_element()? {
+                Some(v) => v,
+                None => {
+                    return Ok(Tag::Other {
+                        tag: tagname.to_string(),
+                        data: vec![],
+                    });
+                }
+            };
+            // Only a value already in canonical form is typed, so that the tag
+            // is written back exactly as it was signed
+            let canonical = split_tag_value(&value).filter(|(kind, pubkey, d)| {
+                format!("{}:{}:{}", u64::from(EventKind::from(*kind)), pubkey, d) == value
+            });
+            let (kind, pubkey, d) = match canonical {
+                Some((kind, pubkey, d)) => (kind, pubkey, d),
+                None => {
+                    let mut data = vec![value.clone()];
+                    while let Some(s) = seq.next_element()? {
+                        data.push(s);
+                    }
+                    return Ok(Tag::Other {
+                        tag: tagname.to_string(),
+                        data,
+                    });
+                }
+            };
+            let relay_url: Option<UncheckedUrl> = seq.next_element()?;
+            let marker: Option<String> = seq.next_element()?;
+            Ok(Tag::Address {
+                kind: EventKind::from(kind),
+                pubkey,
+                d: d.to_owned(),
+                relay_url,
+                marker,
+            })
+        } else if tagname == "content-warning" {
             let msg = match seq.next_element()? {
                 Some(s) => s,
                 None => {
@@ -454,6 +636,8 @@ impl<'de> Visitor<'de> for TagVisitor {
             };
             let target: Option<String> = seq.next_element()?;
             Ok(Tag::Nonce { nonce, target })
+        } else if tagname == "-" {
+            Ok(Tag::Protected)
         } else if tagname == "parameter" {
             let param = match seq.next_element()? {
                 Some(s) => s,
@@ -493,4 +677,99 @@ mod test {
     use super::*;
 
     test_serde! {Tag, test_tag_serde}
+
+    #[test]
+    fn test_delegation_tag_serde() {
+        // A delegation tag serializes as the 4-element NIP-26 array, so it can be
+        // used directly in Event.tags or in other serde structures
+        let json = r#"["delegation","8e0d3d3eb2881ec137a11debe736a9086715a8c8beeeda615780064d68bc25dd","kind=1&created_at>1674834236&created_at<1677426236","6f44d7fe4f1c09f3954640fb58bd12bae8bb8ff4120853c4693106c82e920e2b898f1f9ba9bd65449a987c39932933c7eb5db5b8e4c1c4e1cdc4b1b4a7c9ae73"]"#;
+        let tag: Tag = serde_json::from_str(json).unwrap();
+        assert_eq!(
+            tag.delegation_conditions().map(|c| c.as_string()),
+            Some("kind=1&created_at>1674834236&created_at<1677426236".to_owned())
+        );
+        assert_eq!(serde_json::to_string(&tag).unwrap(), json);
+
+        let tags: Vec<Tag> = serde_json::from_str(&format!("[{json}]")).unwrap();
+        assert_eq!(tags, vec![tag]);
+    }
+
+    #[test]
+    fn test_validate_delegation_for_event() {
+        use crate::{EventKind, PreEvent};
+
+        let delegator = PrivateKey::generate();
+        let delegatee = PrivateKey::generate();
+        let tag = Tag::new_delegation(
+            &delegator,
+            &delegatee.public_key(),
+            &DelegationConditions::new().kind(EventKind::TextNote),
+        )
+        .unwrap();
+        let preevent = PreEvent {
+            pubkey: delegatee.public_key(),
+            created_at: Unixtime::mock(),
+            kind: EventKind::TextNote,
+            tags: vec![tag.clone()],
+            content: "".to_owned(),
+            ots: None,
+        };
+        let event = Event::new(preevent.clone(), &delegatee).unwrap();
+        assert_eq!(
+            tag.delegation_conditions().unwrap().kinds,
+            vec![EventKind::TextNote]
+        );
+        assert_eq!(
+            tag.validate_delegation_for_event(&event).unwrap(),
+            delegator.public_key()
+        );
+
+        // Not on the event
+        let mut other = preevent.clone();
+        other.tags = vec![];
+        let event = Event::new(other, &delegatee).unwrap();
+        assert!(tag.validate_delegation_for_event(&event).is_err());
+
+        // Authored by someone other than the delegatee
+        let mut event = Event::new(preevent, &delegatee).unwrap();
+        event.pubkey = PrivateKey::generate().public_key();
+        assert!(tag.validate_delegation_for_event(&event).is_err());
+    }
+
+    #[test]
+    fn test_address_tag_non_canonical() {
+        use crate::PreEvent;
+
+        let private_key = PrivateKey::generate();
+        let hex = private_key.public_key().as_hex_string();
+        for value in [
+            format!("30023:{}:x", hex.to_ascii_uppercase()),
+            format!("030023:{hex}:x"),
+            format!("+30023:{hex}:x"),
+        ] {
+            let tag = Tag::Other {
+                tag: "a".to_owned(),
+                data: vec![value],
+            };
+            let preevent = PreEvent {
+                pubkey: private_key.public_key(),
+                created_at: Unixtime::mock(),
+                kind: EventKind::TextNote,
+                tags: vec![tag.clone()],
+                content: "".to_owned(),
+                ots: None,
+            };
+            let event = Event::new(preevent, &private_key).unwrap();
+            let json = serde_json::to_string(&event).unwrap();
+            let parsed: Event = serde_json::from_str(&json).unwrap();
+            assert_eq!(parsed.tags, vec![tag]);
+            assert!(parsed.verify(None).is_ok());
+            assert_eq!(serde_json::to_string(&parsed).unwrap(), json);
+        }
+
+        // A canonical value is still typed
+        let value = format!("30023:{hex}:x");
+        let tag: Tag = serde_json::from_str(&format!(r#"["a","{value}"]"#)).unwrap();
+        assert!(matches!(tag, Tag::Address { .. }));
+    }
 }
@@ -0,0 +1,149 @@
+use serde::de::{Deserialize, Deserializer, IgnoredAny, MapAccess, Visitor};
+use serde::ser::{Serialize, SerializeMap, Serializer};
+#[cfg(feature = "speedy")]
+use speedy::{Readable, Writable};
+use std::collections::BTreeMap;
+use std::fmt;
+
+/// The tag queries of a `Filter` (NIP-01): for each single-letter tag name, the
+/// values of which an event must have at least one in a tag of that name.
+///
+/// In JSON these are the `"#<letter>"` keys of the filter. Any letter is kept,
+/// so that filters using tags this crate does not know about round-trip
+/// unchanged.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+#[cfg_attr(feature = "speedy", derive(Readable, Writable))]
+pub struct TagFilter(BTreeMap<char, Vec<String>>);
+
+impl TagFilter {
+    /// Create an empty `TagFilter`
+    pub fn new() -> TagFilter {
+        TagFilter::default()
+    }
+
+    /// The values queried for a tag name, if any
+    pub fn get(&self, letter: char) -> Option<&[String]> {
+        self.0.get(&letter).map(|values| values.as_slice())
+    }
+
+    /// Add a value to query for a tag name
+    pub fn add(&mut self, letter: char, value: String) {
+        let values = self.0.entry(letter).or_default();
+        if !values.contains(&value) {
+            values.push(value);
+        }
+    }
+
+    /// Delete a value queried for a tag name, dropping the query for that name
+    /// if no values remain
+    pub fn del(&mut self, letter: char, value: &str) {
+        if let Some(values) = self.0.get_mut(&letter) {
+            values.retain(|v| v != value);
+            if values.is_empty() {
+                let _ = self.0.remove(&letter);
+            }
+        }
+    }
+
+    /// Set all the values queried for a tag name, replacing any earlier ones. An
+    /// empty set of values removes the query.
+    pub fn set(&mut self, letter: char, values: Vec<String>) {
+        if values.is_empty() {
+            let _ = self.0.remove(&letter);
+        } else {
+            let _ = self.0.insert(letter, values);
+        }
+    }
+
+    /// Whether there are no tag queries
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// Iterate over the tag queries, in order of tag name
+    pub fn iter(&self) -> impl Iterator<Item = (char, &[String])> {
+        self.0
+            .iter()
+            .map(|(letter, values)| (*letter, values.as_slice()))
+    }
+}
+
+impl Serialize for TagFilter {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        let mut map = serializer.serialize_map(Some(self.0.len()))?;
+        for (letter, values) in self.0.iter() {
+            map.serialize_entry(&format!("#{letter}"), values)?;
+        }
+        map.end()
+    }
+}
+
+impl<'de> Deserialize<'de> for TagFilter {
+    fn deserialize<D>(deserializer: D) -> Result<TagFilter, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        deserializer.deserialize_map(TagFilterVisitor)
+    }
+}
+
+struct TagFilterVisitor;
+
+impl<'de> Visitor<'de> for TagFilterVisitor {
+    type Value = TagFilter;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "a map of \"#<letter>\" keys to arrays of strings")
+    }
+
+    fn visit_map<M>(self, mut access: M) -> Result<TagFilter, M::Error>
+    where
+        M: MapAccess<'de>,
+    {
+        let mut tag_filter = TagFilter::new();
+        while let Some(key) = access.next_key::<String>()? {
+            let mut chars = key.chars();
+            match (chars.next(), chars.next(), chars.next()) {
+                (Some('#'), Some(letter), None) => {
+                    tag_filter.set(letter, access.next_value()?);
+                }
+                // Other keys are not tag queries (when flattened into a
+                // Filter, these are its other fields)
+                _ => {
+                    let _ = access.next_value::<IgnoredAny>()?;
+                }
+            }
+        }
+        Ok(tag_filter)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_tag_filter() {
+        let mut tag_filter = TagFilter::new();
+        tag_filter.add('t', "nostr".to_owned());
+        tag_filter.add('t', "nostr".to_owned());
+        tag_filter.add('e', "abcd".to_owned());
+        tag_filter.add('X', "value".to_owned());
+        assert_eq!(tag_filter.get('t'), Some(&["nostr".to_owned()][..]));
+
+        let json = serde_json::to_string(&tag_filter).unwrap();
+        assert_eq!(json, r##"{"#X":["value"],"#e":["abcd"],"#t":["nostr"]}"##);
+        let parsed: TagFilter = serde_json::from_str(&json).unwrap();
+        assert_eq!(parsed, tag_filter);
+
+        tag_filter.del('e', "abcd");
+        assert_eq!(tag_filter.get('e'), None);
+        assert_eq!(
+            tag_filter.iter().map(|(l, _)| l).collect::<Vec<_>>(),
+            vec!['X', 't']
+        );
+    }
+}
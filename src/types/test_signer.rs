@@ -0,0 +1,119 @@
+use super::{Event, Id, KeySecurity, PreEvent, PrivateKey, PublicKey, Signature, Signer};
+use crate::Error;
+use k256::sha2::{Digest, Sha256};
+
+/// A signer for tests and fixtures which derives its private key and the
+/// auxiliary randomness used when signing from a seed. The same seed always
+/// produces the same keys, event ids and signatures.
+///
+/// BIP-340 allows the auxiliary randomness to be anything (even all zeroes)
+/// without endangering the key, so signatures remain valid. But the private
+/// key is trivially recoverable from the seed, so NEVER use this for real keys.
+///
+/// This is only available with the `test-util` feature.
+#[allow(missing_debug_implementations)]
+pub struct TestSigner {
+    seed: Vec<u8>,
+    private_key: PrivateKey,
+}
+
+impl TestSigner {
+    /// Create a new signer from a seed
+    pub fn new(seed: &[u8]) -> TestSigner {
+        let mut counter: u32 = 0;
+        loop {
+            let mut hasher = Sha256::new();
+            hasher.update(b"nostr-types/TestSigner/key");
+            hasher.update(seed);
+            hasher.update(counter.to_be_bytes());
+            let bytes = hasher.finalize();
+            // Not every 32 byte value is a valid key, so try again if needed
+            if let Ok(private_key) = PrivateKey::try_from_bytes(&bytes, KeySecurity::Weak) {
+                return TestSigner {
+                    seed: seed.to_vec(),
+                    private_key,
+                };
+            }
+            counter += 1;
+        }
+    }
+
+    /// Derive another independent signer from this one, e.g. for tests that need
+    /// several identities
+    pub fn derive(&self, index: u32) -> TestSigner {
+        let mut seed = self.seed.clone();
+        seed.extend(b"/");
+        seed.extend(index.to_be_bytes());
+        TestSigner::new(&seed)
+    }
+
+    /// Get the private key
+    pub fn private_key(&self) -> &PrivateKey {
+        &self.private_key
+    }
+
+    /// Get the public key
+    pub fn public_key(&self) -> PublicKey {
+        self.private_key.public_key()
+    }
+
+    /// Sign a 32-byte hash deterministically
+    pub fn sign_id(&self, id: Id) -> Result<Signature, Error> {
+        let mut hasher = Sha256::new();
+        hasher.update(b"nostr-types/TestSigner/aux");
+        hasher.update(&self.seed);
+        hasher.update(id.0);
+        let aux_rand: [u8; 32] = hasher.finalize().into();
+        self.private_key.sign_id_with_aux_rand(id, aux_rand)
+    }
+
+    /// Sign a PreEvent deterministically, creating an Event. This fails if the
+    /// PreEvent is not for this signer's public key.
+    pub fn sign_event(&self, input: PreEvent) -> Result<Event, Error> {
+        Signer::sign_event(self, input)
+    }
+}
+
+impl Signer for TestSigner {
+    fn public_key(&self) -> PublicKey {
+        TestSigner::public_key(self)
+    }
+
+    fn sign_id(&self, id: Id) -> Result<Signature, Error> {
+        TestSigner::sign_id(self, id)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{EventKind, Unixtime};
+
+    #[test]
+    fn test_test_signer_is_deterministic() {
+        let a = TestSigner::new(b"fixture");
+        let b = TestSigner::new(b"fixture");
+        assert_eq!(a.public_key(), b.public_key());
+        assert_ne!(a.public_key(), TestSigner::new(b"other").public_key());
+        assert_ne!(a.public_key(), a.derive(0).public_key());
+        assert_eq!(a.derive(1).public_key(), b.derive(1).public_key());
+
+        let preevent = PreEvent {
+            pubkey: a.public_key(),
+            created_at: Unixtime(1680000000),
+            kind: EventKind::TextNote,
+            tags: vec![],
+            content: "Hello World!".to_string(),
+            ots: None,
+        };
+        let event1 = a.sign_event(preevent.clone()).unwrap();
+        let event2 = b.sign_event(preevent.clone()).unwrap();
+        assert_eq!(event1, event2);
+        assert!(event1.verify(None).is_ok());
+
+        // Events for another author are refused
+        let other = a.derive(0);
+        assert!(other.sign_event(preevent.clone()).is_err());
+        assert!(Signer::sign_event(&other, preevent).is_err());
+    }
+}
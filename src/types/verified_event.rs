@@ -0,0 +1,88 @@
+use super::{Event, Unixtime};
+use crate::Error;
+use serde::Serialize;
+use std::ops::Deref;
+
+/// An event whose Id and signature have been checked. It can only be produced by
+/// verification (see `Event::into_verified()`), so code that takes a
+/// `VerifiedEvent` need not verify it again.
+///
+/// It derefs to the `Event`, but gives no mutable access to it, as any change
+/// would invalidate the signature. For the same reason it serializes like an
+/// `Event` but does not deserialize: deserialize an `Event` and verify it.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+#[serde(transparent)]
+pub struct VerifiedEvent(Event);
+
+impl VerifiedEvent {
+    /// Verify an event (see `Event::verify()`), wrapping it if it is valid
+    pub fn try_new(event: Event, maxtime: Option<Unixtime>) -> Result<VerifiedEvent, Error> {
+        event.verify(maxtime)?;
+        Ok(VerifiedEvent(event))
+    }
+
+    /// Get the inner event
+    pub fn as_event(&self) -> &Event {
+        &self.0
+    }
+
+    /// Unwrap the inner event
+    pub fn into_inner(self) -> Event {
+        self.0
+    }
+}
+
+impl Deref for VerifiedEvent {
+    type Target = Event;
+
+    fn deref(&self) -> &Event {
+        &self.0
+    }
+}
+
+impl AsRef<Event> for VerifiedEvent {
+    fn as_ref(&self) -> &Event {
+        &self.0
+    }
+}
+
+impl From<VerifiedEvent> for Event {
+    fn from(verified: VerifiedEvent) -> Event {
+        verified.0
+    }
+}
+
+impl TryFrom<Event> for VerifiedEvent {
+    type Error = Error;
+
+    fn try_from(event: Event) -> Result<VerifiedEvent, Error> {
+        VerifiedEvent::try_new(event, None)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_verified_event() {
+        let event = Event::mock();
+        let verified = event.clone().into_verified(None).unwrap();
+        assert_eq!(verified.id, event.id);
+        assert_eq!(
+            serde_json::to_string(&verified).unwrap(),
+            serde_json::to_string(&event).unwrap()
+        );
+        assert_eq!(Event::from(verified), event);
+
+        let mut tampered = event.clone();
+        tampered.content = "Tampered".to_owned();
+        assert!(VerifiedEvent::try_from(tampered).is_err());
+
+        let past = Unixtime(event.created_at.0 - 1);
+        assert!(matches!(
+            event.into_verified(Some(past)),
+            Err(Error::EventInFuture)
+        ));
+    }
+}